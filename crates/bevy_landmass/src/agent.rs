@@ -9,13 +9,13 @@ use bevy_ecs::{
 use bevy_log::warn_once;
 use bevy_platform::collections::HashMap;
 use bevy_transform::{components::Transform, helper::TransformHelper};
-use landmass::AnimationLinkId;
+use landmass::{AnimationLinkId, PathSmoothing};
 
 use crate::{
   AgentState, Archipelago, TargetReachedCondition, Velocity,
   coords::{CoordinateSystem, ThreeD, TwoD},
 };
-use crate::{ArchipelagoRef, PermittedAnimationLinks};
+use crate::{ArchipelagoRef, PermittedAnimationLinks, PolygonFlagFilter};
 
 /// A bundle to create agents. This omits the GlobalTransform component, since
 /// this is commonly added in other bundles (which is redundant and can override
@@ -69,6 +69,62 @@ pub struct AgentSettings {
 #[derive(Component, Debug)]
 pub struct AnimationLinkReachedDistance(pub f32);
 
+/// The smoothing to apply to an agent's path, so the agent follows a curve
+/// through the corners of its path instead of turning sharply.
+///
+/// The curve starts heading along the agent's [`Velocity`], so the velocity
+/// should reflect how the agent is actually moving.
+#[derive(Component, Default, Clone, Copy, Debug)]
+pub struct SmoothPath(pub PathSmoothing);
+
+/// The distance (along the path) from the target or the next animation link at
+/// which an agent starts to slow down.
+///
+/// The agent's desired speed decreases linearly down to zero as it approaches
+/// the target or animation link. If not present on an agent, the agent moves at
+/// its desired speed until it reaches the target.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct SlowingDistance(pub f32);
+
+/// The maximum rate (in units per second squared) that an agent's velocity can
+/// change.
+///
+/// The agent's desired velocity is limited to be reachable from its
+/// [`Velocity`] within one update, for agents that cannot change their velocity
+/// instantly (e.g., vehicles). Note this makes avoidance less effective.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct MaxAcceleration(pub f32);
+
+/// The maximum rate (in radians per second) that an agent can turn.
+///
+/// The agent's desired velocity is limited to be within this angle of the
+/// agent's heading (see [`Facing`]) within one update, and the agent is only
+/// asked to move along its heading. Note this makes avoidance less effective.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct MaxTurnRate(pub f32);
+
+/// The direction that an agent is facing. This is only used with
+/// [`MaxTurnRate`].
+///
+/// If not present on an agent, the agent is assumed to be facing along its
+/// [`Velocity`] (or in any direction if it is not moving).
+#[derive(Component)]
+pub struct Facing<CS: CoordinateSystem> {
+  pub facing: CS::Coordinate,
+  // This can't be a tuple struct due to https://github.com/rust-lang/rust/issues/73191
+}
+
+pub type Facing2d = Facing<TwoD>;
+pub type Facing3d = Facing<ThreeD>;
+
+impl<CS: CoordinateSystem<Coordinate: std::fmt::Debug>> std::fmt::Debug
+  for Facing<CS>
+{
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("Facing").field("facing", &self.facing).finish()
+  }
+}
+
 #[derive(Component, Default, Debug)]
 pub struct AgentTypeIndexCostOverrides(HashMap<usize, f32>);
 
@@ -160,14 +216,19 @@ impl<CS: CoordinateSystem> AgentTarget<CS> {
 /// The current desired velocity of the agent. This is set by `landmass` (during
 /// [`crate::LandmassSystemSet::Output`]).
 #[derive(Component)]
-pub struct AgentDesiredVelocity<CS: CoordinateSystem>(CS::Coordinate);
+pub struct AgentDesiredVelocity<CS: CoordinateSystem> {
+  /// The desired velocity in world space.
+  velocity: CS::Coordinate,
+  /// The desired velocity in the frame of the island the agent is on.
+  island_velocity: Option<CS::Coordinate>,
+}
 
 pub type AgentDesiredVelocity2d = AgentDesiredVelocity<TwoD>;
 pub type AgentDesiredVelocity3d = AgentDesiredVelocity<ThreeD>;
 
 impl<CS: CoordinateSystem> Default for AgentDesiredVelocity<CS> {
   fn default() -> Self {
-    Self(Default::default())
+    Self { velocity: Default::default(), island_velocity: None }
   }
 }
 
@@ -175,14 +236,26 @@ impl<CS: CoordinateSystem<Coordinate: std::fmt::Debug>> std::fmt::Debug
   for AgentDesiredVelocity<CS>
 {
   fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-    f.debug_tuple("AgentDesiredVelocity").field(&self.0).finish()
+    f.debug_struct("AgentDesiredVelocity")
+      .field("velocity", &self.velocity)
+      .field("island_velocity", &self.island_velocity)
+      .finish()
   }
 }
 
 impl<CS: CoordinateSystem> AgentDesiredVelocity<CS> {
   /// The desired velocity of the agent.
   pub fn velocity(&self) -> CS::Coordinate {
-    self.0.clone()
+    self.velocity.clone()
+  }
+
+  /// The desired velocity of the agent in the frame of the island it is
+  /// standing on (i.e., rotated into the island's space). The velocity is not
+  /// scaled by the island, so it is still in world units. This is useful for agents on moving islands (e.g., ships) whose movement is
+  /// simulated relative to the island. Returns [`None`] if the agent is not
+  /// on an island.
+  pub fn island_velocity(&self) -> Option<CS::Coordinate> {
+    self.island_velocity.clone()
   }
 }
 
@@ -221,6 +294,34 @@ impl<CS: CoordinateSystem<Coordinate: std::fmt::Debug>> std::fmt::Debug
 #[derive(Component, Default, Clone, Copy, Debug)]
 pub struct PauseAgent;
 
+/// A marker component to indicate that an agent should move as close as it can
+/// to an unreachable target.
+///
+/// Instead of reporting [`AgentState::NoPath`], the agent paths to the closest
+/// reachable point and reports [`AgentState::MovingToPartialTarget`] and
+/// [`AgentState::ReachedPartialTarget`].
+#[derive(Component, Default, Clone, Copy, Debug)]
+pub struct AllowPartialPaths;
+
+/// A marker component to indicate that an agent's path should keep the agent's
+/// body away from the corners of the nav meshes.
+///
+/// The corners of the agent's path are offset by [`AgentSettings::radius`] away
+/// from the boundary of the nav meshes, rather than touching the corners
+/// exactly.
+#[derive(Component, Default, Clone, Copy, Debug)]
+pub struct OffsetPathCorners;
+
+/// A marker component to indicate that an agent should be carried along when
+/// the island it is standing on moves.
+///
+/// The agent's position is synced from its transform every frame, so this only
+/// carries the agent (and its target) while the archipelago updates. Agents
+/// riding an island are usually better off parented to the island instead, in
+/// which case this should not be added.
+#[derive(Component, Default, Clone, Copy, Debug)]
+pub struct CarriedByIslands;
+
 /// A marker component to indicate that an agent is currently using an animation
 /// link and should behave as though it is paused (see [`PauseAgent`] for
 /// details).
@@ -300,10 +401,23 @@ pub(crate) fn sync_agent_input_state<CS: CoordinateSystem>(
       Option<&AgentTarget<CS>>,
       Option<&TargetReachedCondition>,
       Option<&AnimationLinkReachedDistance>,
+      (
+        Option<&SmoothPath>,
+        Option<&SlowingDistance>,
+        Option<&MaxAcceleration>,
+        Option<&MaxTurnRate>,
+        Option<&Facing<CS>>,
+      ),
       Option<&PermittedAnimationLinks>,
+      Option<&PolygonFlagFilter>,
       Option<Ref<AgentTypeIndexCostOverrides>>,
-      Has<PauseAgent>,
-      Has<UsingAnimationLink>,
+      (
+        Has<PauseAgent>,
+        Has<AllowPartialPaths>,
+        Has<OffsetPathCorners>,
+        Has<UsingAnimationLink>,
+        Has<CarriedByIslands>,
+      ),
       HasKeepAvoidanceData,
     ),
     With<Transform>,
@@ -319,10 +433,17 @@ pub(crate) fn sync_agent_input_state<CS: CoordinateSystem>(
     target,
     target_reached_condition,
     animation_link_reached_distance,
+    (smooth_path, slowing_distance, max_acceleration, max_turn_rate, facing),
     permitted_animation_links,
+    polygon_flag_filter,
     type_index_cost_overrides,
-    has_pause_agent,
-    has_using_animation_link,
+    (
+      has_pause_agent,
+      has_allow_partial_paths,
+      has_offset_path_corners,
+      has_using_animation_link,
+      has_carried_by_islands,
+    ),
     keep_avoidance_data,
   ) in agent_query.iter()
   {
@@ -356,9 +477,23 @@ pub(crate) fn sync_agent_input_state<CS: CoordinateSystem>(
       };
     landmass_agent.animation_link_reached_distance =
       animation_link_reached_distance.map(|distance| distance.0);
+    landmass_agent.path_smoothing =
+      smooth_path.map(|smooth_path| smooth_path.0);
+    landmass_agent.slowing_distance =
+      slowing_distance.map(|distance| distance.0);
+    landmass_agent.max_acceleration =
+      max_acceleration.map(|max_acceleration| max_acceleration.0);
+    landmass_agent.max_turn_rate =
+      max_turn_rate.map(|max_turn_rate| max_turn_rate.0);
+    landmass_agent.facing = facing.map(|facing| facing.facing.clone());
     landmass_agent.permitted_animation_links = permitted_animation_links
       .map(PermittedAnimationLinks::to_landmass)
       .unwrap_or(landmass::PermittedAnimationLinks::All);
+    landmass_agent.polygon_flag_filter =
+      polygon_flag_filter.copied().unwrap_or_default().to_landmass();
+    landmass_agent.allow_partial_paths = has_allow_partial_paths;
+    landmass_agent.offset_path_corners = has_offset_path_corners;
+    landmass_agent.carried_by_islands = has_carried_by_islands;
     match type_index_cost_overrides {
       None => {
         for (type_index, _) in
@@ -455,11 +590,12 @@ pub(crate) fn sync_desired_velocity<CS: CoordinateSystem>(
       Some(arch) => arch,
     };
 
-    desired_velocity.0 = archipelago
+    let agent = archipelago
       .get_agent(agent_entity)
-      .expect("the agent is in the archipelago")
-      .get_desired_velocity()
-      .clone();
+      .expect("the agent is in the archipelago");
+    desired_velocity.velocity = agent.get_desired_velocity().clone();
+    desired_velocity.island_velocity =
+      agent.get_island_desired_velocity().cloned();
   }
 }
 
@@ -19,9 +19,9 @@ pub trait CoordinateSystem:
   /// Converts a position in Bevy into this system's coordinate.
   fn from_bevy_position(v: bevy_math::Vec3) -> Self::Coordinate;
 
-  /// Converts a [`bevy_math::Quat`] into the corresponding rotation (in
-  /// radians) in this system.
-  fn from_bevy_rotation(rotation: &bevy_math::Quat) -> f32;
+  /// Converts a [`bevy_math::Quat`] into the corresponding rotation in
+  /// landmass's standard coordinate system.
+  fn from_bevy_rotation(rotation: &bevy_math::Quat) -> landmass::Quat;
 
   /// Converts this system's coordinate into a world position.
   fn to_world_position(c: &Self::Coordinate) -> bevy_math::Vec3;
@@ -55,8 +55,9 @@ impl CoordinateSystem for ThreeD {
     v
   }
 
-  fn from_bevy_rotation(rotation: &bevy_math::Quat) -> f32 {
-    rotation.to_euler(bevy_math::EulerRot::YXZ).0
+  fn from_bevy_rotation(rotation: &bevy_math::Quat) -> landmass::Quat {
+    // Rotate the axis of the rotation the same way as `to_landmass`.
+    landmass::Quat::from_xyzw(rotation.x, -rotation.z, rotation.y, rotation.w)
   }
 
   fn to_world_position(c: &Self::Coordinate) -> bevy_math::Vec3 {
@@ -92,8 +93,12 @@ impl CoordinateSystem for TwoD {
     v.xy()
   }
 
-  fn from_bevy_rotation(rotation: &bevy_math::Quat) -> f32 {
-    rotation.to_euler(bevy_math::EulerRot::ZXY).0
+  fn from_bevy_rotation(rotation: &bevy_math::Quat) -> landmass::Quat {
+    // Tilting out of the 2D plane doesn't make sense, so only keep the
+    // rotation within the plane.
+    landmass::Quat::from_rotation_z(
+      rotation.to_euler(bevy_math::EulerRot::ZXY).0,
+    )
   }
 
   fn to_world_position(c: &Self::Coordinate) -> bevy_math::Vec3 {
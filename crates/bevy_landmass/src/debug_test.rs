@@ -89,6 +89,7 @@ fn draws_archipelago_debug() {
       ],
       polygons: vec![vec![0, 1, 2, 3]],
       polygon_type_indices: vec![0],
+      polygon_flags: None,
       height_mesh: None,
     }
     .validate()
@@ -241,6 +242,7 @@ fn draws_avoidance_data_when_requested() {
       ],
       polygons: vec![vec![0, 1, 2, 3]],
       polygon_type_indices: vec![0],
+      polygon_flags: None,
       height_mesh: None,
     }
     .validate()
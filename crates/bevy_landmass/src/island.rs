@@ -8,6 +8,8 @@ use bevy_ecs::{
   query::With,
   system::{Query, Res},
 };
+use bevy_log::warn_once;
+use bevy_math::Vec3;
 use bevy_platform::collections::{HashMap, HashSet};
 use bevy_transform::{components::Transform, helper::TransformHelper};
 
@@ -64,15 +66,31 @@ pub(crate) fn sync_islands_to_archipelago<CS: CoordinateSystem>(
       continue;
     };
 
+    let island_transform = island_transform.compute_transform();
+    let scale = island_transform.scale;
+    if !(scale.x.is_finite() && scale.x > 0.0) {
+      // The island can't be represented, so it is left out of (or removed
+      // from) the archipelago.
+      warn_once!(
+        "Island {island_entity} has a non-positive or non-finite scale ({scale}), so it is ignored."
+      );
+      continue;
+    }
+    if !scale.abs_diff_eq(Vec3::splat(scale.x), scale.x * 1e-4) {
+      warn_once!(
+        "Island {island_entity} has a non-uniform scale ({scale}), but only uniform scales are supported. Using the X scale."
+      );
+    }
+
     archipelago_to_islands
       .entry(archipelago_ref.entity)
       .or_default()
       .insert(island_entity);
 
-    let island_transform = island_transform.compute_transform();
     let landmass_transform = landmass::Transform {
       translation: CS::from_bevy_position(island_transform.translation),
       rotation: CS::from_bevy_rotation(&island_transform.rotation),
+      scale: scale.x,
     };
 
     match archipelago.get_island_mut(island_entity) {
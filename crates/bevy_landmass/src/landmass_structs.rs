@@ -21,12 +21,21 @@ pub enum AgentState {
   UsingAnimationLink,
   /// The agent has a path and is moving towards their target.
   Moving,
+  /// The agent cannot reach their target, so it is moving towards the closest
+  /// reachable point instead. See [`crate::AllowPartialPaths`].
+  MovingToPartialTarget,
+  /// The agent cannot reach their target, and has reached the closest
+  /// reachable point instead. See [`crate::AllowPartialPaths`].
+  ReachedPartialTarget,
   /// The agent is not on a nav mesh.
   AgentNotOnNavMesh,
   /// The target is not on a nav mesh.
   TargetNotOnNavMesh,
   /// The agent has a target but cannot find a path to it.
   NoPath,
+  /// The agent needs a path, but its search has been deferred to a later
+  /// update due to the archipelago's pathfinding budget.
+  WaitingForPath,
   /// The agent is paused.
   Paused,
 }
@@ -40,9 +49,14 @@ impl AgentState {
       landmass::AgentState::ReachedAnimationLink => Self::ReachedAnimationLink,
       landmass::AgentState::UsingAnimationLink => Self::UsingAnimationLink,
       landmass::AgentState::Moving => Self::Moving,
+      landmass::AgentState::MovingToPartialTarget => {
+        Self::MovingToPartialTarget
+      }
+      landmass::AgentState::ReachedPartialTarget => Self::ReachedPartialTarget,
       landmass::AgentState::AgentNotOnNavMesh => Self::AgentNotOnNavMesh,
       landmass::AgentState::TargetNotOnNavMesh => Self::TargetNotOnNavMesh,
       landmass::AgentState::NoPath => Self::NoPath,
+      landmass::AgentState::WaitingForPath => Self::WaitingForPath,
       landmass::AgentState::Paused => Self::Paused,
     }
   }
@@ -113,3 +127,69 @@ impl PermittedAnimationLinks {
     }
   }
 }
+
+/// Defines the polygons that an agent is allowed to travel through, based on
+/// their flags (see [`crate::NavigationMesh::polygon_flags`]). A polygon is
+/// permitted only if all of its flags are in [`Self::include_flags`] and none
+/// of its flags are in [`Self::exclude_flags`]. Polygons without flags are
+/// always permitted.
+#[derive(Component, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct PolygonFlagFilter {
+  /// The flags that the agent is allowed to travel through.
+  pub include_flags: u32,
+  /// The flags that the agent is not allowed to travel through.
+  pub exclude_flags: u32,
+}
+
+impl Default for PolygonFlagFilter {
+  fn default() -> Self {
+    Self { include_flags: u32::MAX, exclude_flags: 0 }
+  }
+}
+
+impl PolygonFlagFilter {
+  /// Convert the `bevy_landmass` version of the polygon flag filter to the
+  /// `landmass` version.
+  pub(crate) fn to_landmass(self) -> landmass::PolygonFlagFilter {
+    landmass::PolygonFlagFilter {
+      include_flags: self.include_flags,
+      exclude_flags: self.exclude_flags,
+    }
+  }
+}
+
+/// Options for [`crate::Archipelago::find_path`]. By default, paths may travel
+/// through any polygon and take any animation link, and ignore the width of
+/// gaps.
+#[derive(Clone, Default)]
+pub struct PathQuery<'a> {
+  /// Overrides the cost of each type index in this map. Costs must be
+  /// positive.
+  pub override_type_index_costs: std::collections::HashMap<usize, f32>,
+  /// The animation links that the path may take.
+  pub permitted_animation_links: PermittedAnimationLinks,
+  /// Determines the polygons that the path may travel through.
+  pub flag_filter: PolygonFlagFilter,
+  /// The path does not go through gaps (or take animation links) narrower
+  /// than an agent with this radius. `0.0` ignores the width of gaps.
+  pub agent_radius: f32,
+  /// Further customizes the costs of the path.
+  pub cost_filter: Option<&'a dyn landmass::PathCostFilter>,
+  /// If set, the straight lines of the path are smoothed into curves.
+  pub smoothing: Option<landmass::PathSmoothing>,
+}
+
+impl<'a> PathQuery<'a> {
+  /// Convert the `bevy_landmass` version of the path query to the `landmass`
+  /// version.
+  pub(crate) fn to_landmass(&self) -> landmass::PathQuery<'a> {
+    landmass::PathQuery {
+      override_type_index_costs: self.override_type_index_costs.clone(),
+      permitted_animation_links: self.permitted_animation_links.to_landmass(),
+      flag_filter: self.flag_filter.to_landmass(),
+      agent_radius: self.agent_radius,
+      cost_filter: self.cost_filter,
+      smoothing: self.smoothing,
+    }
+  }
+}
@@ -25,9 +25,9 @@ mod link;
 
 pub use landmass::{
   ArchipelagoOptions, FindPathError, FromAgentRadius, HeightNavigationMesh,
-  HeightPolygon, NavigationMesh, PathStep, PointSampleDistance3d,
-  SamplePointError, SetTypeIndexCostError, ValidNavigationMesh,
-  ValidationError,
+  HeightPolygon, NavigationMesh, PathCostFilter, PathSmoothing, PathStep,
+  PathfindingBudget, PathfindingMode, PointSampleDistance3d, SamplePointError,
+  SetTypeIndexCostError, ValidNavigationMesh, ValidationError,
 };
 
 pub use agent::*;
@@ -280,19 +280,18 @@ impl<CS: CoordinateSystem> Archipelago<CS> {
   /// meshes. Only [`SampledPoint`]s from this archipelago are supported. This
   /// should only be used for querying (e.g., finding the walking distance to an
   /// object), not for controlling movement. For controlling movement, use
-  /// agents.
+  /// agents. `query` determines where the path may travel and how it is
+  /// costed (see [`PathQuery`]).
   pub fn find_path(
     &self,
     start_point: &SampledPoint<'_, CS>,
     end_point: &SampledPoint<'_, CS>,
-    override_type_index_costs: &std::collections::HashMap<usize, f32>,
-    permitted_animation_links: PermittedAnimationLinks,
+    query: &PathQuery<'_>,
   ) -> Result<Vec<PathStep<CS>>, FindPathError> {
     self.archipelago.find_path(
       &start_point.sampled_point,
       &end_point.sampled_point,
-      override_type_index_costs,
-      permitted_animation_links.to_landmass(),
+      &query.to_landmass(),
     )
   }
 
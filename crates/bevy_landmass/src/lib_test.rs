@@ -1,4 +1,4 @@
-use std::{collections::HashMap, sync::Arc, time::Duration};
+use std::{sync::Arc, time::Duration};
 
 use bevy::MinimalPlugins;
 use bevy_app::{App, Plugin};
@@ -20,8 +20,8 @@ use crate::{
   ArchipelagoRef3d, Character3dBundle, CharacterSettings, FromAgentRadius,
   Island, Island2dBundle, Island3dBundle, Landmass2dPlugin, Landmass3dPlugin,
   NavMesh2d, NavMesh3d, NavMeshHandle, NavigationMesh, NavigationMesh3d,
-  PauseAgent, PermittedAnimationLinks, ReachedAnimationLink2d,
-  SamplePointError, UsingAnimationLink, Velocity3d,
+  PathQuery, PauseAgent, ReachedAnimationLink2d, SamplePointError,
+  UsingAnimationLink, Velocity3d,
 };
 
 #[test]
@@ -45,6 +45,7 @@ fn computes_path_for_agent_and_updates_desired_velocity() {
       ],
       polygons: vec![vec![0, 1, 4, 5], vec![1, 2, 3, 4]],
       polygon_type_indices: vec![0, 0],
+      polygon_flags: None,
       height_mesh: None,
     }
     .validate()
@@ -342,6 +343,7 @@ fn adds_and_removes_islands() {
           vertices: vec![],
           polygons: vec![],
           polygon_type_indices: vec![],
+          polygon_flags: None,
           height_mesh: None,
         }
         .validate()
@@ -689,6 +691,7 @@ fn type_index_costs_are_used() {
         vec![3, 2, 13, 15],
       ],
       polygon_type_indices: vec![0, 0, 0, 0, 0, 0, 0, 1],
+      polygon_flags: None,
       height_mesh: None,
     }
     .validate()
@@ -796,6 +799,7 @@ fn overridden_type_index_costs_are_used() {
         vec![3, 2, 13, 15],
       ],
       polygon_type_indices: vec![0, 0, 0, 0, 0, 0, 0, 1],
+      polygon_flags: None,
       height_mesh: None,
     }
     .validate()
@@ -878,6 +882,7 @@ fn sample_point_error_on_out_of_range() {
       ],
       polygons: vec![vec![0, 1, 2, 3]],
       polygon_type_indices: vec![0],
+      polygon_flags: None,
       height_mesh: None,
     }
     .validate()
@@ -924,6 +929,7 @@ fn samples_point_on_nav_mesh_or_near_nav_mesh() {
       ],
       polygons: vec![vec![0, 1, 2, 3]],
       polygon_type_indices: vec![0],
+      polygon_flags: None,
       height_mesh: None,
     }
     .validate()
@@ -1001,6 +1007,7 @@ fn samples_type_indices() {
       ],
       polygons: vec![vec![0, 1, 2, 3], vec![2, 1, 4, 5]],
       polygon_type_indices: vec![0, 1],
+      polygon_flags: None,
       height_mesh: None,
     }
     .validate()
@@ -1065,6 +1072,7 @@ fn finds_path() {
       ],
       polygons: vec![vec![0, 1, 2, 3]],
       polygon_type_indices: vec![0],
+      polygon_flags: None,
       height_mesh: None,
     }
     .validate()
@@ -1111,12 +1119,7 @@ fn finds_path() {
     .sample_point(Vec2::new(2.5, 1.25), &1e-5)
     .expect("point is on nav mesh.");
   assert_eq!(
-    archipelago.find_path(
-      &start_point,
-      &end_point,
-      &HashMap::new(),
-      PermittedAnimationLinks::All
-    ),
+    archipelago.find_path(&start_point, &end_point, &PathQuery::default()),
     Ok(vec![
       PathStep::Waypoint(Vec2::new(0.5, 0.5)),
       PathStep::Waypoint(Vec2::new(2.0, 1.0)),
@@ -1144,6 +1147,7 @@ fn island_matches_rotation_3d() {
       ],
       polygons: vec![vec![0, 1, 2, 3]],
       polygon_type_indices: vec![0],
+      polygon_flags: None,
       height_mesh: None,
     }
     .validate()
@@ -1177,7 +1181,11 @@ fn island_matches_rotation_3d() {
     .expect("The island is present.")
     .get_transform()
     .rotation;
-  assert!((rotation - 2.0).abs() < 1e-6, "left={rotation} right=2.0");
+  assert!(
+    rotation.abs_diff_eq(landmass::Quat::from_rotation_z(2.0), 1e-6),
+    "left={rotation} right={}",
+    landmass::Quat::from_rotation_z(2.0)
+  );
 }
 
 #[test]
@@ -1199,6 +1207,7 @@ fn island_matches_rotation_2d() {
       ],
       polygons: vec![vec![0, 1, 2, 3]],
       polygon_type_indices: vec![0],
+      polygon_flags: None,
       height_mesh: None,
     }
     .validate()
@@ -1232,7 +1241,11 @@ fn island_matches_rotation_2d() {
     .expect("The island is present.")
     .get_transform()
     .rotation;
-  assert!((rotation - 2.0).abs() < 1e-6, "left={rotation} right=2.0");
+  assert!(
+    rotation.abs_diff_eq(landmass::Quat::from_rotation_z(2.0), 1e-6),
+    "left={rotation} right={}",
+    landmass::Quat::from_rotation_z(2.0)
+  );
 }
 
 #[googletest::test]
@@ -1256,6 +1269,7 @@ fn agent_is_paused() {
       ],
       polygons: vec![vec![0, 1, 2, 3], vec![3, 2, 4, 5]],
       polygon_type_indices: vec![0; 2],
+      polygon_flags: None,
       height_mesh: None,
     }
     .validate()
@@ -1369,6 +1383,7 @@ fn agent_using_animation_link() {
       ],
       polygons: vec![vec![0, 1, 2, 3]],
       polygon_type_indices: vec![0],
+      polygon_flags: None,
       height_mesh: None,
     }
     .validate()
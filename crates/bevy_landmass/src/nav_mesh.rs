@@ -72,6 +72,7 @@ pub fn bevy_mesh_to_landmass_nav_mesh<CS: CoordinateSystem>(
     vertices,
     polygon_type_indices: (0..polygons.len()).map(|_| 0).collect(),
     polygons,
+    polygon_flags: None,
     height_mesh: None,
   })
 }
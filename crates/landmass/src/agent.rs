@@ -3,15 +3,17 @@ use std::{
   sync::Arc,
 };
 
-use glam::Vec3;
+use glam::{Vec2, Vec3, Vec3Swizzles};
 use slotmap::new_key_type;
 use thiserror::Error;
 
 use crate::{
-  CoordinateSystem, IslandId, NavigationData,
+  CoordinateSystem, IslandId, NavigationData, PathCostFilter, PathSmoothing,
+  flow_field::FlowFieldId,
   link::AnimationLinkId,
   nav_data::{NodeRef, OffMeshLinkId},
   path::{Path, PathIndex, StraightPathStep},
+  pathfinding::PathSearch,
 };
 
 new_key_type! {
@@ -36,12 +38,21 @@ pub enum AgentState {
   UsingAnimationLink,
   /// The agent has a path and is moving towards their target.
   Moving,
+  /// The agent cannot reach their target, so it is moving towards the closest
+  /// reachable point instead. See [`Agent::allow_partial_paths`].
+  MovingToPartialTarget,
+  /// The agent cannot reach their target, and has reached the closest
+  /// reachable point instead. See [`Agent::allow_partial_paths`].
+  ReachedPartialTarget,
   /// The agent is not on a nav mesh.
   AgentNotOnNavMesh,
   /// The target is not on a nav mesh.
   TargetNotOnNavMesh,
   /// The agent has a target but cannot find a path to it.
   NoPath,
+  /// The agent needs a path, but its search has been deferred to a later
+  /// update due to [`crate::ArchipelagoOptions::pathfinding_budget`].
+  WaitingForPath,
   /// The agent is paused.
   Paused,
 }
@@ -65,6 +76,14 @@ pub struct Agent<CS: CoordinateSystem> {
   /// However, swapping between two distant targets every update can be
   /// detrimental to be performance.
   pub current_target: Option<CS::Coordinate>,
+  /// The flow field to follow. If set, this replaces [`Self::current_target`]:
+  /// the agent moves towards the target of the flow field, using the shared
+  /// field instead of searching for its own path. This is useful when many
+  /// agents share the same target.
+  ///
+  /// Note the agent's type index cost overrides are not used when following a
+  /// flow field.
+  pub flow_field: Option<FlowFieldId>,
   /// The condition to test for reaching the target.
   pub target_reached_condition: TargetReachedCondition,
   /// The distance at which an animation link can be used.
@@ -78,12 +97,77 @@ pub struct Agent<CS: CoordinateSystem> {
   /// Note, changing this at runtime may result in the agent continuing on a
   /// path that still contains a previously allowed animation link.
   pub permitted_animation_links: PermittedAnimationLinks,
+  /// The polygons that the agent is allowed to travel through.
+  ///
+  /// Note, changing this at runtime does not cause the agent to repath. This is
+  /// not used when following a flow field.
+  pub polygon_flag_filter: PolygonFlagFilter,
+  /// The filter to further customize the costs of this agent's paths (e.g.,
+  /// to avoid nodes near danger).
+  ///
+  /// Note, changing this (or the results of the filter) at runtime does not
+  /// cause the agent to repath. This is not used when following a flow field.
+  pub cost_filter: Option<Arc<dyn PathCostFilter>>,
   /// Whether this agent is "paused". Paused agents are not considered for
   /// avoidance, and will not recompute their paths. However, their paths are
   /// still kept "consistent" - meaning that once the agent becomes unpaused,
   /// it can reuse that path if it is still valid and relevant (the agent still
   /// wants to go to the same place).
   pub paused: bool,
+  /// Whether the agent should move as close as it can to an unreachable
+  /// target. If false, an unreachable target results in
+  /// [`AgentState::NoPath`]. If true, the agent instead paths to the closest
+  /// reachable point (as estimated by the pathfinding heuristic), reporting
+  /// [`AgentState::MovingToPartialTarget`] and
+  /// [`AgentState::ReachedPartialTarget`]. The agent tries to reach the real
+  /// target again whenever the target moves to a different node or the nav
+  /// data changes.
+  ///
+  /// Note finding the closest point may require an additional search that
+  /// does not respect [`crate::ArchipelagoOptions::pathfinding_budget`]. This
+  /// is not used when following a flow field.
+  pub allow_partial_paths: bool,
+  /// Whether the agent is carried along when the island it is standing on
+  /// moves (e.g., an elevator or a ship). If true, [`Self::position`] (and
+  /// [`Self::current_target`] if it is on the same island) is moved along with
+  /// the island during [`crate::Archipelago::update`]. Leave this disabled if
+  /// the agent is already moved along with the island (e.g., it is parented
+  /// to the island in a scene graph).
+  pub carried_by_islands: bool,
+  /// Whether the agent's path keeps the agent's body away from the corners of
+  /// the nav meshes. If true, the corners of the agent's straight path are
+  /// offset by [`Self::radius`] away from the boundary of the nav meshes
+  /// (rather than touching the corners exactly), so the agent does not need
+  /// to rely on avoidance to stay off walls.
+  pub offset_path_corners: bool,
+  /// The smoothing to apply to the agent's path. If set, the agent follows a
+  /// curve through the corners of its path instead of turning sharply at each
+  /// corner. The curve starts heading along [`Self::velocity`], so the
+  /// velocity should reflect how the agent is actually moving.
+  pub path_smoothing: Option<PathSmoothing>,
+  /// The distance (along the path) from the target or the next animation link
+  /// at which the agent starts to slow down. If set, the agent's desired speed
+  /// decreases linearly from [`Self::desired_speed`] down to zero as it
+  /// approaches the target or animation link, so it arrives smoothly instead
+  /// of stopping abruptly. If [`None`], the agent moves at its desired speed
+  /// until it reaches the target.
+  pub slowing_distance: Option<f32>,
+  /// The maximum rate (in units per second squared) that the agent's velocity
+  /// can change. If set, the desired velocity is limited to be reachable from
+  /// [`Self::velocity`] within one update, so agents that cannot change their
+  /// velocity instantly (e.g., vehicles) are not asked to. Note limiting the
+  /// desired velocity makes avoidance less effective.
+  pub max_acceleration: Option<f32>,
+  /// The maximum rate (in radians per second) that the agent can turn. If set,
+  /// the desired velocity is limited to be within this angle of the agent's
+  /// heading (see [`Self::facing`]) within one update, and the agent is only
+  /// asked to move along its heading (it never moves sideways). Note limiting
+  /// the desired velocity makes avoidance less effective.
+  pub max_turn_rate: Option<f32>,
+  /// The direction that the agent is facing. This is only used with
+  /// [`Self::max_turn_rate`]. If [`None`], the agent is assumed to be facing
+  /// along [`Self::velocity`] (or in any direction if it is not moving).
+  pub facing: Option<CS::Coordinate>,
   #[cfg(feature = "debug-avoidance")]
   /// If true, avoidance debug data will be stored during update iterations.
   /// This can later be used for visualization.
@@ -93,8 +177,25 @@ pub struct Agent<CS: CoordinateSystem> {
   /// The current path of the agent. None if a path is unavailable or a new
   /// path has not been computed yet (i.e., no path).
   pub(crate) current_path: Option<Path>,
+  /// The closest reachable point that [`Self::current_path`] leads to, if the
+  /// target is unreachable. See [`Self::allow_partial_paths`].
+  pub(crate) partial_target: Option<PartialTarget>,
+  /// Where the agent and its target were along [`Self::current_path`] during
+  /// the last update.
+  pub(crate) path_progress: Option<PathProgress>,
+  /// The search for a new path that is still in progress. This is only used
+  /// when the search is spread over multiple updates due to
+  /// [`crate::ArchipelagoOptions::pathfinding_budget`].
+  pub(crate) path_search: Option<PathSearch>,
   /// The desired velocity of the agent to move towards its goal.
   pub(crate) current_desired_move: CS::Coordinate,
+  /// The desired velocity of the agent in the frame of
+  /// [`Self::current_island`].
+  pub(crate) current_island_desired_move: Option<CS::Coordinate>,
+  /// The island that the agent was standing on during the last update.
+  pub(crate) current_island: Option<IslandId>,
+  /// The island that [`Self::current_target`] was on during the last update.
+  pub(crate) target_island: Option<IslandId>,
   /// The state of the agent.
   pub(crate) state: AgentState,
   /// The animation link that the agent has reached. This includes the
@@ -108,6 +209,31 @@ pub struct Agent<CS: CoordinateSystem> {
   pub(crate) avoidance_data: Option<dodgy_2d::debug::DebugData>,
 }
 
+/// The closest reachable point to an unreachable target.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(crate) struct PartialTarget {
+  /// The node of the real (unreachable) target when the partial path was
+  /// found.
+  pub(crate) target_node: NodeRef,
+  /// The node containing the closest reachable point.
+  pub(crate) node: NodeRef,
+  /// The closest reachable point (in world space).
+  pub(crate) point: Vec3,
+}
+
+/// Where an agent and its target are along the agent's path.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(crate) struct PathProgress {
+  /// The index of the agent's node in the path.
+  pub(crate) agent_index: PathIndex,
+  /// The agent's position (in world space).
+  pub(crate) agent_point: Vec3,
+  /// The index of the target's node in the path.
+  pub(crate) target_index: PathIndex,
+  /// The target's position (in world space).
+  pub(crate) target_point: Vec3,
+}
+
 /// An animation link that an agent has reached (in order to use it).
 pub struct ReachedAnimationLink<CS: CoordinateSystem> {
   /// The ID of the animation link.
@@ -166,7 +292,7 @@ impl Default for TargetReachedCondition {
 }
 
 /// Defines the list of animation links that an agent is allowed to use.
-#[derive(Clone, Default, Debug)]
+#[derive(Clone, Default, PartialEq, Eq, Debug)]
 pub enum PermittedAnimationLinks {
   /// Every animation link is permitted.
   #[default]
@@ -186,6 +312,33 @@ impl PermittedAnimationLinks {
   }
 }
 
+/// Defines the polygons that an agent is allowed to travel through, based on
+/// their flags (see [`crate::NavigationMesh::polygon_flags`]). A polygon is
+/// permitted only if all of its flags are in [`Self::include_flags`] and none
+/// of its flags are in [`Self::exclude_flags`]. Polygons without flags are
+/// always permitted.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct PolygonFlagFilter {
+  /// The flags that the agent is allowed to travel through.
+  pub include_flags: u32,
+  /// The flags that the agent is not allowed to travel through.
+  pub exclude_flags: u32,
+}
+
+impl Default for PolygonFlagFilter {
+  fn default() -> Self {
+    Self { include_flags: u32::MAX, exclude_flags: 0 }
+  }
+}
+
+impl PolygonFlagFilter {
+  /// Returns whether a polygon with `flags` is permitted.
+  #[inline]
+  pub fn is_permitted(&self, flags: u32) -> bool {
+    flags & !self.include_flags == 0 && flags & self.exclude_flags == 0
+  }
+}
+
 impl<CS: CoordinateSystem> Agent<CS> {
   /// Creates a new agent.
   pub fn create(
@@ -202,15 +355,32 @@ impl<CS: CoordinateSystem> Agent<CS> {
       desired_speed,
       max_speed,
       current_target: None,
+      flow_field: None,
       target_reached_condition: TargetReachedCondition::Distance(None),
       animation_link_reached_distance: None,
       permitted_animation_links: PermittedAnimationLinks::All,
+      polygon_flag_filter: PolygonFlagFilter::default(),
+      cost_filter: None,
       paused: false,
+      allow_partial_paths: false,
+      carried_by_islands: false,
+      offset_path_corners: false,
+      path_smoothing: None,
+      slowing_distance: None,
+      max_acceleration: None,
+      max_turn_rate: None,
+      facing: None,
       #[cfg(feature = "debug-avoidance")]
       keep_avoidance_data: false,
       override_type_index_to_cost: HashMap::new(),
       current_path: None,
+      partial_target: None,
+      path_progress: None,
+      path_search: None,
       current_desired_move: CS::from_landmass(&Vec3::ZERO),
+      current_island_desired_move: None,
+      current_island: None,
+      target_island: None,
       state: AgentState::Idle,
       current_animation_link: None,
       using_animation_link: false,
@@ -230,6 +400,8 @@ impl<CS: CoordinateSystem> Agent<CS> {
       return false;
     }
     self.override_type_index_to_cost.insert(type_index, cost);
+    // Any search in progress was using the old costs.
+    self.path_search = None;
     true
   }
 
@@ -239,6 +411,8 @@ impl<CS: CoordinateSystem> Agent<CS> {
     &mut self,
     type_index: usize,
   ) -> bool {
+    // Any search in progress was using the old costs.
+    self.path_search = None;
     self.override_type_index_to_cost.remove(&type_index).is_some()
   }
 
@@ -254,10 +428,29 @@ impl<CS: CoordinateSystem> Agent<CS> {
 
   /// Returns the desired velocity. This will only be updated if `update` was
   /// called on the associated [`crate::Archipelago`].
+  ///
+  /// This is the velocity relative to the island the agent is standing on. If
+  /// the island is moving, the motion of the island is not included.
   pub fn get_desired_velocity(&self) -> &CS::Coordinate {
     &self.current_desired_move
   }
 
+  /// Returns the desired velocity in the frame of the island that the agent is
+  /// standing on (i.e., rotated into the island's space). The velocity is not
+  /// scaled by the island, so it is still in world units. This is useful for
+  /// agents on moving platforms that are simulated in the platform's frame.
+  /// Returns [`None`] if the agent was not on an island during the last
+  /// update.
+  pub fn get_island_desired_velocity(&self) -> Option<&CS::Coordinate> {
+    self.current_island_desired_move.as_ref()
+  }
+
+  /// Returns the island that the agent was standing on during the last
+  /// update.
+  pub fn get_current_island(&self) -> Option<IslandId> {
+    self.current_island
+  }
+
   /// Returns the state of the agent. This will only be updated if `update` was
   /// called on the associated [`crate::Archipelago`].
   pub fn state(&self) -> AgentState {
@@ -308,6 +501,11 @@ impl<CS: CoordinateSystem> Agent<CS> {
     self.using_animation_link
   }
 
+  /// Gets the distance to offset the corners of the agent's straight path by.
+  pub(crate) fn corner_offset(&self) -> f32 {
+    if self.offset_path_corners { self.radius } else { 0.0 }
+  }
+
   /// Gets the distance at which to consider to reach animation links.
   pub(crate) fn animation_link_reached_distance(&self) -> f32 {
     if let Some(distance) = self.animation_link_reached_distance {
@@ -322,6 +520,111 @@ impl<CS: CoordinateSystem> Agent<CS> {
     }
   }
 
+  /// Determines the speed the agent should move at along `path`, slowing down
+  /// within [`Self::slowing_distance`] of the end of the straight path (the
+  /// target or the next animation link). The arguments match
+  /// [`Self::has_reached_target`].
+  pub(crate) fn arrival_speed(
+    &self,
+    path: &Path,
+    nav_data: &NavigationData<CS>,
+    sampled_point: Vec3,
+    next_waypoint: (PathIndex, StraightPathStep),
+    target_waypoint: (PathIndex, Vec3),
+  ) -> f32 {
+    let Some(slowing_distance) = self.slowing_distance else {
+      return self.desired_speed;
+    };
+    if slowing_distance <= 0.0 {
+      return self.desired_speed;
+    }
+
+    // Measure the straight path distance until either we reach the end of the
+    // straight path or we are far enough that we don't need to slow down.
+    let mut current_waypoint = next_waypoint;
+    let mut current_point = sampled_point;
+    let mut distance = 0.0;
+    loop {
+      let next_point = match current_waypoint.1 {
+        StraightPathStep::Waypoint(point) => point,
+        StraightPathStep::AnimationLink { start_point, .. } => {
+          distance += current_point.distance(start_point);
+          break;
+        }
+      };
+      distance += current_point.distance(next_point);
+      if (current_waypoint.0, next_point) == target_waypoint
+        || distance >= slowing_distance
+      {
+        break;
+      }
+      current_point = next_point;
+      current_waypoint = path.find_next_point_in_straight_path(
+        nav_data,
+        current_waypoint.0,
+        current_point,
+        target_waypoint.0,
+        target_waypoint.1,
+        self.corner_offset(),
+      );
+    }
+
+    self.desired_speed * (distance / slowing_distance).min(1.0)
+  }
+
+  /// Limits `desired_move` (in world space) so that the agent can reach it
+  /// from its current velocity within `delta_time`, according to
+  /// [`Self::max_turn_rate`] and [`Self::max_acceleration`].
+  pub(crate) fn limit_desired_move(
+    &self,
+    desired_move: Vec3,
+    delta_time: f32,
+  ) -> Vec3 {
+    if self.max_acceleration.is_none() && self.max_turn_rate.is_none() {
+      return desired_move;
+    }
+    let velocity = CS::to_landmass(&self.velocity).xy();
+    let desired_move = desired_move.xy();
+    let max_velocity_change = self
+      .max_acceleration
+      .map(|max_acceleration| max_acceleration.max(0.0) * delta_time);
+
+    let heading = self
+      .facing
+      .as_ref()
+      .map_or(velocity, |facing| CS::to_landmass(facing).xy())
+      .normalize_or_zero();
+    let limited_move = match self.max_turn_rate {
+      Some(max_turn_rate) if heading != Vec2::ZERO => {
+        let max_angle = max_turn_rate.max(0.0) * delta_time;
+        let direction = if desired_move == Vec2::ZERO {
+          heading
+        } else {
+          let angle = heading.angle_to(desired_move);
+          Vec2::from_angle(angle.clamp(-max_angle, max_angle)).rotate(heading)
+        };
+        // The agent can only move along its heading, so only its speed along
+        // that heading can be changed.
+        let speed = velocity.dot(direction).max(0.0);
+        let desired_speed = desired_move.length();
+        let speed = match max_velocity_change {
+          None => desired_speed,
+          Some(max_velocity_change) => desired_speed
+            .clamp(speed - max_velocity_change, speed + max_velocity_change),
+        };
+        direction * speed
+      }
+      _ => match max_velocity_change {
+        None => desired_move,
+        Some(max_velocity_change) => {
+          velocity
+            + (desired_move - velocity).clamp_length_max(max_velocity_change)
+        }
+      },
+    };
+    limited_move.extend(0.0)
+  }
+
   /// Determines if this agent has reached its target. `next_waypoint` and
   /// `target_waypoint` are formatted as an index into the `path` and the point
   /// of the waypoint. `next_waypoint` is the next waypoint on the way to the
@@ -347,7 +650,7 @@ impl<CS: CoordinateSystem> Agent<CS> {
           // the target (since there's no straight line walkable path).
           break 'result false;
         };
-        next_waypoint.0 == target_waypoint.0
+        (next_waypoint.0, next_point) == target_waypoint
           && sampled_point.distance_squared(next_point) < distance * distance
       }
       TargetReachedCondition::StraightPathDistance(distance) => 'result: {
@@ -360,12 +663,6 @@ impl<CS: CoordinateSystem> Agent<CS> {
           break 'result false;
         }
 
-        // If the next waypoint is the target point, then we've already
-        // computed the straight line distance and it is below the limit.
-        if next_waypoint.0 == target_waypoint.0 {
-          break 'result true;
-        }
-
         let StraightPathStep::Waypoint(next_point) = next_waypoint.1 else {
           // If the next waypoint isn't just a walk step, we don't consider
           // ourselves within the straight path distance (since an animation
@@ -373,10 +670,16 @@ impl<CS: CoordinateSystem> Agent<CS> {
           break 'result false;
         };
 
+        // If the next waypoint is the target point, then we've already
+        // computed the straight line distance and it is below the limit.
+        if (next_waypoint.0, next_point) == target_waypoint {
+          break 'result true;
+        }
+
         let mut straight_line_distance = sampled_point.distance(next_point);
         let mut current_waypoint = (next_waypoint.0, next_point);
 
-        while current_waypoint.0 != target_waypoint.0
+        while current_waypoint != target_waypoint
           && straight_line_distance < distance
         {
           let next_waypoint = path.find_next_point_in_straight_path(
@@ -385,6 +688,7 @@ impl<CS: CoordinateSystem> Agent<CS> {
             current_waypoint.1,
             target_waypoint.0,
             target_waypoint.1,
+            self.corner_offset(),
           );
 
           let StraightPathStep::Waypoint(next_point) = next_waypoint.1 else {
@@ -428,8 +732,9 @@ pub(crate) fn does_agent_need_repath<CS: CoordinateSystem>(
   target_node: Option<NodeRef>,
   invalidated_off_mesh_links: &HashSet<OffMeshLinkId>,
   invalidated_islands: &HashSet<IslandId>,
+  invalidated_nodes: &HashSet<NodeRef>,
 ) -> RepathResult {
-  if agent.current_target.is_none() {
+  if agent.current_target.is_none() && agent.flow_field.is_none() {
     if agent.current_path.is_some() {
       return RepathResult::ClearPathNoTarget;
     } else {
@@ -451,7 +756,24 @@ pub(crate) fn does_agent_need_repath<CS: CoordinateSystem>(
     Some(current_path) => current_path,
   };
 
-  if !current_path.is_valid(invalidated_off_mesh_links, invalidated_islands) {
+  let target_node = match &agent.partial_target {
+    None => target_node,
+    Some(partial_target) => {
+      if !agent.allow_partial_paths || partial_target.target_node != target_node
+      {
+        // Either partial paths are no longer allowed, or the target has moved
+        // and may now be reachable.
+        return RepathResult::NeedsRepath;
+      }
+      partial_target.node
+    }
+  };
+
+  if !current_path.is_valid(
+    invalidated_off_mesh_links,
+    invalidated_islands,
+    invalidated_nodes,
+  ) {
     return RepathResult::NeedsRepath;
   }
 
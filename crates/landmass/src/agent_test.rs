@@ -1,6 +1,6 @@
 use std::{collections::HashSet, f32::consts::PI, sync::Arc};
 
-use glam::{Vec2, Vec3};
+use glam::{Quat, Vec2, Vec3};
 use googletest::{expect_that, expect_true, matchers::*};
 use slotmap::HopSlotMap;
 
@@ -73,12 +73,16 @@ fn has_reached_target_at_end_node() {
     vertices: vec![],
     polygons: vec![],
     polygon_type_indices: vec![],
+    polygon_flags: None,
     height_mesh: None,
   }
   .validate()
   .expect("nav mesh is valid");
-  let transform =
-    Transform { translation: Vec3::new(2.0, 3.0, 4.0), rotation: PI * 0.85 };
+  let transform = Transform {
+    translation: Vec3::new(2.0, 3.0, 4.0),
+    rotation: Quat::from_rotation_z(PI * 0.85),
+    scale: 1.0,
+  };
   let mut archipelago =
     Archipelago::<XYZ>::new(ArchipelagoOptions::from_agent_radius(0.5));
   let island_id =
@@ -166,13 +170,17 @@ fn long_detour_reaches_target_in_different_ways() {
     ],
     polygons: vec![vec![0, 1, 2], vec![2, 1, 3, 4], vec![4, 3, 5]],
     polygon_type_indices: vec![0, 0, 0],
+    polygon_flags: None,
     height_mesh: None,
   }
   .validate()
   .expect("nav mesh is valid");
 
-  let transform =
-    Transform { translation: Vec3::new(2.0, 4.0, 3.0), rotation: PI * -0.85 };
+  let transform = Transform {
+    translation: Vec3::new(2.0, 4.0, 3.0),
+    rotation: Quat::from_rotation_z(PI * -0.85),
+    scale: 1.0,
+  };
   let mut archipelago =
     Archipelago::<XYZ>::new(ArchipelagoOptions::from_agent_radius(0.5));
   let island_id =
@@ -414,6 +422,7 @@ fn using_animation_link_does_not_reach_target() {
         vec![6, 7, 10, 9],
       ],
       polygon_type_indices: vec![0; 5],
+      polygon_flags: None,
       height_mesh: None,
     }
     .validate()
@@ -549,6 +558,7 @@ fn uses_sampled_point_for_reaching_target() {
     vertices: vec![],
     polygons: vec![],
     polygon_type_indices: vec![],
+    polygon_flags: None,
     height_mesh: None,
   }
   .validate()
@@ -606,7 +616,8 @@ fn nothing_or_clear_path_for_no_target() {
       None,
       None,
       &HashSet::new(),
-      &HashSet::new()
+      &HashSet::new(),
+      &HashSet::new(),
     ),
     RepathResult::DoNothing
   );
@@ -624,7 +635,8 @@ fn nothing_or_clear_path_for_no_target() {
       None,
       None,
       &HashSet::new(),
-      &HashSet::new()
+      &HashSet::new(),
+      &HashSet::new(),
     ),
     RepathResult::ClearPathNoTarget,
   );
@@ -652,6 +664,7 @@ fn clears_path_for_missing_nodes() {
       Some(NodeRef { island_id, polygon_index: 0 }),
       &HashSet::new(),
       &HashSet::new(),
+      &HashSet::new(),
     ),
     RepathResult::ClearPathBadAgent,
   );
@@ -663,6 +676,7 @@ fn clears_path_for_missing_nodes() {
       None,
       &HashSet::new(),
       &HashSet::new(),
+      &HashSet::new(),
     ),
     RepathResult::ClearPathBadTarget,
   );
@@ -692,6 +706,7 @@ fn repaths_for_invalid_path_or_nodes_off_path() {
       Some(NodeRef { island_id, polygon_index: 3 }),
       &HashSet::new(),
       &HashSet::new(),
+      &HashSet::new(),
     ),
     RepathResult::NeedsRepath,
   );
@@ -715,6 +730,7 @@ fn repaths_for_invalid_path_or_nodes_off_path() {
       Some(NodeRef { island_id, polygon_index: 1 }),
       &HashSet::new(),
       &HashSet::from([island_id]),
+      &HashSet::new(),
     ),
     RepathResult::NeedsRepath
   );
@@ -727,6 +743,7 @@ fn repaths_for_invalid_path_or_nodes_off_path() {
       Some(NodeRef { island_id, polygon_index: 1 }),
       &HashSet::new(),
       &HashSet::new(),
+      &HashSet::new(),
     ),
     RepathResult::NeedsRepath,
   );
@@ -739,6 +756,7 @@ fn repaths_for_invalid_path_or_nodes_off_path() {
       Some(NodeRef { island_id, polygon_index: 6 }),
       &HashSet::new(),
       &HashSet::new(),
+      &HashSet::new(),
     ),
     RepathResult::NeedsRepath,
   );
@@ -751,6 +769,7 @@ fn repaths_for_invalid_path_or_nodes_off_path() {
       Some(NodeRef { island_id, polygon_index: 3 }),
       &HashSet::new(),
       &HashSet::new(),
+      &HashSet::new(),
     ),
     RepathResult::NeedsRepath,
   );
@@ -764,6 +783,7 @@ fn repaths_for_invalid_path_or_nodes_off_path() {
       &HashSet::new(),
       // This island is not involved in the path, so the path is still valid.
       &HashSet::from([missing_island_id]),
+      &HashSet::new(),
     ),
     RepathResult::FollowPath(
       PathIndex::from_corridor_index(0, 1),
@@ -797,3 +817,78 @@ fn cannot_start_or_end_using_animation_link_without_reached_animation_link() {
   expect_that!(agent.start_animation_link(), ok(()));
   expect_that!(agent.end_animation_link(), ok(()));
 }
+
+#[test]
+fn limits_desired_move_by_acceleration() {
+  let mut agent = Agent::<XY>::create(
+    /* position= */ Vec2::ZERO,
+    /* velocity= */ Vec2::new(1.0, 0.0),
+    /* radius= */ 0.5,
+    /* desired_speed= */ 1.0,
+    /* max_speed= */ 2.0,
+  );
+  let desired_move = Vec3::new(0.0, 1.0, 0.0);
+
+  // Without limits, the desired move is unchanged.
+  assert_eq!(agent.limit_desired_move(desired_move, 0.5), desired_move);
+
+  agent.max_acceleration = Some(1.0);
+  let limited_move = agent.limit_desired_move(desired_move, 0.5);
+  assert!(
+    limited_move.abs_diff_eq(
+      Vec3::new(1.0, 0.0, 0.0) + Vec3::new(-1.0, 1.0, 0.0).normalize() * 0.5,
+      1e-5
+    ),
+    "limited_move={limited_move}"
+  );
+
+  // Small changes are not limited.
+  let desired_move = Vec3::new(1.25, 0.0, 0.0);
+  assert_eq!(agent.limit_desired_move(desired_move, 0.5), desired_move);
+}
+
+#[test]
+fn limits_desired_move_by_turn_rate() {
+  let mut agent = Agent::<XY>::create(
+    /* position= */ Vec2::ZERO,
+    /* velocity= */ Vec2::new(1.0, 0.0),
+    /* radius= */ 0.5,
+    /* desired_speed= */ 1.0,
+    /* max_speed= */ 2.0,
+  );
+  agent.max_turn_rate = Some(PI * 0.5);
+  let desired_move = Vec3::new(-1.0, 0.0, 0.0);
+
+  // The agent turns (in either direction) at most 45 degrees, and keeps its
+  // desired speed.
+  let limited_move = agent.limit_desired_move(desired_move, 0.5);
+  assert!(
+    limited_move.abs_diff_eq(Vec3::new(1.0, 1.0, 0.0).normalize(), 1e-5)
+      || limited_move.abs_diff_eq(Vec3::new(1.0, -1.0, 0.0).normalize(), 1e-5),
+    "limited_move={limited_move}"
+  );
+
+  let desired_move = Vec3::new(2.0, -1.0, 0.0);
+  assert!(
+    agent.limit_desired_move(desired_move, 0.5).abs_diff_eq(desired_move, 1e-5)
+  );
+
+  // The facing direction overrides the velocity direction, and the agent
+  // only accelerates along its heading.
+  agent.velocity = Vec2::ZERO;
+  agent.facing = Some(Vec2::new(0.0, 1.0));
+  agent.max_acceleration = Some(1.0);
+  let limited_move = agent.limit_desired_move(Vec3::new(2.0, 1.0, 0.0), 0.5);
+  assert!(
+    limited_move.abs_diff_eq(Vec3::new(1.0, 1.0, 0.0).normalize() * 0.5, 1e-5),
+    "limited_move={limited_move}"
+  );
+
+  // Without a heading, the agent can start moving in any direction.
+  agent.facing = None;
+  let limited_move = agent.limit_desired_move(Vec3::new(-2.0, 0.0, 0.0), 0.5);
+  assert!(
+    limited_move.abs_diff_eq(Vec3::new(-0.5, 0.0, 0.0), 1e-5),
+    "limited_move={limited_move}"
+  );
+}
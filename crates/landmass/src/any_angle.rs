@@ -0,0 +1,738 @@
+use std::{
+  collections::{HashMap, HashSet},
+  hash::{Hash, Hasher},
+};
+
+use glam::{Vec2, Vec3, Vec3Swizzles};
+
+use crate::{
+  CoordinateSystem, NavigationData, PathCostFilter,
+  agent::{PermittedAnimationLinks, PolygonFlagFilter},
+  astar::AStarProblem,
+  island::IslandId,
+  nav_data::{KindedOffMeshLink, NodeRef, OffMeshLinkId},
+  pathfinding::PathStep,
+  util::FloatOrd,
+};
+
+/// The distance below which points are considered to be the same.
+const EPSILON: f32 = 1e-5;
+
+/// An A* problem that finds the shortest (Euclidean) path from a point to the
+/// nearest of several end points, rather than routing through the midpoints of
+/// edges. This is based on
+/// [Polyanya](https://ojs.aaai.org/index.php/ICAPS/article/view/13883).
+///
+/// Search nodes are made up of a "root" (the last point the path turns at) and
+/// the region of a node that is visible from that root. The region is either
+/// the entire node, or the part of the node that can be seen through an
+/// interval of the portal that we entered the node from. The path only turns
+/// at the ends of portals, so the result is the shortest path through the
+/// corridor that the search finds.
+///
+/// Since the shortest path is purely geometric, type index costs are only used
+/// to exclude nodes with infinite cost. Similarly, node cost multipliers from
+/// the cost filter are only used to exclude nodes, but edge costs and animation
+/// link costs are still applied. Animation links are taken from the point on
+/// the link closest to the current root.
+pub(crate) struct AnyAnglePathProblem<'a, CS: CoordinateSystem> {
+  /// The navigation data to search.
+  pub(crate) nav_data: &'a NavigationData<CS>,
+  /// The node the agent is starting from.
+  pub(crate) start_node: NodeRef,
+  /// The point the agent is starting from.
+  pub(crate) start_point: Vec3,
+  /// The nodes the targets are in, and the target points in those nodes.
+  /// Reaching any of the targets solves the problem.
+  pub(crate) ends: Vec<(NodeRef, Vec3)>,
+  /// Replacement costs for the `nav_data.type_index_to_cost`.
+  pub(crate) override_type_index_to_cost: &'a HashMap<usize, f32>,
+  /// The set of permitted animation links for the agent.
+  pub(crate) permitted_animation_links: PermittedAnimationLinks,
+  /// The polygons that the path may travel through.
+  pub(crate) flag_filter: PolygonFlagFilter,
+  /// The radius of the agent. Portals and off mesh links narrower than the
+  /// agent are not taken.
+  pub(crate) agent_radius: f32,
+  /// The filter that customizes costs further, if any.
+  pub(crate) cost_filter: Option<&'a dyn PathCostFilter>,
+  /// The islands that the path may travel through. If [`None`], all islands
+  /// are permitted.
+  pub(crate) permitted_islands: Option<&'a HashSet<IslandId>>,
+}
+
+/// An action taken in the path.
+#[derive(Clone, Copy)]
+pub(crate) enum AnyAngleStep {
+  /// Move into another node.
+  Portal(PathStep),
+  /// Turn around a corner in the current node. This does not change the node
+  /// that the path is in.
+  TurnAtCorner,
+}
+
+/// A point that can be used in a hashed state. Points are only considered
+/// equal if they are exactly equal.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct HashablePoint(Vec3);
+
+impl PartialEq for HashablePoint {
+  fn eq(&self, other: &Self) -> bool {
+    self.0.to_array().map(f32::to_bits) == other.0.to_array().map(f32::to_bits)
+  }
+}
+
+impl Eq for HashablePoint {}
+
+impl Hash for HashablePoint {
+  fn hash<H: Hasher>(&self, state: &mut H) {
+    self.0.to_array().map(f32::to_bits).hash(state);
+  }
+}
+
+/// The way a node was entered.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub(crate) enum Entry {
+  /// The node was entered through the edge with this index.
+  Edge(usize),
+  /// The node was entered through this off mesh link.
+  OffMeshLink(OffMeshLinkId),
+}
+
+/// A node in the search.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub(crate) enum AnyAnglePathNode {
+  /// The end of the path.
+  End,
+  /// The entire node is visible from the root.
+  Node {
+    /// The point the path last turned at.
+    root: HashablePoint,
+    /// The node that we are now in.
+    node: NodeRef,
+  },
+  /// The part of the node that is visible from the root through an interval
+  /// on the portal the node was entered from.
+  Interval {
+    /// The point the path last turned at.
+    root: HashablePoint,
+    /// The node that we are now in.
+    node: NodeRef,
+    /// How the node was entered. The interval is on this portal.
+    entry: Entry,
+    /// The left end of the interval, as seen from the root.
+    left: HashablePoint,
+    /// The right end of the interval, as seen from the root.
+    right: HashablePoint,
+    /// Whether [`Self::Interval::left`] is also the end of the portal, meaning
+    /// the path could turn around it.
+    left_is_corner: bool,
+    /// Whether [`Self::Interval::right`] is also the end of the portal,
+    /// meaning the path could turn around it.
+    right_is_corner: bool,
+  },
+}
+
+/// A way to leave a node.
+struct Exit {
+  /// The step to take to leave the node.
+  step: PathStep,
+  /// The portal that the exit occupies in world space, in left-to-right order
+  /// as seen from inside the node.
+  portal: (Vec3, Vec3),
+  /// Where the exit leads.
+  destination: ExitDestination,
+}
+
+/// Where an [`Exit`] leads.
+enum ExitDestination {
+  /// The exit leads directly into `node`, sharing the same portal, costing
+  /// `cost` to cross.
+  Portal { node: NodeRef, entry: Entry, cost: f32 },
+  /// The exit is an animation link that leads to `portal` in `node`, costing
+  /// `cost`.
+  AnimationLink { node: NodeRef, portal: (Vec3, Vec3), cost: f32 },
+}
+
+impl<CS: CoordinateSystem> AnyAnglePathProblem<'_, CS> {
+  /// Determines the cost of `type_index`.
+  fn type_index_to_cost(&self, type_index: usize) -> f32 {
+    self.override_type_index_to_cost.get(&type_index).copied().unwrap_or_else(
+      || self.nav_data.get_type_index_cost(type_index).unwrap_or(1.0),
+    )
+  }
+
+  /// Determines whether `node_ref` (with `type_index`) can be travelled
+  /// through.
+  fn is_node_permitted(&self, node_ref: NodeRef, type_index: usize) -> bool {
+    let island = self.nav_data.get_island(node_ref.island_id).unwrap();
+    self
+      .flag_filter
+      .is_permitted(island.nav_mesh.polygons[node_ref.polygon_index].flags)
+      && self
+        .nav_data
+        .modified_node_cost(node_ref, type_index, |type_index| {
+          self.type_index_to_cost(type_index)
+        })
+        .is_finite()
+      && self.cost_filter.is_none_or(|cost_filter| {
+        cost_filter.node_cost_multiplier(node_ref, type_index).is_some()
+      })
+  }
+
+  /// Determines the extra cost of crossing from `from` into `to`, or [`None`]
+  /// if the crossing is excluded.
+  fn edge_cost(&self, from: NodeRef, to: NodeRef) -> Option<f32> {
+    match self.cost_filter {
+      None => Some(0.0),
+      Some(cost_filter) => cost_filter.edge_cost(from, to),
+    }
+  }
+
+  /// Determines whether `exit` (a portal or point in world space) can be
+  /// reached from `entry` within `node_ref`. If obstacles have split the node,
+  /// only the piece of the node that we entered can be reached.
+  fn is_reachable(
+    &self,
+    node_ref: NodeRef,
+    entry: (Vec2, Vec2),
+    exit: (Vec2, Vec2),
+  ) -> bool {
+    self
+      .nav_data
+      .modified_nodes
+      .get(&node_ref)
+      .filter(|modified_node| modified_node.is_split())
+      .is_none_or(|modified_node| modified_node.are_connected(entry, exit))
+  }
+
+  /// Finds all the ways to leave `node_ref` that can be reached from `entry`
+  /// (the portal or point in world space where the node was entered), except
+  /// for `ignore_entry`.
+  fn exits(
+    &self,
+    node_ref: NodeRef,
+    entry: (Vec2, Vec2),
+    ignore_entry: Option<Entry>,
+  ) -> Vec<Exit> {
+    let island = self.nav_data.get_island(node_ref.island_id).unwrap();
+    let polygon = &island.nav_mesh.polygons[node_ref.polygon_index];
+
+    let mut exits = vec![];
+    for (edge_index, connectivity) in polygon.connectivity.iter().enumerate() {
+      let Some(connectivity) = connectivity else {
+        continue;
+      };
+      if ignore_entry == Some(Entry::Edge(edge_index)) {
+        continue;
+      }
+      let target_node = NodeRef {
+        island_id: node_ref.island_id,
+        polygon_index: connectivity.polygon_index,
+      };
+      if !self.is_node_permitted(
+        target_node,
+        island.nav_mesh.polygons[connectivity.polygon_index].type_index,
+      ) {
+        continue;
+      }
+      let Some(cost) = self.edge_cost(node_ref, target_node) else {
+        continue;
+      };
+      if connectivity.width * island.transform.scale < self.agent_radius * 2.0 {
+        continue;
+      }
+      let (left, right) = polygon.get_edge_indices(edge_index);
+      let portal = (
+        island.transform.apply(island.nav_mesh.vertices[left]),
+        island.transform.apply(island.nav_mesh.vertices[right]),
+      );
+      if self.nav_data.is_crossing_carved(
+        node_ref,
+        target_node,
+        (portal.0.xy(), portal.1.xy()),
+      ) || !self.is_reachable(
+        node_ref,
+        entry,
+        (portal.0.xy(), portal.1.xy()),
+      ) {
+        continue;
+      }
+      exits.push(Exit {
+        step: PathStep::NodeConnection(edge_index),
+        portal,
+        destination: ExitDestination::Portal {
+          node: target_node,
+          entry: Entry::Edge(connectivity.reverse_edge),
+          cost,
+        },
+      });
+    }
+
+    let Some(off_mesh_links) =
+      self.nav_data.node_to_off_mesh_link_ids.get(&node_ref)
+    else {
+      return exits;
+    };
+    for &link_id in off_mesh_links {
+      if ignore_entry == Some(Entry::OffMeshLink(link_id)) {
+        continue;
+      }
+      let link = self.nav_data.off_mesh_links.get(link_id).unwrap();
+      if !self.is_reachable(
+        node_ref,
+        entry,
+        (link.portal.0.xy(), link.portal.1.xy()),
+      ) {
+        continue;
+      }
+      if let Some(permitted_islands) = self.permitted_islands
+        && !permitted_islands.contains(&link.destination_node.island_id)
+      {
+        continue;
+      }
+      if !self
+        .is_node_permitted(link.destination_node, link.destination_type_index)
+      {
+        continue;
+      }
+      if self.agent_radius > 0.0
+        && self.nav_data.off_mesh_link_width(node_ref, link)
+          < self.agent_radius * 2.0
+      {
+        continue;
+      }
+      let destination = match &link.kinded {
+        KindedOffMeshLink::BoundaryLink { reverse_link } => {
+          if self.nav_data.is_crossing_carved(
+            node_ref,
+            link.destination_node,
+            (link.portal.0.xy(), link.portal.1.xy()),
+          ) {
+            continue;
+          }
+          let Some(cost) = self.edge_cost(node_ref, link.destination_node)
+          else {
+            continue;
+          };
+          ExitDestination::Portal {
+            node: link.destination_node,
+            entry: Entry::OffMeshLink(*reverse_link),
+            cost,
+          }
+        }
+        KindedOffMeshLink::AnimationLink {
+          destination_portal,
+          cost,
+          kind,
+          animation_link,
+        } => {
+          if !self.permitted_animation_links.is_permitted(*kind) {
+            continue;
+          }
+          let cost = match self.cost_filter {
+            None => *cost,
+            Some(cost_filter) => {
+              let Some(cost) = cost_filter.animation_link_cost(
+                *animation_link,
+                *kind,
+                node_ref,
+                link.destination_node,
+                *cost,
+              ) else {
+                continue;
+              };
+              cost
+            }
+          };
+          ExitDestination::AnimationLink {
+            node: link.destination_node,
+            portal: *destination_portal,
+            cost,
+          }
+        }
+      };
+      exits.push(Exit {
+        step: PathStep::OffMeshLink(link_id),
+        portal: link.portal,
+        destination,
+      });
+    }
+    exits
+  }
+
+  /// Creates the successor for taking `exit` from `root`, where only the
+  /// fraction `interval` of the exit's portal is visible. The fractions go
+  /// from the right end of the portal to the left end.
+  fn take_exit(
+    &self,
+    root: Vec3,
+    exit: &Exit,
+    interval: (f32, f32),
+  ) -> (f32, AnyAngleStep, AnyAnglePathNode) {
+    let (left, right) = exit.portal;
+    let visible_right = right.lerp(left, interval.0);
+    let visible_left = right.lerp(left, interval.1);
+    match exit.destination {
+      ExitDestination::Portal { node, entry, cost } => (
+        cost,
+        AnyAngleStep::Portal(exit.step),
+        AnyAnglePathNode::Interval {
+          root: HashablePoint(root),
+          node,
+          entry,
+          left: HashablePoint(visible_left),
+          right: HashablePoint(visible_right),
+          left_is_corner: interval.1 >= 1.0 - EPSILON,
+          right_is_corner: interval.0 <= EPSILON,
+        },
+      ),
+      ExitDestination::AnimationLink { node, portal, cost } => {
+        // Jump from the closest point on the animation link.
+        let fraction =
+          project_fraction(root.xy(), visible_right.xy(), visible_left.xy());
+        let start_point = visible_right.lerp(visible_left, fraction);
+        let fraction = interval.0 + (interval.1 - interval.0) * fraction;
+        let end_point = portal.1.lerp(portal.0, fraction);
+        (
+          root.distance(start_point) + cost,
+          AnyAngleStep::Portal(exit.step),
+          AnyAnglePathNode::Node { root: HashablePoint(end_point), node },
+        )
+      }
+    }
+  }
+
+  /// Creates the successors for when `root` can see all of `node`.
+  fn full_node_successors(
+    &self,
+    root: Vec3,
+    node: NodeRef,
+    ignore_entry: Option<Entry>,
+  ) -> Vec<(f32, AnyAngleStep, AnyAnglePathNode)> {
+    let entry = (root.xy(), root.xy());
+    let mut successors =
+      self.end_successors(node, entry, |end_point| root.distance(end_point));
+    if successors.len() == self.ends.len() {
+      // Every end is in this node, so there is no need to leave it.
+      return successors;
+    }
+
+    successors.extend(
+      self
+        .exits(node, entry, ignore_entry)
+        .iter()
+        .filter(|exit| {
+          // If the root is in line with a portal, the portal can only be
+          // entered if the root is one of its ends. Otherwise, there is no
+          // region that can be seen through the portal.
+          matches!(exit.destination, ExitDestination::AnimationLink { .. })
+            || !is_degenerate(root, exit.portal.0, exit.portal.1)
+            || is_same_point(root, exit.portal.0)
+            || is_same_point(root, exit.portal.1)
+        })
+        .map(|exit| self.take_exit(root, exit, (0.0, 1.0))),
+    );
+    successors
+  }
+
+  /// Creates the successors that go to the ends in `node` that can be reached
+  /// from `entry`, where reaching each end point costs `cost_to_end`.
+  fn end_successors(
+    &self,
+    node: NodeRef,
+    entry: (Vec2, Vec2),
+    cost_to_end: impl Fn(Vec3) -> f32,
+  ) -> Vec<(f32, AnyAngleStep, AnyAnglePathNode)> {
+    self
+      .ends
+      .iter()
+      .enumerate()
+      .filter(|(_, (end_node, _))| *end_node == node)
+      .filter(|(_, (_, end_point))| {
+        self.is_reachable(node, entry, (end_point.xy(), end_point.xy()))
+      })
+      .map(|(end_index, &(_, end_point))| {
+        (
+          cost_to_end(end_point),
+          AnyAngleStep::Portal(PathStep::GoToEnd(end_index)),
+          AnyAnglePathNode::End,
+        )
+      })
+      .collect()
+  }
+
+  /// Estimates the cost from `state` to `end_point`, without overestimating.
+  fn heuristic_to_end(&self, state: &AnyAnglePathNode, end_point: Vec3) -> f32 {
+    // The heuristic is computed in 2D to ensure it never overestimates the
+    // (3D) cost.
+    let end_point = end_point.xy();
+    match *state {
+      AnyAnglePathNode::End => 0.0,
+      AnyAnglePathNode::Node { root, .. } => root.0.xy().distance(end_point),
+      AnyAnglePathNode::Interval { root, left, right, .. } => {
+        let (root, left, right) = (root.0.xy(), left.0.xy(), right.0.xy());
+        let edge = left - right;
+        let root_side = edge.perp_dot(root - right);
+        let end_side = edge.perp_dot(end_point - right);
+        if root_side.abs() < EPSILON * edge.length() {
+          return root.distance(end_point);
+        }
+        // The path must cross the interval, so if the end point is on the same
+        // side as the root, the path will need to come back. Reflecting the
+        // end point across the interval accounts for this.
+        let end_point = if root_side.signum() == end_side.signum() {
+          let normal = edge.perp().normalize();
+          end_point - 2.0 * normal.dot(end_point - right) * normal
+        } else {
+          end_point
+        };
+        if segments_intersect(root, end_point, right, left) {
+          root.distance(end_point)
+        } else {
+          (root.distance(left) + left.distance(end_point))
+            .min(root.distance(right) + right.distance(end_point))
+        }
+      }
+    }
+  }
+}
+
+impl<CS: CoordinateSystem> AStarProblem for AnyAnglePathProblem<'_, CS> {
+  type ActionType = AnyAngleStep;
+
+  type StateType = AnyAnglePathNode;
+
+  fn initial_state(&self) -> Self::StateType {
+    AnyAnglePathNode::Node {
+      root: HashablePoint(self.start_point),
+      node: self.start_node,
+    }
+  }
+
+  fn successors(
+    &self,
+    state: &Self::StateType,
+  ) -> Vec<(f32, Self::ActionType, Self::StateType)> {
+    match *state {
+      AnyAnglePathNode::End => {
+        unreachable!("we never need the successors of the goal node")
+      }
+      AnyAnglePathNode::Node { root, node } => {
+        self.full_node_successors(root.0, node, None)
+      }
+      AnyAnglePathNode::Interval {
+        root,
+        node,
+        entry,
+        left,
+        right,
+        left_is_corner,
+        right_is_corner,
+      } => {
+        let (root, left, right) = (root.0, left.0, right.0);
+        if is_same_point(root, left) || is_same_point(root, right) {
+          // The root is on the boundary of the node, so it can see the whole
+          // node.
+          return self.full_node_successors(root, node, Some(entry));
+        }
+        if is_degenerate(root, left, right) {
+          // Nothing can be seen through an interval that is in line with the
+          // root.
+          return vec![];
+        }
+
+        let entry_portal = (left.xy(), right.xy());
+        let mut successors =
+          self.end_successors(node, entry_portal, |end_point| {
+            shortest_path_through_interval(root, left, right, end_point)
+          });
+        if successors.len() == self.ends.len() {
+          // Every end is in this node, so there is no need to leave it.
+          return successors;
+        }
+
+        successors.extend(
+          self.exits(node, entry_portal, Some(entry)).iter().filter_map(
+            |exit| {
+              if matches!(exit.destination, ExitDestination::Portal { .. })
+                && is_degenerate(root, exit.portal.0, exit.portal.1)
+              {
+                return None;
+              }
+              let interval = clip_segment_to_cone(
+                root.xy(),
+                left.xy(),
+                right.xy(),
+                exit.portal.1.xy(),
+                exit.portal.0.xy(),
+              )?;
+              Some(self.take_exit(root, exit, interval))
+            },
+          ),
+        );
+
+        // Any part of the node that can't be seen from the root can be seen by
+        // turning around the corners of the portal.
+        for (corner, is_corner) in
+          [(left, left_is_corner), (right, right_is_corner)]
+        {
+          if !is_corner {
+            continue;
+          }
+          successors.push((
+            root.distance(corner),
+            AnyAngleStep::TurnAtCorner,
+            AnyAnglePathNode::Node { root: HashablePoint(corner), node },
+          ));
+        }
+        successors
+      }
+    }
+  }
+
+  fn heuristic(&self, state: &Self::StateType) -> f32 {
+    self
+      .ends
+      .iter()
+      .map(|&(_, end_point)| FloatOrd(self.heuristic_to_end(state, end_point)))
+      .min()
+      .map_or(0.0, |FloatOrd(heuristic)| heuristic)
+  }
+
+  fn is_goal_state(&self, state: &Self::StateType) -> bool {
+    matches!(state, AnyAnglePathNode::End)
+  }
+}
+
+/// Finds the length of the shortest path from `root` to `end_point` that passes
+/// through the interval from `left` to `right`.
+fn shortest_path_through_interval(
+  root: Vec3,
+  left: Vec3,
+  right: Vec3,
+  end_point: Vec3,
+) -> f32 {
+  let point = match line_intersection_fraction(
+    right.xy(),
+    left.xy(),
+    root.xy(),
+    end_point.xy(),
+  ) {
+    Some(fraction) if (0.0..=1.0).contains(&fraction) => {
+      right.lerp(left, fraction)
+    }
+    _ => {
+      if root.distance(left) + left.distance(end_point)
+        < root.distance(right) + right.distance(end_point)
+      {
+        left
+      } else {
+        right
+      }
+    }
+  };
+  root.distance(point) + point.distance(end_point)
+}
+
+/// Finds the fraction along the line from `start` to `end` where it intersects
+/// the line through `a` and `b`. Returns [`None`] if the lines are parallel.
+fn line_intersection_fraction(
+  start: Vec2,
+  end: Vec2,
+  a: Vec2,
+  b: Vec2,
+) -> Option<f32> {
+  let direction = b - a;
+  let denominator = direction.perp_dot(end - start);
+  if denominator.abs() < 1e-12 {
+    return None;
+  }
+  Some(direction.perp_dot(a - start) / denominator)
+}
+
+/// Determines whether the segment from `a_start` to `a_end` intersects the
+/// segment from `b_start` to `b_end`.
+fn segments_intersect(
+  a_start: Vec2,
+  a_end: Vec2,
+  b_start: Vec2,
+  b_end: Vec2,
+) -> bool {
+  let Some(b_fraction) =
+    line_intersection_fraction(b_start, b_end, a_start, a_end)
+  else {
+    return false;
+  };
+  let Some(a_fraction) =
+    line_intersection_fraction(a_start, a_end, b_start, b_end)
+  else {
+    return false;
+  };
+  (0.0..=1.0).contains(&a_fraction) && (0.0..=1.0).contains(&b_fraction)
+}
+
+/// Finds the fraction along the segment from `start` to `end` of the point
+/// closest to `point`.
+fn project_fraction(point: Vec2, start: Vec2, end: Vec2) -> f32 {
+  let direction = end - start;
+  let length_squared = direction.length_squared();
+  if length_squared == 0.0 {
+    return 0.0;
+  }
+  ((point - start).dot(direction) / length_squared).clamp(0.0, 1.0)
+}
+
+/// Determines whether `a` and `b` are the same point (in 2D).
+fn is_same_point(a: Vec3, b: Vec3) -> bool {
+  a.xy().distance_squared(b.xy()) < EPSILON * EPSILON
+}
+
+/// Determines whether `root` is in line with the segment from `left` to
+/// `right` (in 2D).
+fn is_degenerate(root: Vec3, left: Vec3, right: Vec3) -> bool {
+  let edge = left.xy() - right.xy();
+  edge.perp_dot(root.xy() - right.xy()).abs() <= EPSILON * edge.length()
+}
+
+/// Clips the segment from `start` to `end` to the cone starting at `root` and
+/// bounded by the rays through `cone_left` and `cone_right`. Returns the
+/// fractions along the segment of the part of the segment inside the cone.
+fn clip_segment_to_cone(
+  root: Vec2,
+  cone_left: Vec2,
+  cone_right: Vec2,
+  start: Vec2,
+  end: Vec2,
+) -> Option<(f32, f32)> {
+  let mut interval = (0.0f32, 1.0f32);
+  // Points in the cone are to the left of the right ray, and to the right of
+  // the left ray.
+  for (ray, sign) in [(cone_right - root, 1.0), (cone_left - root, -1.0)] {
+    let ray = ray.normalize();
+    // The signed distance from the ray at `start + t * (end - start)` is
+    // `offset + t * slope`.
+    let offset = sign * ray.perp_dot(start - root);
+    let slope = sign * ray.perp_dot(end - start);
+    if slope.abs() < 1e-12 {
+      if offset < -EPSILON {
+        return None;
+      }
+      continue;
+    }
+    let t = -offset / slope;
+    if slope > 0.0 {
+      interval.0 = interval.0.max(t);
+    } else {
+      interval.1 = interval.1.min(t);
+    }
+  }
+  if (interval.1 - interval.0) * start.distance(end) <= EPSILON {
+    return None;
+  }
+  Some(interval)
+}
+
+#[cfg(test)]
+#[path = "any_angle_test.rs"]
+mod test;
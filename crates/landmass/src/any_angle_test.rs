@@ -0,0 +1,387 @@
+use std::{collections::HashMap, sync::Arc};
+
+use glam::Vec2;
+use googletest::{expect_that, matchers::*};
+
+use crate::{
+  Archipelago, ArchipelagoOptions, FindPathError, FromAgentRadius, Island,
+  NavigationMesh, NodeRef, Obstacle, PathCostFilter, PathQuery, PathStep,
+  PathfindingMode, Transform, ValidNavigationMesh, coords::XY,
+  link::AnimationLink,
+};
+
+// A ring of nodes around a hole. The left side of the ring is made of long
+// nodes whose edge midpoints are far from the hole, while the right side is
+// made of short nodes whose edge midpoints hug the hole.
+//
+//  +-+---------+-+
+//  | |    M2   | |
+//  | +------+--+-+
+//  |L|      |R |
+//  | +------+--+-+
+//  | |    M0   | |
+//  | |         +-+
+//  | |         |
+//  +-+---------+
+fn ring_nav_mesh() -> Arc<ValidNavigationMesh<XY>> {
+  Arc::new(
+    NavigationMesh {
+      vertices: vec![
+        Vec2::new(0.0, -20.0),
+        Vec2::new(1.0, -20.0),
+        Vec2::new(9.0, -20.0),
+        Vec2::new(9.0, 3.0),
+        Vec2::new(10.0, 3.0),
+        Vec2::new(9.0, 4.0),
+        Vec2::new(10.0, 4.0),
+        Vec2::new(1.0, 4.0),
+        Vec2::new(0.0, 4.0),
+        Vec2::new(0.0, 6.0),
+        Vec2::new(1.0, 6.0),
+        Vec2::new(9.0, 6.0),
+        Vec2::new(10.0, 6.0),
+        Vec2::new(9.0, 7.0),
+        Vec2::new(10.0, 7.0),
+        Vec2::new(0.0, 10.0),
+        Vec2::new(1.0, 10.0),
+        Vec2::new(9.0, 10.0),
+      ],
+      polygons: vec![
+        vec![0, 1, 7, 8],
+        vec![1, 2, 3, 5, 7],
+        vec![3, 4, 6, 5],
+        vec![8, 7, 10, 9],
+        vec![5, 6, 12, 11],
+        vec![9, 10, 16, 15],
+        vec![10, 11, 13, 17, 16],
+        vec![11, 12, 14, 13],
+      ],
+      // The node joining the left side of the ring has its own type index so
+      // it can be made unwalkable.
+      polygon_type_indices: vec![0, 0, 0, 1, 0, 0, 0, 0],
+      polygon_flags: None,
+      height_mesh: None,
+    }
+    .validate()
+    .expect("nav mesh is valid"),
+  )
+}
+
+fn find_path_with_mode(
+  nav_mesh: Arc<ValidNavigationMesh<XY>>,
+  start_point: Vec2,
+  end_point: Vec2,
+  pathfinding_mode: PathfindingMode,
+) -> Vec<PathStep<XY>> {
+  let mut archipelago = Archipelago::<XY>::new(ArchipelagoOptions {
+    pathfinding_mode,
+    ..ArchipelagoOptions::from_agent_radius(0.5)
+  });
+  archipelago.add_island(Island::new(Transform::default(), nav_mesh));
+  archipelago.update(1.0);
+
+  let start_point = archipelago.sample_point(start_point, &0.1).unwrap();
+  let end_point = archipelago.sample_point(end_point, &0.1).unwrap();
+  archipelago
+    .find_path(&start_point, &end_point, &PathQuery::default())
+    .unwrap()
+}
+
+#[googletest::test]
+fn edge_midpoints_takes_longer_route_around_hole() {
+  let start_point = Vec2::new(2.0, 3.5);
+  let end_point = Vec2::new(2.0, 6.5);
+  let path = find_path_with_mode(
+    ring_nav_mesh(),
+    start_point,
+    end_point,
+    PathfindingMode::EdgeMidpoints,
+  );
+
+  // This is the motivating case for any-angle pathfinding: the midpoints of the
+  // right side make it look cheaper, even though it is much longer.
+  expect_that!(
+    path,
+    elements_are!(
+      &PathStep::Waypoint(start_point),
+      &PathStep::Waypoint(Vec2::new(9.0, 4.0)),
+      &PathStep::Waypoint(Vec2::new(9.0, 6.0)),
+      &PathStep::Waypoint(end_point),
+    )
+  );
+}
+
+#[googletest::test]
+fn any_angle_takes_shortest_route_around_hole() {
+  let start_point = Vec2::new(2.0, 3.5);
+  let end_point = Vec2::new(2.0, 6.5);
+  let path = find_path_with_mode(
+    ring_nav_mesh(),
+    start_point,
+    end_point,
+    PathfindingMode::AnyAngle,
+  );
+
+  expect_that!(
+    path,
+    elements_are!(
+      &PathStep::Waypoint(start_point),
+      &PathStep::Waypoint(Vec2::new(1.0, 4.0)),
+      &PathStep::Waypoint(Vec2::new(1.0, 6.0)),
+      &PathStep::Waypoint(end_point),
+    )
+  );
+}
+
+#[googletest::test]
+fn any_angle_walks_straight_through_many_nodes() {
+  let start_point = Vec2::new(0.5, -19.0);
+  let end_point = Vec2::new(0.5, 9.5);
+  let path = find_path_with_mode(
+    ring_nav_mesh(),
+    start_point,
+    end_point,
+    PathfindingMode::AnyAngle,
+  );
+
+  expect_that!(
+    path,
+    elements_are!(
+      &PathStep::Waypoint(start_point),
+      &PathStep::Waypoint(end_point),
+    )
+  );
+}
+
+#[googletest::test]
+fn any_angle_in_same_node() {
+  let start_point = Vec2::new(2.0, -10.0);
+  let end_point = Vec2::new(8.0, 3.0);
+  let path = find_path_with_mode(
+    ring_nav_mesh(),
+    start_point,
+    end_point,
+    PathfindingMode::AnyAngle,
+  );
+
+  expect_that!(
+    path,
+    elements_are!(
+      &PathStep::Waypoint(start_point),
+      &PathStep::Waypoint(end_point),
+    )
+  );
+}
+
+#[googletest::test]
+fn any_angle_ignores_infinite_cost_nodes() {
+  let mut archipelago = Archipelago::<XY>::new(ArchipelagoOptions {
+    pathfinding_mode: PathfindingMode::AnyAngle,
+    ..ArchipelagoOptions::from_agent_radius(0.5)
+  });
+  archipelago.add_island(Island::new(Transform::default(), ring_nav_mesh()));
+  archipelago.update(1.0);
+
+  let start_point = Vec2::new(2.0, 3.5);
+  let end_point = Vec2::new(2.0, 6.5);
+  let start_sampled_point =
+    archipelago.sample_point(start_point, &0.1).unwrap();
+  let end_sampled_point = archipelago.sample_point(end_point, &0.1).unwrap();
+  let path = archipelago
+    .find_path(
+      &start_sampled_point,
+      &end_sampled_point,
+      &PathQuery {
+        // Make the left side of the ring unwalkable.
+        override_type_index_costs: HashMap::from([(1, f32::INFINITY)]),
+        ..Default::default()
+      },
+    )
+    .unwrap();
+
+  expect_that!(
+    path,
+    elements_are!(
+      &PathStep::Waypoint(start_point),
+      &PathStep::Waypoint(Vec2::new(9.0, 4.0)),
+      &PathStep::Waypoint(Vec2::new(9.0, 6.0)),
+      &PathStep::Waypoint(end_point),
+    )
+  );
+}
+
+#[googletest::test]
+fn any_angle_does_not_cross_obstacle_splitting_node() {
+  // +----+----+
+  // | |O|    |
+  // |S|O|   E|
+  // | |O|    |
+  // +----+----+
+  let nav_mesh = Arc::new(
+    NavigationMesh {
+      vertices: vec![
+        Vec2::new(0.0, 0.0),
+        Vec2::new(5.0, 0.0),
+        Vec2::new(10.0, 0.0),
+        Vec2::new(10.0, 10.0),
+        Vec2::new(5.0, 10.0),
+        Vec2::new(0.0, 10.0),
+      ],
+      polygons: vec![vec![0, 1, 4, 5], vec![1, 2, 3, 4]],
+      polygon_type_indices: vec![0, 0],
+      polygon_flags: None,
+      height_mesh: None,
+    }
+    .validate()
+    .expect("nav mesh is valid"),
+  );
+
+  for pathfinding_mode in
+    [PathfindingMode::EdgeMidpoints, PathfindingMode::AnyAngle]
+  {
+    let mut archipelago = Archipelago::<XY>::new(ArchipelagoOptions {
+      pathfinding_mode,
+      ..ArchipelagoOptions::from_agent_radius(0.5)
+    });
+    archipelago
+      .add_island(Island::new(Transform::default(), Arc::clone(&nav_mesh)));
+    // The obstacle cuts the left node in two.
+    archipelago
+      .add_obstacle(Obstacle::Aabb {
+        min: Vec2::new(2.0, -1.0),
+        max: Vec2::new(3.0, 11.0),
+      })
+      .unwrap();
+    archipelago.update(1.0);
+
+    let start_point =
+      archipelago.sample_point(Vec2::new(1.0, 5.0), &0.1).unwrap();
+    let end_point =
+      archipelago.sample_point(Vec2::new(8.0, 5.0), &0.1).unwrap();
+    expect_that!(
+      archipelago.find_path(&start_point, &end_point, &PathQuery::default()),
+      err(eq(&FindPathError::NoPathFound)),
+      "{pathfinding_mode:?}"
+    );
+
+    // The piece on the other side of the obstacle can still reach the end.
+    let start_point =
+      archipelago.sample_point(Vec2::new(4.0, 5.0), &0.1).unwrap();
+    expect_that!(
+      archipelago.find_path(&start_point, &end_point, &PathQuery::default()),
+      ok(anything()),
+      "{pathfinding_mode:?}"
+    );
+  }
+}
+
+#[googletest::test]
+fn any_angle_ignores_edges_excluded_by_cost_filter() {
+  let mut archipelago = Archipelago::<XY>::new(ArchipelagoOptions {
+    pathfinding_mode: PathfindingMode::AnyAngle,
+    ..ArchipelagoOptions::from_agent_radius(0.5)
+  });
+  let island_id =
+    archipelago.add_island(Island::new(Transform::default(), ring_nav_mesh()));
+  archipelago.update(1.0);
+
+  // Prevents entering the left side of the ring from the bottom.
+  struct ExcludeEdge(NodeRef, NodeRef);
+  impl PathCostFilter for ExcludeEdge {
+    fn edge_cost(&self, from: NodeRef, to: NodeRef) -> Option<f32> {
+      if (from, to) == (self.0, self.1) { None } else { Some(0.0) }
+    }
+  }
+
+  let start_point = Vec2::new(2.0, 3.5);
+  let end_point = Vec2::new(2.0, 6.5);
+  let start_sampled_point =
+    archipelago.sample_point(start_point, &0.1).unwrap();
+  let end_sampled_point = archipelago.sample_point(end_point, &0.1).unwrap();
+  let path = archipelago
+    .find_path(
+      &start_sampled_point,
+      &end_sampled_point,
+      &PathQuery {
+        cost_filter: Some(&ExcludeEdge(
+          NodeRef { island_id, polygon_index: 1 },
+          NodeRef { island_id, polygon_index: 0 },
+        )),
+        ..Default::default()
+      },
+    )
+    .unwrap();
+
+  expect_that!(
+    path,
+    elements_are!(
+      &PathStep::Waypoint(start_point),
+      &PathStep::Waypoint(Vec2::new(9.0, 4.0)),
+      &PathStep::Waypoint(Vec2::new(9.0, 6.0)),
+      &PathStep::Waypoint(end_point),
+    )
+  );
+}
+
+#[googletest::test]
+fn any_angle_uses_animation_link() {
+  let mut archipelago = Archipelago::<XY>::new(ArchipelagoOptions {
+    pathfinding_mode: PathfindingMode::AnyAngle,
+    ..ArchipelagoOptions::from_agent_radius(0.5)
+  });
+
+  let nav_mesh = Arc::new(
+    NavigationMesh {
+      vertices: vec![
+        Vec2::new(0.0, 0.0),
+        Vec2::new(1.0, 0.0),
+        Vec2::new(1.0, 1.0),
+        Vec2::new(0.0, 1.0),
+        Vec2::new(0.0, 2.0),
+        Vec2::new(1.0, 2.0),
+        Vec2::new(1.0, 3.0),
+        Vec2::new(0.0, 3.0),
+      ],
+      polygons: vec![vec![0, 1, 2, 3], vec![4, 5, 6, 7]],
+      polygon_type_indices: vec![0; 2],
+      polygon_flags: None,
+      height_mesh: None,
+    }
+    .validate()
+    .expect("nav mesh is valid"),
+  );
+
+  archipelago.add_island(Island::new(Transform::default(), nav_mesh));
+  let link_id = archipelago.add_animation_link(AnimationLink {
+    start_edge: (Vec2::new(0.0, 1.0), Vec2::new(1.0, 1.0)),
+    end_edge: (Vec2::new(0.0, 2.0), Vec2::new(1.0, 2.0)),
+    cost: 1.0,
+    kind: 0,
+    bidirectional: false,
+  });
+
+  archipelago.update(1.0);
+
+  let start_point = Vec2::new(0.25, 0.25);
+  let end_point = Vec2::new(0.75, 2.75);
+
+  let start_sampled_point =
+    archipelago.sample_point(start_point, &0.1).unwrap();
+  let end_sampled_point = archipelago.sample_point(end_point, &0.1).unwrap();
+  let path = archipelago
+    .find_path(&start_sampled_point, &end_sampled_point, &PathQuery::default())
+    .unwrap();
+
+  expect_that!(
+    path,
+    elements_are!(
+      &PathStep::Waypoint(start_point),
+      &PathStep::AnimationLink {
+        start_point: Vec2::new(0.25, 1.0),
+        end_point: Vec2::new(0.25, 2.0),
+        link_id,
+      },
+      &PathStep::Waypoint(end_point)
+    )
+  );
+}
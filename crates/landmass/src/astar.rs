@@ -4,6 +4,8 @@ use std::{
   hash::Hash,
 };
 
+use crate::util::FloatOrd;
+
 /// A generic A* problem.
 pub(crate) trait AStarProblem {
   /// The action that allows moving between states.
@@ -31,15 +33,15 @@ pub(crate) trait AStarProblem {
 }
 
 /// A node which represents a single path (by following the previous nodes).
-struct Node<ProblemType: AStarProblem> {
+struct Node<ActionType, StateType> {
   /// The cost of all actions taken by this path.
   cost: f32,
   /// The state that the path results in.
-  state: ProblemType::StateType,
+  state: StateType,
   /// The previous node in the path. This is stored as the index of the node
   /// and the action used to get to this state from the previous state. Only
   /// `None` for the initial state.
-  previous_node: Option<(usize, ProblemType::ActionType)>,
+  previous_node: Option<(usize, ActionType)>,
 }
 
 /// A reference to a node.
@@ -82,13 +84,12 @@ impl Ord for NodeRef {
   }
 }
 
-/// Determines the list of actions taken by `node_ref`.
-fn recover_path_from_node<ProblemType: AStarProblem>(
-  node_ref: &NodeRef,
-  nodes: &[Node<ProblemType>],
-) -> Vec<ProblemType::ActionType> {
+/// Determines the list of actions taken by the node at `node_index`.
+fn recover_path_from_node<ActionType: Clone, StateType>(
+  mut node_index: usize,
+  nodes: &[Node<ActionType, StateType>],
+) -> Vec<ActionType> {
   let mut path = Vec::new();
-  let mut node_index = node_ref.index;
   loop {
     let node = &nodes[node_index];
     match &node.previous_node {
@@ -105,7 +106,7 @@ fn recover_path_from_node<ProblemType: AStarProblem>(
 }
 
 /// Stats about the pathfinding process.
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug)]
 pub(crate) struct PathStats {
   /// The number of nodes that were explored. This can exceed the number of
   /// states if there are faster paths than the heuristic "predicts".
@@ -126,83 +127,193 @@ pub(crate) struct PathResult<ActionType> {
 pub(crate) fn find_path<ProblemType: AStarProblem>(
   problem: &ProblemType,
 ) -> PathResult<ProblemType::ActionType> {
-  let mut stats = PathStats { explored_nodes: 0 };
+  AStarSearch::new(problem)
+    .step(problem, /* max_explored_nodes= */ None)
+    .expect("The search has no limit, so it must finish.")
+}
+
+/// Finds the cheapest cost to reach every state of `problem` whose cost is at
+/// most `max_cost`. The heuristic and goal states of `problem` are ignored.
+pub(crate) fn explore<ProblemType: AStarProblem>(
+  problem: &ProblemType,
+  max_cost: f32,
+) -> HashMap<ProblemType::StateType, f32> {
+  let mut best_costs = HashMap::new();
+  let mut all_states = vec![];
+  let mut open_states = BinaryHeap::new();
+
+  let initial_state = problem.initial_state();
+  best_costs.insert(initial_state.clone(), 0.0);
+  open_states.push(Reverse((FloatOrd(0.0), all_states.len())));
+  all_states.push(initial_state);
+
+  while let Some(Reverse((FloatOrd(cost), index))) = open_states.pop() {
+    let state = &all_states[index];
+    // If this is not the best path to the state, it must have already been
+    // explored.
+    if best_costs[state] < cost {
+      continue;
+    }
+    for (action_cost, _, next_state) in problem.successors(state) {
+      let next_cost = cost + action_cost;
+      if next_cost > max_cost
+        || best_costs
+          .get(&next_state)
+          .is_some_and(|&best_cost| best_cost <= next_cost)
+      {
+        continue;
+      }
+      best_costs.insert(next_state.clone(), next_cost);
+      open_states.push(Reverse((FloatOrd(next_cost), all_states.len())));
+      all_states.push(next_state);
+    }
+  }
 
-  let mut best_estimates = HashMap::new();
+  best_costs
+}
+
+/// An in-progress A* search. Unlike [`find_path`], the search can be paused
+/// after exploring some number of nodes, and then resumed later on.
+///
+/// The search does not hold onto the problem, so the problem can be recreated
+/// for every step. However, the problem must be equivalent for every step (and
+/// the one used to create the search), otherwise the results are meaningless.
+pub(crate) struct AStarSearch<ActionType, StateType> {
+  /// Stats about the search so far.
+  stats: PathStats,
+  /// The best estimate for every state that has been reached so far.
+  best_estimates: HashMap<StateType, f32>,
+  /// Every node that has been reached so far.
+  all_nodes: Vec<Node<ActionType, StateType>>,
+  /// The nodes that have been reached but not explored yet.
+  open_nodes: BinaryHeap<Reverse<NodeRef>>,
+  /// The explored node with the lowest [`AStarProblem::heuristic`] so far,
+  /// stored as the heuristic and the index of the node.
+  closest_node: Option<(f32, usize)>,
+}
+
+impl<ActionType: Clone, StateType: Hash + Eq + Clone>
+  AStarSearch<ActionType, StateType>
+{
+  /// Starts a new search from the initial state of `problem`.
+  pub(crate) fn new<
+    ProblemType: AStarProblem<ActionType = ActionType, StateType = StateType>,
+  >(
+    problem: &ProblemType,
+  ) -> Self {
+    let mut search = Self {
+      stats: PathStats { explored_nodes: 0 },
+      best_estimates: HashMap::new(),
+      all_nodes: Vec::new(),
+      open_nodes: BinaryHeap::new(),
+      closest_node: None,
+    };
+    let initial_node =
+      Node { cost: 0.0, state: problem.initial_state(), previous_node: None };
+    search.try_add_node(problem, initial_node);
+    search
+  }
 
-  let mut all_nodes = Vec::<Node<ProblemType>>::new();
-  let mut open_nodes = BinaryHeap::new();
+  /// Returns the stats of the search so far.
+  pub(crate) fn stats(&self) -> PathStats {
+    self.stats
+  }
+
+  /// Returns the path to the explored state with the lowest heuristic (i.e.,
+  /// the state estimated to be closest to a goal state), along with that
+  /// state. This is useful for getting as close as possible to an unreachable
+  /// goal. Returns [`None`] if no states have been explored yet.
+  pub(crate) fn closest_path(&self) -> Option<(Vec<ActionType>, &StateType)> {
+    let (_, index) = self.closest_node?;
+    let path = recover_path_from_node(index, &self.all_nodes);
+    Some((path, &self.all_nodes[index].state))
+  }
 
-  fn try_add_node<ProblemType: AStarProblem>(
+  /// Continues the search, exploring at most `max_explored_nodes` (or until
+  /// the search finishes if [`None`]). Returns [`None`] if the search has not
+  /// finished yet. Once the search has finished, it should not be stepped
+  /// again.
+  pub(crate) fn step<
+    ProblemType: AStarProblem<ActionType = ActionType, StateType = StateType>,
+  >(
+    &mut self,
     problem: &ProblemType,
-    node: Node<ProblemType>,
-    all_nodes: &mut Vec<Node<ProblemType>>,
-    open_nodes: &mut BinaryHeap<Reverse<NodeRef>>,
-    best_estimates: &mut HashMap<ProblemType::StateType, f32>,
+    max_explored_nodes: Option<u32>,
+  ) -> Option<PathResult<ActionType>> {
+    let mut explored_nodes = 0;
+    loop {
+      if max_explored_nodes.is_some_and(|max| explored_nodes >= max) {
+        return None;
+      }
+      let Some(Reverse(current_node_ref)) = self.open_nodes.pop() else {
+        return Some(PathResult { stats: self.stats, path: None });
+      };
+      let current_node = &self.all_nodes[current_node_ref.index];
+      // If this node is not the best path to the state, skip it. This state
+      // must have already been explored ahead of this node.
+      if *self.best_estimates.get(&current_node.state).unwrap()
+        < current_node_ref.estimate
+      {
+        continue;
+      }
+      self.stats.explored_nodes += 1;
+      explored_nodes += 1;
+
+      let heuristic = problem.heuristic(&current_node.state);
+      if self
+        .closest_node
+        .is_none_or(|(closest_heuristic, _)| heuristic < closest_heuristic)
+      {
+        self.closest_node = Some((heuristic, current_node_ref.index));
+      }
+
+      if problem.is_goal_state(&current_node.state) {
+        return Some(PathResult {
+          stats: self.stats,
+          path: Some(recover_path_from_node(
+            current_node_ref.index,
+            &self.all_nodes,
+          )),
+        });
+      }
+
+      let current_cost = current_node.cost;
+      for (action_cost, action, state) in
+        problem.successors(&current_node.state)
+      {
+        let new_node = Node {
+          cost: current_cost + action_cost,
+          state,
+          previous_node: Some((current_node_ref.index, action)),
+        };
+
+        self.try_add_node(problem, new_node);
+      }
+    }
+  }
+
+  /// Adds `node` to the search if it is the best path to its state so far.
+  fn try_add_node<
+    ProblemType: AStarProblem<ActionType = ActionType, StateType = StateType>,
+  >(
+    &mut self,
+    problem: &ProblemType,
+    node: Node<ActionType, StateType>,
   ) {
     let estimate = node.cost + problem.heuristic(&node.state);
     let best_estimate =
-      best_estimates.entry(node.state.clone()).or_insert(f32::INFINITY);
+      self.best_estimates.entry(node.state.clone()).or_insert(f32::INFINITY);
     if *best_estimate <= estimate {
       return;
     }
     *best_estimate = estimate;
-    open_nodes.push(Reverse(NodeRef {
+    self.open_nodes.push(Reverse(NodeRef {
       cost: node.cost,
       estimate,
-      index: all_nodes.len(),
+      index: self.all_nodes.len(),
     }));
-    all_nodes.push(node);
+    self.all_nodes.push(node);
   }
-
-  let initial_node =
-    Node { cost: 0.0, state: problem.initial_state(), previous_node: None };
-  try_add_node(
-    problem,
-    initial_node,
-    &mut all_nodes,
-    &mut open_nodes,
-    &mut best_estimates,
-  );
-
-  while let Some(Reverse(current_node_ref)) = open_nodes.pop() {
-    let current_node = &all_nodes[current_node_ref.index];
-    // If this node is not the best path to the state, skip it. This state must
-    // have already been explored ahead of this node.
-    if *best_estimates.get(&current_node.state).unwrap()
-      < current_node_ref.estimate
-    {
-      continue;
-    }
-    stats.explored_nodes += 1;
-
-    if problem.is_goal_state(&current_node.state) {
-      return PathResult {
-        stats,
-        path: Some(recover_path_from_node(&current_node_ref, &all_nodes)),
-      };
-    }
-
-    let current_cost = current_node.cost;
-    for (action_cost, action, state) in problem.successors(&current_node.state)
-    {
-      let new_node = Node {
-        cost: current_cost + action_cost,
-        state,
-        previous_node: Some((current_node_ref.index, action)),
-      };
-
-      try_add_node(
-        problem,
-        new_node,
-        &mut all_nodes,
-        &mut open_nodes,
-        &mut best_estimates,
-      );
-    }
-  }
-
-  PathResult { stats, path: None }
 }
 
 #[cfg(test)]
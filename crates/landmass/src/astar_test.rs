@@ -1,4 +1,6 @@
-use super::{AStarProblem, find_path};
+use std::collections::HashMap;
+
+use super::{AStarProblem, AStarSearch, explore, find_path};
 
 struct AdjacencyListProblemState {
   adjacency: Vec<(f32, i32, usize)>,
@@ -216,3 +218,102 @@ fn dead_end_wrong_heuristic() {
   // was found.
   assert_eq!(path.stats.explored_nodes, 4);
 }
+
+#[test]
+fn search_can_be_resumed() {
+  let problem = AdjacencyListProblem {
+    start: 0,
+    end: 3,
+    states: vec![
+      AdjacencyListProblemState {
+        adjacency: vec![(1.0, 1, 1), (5.0, 2, 2)],
+        heuristic: 0.0,
+      },
+      AdjacencyListProblemState {
+        adjacency: vec![(1.0, 3, 2)],
+        heuristic: 0.0,
+      },
+      AdjacencyListProblemState {
+        adjacency: vec![(1.0, 4, 3)],
+        heuristic: 0.0,
+      },
+      AdjacencyListProblemState { adjacency: vec![], heuristic: 0.0 },
+    ],
+  };
+
+  let mut search = AStarSearch::new(&problem);
+  assert!(search.step(&problem, /* max_explored_nodes= */ Some(2)).is_none());
+  assert_eq!(search.stats().explored_nodes, 2);
+  assert!(search.step(&problem, /* max_explored_nodes= */ Some(1)).is_none());
+  assert_eq!(search.stats().explored_nodes, 3);
+
+  let result = search
+    .step(&problem, /* max_explored_nodes= */ Some(1))
+    .expect("The search has finished.");
+  assert_eq!(result.path, Some(vec![1, 3, 4]));
+  assert_eq!(result.stats.explored_nodes, 4);
+  assert_eq!(result.path, find_path(&problem).path);
+}
+
+#[test]
+fn explores_states_within_max_cost() {
+  let problem = AdjacencyListProblem {
+    start: 0,
+    end: 3,
+    states: vec![
+      AdjacencyListProblemState {
+        adjacency: vec![(10.0, 1, 1), (3.0, 2, 2)],
+        // The heuristic should be ignored.
+        heuristic: 100.0,
+      },
+      AdjacencyListProblemState {
+        adjacency: vec![(2.0, 3, 3)],
+        heuristic: 0.0,
+      },
+      AdjacencyListProblemState {
+        adjacency: vec![(4.0, 4, 1), (3.0, 5, 0)],
+        heuristic: 0.0,
+      },
+      AdjacencyListProblemState { adjacency: vec![], heuristic: 0.0 },
+    ],
+  };
+
+  assert_eq!(
+    explore(&problem, /* max_cost= */ 8.0),
+    HashMap::from([(0, 0.0), (1, 7.0), (2, 3.0)])
+  );
+  assert_eq!(
+    explore(&problem, /* max_cost= */ 9.0),
+    HashMap::from([(0, 0.0), (1, 7.0), (2, 3.0), (3, 9.0)])
+  );
+}
+
+#[test]
+fn closest_path_leads_to_lowest_heuristic() {
+  let problem = AdjacencyListProblem {
+    start: 0,
+    // This state doesn't exist, so a path can't be found!
+    end: 4,
+    states: vec![
+      AdjacencyListProblemState {
+        adjacency: vec![(1.0, 1, 1), (1.0, 2, 2)],
+        heuristic: 5.0,
+      },
+      AdjacencyListProblemState {
+        adjacency: vec![(1.0, 3, 3)],
+        heuristic: 4.0,
+      },
+      AdjacencyListProblemState { adjacency: vec![], heuristic: 3.0 },
+      AdjacencyListProblemState { adjacency: vec![], heuristic: 2.0 },
+    ],
+  };
+
+  let mut search = AStarSearch::new(&problem);
+  assert!(search.closest_path().is_none());
+
+  let result = search
+    .step(&problem, /* max_explored_nodes= */ None)
+    .expect("The search has finished.");
+  assert!(result.path.is_none());
+  assert_eq!(search.closest_path(), Some((vec![1, 3], &3)));
+}
@@ -9,6 +9,7 @@ use crate::{
   Agent, AgentId, AgentState, ArchipelagoOptions, Character, CharacterId,
   CoordinateSystem, Island, IslandId, NavigationData,
   nav_data::{KindedOffMeshLink, ModifiedNode, NodeRef},
+  util::par_for_each,
 };
 
 /// Adjusts the velocity of `agents` to apply local avoidance. `delta_time` must
@@ -44,7 +45,10 @@ pub(crate) fn apply_avoidance_to_agents<CS: CoordinateSystem>(
         position: to_dodgy_vec2(agent_point.xy()),
         velocity: to_dodgy_vec2(CS::to_landmass(&agent.velocity).xy()),
         radius: agent.radius,
-        avoidance_responsibility: if agent.state == AgentState::ReachedTarget {
+        avoidance_responsibility: if matches!(
+          agent.state,
+          AgentState::ReachedTarget | AgentState::ReachedPartialTarget
+        ) {
           agent_options.reached_destination_avoidance_responsibility
         } else {
           1.0
@@ -81,9 +85,9 @@ pub(crate) fn apply_avoidance_to_agents<CS: CoordinateSystem>(
 
   let neighbourhood = agent_max_radius + agent_options.neighbourhood;
   let neighbourhood_squared = neighbourhood * neighbourhood;
-  for (agent_id, agent) in agents.iter_mut() {
+  par_for_each(agents.iter_mut().collect(), |(agent_id, agent)| {
     let agent_node = match agent_id_to_agent_node.get(&agent_id) {
-      None => continue,
+      None => return,
       Some(agent_node) => agent_node,
     };
     let agent_point = agent_node.0;
@@ -175,7 +179,7 @@ pub(crate) fn apply_avoidance_to_agents<CS: CoordinateSystem>(
 
     agent.current_desired_move =
       CS::from_landmass(&glam::Vec3::new(desired_move.x, desired_move.y, 0.0));
-  }
+  });
 }
 
 fn to_dodgy_vec2(v: glam::Vec2) -> dodgy_2d::Vec2 {
@@ -1,6 +1,6 @@
 use std::{collections::HashMap, sync::Arc};
 
-use glam::{Vec2, Vec3};
+use glam::{Quat, Vec2, Vec3};
 use slotmap::HopSlotMap;
 
 use crate::{
@@ -76,6 +76,7 @@ fn computes_obstacle_for_box() {
     ],
     polygons: vec![vec![0, 1, 2, 3]],
     polygon_type_indices: vec![0],
+    polygon_flags: None,
     height_mesh: None,
   }
   .validate()
@@ -88,7 +89,11 @@ fn computes_obstacle_for_box() {
     dodgy_2d::Vec2::new(island_offset.x, island_offset.y);
 
   let island_id = nav_data.add_island(Island::new(
-    Transform { translation: island_offset, rotation: 0.0 },
+    Transform {
+      translation: island_offset,
+      rotation: Quat::IDENTITY,
+      scale: 1.0,
+    },
     Arc::new(nav_mesh),
   ));
 
@@ -137,6 +142,7 @@ fn dead_end_makes_open_obstacle() {
       vec![9, 8, 10, 11],
     ],
     polygon_type_indices: vec![0, 0, 0, 0, 0],
+    polygon_flags: None,
     height_mesh: None,
   }
   .validate()
@@ -144,7 +150,7 @@ fn dead_end_makes_open_obstacle() {
 
   let mut nav_data = NavigationData::<XYZ>::new();
   let island_id = nav_data.add_island(Island::new(
-    Transform { translation: Vec3::ZERO, rotation: 0.0 },
+    Transform { translation: Vec3::ZERO, rotation: Quat::IDENTITY, scale: 1.0 },
     Arc::new(nav_mesh),
   ));
 
@@ -280,6 +286,7 @@ fn split_borders() {
       vec![23, 22, 21, 24],
     ],
     polygon_type_indices: vec![0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    polygon_flags: None,
     height_mesh: None,
   }
   .validate()
@@ -287,7 +294,7 @@ fn split_borders() {
 
   let mut nav_data = NavigationData::<XYZ>::new();
   let island_id = nav_data.add_island(Island::new(
-    Transform { translation: Vec3::ZERO, rotation: 0.0 },
+    Transform { translation: Vec3::ZERO, rotation: Quat::IDENTITY, scale: 1.0 },
     Arc::new(nav_mesh),
   ));
 
@@ -338,6 +345,7 @@ fn creates_obstacles_across_boundary_link() {
       ],
       polygons: vec![vec![0, 1, 2, 3]],
       polygon_type_indices: vec![0],
+      polygon_flags: None,
       height_mesh: None,
     }
     .validate()
@@ -346,11 +354,15 @@ fn creates_obstacles_across_boundary_link() {
 
   let mut nav_data = NavigationData::<XYZ>::new();
   nav_data.add_island(Island::new(
-    Transform { translation: Vec3::ZERO, rotation: 0.0 },
+    Transform { translation: Vec3::ZERO, rotation: Quat::IDENTITY, scale: 1.0 },
     Arc::clone(&nav_mesh),
   ));
   let island_id_2 = nav_data.add_island(Island::new(
-    Transform { translation: Vec3::new(1.0, 0.0, 0.0), rotation: 0.0 },
+    Transform {
+      translation: Vec3::new(1.0, 0.0, 0.0),
+      rotation: Quat::IDENTITY,
+      scale: 1.0,
+    },
     nav_mesh,
   ));
 
@@ -397,6 +409,7 @@ fn applies_no_avoidance_for_far_agents() {
     ],
     polygons: vec![vec![0, 1, 2, 3]],
     polygon_type_indices: vec![0],
+    polygon_flags: None,
     height_mesh: None,
   }
   .validate()
@@ -404,7 +417,7 @@ fn applies_no_avoidance_for_far_agents() {
 
   let mut nav_data = NavigationData::<XYZ>::new();
   let island_id = nav_data.add_island(Island::new(
-    Transform { translation: Vec3::ZERO, rotation: 0.0 },
+    Transform { translation: Vec3::ZERO, rotation: Quat::IDENTITY, scale: 1.0 },
     Arc::new(nav_mesh),
   ));
 
@@ -498,6 +511,7 @@ fn applies_avoidance_for_two_agents() {
     ],
     polygons: vec![vec![0, 1, 2, 3]],
     polygon_type_indices: vec![0],
+    polygon_flags: None,
     height_mesh: None,
   }
   .validate()
@@ -505,7 +519,7 @@ fn applies_avoidance_for_two_agents() {
 
   let mut nav_data = NavigationData::<XYZ>::new();
   let island_id = nav_data.add_island(Island::new(
-    Transform { translation: Vec3::ZERO, rotation: 0.0 },
+    Transform { translation: Vec3::ZERO, rotation: Quat::IDENTITY, scale: 1.0 },
     Arc::new(nav_mesh),
   ));
 
@@ -593,6 +607,7 @@ fn agent_avoids_character() {
     ],
     polygons: vec![vec![0, 1, 2, 3]],
     polygon_type_indices: vec![0],
+    polygon_flags: None,
     height_mesh: None,
   }
   .validate()
@@ -600,7 +615,7 @@ fn agent_avoids_character() {
 
   let mut nav_data = NavigationData::<XYZ>::new();
   let island_id = nav_data.add_island(Island::new(
-    Transform { translation: Vec3::ZERO, rotation: 0.0 },
+    Transform { translation: Vec3::ZERO, rotation: Quat::IDENTITY, scale: 1.0 },
     Arc::new(nav_mesh),
   ));
 
@@ -675,6 +690,7 @@ fn agent_speeds_up_to_avoid_character() {
     ],
     polygons: vec![vec![0, 1, 2, 3]],
     polygon_type_indices: vec![0],
+    polygon_flags: None,
     height_mesh: None,
   }
   .validate()
@@ -682,7 +698,7 @@ fn agent_speeds_up_to_avoid_character() {
 
   let mut nav_data = NavigationData::<XY>::new();
   let island_id = nav_data.add_island(Island::new(
-    Transform { translation: Vec2::ZERO, rotation: 0.0 },
+    Transform { translation: Vec2::ZERO, rotation: Quat::IDENTITY, scale: 1.0 },
     Arc::new(nav_mesh),
   ));
 
@@ -777,6 +793,7 @@ fn reached_target_agent_has_different_avoidance() {
       ],
       polygons: vec![vec![0, 1, 2, 3]],
       polygon_type_indices: vec![0],
+      polygon_flags: None,
       height_mesh: None,
     }
     .validate()
@@ -871,6 +888,7 @@ fn switching_nav_mesh_to_fewer_vertices_does_not_result_in_panic() {
     ],
     polygons: vec![vec![0, 1, 2, 3, 4, 5]],
     polygon_type_indices: vec![0],
+    polygon_flags: None,
     height_mesh: None,
   }
   .validate()
@@ -886,6 +904,7 @@ fn switching_nav_mesh_to_fewer_vertices_does_not_result_in_panic() {
     ],
     polygons: vec![vec![0, 1, 2, 3]],
     polygon_type_indices: vec![0],
+    polygon_flags: None,
     height_mesh: None,
   }
   .validate()
@@ -897,7 +916,11 @@ fn switching_nav_mesh_to_fewer_vertices_does_not_result_in_panic() {
   archipelago.add_island(Island::new(
     // This island is shifted over but is slightly misaligned to generate new
     // vertices.
-    Transform { translation: Vec2::new(1.0, 0.25), rotation: 0.0 },
+    Transform {
+      translation: Vec2::new(1.0, 0.25),
+      rotation: Quat::IDENTITY,
+      scale: 1.0,
+    },
     redundant_mesh,
   ));
 
@@ -3,11 +3,11 @@ use glam::{Vec2, Vec3};
 /// A coordinate system used to convert from a user-facing coordinate system
 /// into landmass's standard coordinate system. The standard coordinate system
 /// is [`crate::coords::XYZ`].
-pub trait CoordinateSystem {
+pub trait CoordinateSystem: ThreadSafe {
   /// The user-facing coordinate type.
-  type Coordinate: Clone;
+  type Coordinate: Clone + ThreadSafe;
   /// The type to use for point sampling options.
-  type SampleDistance: PointSampleDistance;
+  type SampleDistance: PointSampleDistance + ThreadSafe;
 
   /// Whether to flip polygons after conversion.
   ///
@@ -23,6 +23,24 @@ pub trait CoordinateSystem {
   fn from_landmass(v: &Vec3) -> Self::Coordinate;
 }
 
+/// Types that can be shared between threads. With the `parallel` feature, this
+/// requires [`Send`] and [`Sync`], since agents are updated across a thread
+/// pool. Otherwise, this is implemented for every type.
+#[cfg(feature = "parallel")]
+pub trait ThreadSafe: Send + Sync {}
+
+#[cfg(feature = "parallel")]
+impl<T: Send + Sync> ThreadSafe for T {}
+
+/// Types that can be shared between threads. With the `parallel` feature, this
+/// requires [`Send`] and [`Sync`], since agents are updated across a thread
+/// pool. Otherwise, this is implemented for every type.
+#[cfg(not(feature = "parallel"))]
+pub trait ThreadSafe {}
+
+#[cfg(not(feature = "parallel"))]
+impl<T> ThreadSafe for T {}
+
 /// A trait to create a default instance based on an agent's radius.
 ///
 /// This is an easy starting point when using struct-update-syntax.
@@ -0,0 +1,51 @@
+use crate::{AnimationLinkId, NodeRef};
+
+/// Customizes the costs of paths beyond type index costs and permitted
+/// animation links. This allows excluding or scaling the cost of individual
+/// nodes, crossings between nodes, and animation links. Every method defaults
+/// to leaving the path unchanged, so only the relevant methods need to be
+/// implemented.
+///
+/// These methods are called many times while searching for a path, so they
+/// should be cheap. Returning [`None`] from any of them excludes that part of
+/// the navigation data from the path.
+pub trait PathCostFilter: Send + Sync {
+  /// Returns the multiplier for the cost of travelling through `node`, which
+  /// has the type index `type_index`. This is applied on top of the type index
+  /// cost. Multipliers must be positive. Multipliers less than 1.0 can result
+  /// in suboptimal paths.
+  ///
+  /// Note [`crate::PathfindingMode::AnyAngle`] ignores node costs, so only
+  /// excluding nodes affects those paths.
+  fn node_cost_multiplier(
+    &self,
+    node: NodeRef,
+    type_index: usize,
+  ) -> Option<f32> {
+    let _ = (node, type_index);
+    Some(1.0)
+  }
+
+  /// Returns the extra cost for crossing directly from `from` into `to` (i.e.,
+  /// through an edge they share, including between islands). Extra costs must
+  /// be non-negative.
+  fn edge_cost(&self, from: NodeRef, to: NodeRef) -> Option<f32> {
+    let _ = (from, to);
+    Some(0.0)
+  }
+
+  /// Returns the cost of using the animation link `link_id` (with `kind`) to
+  /// go from `from` to `to`. `cost` is the cost of the animation link itself.
+  /// Costs must be non-negative.
+  fn animation_link_cost(
+    &self,
+    link_id: AnimationLinkId,
+    kind: usize,
+    from: NodeRef,
+    to: NodeRef,
+    cost: f32,
+  ) -> Option<f32> {
+    let _ = (link_id, kind, from, to);
+    Some(cost)
+  }
+}
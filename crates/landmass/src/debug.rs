@@ -389,6 +389,7 @@ fn draw_path<CS: CoordinateSystem>(
       agent_sample_point,
       target_corridor_index,
       target_sample_point,
+      agent.corner_offset(),
     )
     .1;
   // Convert the path step into the point the agent is walking towards.
@@ -1,6 +1,6 @@
 use std::sync::Arc;
 
-use glam::Vec3;
+use glam::{Quat, Vec3};
 use googletest::{expect_that, matchers::*};
 
 use crate::{
@@ -68,6 +68,7 @@ fn draws_island_meshes_and_agents() {
       vec![10, 4, 14, 13],
     ],
     polygon_type_indices: vec![0, 0, 0, 0],
+    polygon_flags: None,
     height_mesh: None,
   }
   .validate()
@@ -77,14 +78,20 @@ fn draws_island_meshes_and_agents() {
     Archipelago::<XYZ>::new(ArchipelagoOptions::from_agent_radius(0.5));
   const TRANSLATION: Vec3 = Vec3::ONE;
   archipelago.add_island(Island::new(
-    Transform { translation: TRANSLATION, rotation: 0.0 },
+    Transform {
+      translation: TRANSLATION,
+      rotation: Quat::IDENTITY,
+      scale: 1.0,
+    },
     Arc::new(nav_mesh),
   ));
 
   let agent_id = archipelago.add_agent(Agent::create(
     /* position= */ Vec3::new(3.9, 1.5, 0.0) + TRANSLATION,
     /* velocity= */ Vec3::ZERO,
-    /* radius= */ 1.0,
+    // The agent needs to fit through the 1 unit wide corridor.
+    /* radius= */
+    0.25,
     /* desired_speed= */ 1.0,
     /* max_speed= */ 1.0,
   ));
@@ -404,6 +411,7 @@ fn draws_boundary_links() {
       ],
       polygons: vec![vec![0, 1, 2, 3]],
       polygon_type_indices: vec![0],
+      polygon_flags: None,
       height_mesh: None,
     }
     .validate()
@@ -414,7 +422,11 @@ fn draws_boundary_links() {
     Archipelago::<XYZ>::new(ArchipelagoOptions::from_agent_radius(0.5));
   archipelago.add_island(Island::new(Transform::default(), nav_mesh.clone()));
   archipelago.add_island(Island::new(
-    Transform { translation: Vec3::new(1.0, 0.0, 0.0), rotation: 0.0 },
+    Transform {
+      translation: Vec3::new(1.0, 0.0, 0.0),
+      rotation: Quat::IDENTITY,
+      scale: 1.0,
+    },
     nav_mesh.clone(),
   ));
 
@@ -478,6 +490,7 @@ fn draws_animation_links() {
       ],
       polygons: vec![vec![0, 1, 2, 3]],
       polygon_type_indices: vec![0],
+      polygon_flags: None,
       height_mesh: None,
     }
     .validate()
@@ -488,7 +501,11 @@ fn draws_animation_links() {
     Archipelago::<XYZ>::new(ArchipelagoOptions::from_agent_radius(0.5));
   archipelago.add_island(Island::new(Transform::default(), nav_mesh.clone()));
   archipelago.add_island(Island::new(
-    Transform { translation: Vec3::new(2.0, 0.0, 0.0), rotation: 0.0 },
+    Transform {
+      translation: Vec3::new(2.0, 0.0, 0.0),
+      rotation: Quat::IDENTITY,
+      scale: 1.0,
+    },
     nav_mesh.clone(),
   ));
   let link_id = archipelago.add_animation_link(AnimationLink {
@@ -560,6 +577,7 @@ fn draws_height_mesh() {
       ],
       polygons: vec![vec![0, 1, 2, 3], vec![3, 2, 4, 5]],
       polygon_type_indices: vec![0; 2],
+      polygon_flags: None,
       height_mesh: Some(HeightNavigationMesh {
         vertices: vec![
           Vec3::new(0.0, 0.0, -2.0),
@@ -716,6 +734,7 @@ fn fails_to_draw_dirty_archipelago() {
       ],
       polygons: vec![vec![0, 1, 2, 3]],
       polygon_type_indices: vec![0],
+      polygon_flags: None,
       height_mesh: None,
     }
     .validate()
@@ -773,6 +792,7 @@ fn draws_avoidance_data_when_requested() {
       ],
       polygons: vec![vec![0, 1, 2, 3]],
       polygon_type_indices: vec![0],
+      polygon_flags: None,
       height_mesh: None,
     }
     .validate()
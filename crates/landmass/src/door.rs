@@ -0,0 +1,132 @@
+use std::collections::HashSet;
+
+use glam::{Vec2, Vec3};
+use slotmap::new_key_type;
+
+use crate::{
+  CoordinateSystem, geometry::to_ccw_convex_polygon, nav_data::NodeRef,
+};
+
+new_key_type! {
+  /// The ID of a [`Door`].
+  pub struct DoorId;
+}
+
+/// A door (or gate) that can be opened and closed to allow or block traversal
+/// across some portals, without changing the navigation meshes. While closed,
+/// agents will not path through the door, and avoidance treats it as a wall.
+pub struct Door<CS: CoordinateSystem> {
+  /// The edges that the door blocks when closed.
+  pub edges: DoorEdges<CS>,
+  /// Whether the door is open.
+  pub open: bool,
+}
+
+/// The edges that a [`Door`] blocks when closed.
+pub enum DoorEdges<CS: CoordinateSystem> {
+  /// Edges of nodes in the navigation meshes, given by the node and the index
+  /// of the edge in its polygon (the edge from the vertex at that index to the
+  /// next vertex). Edges are blocked in both directions. Edges of nodes that
+  /// don't exist are ignored.
+  NodeEdges(Vec<(NodeRef, usize)>),
+  /// A line segment from the first point to the second point. Any part of the
+  /// navigation meshes that the segment passes through (or along) is cut, so
+  /// agents cannot cross the segment. Only nodes whose bounds overlap the
+  /// height of the segment are cut.
+  Cut(CS::Coordinate, CS::Coordinate),
+}
+
+/// The thickness of the area that a closed door carves out of nodes. This must
+/// be large enough that the two sides of a cut node are not considered to be
+/// touching.
+const DOOR_THICKNESS: f32 = 1e-3;
+
+/// Computes the footprint (a thin, convex, counter-clockwise polygon) and the
+/// height range of a closed door along `segment` (in world space). Returns
+/// [`None`] if the segment has no horizontal length.
+pub(crate) fn segment_to_footprint(
+  (start, end): (Vec3, Vec3),
+) -> Option<(Vec<Vec2>, (f32, f32))> {
+  let offset = (end.truncate() - start.truncate()).try_normalize()?.perp()
+    * DOOR_THICKNESS
+    * 0.5;
+  let footprint = to_ccw_convex_polygon(vec![
+    start.truncate() - offset,
+    end.truncate() - offset,
+    end.truncate() + offset,
+    start.truncate() + offset,
+  ])?;
+  Some((footprint, (start.z.min(end.z), start.z.max(end.z))))
+}
+
+/// The state of a door.
+pub(crate) struct DoorState<CS: CoordinateSystem> {
+  /// The door given to us by the user.
+  pub(crate) door: Door<CS>,
+  /// The footprints (in world space) that the door carves out of nodes. This
+  /// is empty while the door is open.
+  pub(crate) footprints: Vec<Vec<Vec2>>,
+  /// The nodes that the door carved as of the last update.
+  pub(crate) nodes: HashSet<NodeRef>,
+}
+
+impl<CS: CoordinateSystem<Coordinate: std::fmt::Debug>> std::fmt::Debug
+  for Door<CS>
+{
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("Door")
+      .field("edges", &self.edges)
+      .field("open", &self.open)
+      .finish()
+  }
+}
+
+impl<CS: CoordinateSystem<Coordinate: std::fmt::Debug>> std::fmt::Debug
+  for DoorEdges<CS>
+{
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Self::NodeEdges(edges) => {
+        f.debug_tuple("NodeEdges").field(edges).finish()
+      }
+      Self::Cut(start, end) => {
+        f.debug_tuple("Cut").field(start).field(end).finish()
+      }
+    }
+  }
+}
+
+impl<CS: CoordinateSystem> Clone for Door<CS> {
+  fn clone(&self) -> Self {
+    Self { edges: self.edges.clone(), open: self.open }
+  }
+}
+
+impl<CS: CoordinateSystem> Clone for DoorEdges<CS> {
+  fn clone(&self) -> Self {
+    match self {
+      Self::NodeEdges(edges) => Self::NodeEdges(edges.clone()),
+      Self::Cut(start, end) => Self::Cut(start.clone(), end.clone()),
+    }
+  }
+}
+
+impl<CS: CoordinateSystem<Coordinate: PartialEq>> PartialEq for Door<CS> {
+  fn eq(&self, other: &Self) -> bool {
+    self.edges == other.edges && self.open == other.open
+  }
+}
+
+impl<CS: CoordinateSystem<Coordinate: PartialEq>> PartialEq for DoorEdges<CS> {
+  fn eq(&self, other: &Self) -> bool {
+    match (self, other) {
+      (Self::NodeEdges(l_edges), Self::NodeEdges(r_edges)) => {
+        l_edges == r_edges
+      }
+      (Self::Cut(l_start, l_end), Self::Cut(r_start, r_end)) => {
+        l_start == r_start && l_end == r_end
+      }
+      _ => false,
+    }
+  }
+}
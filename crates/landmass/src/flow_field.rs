@@ -0,0 +1,372 @@
+use std::{
+  cmp::Reverse,
+  collections::{BinaryHeap, HashMap},
+};
+
+use glam::{Vec2, Vec3, Vec3Swizzles};
+use slotmap::new_key_type;
+
+use crate::{
+  CoordinateSystem, NavigationData,
+  agent::PermittedAnimationLinks,
+  nav_data::{KindedOffMeshLink, NodeRef, OffMeshLinkId},
+  path::{IslandSegment, OffMeshLinkSegment, Path},
+  util::FloatOrd,
+};
+
+new_key_type! {
+  /// The ID of a flow field.
+  pub struct FlowFieldId;
+}
+
+/// A shared target for many agents. Rather than each agent searching for its
+/// own path, the flow field stores the best way to reach the target from every
+/// node, and agents following it just look up their next step.
+///
+/// The field is computed during [`crate::Archipelago::update`], and is reused
+/// as long as the target stays in the same node (and the navigation data does
+/// not change).
+pub struct FlowField<CS: CoordinateSystem> {
+  /// The current target of the flow field. Moving the target within the same
+  /// node is cheap, but moving it to a different node recomputes the field.
+  pub target: CS::Coordinate,
+  /// The animation links that agents following the field are allowed to use.
+  pub(crate) permitted_animation_links: PermittedAnimationLinks,
+  /// The sampled target and the computed field. [`None`] if the target is not
+  /// on the navigation data or the field has not been computed yet.
+  pub(crate) field: Option<FlowFieldData>,
+}
+
+impl<CS: CoordinateSystem> FlowField<CS> {
+  /// Creates a new flow field targeting `target`. Agents following the field
+  /// will only use `permitted_animation_links`.
+  pub fn new(
+    target: CS::Coordinate,
+    permitted_animation_links: PermittedAnimationLinks,
+  ) -> Self {
+    Self { target, permitted_animation_links, field: None }
+  }
+
+  /// Returns whether the target of the field was on the navigation data during
+  /// the last update. This is also false if the field has not been computed
+  /// yet.
+  pub fn is_target_on_nav_mesh(&self) -> bool {
+    self.field.is_some()
+  }
+}
+
+/// The computed part of a [`FlowField`].
+pub(crate) struct FlowFieldData {
+  /// The node that the target was in when the field was computed.
+  pub(crate) target_node: NodeRef,
+  /// The most recent sampled target point.
+  pub(crate) target_point: Vec3,
+  /// The step to take from each node that can reach the target. Nodes that
+  /// cannot reach the target are absent.
+  nodes: HashMap<NodeRef, FlowFieldNode>,
+}
+
+/// A single node of a flow field.
+#[derive(Clone, Copy, PartialEq, Debug)]
+struct FlowFieldNode {
+  /// The cost of reaching the target from this node.
+  cost: f32,
+  /// The point that the cost is measured from, i.e., the point where agents
+  /// enter this node on their way to the target (or the target itself).
+  point: Vec3,
+  /// The step to take from this node to get closer to the target.
+  step: FlowFieldStep,
+  /// The portal (or the target point) in world space that agents leave this
+  /// node through.
+  exit: (Vec2, Vec2),
+}
+
+/// A step towards the target of a flow field.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum FlowFieldStep {
+  /// The node contains the target.
+  Target,
+  /// Take the node connection at the specified edge index.
+  NodeConnection(usize),
+  /// Take the off mesh link with the specified ID.
+  OffMeshLink(OffMeshLinkId),
+}
+
+impl FlowFieldData {
+  /// Computes the flow field towards `target_point` in `target_node`. Costs
+  /// are computed assuming agents travel through the midpoints of edges (like
+  /// [`crate::PathfindingMode::EdgeMidpoints`]). Each node only has a single
+  /// step, so if obstacles split a node, only the piece of the node that can
+  /// reach that step is part of the field.
+  pub(crate) fn compute<CS: CoordinateSystem>(
+    nav_data: &NavigationData<CS>,
+    target_node: NodeRef,
+    target_point: Vec3,
+    permitted_animation_links: &PermittedAnimationLinks,
+  ) -> Self {
+    let node_cost = |node_ref: NodeRef| {
+      let island = nav_data.get_island(node_ref.island_id).unwrap();
+      let type_index =
+        island.nav_mesh.polygons[node_ref.polygon_index].type_index;
+      nav_data.modified_node_cost(node_ref, type_index, |type_index| {
+        nav_data.get_type_index_cost(type_index).unwrap_or(1.0)
+      })
+    };
+
+    // Off mesh links are only stored by their starting node, but we are
+    // searching backwards, so we need to find them by their destination.
+    let mut destination_to_off_mesh_links = HashMap::<_, Vec<_>>::new();
+    for (&start_node, link_ids) in nav_data.node_to_off_mesh_link_ids.iter() {
+      for &link_id in link_ids.iter() {
+        let link = nav_data.off_mesh_links.get(link_id).unwrap();
+        destination_to_off_mesh_links
+          .entry(link.destination_node)
+          .or_default()
+          .push((start_node, link_id));
+      }
+    }
+
+    let mut nodes = HashMap::new();
+    let mut queue = BinaryHeap::new();
+    if node_cost(target_node).is_finite() {
+      relax(
+        &mut nodes,
+        &mut queue,
+        target_node,
+        FlowFieldNode {
+          cost: 0.0,
+          point: target_point,
+          step: FlowFieldStep::Target,
+          exit: (target_point.xy(), target_point.xy()),
+        },
+      );
+    }
+
+    while let Some(Reverse((FloatOrd(cost), node_ref))) = queue.pop() {
+      let FlowFieldNode { cost: best_cost, point, exit, .. } = nodes[&node_ref];
+      if cost > best_cost {
+        continue;
+      }
+      let current_node_cost = node_cost(node_ref);
+
+      let island = nav_data.get_island(node_ref.island_id).unwrap();
+      let polygon = &island.nav_mesh.polygons[node_ref.polygon_index];
+      for connectivity in polygon.connectivity.iter().flatten() {
+        let previous_node = NodeRef {
+          island_id: node_ref.island_id,
+          polygon_index: connectivity.polygon_index,
+        };
+        if !node_cost(previous_node).is_finite() {
+          continue;
+        }
+        let (i, j) = island.nav_mesh.polygons[connectivity.polygon_index]
+          .get_edge_indices(connectivity.reverse_edge);
+        let portal = (
+          island.transform.apply(island.nav_mesh.vertices[i]).xy(),
+          island.transform.apply(island.nav_mesh.vertices[j]).xy(),
+        );
+        if nav_data.is_crossing_carved(previous_node, node_ref, portal)
+          || !is_reachable(nav_data, node_ref, portal, exit)
+        {
+          continue;
+        }
+        let midpoint = island.transform.apply(
+          island.nav_mesh.vertices[i].midpoint(island.nav_mesh.vertices[j]),
+        );
+        relax(
+          &mut nodes,
+          &mut queue,
+          previous_node,
+          FlowFieldNode {
+            cost: cost + midpoint.distance(point) * current_node_cost,
+            point: midpoint,
+            step: FlowFieldStep::NodeConnection(connectivity.reverse_edge),
+            exit: portal,
+          },
+        );
+      }
+
+      let Some(incoming_links) = destination_to_off_mesh_links.get(&node_ref)
+      else {
+        continue;
+      };
+      for &(previous_node, link_id) in incoming_links.iter() {
+        if !node_cost(previous_node).is_finite() {
+          continue;
+        }
+        let link = nav_data.off_mesh_links.get(link_id).unwrap();
+        let (destination_portal, link_cost) = match &link.kinded {
+          // Boundary links have no additional cost, so 0.0 is correct.
+          KindedOffMeshLink::BoundaryLink { .. } => {
+            if nav_data.is_crossing_carved(
+              previous_node,
+              node_ref,
+              (link.portal.0.xy(), link.portal.1.xy()),
+            ) {
+              continue;
+            }
+            (link.portal, 0.0)
+          }
+          KindedOffMeshLink::AnimationLink {
+            destination_portal,
+            cost,
+            kind,
+            ..
+          } => {
+            if !permitted_animation_links.is_permitted(*kind) {
+              continue;
+            }
+            (*destination_portal, *cost)
+          }
+        };
+        if !is_reachable(
+          nav_data,
+          node_ref,
+          (destination_portal.0.xy(), destination_portal.1.xy()),
+          exit,
+        ) {
+          continue;
+        }
+        let destination_point =
+          destination_portal.0.midpoint(destination_portal.1);
+        relax(
+          &mut nodes,
+          &mut queue,
+          previous_node,
+          FlowFieldNode {
+            cost: cost
+              + destination_point.distance(point) * current_node_cost
+              + link_cost,
+            point: link.portal.0.midpoint(link.portal.1),
+            step: FlowFieldStep::OffMeshLink(link_id),
+            exit: (link.portal.0.xy(), link.portal.1.xy()),
+          },
+        );
+      }
+    }
+
+    Self { target_node, target_point, nodes }
+  }
+
+  /// Determines whether the field can be reused for a target at
+  /// `target_point` in `target_node`, i.e., the target is still in the same
+  /// node (and the same piece of the node if obstacles split it).
+  pub(crate) fn can_reuse_for_target<CS: CoordinateSystem>(
+    &self,
+    nav_data: &NavigationData<CS>,
+    target_node: NodeRef,
+    target_point: Vec3,
+  ) -> bool {
+    self.target_node == target_node
+      && is_reachable(
+        nav_data,
+        target_node,
+        (self.target_point.xy(), self.target_point.xy()),
+        (target_point.xy(), target_point.xy()),
+      )
+  }
+
+  /// Creates a path from `start_node` to the target by following the field.
+  /// Returns [`None`] if the target cannot be reached from `start_point` in
+  /// `start_node`.
+  pub(crate) fn path_from<CS: CoordinateSystem>(
+    &self,
+    nav_data: &NavigationData<CS>,
+    start_node: NodeRef,
+    start_point: Vec3,
+  ) -> Option<Path> {
+    // If obstacles split the start node, the agent may be in a piece that
+    // can't reach the node's step.
+    let start = self.nodes.get(&start_node)?;
+    if !is_reachable(
+      nav_data,
+      start_node,
+      (start_point.xy(), start_point.xy()),
+      start.exit,
+    ) {
+      return None;
+    }
+
+    let mut path = Path {
+      island_segments: vec![IslandSegment {
+        island_id: start_node.island_id,
+        corridor: vec![start_node.polygon_index],
+        portal_edge_index: vec![],
+      }],
+      off_mesh_link_segments: vec![],
+      start_point,
+      end_point: self.target_point,
+    };
+
+    let mut current_node = start_node;
+    loop {
+      match self.nodes.get(&current_node)?.step {
+        FlowFieldStep::Target => return Some(path),
+        FlowFieldStep::NodeConnection(edge_index) => {
+          let island = nav_data.get_island(current_node.island_id).unwrap();
+          let connectivity = island.nav_mesh.polygons
+            [current_node.polygon_index]
+            .connectivity[edge_index]
+            .as_ref()
+            .unwrap();
+          current_node = NodeRef {
+            island_id: current_node.island_id,
+            polygon_index: connectivity.polygon_index,
+          };
+          let last_segment = path.island_segments.last_mut().unwrap();
+          last_segment.corridor.push(current_node.polygon_index);
+          last_segment.portal_edge_index.push(edge_index);
+        }
+        FlowFieldStep::OffMeshLink(link_id) => {
+          let link = nav_data.off_mesh_links.get(link_id).unwrap();
+          path.off_mesh_link_segments.push(OffMeshLinkSegment {
+            starting_node: current_node,
+            end_node: link.destination_node,
+            off_mesh_link: link_id,
+          });
+          current_node = link.destination_node;
+          path.island_segments.push(IslandSegment {
+            island_id: current_node.island_id,
+            corridor: vec![current_node.polygon_index],
+            portal_edge_index: vec![],
+          });
+        }
+      }
+    }
+  }
+}
+
+/// Determines whether `exit` (a portal or point in world space) can be reached
+/// from `entry` within `node_ref`. If obstacles have split the node, only the
+/// piece of the node that was entered can be reached.
+fn is_reachable<CS: CoordinateSystem>(
+  nav_data: &NavigationData<CS>,
+  node_ref: NodeRef,
+  entry: (Vec2, Vec2),
+  exit: (Vec2, Vec2),
+) -> bool {
+  nav_data
+    .modified_nodes
+    .get(&node_ref)
+    .filter(|modified_node| modified_node.is_split())
+    .is_none_or(|modified_node| modified_node.are_connected(entry, exit))
+}
+
+/// Sets the flow field `node` for `node_ref` and queues it to be expanded, if
+/// it is cheaper than what was previously found.
+fn relax(
+  nodes: &mut HashMap<NodeRef, FlowFieldNode>,
+  queue: &mut BinaryHeap<Reverse<(FloatOrd, NodeRef)>>,
+  node_ref: NodeRef,
+  node: FlowFieldNode,
+) {
+  if nodes.get(&node_ref).is_some_and(|old_node| old_node.cost <= node.cost) {
+    return;
+  }
+  queue.push(Reverse((FloatOrd(node.cost), node_ref)));
+  nodes.insert(node_ref, node);
+}
+
+#[cfg(test)]
+#[path = "flow_field_test.rs"]
+mod test;
@@ -0,0 +1,288 @@
+use std::{collections::HashMap, sync::Arc};
+
+use glam::Vec2;
+use googletest::{expect_eq, expect_that, expect_true, matchers::*};
+
+use crate::{
+  Agent, AgentId, AgentState, Archipelago, ArchipelagoOptions, FlowField,
+  FlowFieldId, FromAgentRadius, Island, NavigationMesh, Obstacle, Transform,
+  ValidNavigationMesh, agent::PermittedAnimationLinks, coords::XY,
+};
+
+/// Creates a nav mesh where each non-space character in `layout` is a unit
+/// square. The first row of `layout` is the top (highest y) of the nav mesh.
+fn grid_nav_mesh(layout: &[&str]) -> Arc<ValidNavigationMesh<XY>> {
+  let mut vertices = vec![];
+  let mut vertex_to_index = HashMap::new();
+  let mut vertex_index = |x: usize, y: usize| {
+    *vertex_to_index.entry((x, y)).or_insert_with(|| {
+      vertices.push(Vec2::new(x as f32, y as f32));
+      vertices.len() - 1
+    })
+  };
+
+  let mut polygons = vec![];
+  for (y, row) in layout.iter().rev().enumerate() {
+    for (x, cell) in row.chars().enumerate() {
+      if cell == ' ' {
+        continue;
+      }
+      polygons.push(vec![
+        vertex_index(x, y),
+        vertex_index(x + 1, y),
+        vertex_index(x + 1, y + 1),
+        vertex_index(x, y + 1),
+      ]);
+    }
+  }
+
+  Arc::new(
+    NavigationMesh {
+      vertices,
+      polygon_type_indices: vec![0; polygons.len()],
+      polygons,
+      polygon_flags: None,
+      height_mesh: None,
+    }
+    .validate()
+    .expect("nav mesh is valid"),
+  )
+}
+
+fn add_agent_following(
+  archipelago: &mut Archipelago<XY>,
+  position: Vec2,
+  flow_field_id: FlowFieldId,
+) -> AgentId {
+  let mut agent = Agent::create(position, Vec2::ZERO, 0.25, 1.0, 2.0);
+  agent.flow_field = Some(flow_field_id);
+  archipelago.add_agent(agent)
+}
+
+#[googletest::test]
+fn agents_follow_flow_field_to_target() {
+  let mut archipelago =
+    Archipelago::<XY>::new(ArchipelagoOptions::from_agent_radius(0.25));
+  archipelago.add_island(Island::new(
+    Transform::default(),
+    grid_nav_mesh(&[
+      ".....", //
+      ".   .", //
+      ".....", //
+    ]),
+  ));
+
+  let flow_field_id = archipelago.add_flow_field(FlowField::new(
+    Vec2::new(0.5, 0.5),
+    PermittedAnimationLinks::All,
+  ));
+  let top_left =
+    add_agent_following(&mut archipelago, Vec2::new(0.5, 2.5), flow_field_id);
+  let top_right =
+    add_agent_following(&mut archipelago, Vec2::new(3.5, 2.5), flow_field_id);
+  let right =
+    add_agent_following(&mut archipelago, Vec2::new(4.5, 1.5), flow_field_id);
+  let at_target =
+    add_agent_following(&mut archipelago, Vec2::new(0.6, 0.6), flow_field_id);
+
+  archipelago.update(1.0);
+
+  expect_true!(
+    archipelago.get_flow_field(flow_field_id).unwrap().is_target_on_nav_mesh()
+  );
+  // Following the flow field doesn't require searching for paths.
+  expect_that!(archipelago.get_pathing_results(), is_empty());
+
+  for agent_id in [top_left, top_right, right] {
+    expect_eq!(
+      archipelago.get_agent(agent_id).unwrap().state(),
+      AgentState::Moving
+    );
+  }
+  expect_eq!(
+    archipelago.get_agent(at_target).unwrap().state(),
+    AgentState::ReachedTarget
+  );
+
+  // Each agent takes the shorter way around the hole.
+  let top_left_velocity =
+    *archipelago.get_agent(top_left).unwrap().get_desired_velocity();
+  expect_that!(top_left_velocity.x, near(0.0, 1e-5));
+  expect_that!(top_left_velocity.y, near(-1.0, 1e-5));
+  let top_right_velocity =
+    *archipelago.get_agent(top_right).unwrap().get_desired_velocity();
+  expect_that!(top_right_velocity.x, lt(0.0));
+  let right_velocity =
+    *archipelago.get_agent(right).unwrap().get_desired_velocity();
+  expect_that!(right_velocity.y, lt(0.0));
+}
+
+#[googletest::test]
+fn flow_field_is_reused_while_target_stays_in_node() {
+  let mut archipelago =
+    Archipelago::<XY>::new(ArchipelagoOptions::from_agent_radius(0.25));
+  archipelago.add_island(Island::new(
+    Transform::default(),
+    grid_nav_mesh(&[
+      "...", //
+    ]),
+  ));
+
+  let flow_field_id = archipelago.add_flow_field(FlowField::new(
+    Vec2::new(2.5, 0.5),
+    PermittedAnimationLinks::All,
+  ));
+  let agent_id =
+    add_agent_following(&mut archipelago, Vec2::new(0.5, 0.5), flow_field_id);
+
+  archipelago.update(1.0);
+  let original_path =
+    archipelago.get_agent(agent_id).unwrap().current_path.clone();
+  expect_true!(original_path.is_some());
+
+  // Moving the target within its node keeps the field (and so the agent's
+  // path).
+  archipelago.get_flow_field_mut(flow_field_id).unwrap().target =
+    Vec2::new(2.25, 0.75);
+  archipelago.get_agent_mut(agent_id).unwrap().position = Vec2::new(0.25, 0.5);
+  archipelago.update(1.0);
+  expect_eq!(
+    archipelago.get_agent(agent_id).unwrap().current_path,
+    original_path
+  );
+
+  // Moving the target to a different node recomputes the field, so the agent
+  // takes its path from the new field.
+  archipelago.get_flow_field_mut(flow_field_id).unwrap().target =
+    Vec2::new(1.5, 0.5);
+  archipelago.update(1.0);
+  let path = archipelago
+    .get_agent(agent_id)
+    .unwrap()
+    .current_path
+    .as_ref()
+    .expect("agent has a path");
+  expect_eq!(path.island_segments.len(), 1);
+  expect_eq!(path.island_segments[0].corridor, [0, 1]);
+  expect_eq!(path.start_point, Vec2::new(0.25, 0.5).extend(0.0));
+}
+
+#[googletest::test]
+fn agent_cannot_reach_flow_field_target() {
+  let mut archipelago =
+    Archipelago::<XY>::new(ArchipelagoOptions::from_agent_radius(0.25));
+  archipelago.add_island(Island::new(
+    Transform::default(),
+    grid_nav_mesh(&[
+      ". .", //
+    ]),
+  ));
+
+  let flow_field_id = archipelago.add_flow_field(FlowField::new(
+    Vec2::new(2.5, 0.5),
+    PermittedAnimationLinks::All,
+  ));
+  let agent_id =
+    add_agent_following(&mut archipelago, Vec2::new(0.5, 0.5), flow_field_id);
+
+  archipelago.update(1.0);
+  expect_eq!(
+    archipelago.get_agent(agent_id).unwrap().state(),
+    AgentState::NoPath
+  );
+
+  // A target off the nav mesh is reported by the agents following it.
+  archipelago.get_flow_field_mut(flow_field_id).unwrap().target =
+    Vec2::new(10.0, 10.0);
+  archipelago.update(1.0);
+  expect_that!(
+    archipelago.get_flow_field(flow_field_id).unwrap().is_target_on_nav_mesh(),
+    eq(false)
+  );
+  expect_eq!(
+    archipelago.get_agent(agent_id).unwrap().state(),
+    AgentState::TargetNotOnNavMesh
+  );
+}
+
+#[googletest::test]
+fn agents_cut_off_by_obstacle_cannot_reach_flow_field_target() {
+  let mut archipelago =
+    Archipelago::<XY>::new(ArchipelagoOptions::from_agent_radius(0.25));
+  archipelago.add_island(Island::new(
+    Transform::default(),
+    grid_nav_mesh(&[
+      "....", //
+    ]),
+  ));
+  // The obstacle splits the second node in two.
+  archipelago
+    .add_obstacle(Obstacle::Aabb {
+      min: Vec2::new(1.3, -1.0),
+      max: Vec2::new(1.7, 2.0),
+    })
+    .unwrap();
+
+  let flow_field_id = archipelago.add_flow_field(FlowField::new(
+    Vec2::new(3.5, 0.5),
+    PermittedAnimationLinks::All,
+  ));
+  let behind_obstacle =
+    add_agent_following(&mut archipelago, Vec2::new(0.5, 0.5), flow_field_id);
+  let cut_off_piece =
+    add_agent_following(&mut archipelago, Vec2::new(1.1, 0.5), flow_field_id);
+  let connected_piece =
+    add_agent_following(&mut archipelago, Vec2::new(1.9, 0.5), flow_field_id);
+
+  archipelago.update(1.0);
+  archipelago.update(1.0);
+
+  expect_eq!(
+    archipelago.get_agent(behind_obstacle).unwrap().state(),
+    AgentState::NoPath
+  );
+  expect_eq!(
+    archipelago.get_agent(cut_off_piece).unwrap().state(),
+    AgentState::NoPath
+  );
+  let agent = archipelago.get_agent(connected_piece).unwrap();
+  expect_eq!(agent.state(), AgentState::Moving);
+  expect_eq!(*agent.get_desired_velocity(), Vec2::new(1.0, 0.0));
+}
+
+#[googletest::test]
+fn removing_flow_field_stops_agents_following_it() {
+  let mut archipelago =
+    Archipelago::<XY>::new(ArchipelagoOptions::from_agent_radius(0.25));
+  archipelago.add_island(Island::new(
+    Transform::default(),
+    grid_nav_mesh(&[
+      "...", //
+    ]),
+  ));
+
+  let flow_field_id = archipelago.add_flow_field(FlowField::new(
+    Vec2::new(2.5, 0.5),
+    PermittedAnimationLinks::All,
+  ));
+  let agent_id =
+    add_agent_following(&mut archipelago, Vec2::new(0.5, 0.5), flow_field_id);
+
+  archipelago.update(1.0);
+  expect_eq!(
+    archipelago.get_agent(agent_id).unwrap().state(),
+    AgentState::Moving
+  );
+
+  archipelago.remove_flow_field(flow_field_id);
+  expect_that!(
+    archipelago.get_flow_field_ids().collect::<Vec<_>>(),
+    is_empty()
+  );
+  expect_eq!(archipelago.get_agent(agent_id).unwrap().flow_field, None);
+
+  archipelago.update(1.0);
+  let agent = archipelago.get_agent(agent_id).unwrap();
+  expect_eq!(agent.state(), AgentState::Idle);
+  expect_true!(agent.current_path.is_none());
+}
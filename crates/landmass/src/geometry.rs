@@ -188,6 +188,256 @@ pub(crate) fn project_point_to_line_segment(
   (dir * fraction + start, fraction)
 }
 
+/// The number of pieces a segment is split into when estimating its clearance
+/// in [`segment_clearance`].
+const CLEARANCE_SAMPLES: usize = 16;
+
+/// Estimates the diameter of the widest circle that can be centered somewhere
+/// on `segment` without overlapping any of `walls`. This samples evenly spaced
+/// points along the segment. Returns [`f32::INFINITY`] if there are no walls.
+pub(crate) fn segment_clearance(
+  (start, end): (Vec2, Vec2),
+  walls: &[(Vec2, Vec2)],
+) -> f32 {
+  (0..=CLEARANCE_SAMPLES)
+    .map(|i| {
+      let point = start.lerp(end, i as f32 / CLEARANCE_SAMPLES as f32);
+      walls
+        .iter()
+        .map(|&(wall_start, wall_end)| {
+          let (projected_point, _) = project_point_to_line_segment(
+            point.extend(0.0),
+            (wall_start.extend(0.0), wall_end.extend(0.0)),
+          );
+          point.distance(projected_point.truncate())
+        })
+        .fold(f32::INFINITY, f32::min)
+    })
+    .fold(0.0, f32::max)
+    * 2.0
+}
+
+/// Estimates the diameter of the widest circle that can be centered somewhere
+/// in the convex `polygon` without overlapping any of `walls`. This samples
+/// points between the center of the polygon and its vertices and edge
+/// midpoints. Returns [`f32::INFINITY`] if there are no walls.
+pub(crate) fn convex_polygon_clearance(
+  polygon: &[Vec2],
+  walls: &[(Vec2, Vec2)],
+) -> f32 {
+  let center = polygon.iter().sum::<Vec2>() / polygon.len() as f32;
+  polygon
+    .iter()
+    .zip(polygon.iter().cycle().skip(1))
+    .flat_map(|(&vertex, &next_vertex)| [vertex, vertex.midpoint(next_vertex)])
+    .map(|point| segment_clearance((center, point), walls))
+    .fold(0.0, f32::max)
+}
+
+/// Computes the signed area of `polygon`. The area is positive if the polygon
+/// is counter-clockwise.
+pub(crate) fn polygon_signed_area(polygon: &[Vec2]) -> f32 {
+  polygon
+    .iter()
+    .zip(polygon.iter().cycle().skip(1))
+    .map(|(a, b)| a.perp_dot(*b))
+    .sum::<f32>()
+    * 0.5
+}
+
+/// Puts `vertices` in counter-clockwise order, or returns [`None`] if they are
+/// not convex or have fewer than 3 (non-collinear) vertices.
+pub(crate) fn to_ccw_convex_polygon(
+  mut vertices: Vec<Vec2>,
+) -> Option<Vec<Vec2>> {
+  if polygon_signed_area(&vertices) < 0.0 {
+    vertices.reverse();
+  }
+  let is_convex = vertices.len() >= 3
+    && polygon_signed_area(&vertices) > 0.0
+    && (0..vertices.len()).all(|i| {
+      let previous = vertices[i];
+      let current = vertices[(i + 1) % vertices.len()];
+      let next = vertices[(i + 2) % vertices.len()];
+      (current - previous).perp_dot(next - current) >= 0.0
+    });
+  is_convex.then_some(vertices)
+}
+
+/// Finds the interval of `segment` that is inside the convex,
+/// counter-clockwise `polygon`. The interval is given as fractions along the
+/// segment. Returns [`None`] if the segment does not overlap the polygon (or
+/// only touches it at a point).
+pub(crate) fn convex_polygon_segment_interval(
+  polygon: &[Vec2],
+  segment: (Vec2, Vec2),
+) -> Option<(f32, f32)> {
+  let direction = segment.1 - segment.0;
+  let (mut start, mut end) = (0.0f32, 1.0f32);
+  for (&current, &next) in polygon.iter().zip(polygon.iter().cycle().skip(1)) {
+    let edge = next - current;
+    // The segment is inside this edge's half-plane where
+    // `offset + t * speed >= 0`.
+    let offset = edge.perp_dot(segment.0 - current);
+    let speed = edge.perp_dot(direction);
+    if speed == 0.0 {
+      if offset < 0.0 {
+        return None;
+      }
+      continue;
+    }
+    let t = -offset / speed;
+    if speed > 0.0 {
+      start = start.max(t);
+    } else {
+      end = end.min(t);
+    }
+  }
+  (start < end).then_some((start, end))
+}
+
+/// Determines whether segments `a` and `b` cross each other. Segments that
+/// only touch (e.g., at an endpoint) or that are collinear do not cross.
+pub(crate) fn segments_cross(a: (Vec2, Vec2), b: (Vec2, Vec2)) -> bool {
+  const EPSILON: f32 = 1e-6;
+  let side = |(start, end): (Vec2, Vec2), point: Vec2| {
+    let side = (end - start).perp_dot(point - start);
+    if side > EPSILON {
+      1
+    } else if side < -EPSILON {
+      -1
+    } else {
+      0
+    }
+  };
+  side(a, b.0) * side(a, b.1) < 0 && side(b, a.0) * side(b, a.1) < 0
+}
+
+/// Moves each vertex of the convex, counter-clockwise `polygon` outwards so
+/// that it is `offset` away from both edges adjacent to it.
+pub(crate) fn offset_convex_polygon_vertices(
+  polygon: &[Vec2],
+  offset: f32,
+) -> Vec<Vec2> {
+  (0..polygon.len())
+    .map(|i| {
+      let previous = polygon[(i + polygon.len() - 1) % polygon.len()];
+      let current = polygon[i];
+      let next = polygon[(i + 1) % polygon.len()];
+      // The outward normals of the edges before and after the vertex.
+      let normal_before = -(current - previous).perp().normalize_or_zero();
+      let normal_after = -(next - current).perp().normalize_or_zero();
+      let direction = (normal_before + normal_after).normalize_or_zero();
+      // Limit how far very sharp corners get pushed out.
+      current + direction * offset / direction.dot(normal_before).max(0.25)
+    })
+    .collect()
+}
+
+/// Computes the area of the intersection between two convex,
+/// counter-clockwise polygons.
+pub(crate) fn convex_polygon_intersection_area(
+  polygon: &[Vec2],
+  clip_polygon: &[Vec2],
+) -> f32 {
+  // Sutherland-Hodgman: clip the polygon by each edge of the clip polygon.
+  let mut clipped = polygon.to_vec();
+  for (&edge_start, &edge_end) in
+    clip_polygon.iter().zip(clip_polygon.iter().cycle().skip(1))
+  {
+    if clipped.is_empty() {
+      break;
+    }
+    let edge = edge_end - edge_start;
+    // Positive if the point is on the inside (left) of the edge.
+    let side = |point: Vec2| edge.perp_dot(point - edge_start);
+
+    let mut next_clipped = Vec::with_capacity(clipped.len() + 1);
+    for (&current, &next) in clipped.iter().zip(clipped.iter().cycle().skip(1))
+    {
+      let (current_side, next_side) = (side(current), side(next));
+      if current_side >= 0.0 {
+        next_clipped.push(current);
+      }
+      if (current_side >= 0.0) != (next_side >= 0.0) {
+        let t = current_side / (current_side - next_side);
+        next_clipped.push(current.lerp(next, t));
+      }
+    }
+    clipped = next_clipped;
+  }
+
+  if clipped.len() < 3 {
+    return 0.0;
+  }
+  polygon_signed_area(&clipped)
+}
+
+/// Computes the area of the intersection between a circle and a convex,
+/// counter-clockwise polygon.
+pub(crate) fn circle_polygon_intersection_area(
+  center: Vec2,
+  radius: f32,
+  polygon: &[Vec2],
+) -> f32 {
+  // The area of the circle's sector between `a` and `b` (relative to the
+  // center).
+  let sector_area =
+    |a: Vec2, b: Vec2| radius * radius * 0.5 * a.perp_dot(b).atan2(a.dot(b));
+
+  // The signed area of the intersection between the circle and the triangle
+  // made of the center, `a` and `b` (relative to the center). Summing these
+  // over every edge of the polygon gives the total area.
+  let triangle_area = |a: Vec2, b: Vec2| {
+    let radius_squared = radius * radius;
+    let a_inside = a.length_squared() <= radius_squared;
+    let b_inside = b.length_squared() <= radius_squared;
+    if a_inside && b_inside {
+      return a.perp_dot(b) * 0.5;
+    }
+
+    // Find where the edge crosses the circle by solving
+    // |a + t * delta| = radius.
+    let delta = b - a;
+    let delta_length_squared = delta.length_squared();
+    if delta_length_squared == 0.0 {
+      return 0.0;
+    }
+    let half_b = a.dot(delta);
+    let discriminant = half_b * half_b
+      - delta_length_squared * (a.length_squared() - radius_squared);
+    if discriminant <= 0.0 {
+      return sector_area(a, b);
+    }
+    let root = discriminant.sqrt();
+    let t_1 = (-half_b - root) / delta_length_squared;
+    let t_2 = (-half_b + root) / delta_length_squared;
+
+    if a_inside {
+      let exit = a + delta * t_2;
+      return a.perp_dot(exit) * 0.5 + sector_area(exit, b);
+    }
+    if b_inside {
+      let entry = a + delta * t_1;
+      return sector_area(a, entry) + entry.perp_dot(b) * 0.5;
+    }
+    if 0.0 < t_1 && t_2 < 1.0 {
+      let entry = a + delta * t_1;
+      let exit = a + delta * t_2;
+      return sector_area(a, entry)
+        + entry.perp_dot(exit) * 0.5
+        + sector_area(exit, b);
+    }
+    sector_area(a, b)
+  };
+
+  polygon
+    .iter()
+    .zip(polygon.iter().cycle().skip(1))
+    .map(|(&a, &b)| triangle_area(a - center, b - center))
+    .sum()
+}
+
 #[cfg(test)]
 #[path = "geometry_test.rs"]
 mod test;
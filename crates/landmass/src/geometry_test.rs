@@ -1,9 +1,16 @@
 use glam::{Vec2, Vec3};
-use googletest::{expect_eq, expect_that, matchers::*};
+use googletest::{
+  expect_eq, expect_false, expect_that, expect_true, matchers::*,
+};
 
 use crate::geometry::project_point_to_line_segment;
 
-use super::{clip_edge_to_triangle, edge_intersection};
+use super::{
+  circle_polygon_intersection_area, clip_edge_to_triangle,
+  convex_polygon_intersection_area, convex_polygon_segment_interval,
+  edge_intersection, offset_convex_polygon_vertices, polygon_signed_area,
+  segments_cross,
+};
 
 #[test]
 fn edge_intersects_when_on_same_line() {
@@ -587,3 +594,163 @@ fn projects_point_to_degenerate_line_segment() {
     (Vec3::new(10.0, 3.0, 0.0), 0.0)
   );
 }
+
+#[googletest::test]
+fn computes_convex_polygon_intersection_area() {
+  let square = [
+    Vec2::new(0.0, 0.0),
+    Vec2::new(2.0, 0.0),
+    Vec2::new(2.0, 2.0),
+    Vec2::new(0.0, 2.0),
+  ];
+  expect_eq!(polygon_signed_area(&square), 4.0);
+
+  // Overlapping one corner.
+  expect_eq!(
+    convex_polygon_intersection_area(
+      &square,
+      &square.map(|vertex| vertex + Vec2::new(1.0, 1.5)),
+    ),
+    0.5
+  );
+  // Fully contained.
+  expect_eq!(
+    convex_polygon_intersection_area(
+      &square,
+      &square.map(|vertex| vertex * 2.0 - Vec2::ONE),
+    ),
+    4.0
+  );
+  // Disjoint.
+  expect_eq!(
+    convex_polygon_intersection_area(
+      &square,
+      &square.map(|vertex| vertex + Vec2::new(3.0, 0.0)),
+    ),
+    0.0
+  );
+}
+
+#[googletest::test]
+fn computes_circle_polygon_intersection_area() {
+  let square = [
+    Vec2::new(-1.0, -1.0),
+    Vec2::new(1.0, -1.0),
+    Vec2::new(1.0, 1.0),
+    Vec2::new(-1.0, 1.0),
+  ];
+
+  // The circle is inside the square.
+  expect_that!(
+    circle_polygon_intersection_area(Vec2::ZERO, 0.5, &square),
+    near(std::f32::consts::PI * 0.25, 1e-5)
+  );
+  // The square is inside the circle.
+  expect_that!(
+    circle_polygon_intersection_area(Vec2::ZERO, 2.0, &square),
+    near(4.0, 1e-5)
+  );
+  // A quarter of the circle is inside the square.
+  expect_that!(
+    circle_polygon_intersection_area(Vec2::new(1.0, 1.0), 0.5, &square),
+    near(std::f32::consts::PI * 0.25 * 0.25, 1e-5)
+  );
+  // The circle crosses an edge without containing either of its vertices.
+  expect_that!(
+    circle_polygon_intersection_area(Vec2::new(1.0, 0.0), 0.5, &square),
+    near(std::f32::consts::PI * 0.25 * 0.5, 1e-5)
+  );
+  // Disjoint.
+  expect_that!(
+    circle_polygon_intersection_area(Vec2::new(5.0, 0.0), 0.5, &square),
+    near(0.0, 1e-5)
+  );
+}
+
+#[googletest::test]
+fn finds_segment_interval_in_convex_polygon() {
+  let square = [
+    Vec2::new(0.0, 0.0),
+    Vec2::new(2.0, 0.0),
+    Vec2::new(2.0, 2.0),
+    Vec2::new(0.0, 2.0),
+  ];
+
+  // Crossing the whole polygon.
+  expect_eq!(
+    convex_polygon_segment_interval(
+      &square,
+      (Vec2::new(-1.0, 1.0), Vec2::new(3.0, 1.0)),
+    ),
+    Some((0.25, 0.75))
+  );
+  // Starting inside the polygon.
+  expect_eq!(
+    convex_polygon_segment_interval(
+      &square,
+      (Vec2::new(1.0, 1.0), Vec2::new(1.0, 5.0)),
+    ),
+    Some((0.0, 0.25))
+  );
+  // Along an edge of the polygon.
+  expect_eq!(
+    convex_polygon_segment_interval(
+      &square,
+      (Vec2::new(-2.0, 0.0), Vec2::new(2.0, 0.0)),
+    ),
+    Some((0.5, 1.0))
+  );
+  // Missing the polygon.
+  expect_eq!(
+    convex_polygon_segment_interval(
+      &square,
+      (Vec2::new(-1.0, 3.0), Vec2::new(3.0, 3.0)),
+    ),
+    None
+  );
+}
+
+#[googletest::test]
+fn segments_only_cross_through_their_interiors() {
+  let segment = (Vec2::new(0.0, 0.0), Vec2::new(2.0, 2.0));
+
+  expect_true!(segments_cross(
+    segment,
+    (Vec2::new(0.0, 2.0), Vec2::new(2.0, 0.0))
+  ));
+  // Touching at an endpoint.
+  expect_false!(segments_cross(
+    segment,
+    (Vec2::new(2.0, 2.0), Vec2::new(3.0, 0.0))
+  ));
+  // Collinear and overlapping.
+  expect_false!(segments_cross(
+    segment,
+    (Vec2::new(1.0, 1.0), Vec2::new(3.0, 3.0))
+  ));
+  // The lines cross, but not the segments.
+  expect_false!(segments_cross(
+    segment,
+    (Vec2::new(3.0, 0.0), Vec2::new(5.0, -2.0))
+  ));
+}
+
+#[googletest::test]
+fn offsets_convex_polygon_vertices() {
+  let square = [
+    Vec2::new(0.0, 0.0),
+    Vec2::new(2.0, 0.0),
+    Vec2::new(2.0, 2.0),
+    Vec2::new(0.0, 2.0),
+  ];
+
+  let offset = offset_convex_polygon_vertices(&square, 0.5);
+  for (actual, expected) in offset.iter().zip([
+    Vec2::new(-0.5, -0.5),
+    Vec2::new(2.5, -0.5),
+    Vec2::new(2.5, 2.5),
+    Vec2::new(-0.5, 2.5),
+  ]) {
+    expect_that!(actual.distance(expected), near(0.0, 1e-5));
+  }
+}
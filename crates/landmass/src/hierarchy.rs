@@ -0,0 +1,485 @@
+use std::{
+  cmp::Reverse,
+  collections::{BinaryHeap, HashMap, HashSet},
+};
+
+use glam::{Vec3, Vec3Swizzles};
+
+use crate::{
+  CoordinateSystem, NavigationData,
+  agent::PermittedAnimationLinks,
+  astar::{self, AStarProblem},
+  island::IslandId,
+  nav_data::{KindedOffMeshLink, NodeRef, OffMeshLink, OffMeshLinkId},
+  util::FloatOrd,
+};
+
+/// An abstraction of the navigation data, where each island is a cluster and
+/// the off mesh links between islands are portals. The cost of travelling
+/// between each pair of portals in an island is precomputed, so paths between
+/// distant islands can be planned without exploring every node along the way.
+///
+/// Costs are only estimates (travelling through node centers), using the
+/// default type index costs of the navigation data. Nodes and portals that are
+/// carved out by obstacles (or closed doors) are not travelled through.
+#[derive(Default)]
+pub(crate) struct IslandGraph {
+  /// The portals of each island that is connected to another island.
+  islands: HashMap<IslandId, IslandPortals>,
+}
+
+/// The portals of a single island, and the costs of travelling between them.
+#[derive(Default, PartialEq, Debug)]
+struct IslandPortals {
+  /// The off mesh links that lead into this island from another island. These
+  /// are sorted.
+  entries: Vec<OffMeshLinkId>,
+  /// The off mesh links that lead from this island to another island, along
+  /// with the node they are taken from. These are sorted.
+  exits: Vec<(OffMeshLinkId, NodeRef)>,
+  /// The cost of travelling from each entry to each exit (including the cost
+  /// of taking the exit), indexed by `entry_index * exits.len() +
+  /// exit_index`. Pairs that cannot reach each other have infinite cost.
+  costs: Vec<f32>,
+}
+
+impl IslandGraph {
+  /// Updates the graph to match `nav_data`. Only islands whose portals have
+  /// changed, or that contain `changed_nodes` (e.g., nodes carved by
+  /// obstacles), are recomputed.
+  pub(crate) fn update<CS: CoordinateSystem>(
+    &mut self,
+    nav_data: &NavigationData<CS>,
+    changed_nodes: &HashSet<NodeRef>,
+  ) {
+    let changed_islands = changed_nodes
+      .iter()
+      .map(|node_ref| node_ref.island_id)
+      .collect::<HashSet<_>>();
+
+    let mut island_to_portals = HashMap::<IslandId, IslandPortals>::new();
+    for (node_ref, link_ids) in nav_data.node_to_off_mesh_link_ids.iter() {
+      for &link_id in link_ids {
+        let link = nav_data.off_mesh_links.get(link_id).unwrap();
+        if link.destination_node.island_id == node_ref.island_id {
+          continue;
+        }
+        island_to_portals
+          .entry(node_ref.island_id)
+          .or_default()
+          .exits
+          .push((link_id, *node_ref));
+        island_to_portals
+          .entry(link.destination_node.island_id)
+          .or_default()
+          .entries
+          .push(link_id);
+      }
+    }
+
+    // Remove any islands that no longer have portals.
+    self
+      .islands
+      .retain(|island_id, _| island_to_portals.contains_key(island_id));
+
+    for (island_id, mut portals) in island_to_portals {
+      portals.entries.sort();
+      portals.exits.sort();
+      if !changed_islands.contains(&island_id)
+        && let Some(old_portals) = self.islands.get(&island_id)
+        && old_portals.entries == portals.entries
+        && old_portals.exits == portals.exits
+      {
+        // Changing an island (or its neighbours) always replaces its links, so
+        // if the links are the same, the costs are still correct.
+        continue;
+      }
+
+      portals.costs =
+        Vec::with_capacity(portals.entries.len() * portals.exits.len());
+      for &entry in portals.entries.iter() {
+        let entry = nav_data.off_mesh_links.get(entry).unwrap();
+        let node_costs = node_costs_from_point(
+          nav_data,
+          entry.destination_node,
+          destination_point(entry),
+        );
+        portals.costs.extend(
+          portals
+            .exits
+            .iter()
+            .map(|&exit| exit_cost(nav_data, &node_costs, exit)),
+        );
+      }
+      self.islands.insert(island_id, portals);
+    }
+  }
+
+  /// Finds the islands that a path from `start_node` to `end_node` is likely
+  /// to travel through. Returns [`None`] if no path could be found in the
+  /// graph.
+  pub(crate) fn find_island_corridor<CS: CoordinateSystem>(
+    &self,
+    nav_data: &NavigationData<CS>,
+    (start_node, start_point): (NodeRef, Vec3),
+    (end_node, end_point): (NodeRef, Vec3),
+    permitted_animation_links: PermittedAnimationLinks,
+  ) -> Option<HashSet<IslandId>> {
+    let start_node_costs =
+      node_costs_from_point(nav_data, start_node, start_point);
+    let start_costs = self
+      .islands
+      .get(&start_node.island_id)
+      .map(|portals| {
+        portals
+          .exits
+          .iter()
+          .map(|&exit| (exit.0, exit_cost(nav_data, &start_node_costs, exit)))
+          .filter(|(_, cost)| cost.is_finite())
+          .collect()
+      })
+      .unwrap_or_default();
+
+    let end_node_costs = node_costs_from_point(nav_data, end_node, end_point);
+    let end_costs = self
+      .islands
+      .get(&end_node.island_id)
+      .map(|portals| {
+        portals
+          .entries
+          .iter()
+          .map(|&entry| {
+            let link = nav_data.off_mesh_links.get(entry).unwrap();
+            (
+              entry,
+              point_cost(
+                nav_data,
+                &end_node_costs,
+                link.destination_node,
+                destination_point(link),
+              ),
+            )
+          })
+          .filter(|(_, cost)| cost.is_finite())
+          .collect()
+      })
+      .unwrap_or_default();
+
+    let direct_cost = if start_node.island_id == end_node.island_id {
+      Some(point_cost(nav_data, &start_node_costs, end_node, end_point))
+        .filter(|cost| cost.is_finite())
+    } else {
+      None
+    };
+
+    let problem = IslandGraphProblem {
+      nav_data,
+      island_graph: self,
+      end_island: end_node.island_id,
+      end_point,
+      start_costs,
+      end_costs,
+      direct_cost,
+      cheapest_type_index_cost: nav_data
+        .get_type_index_costs()
+        .map(|(_, cost)| FloatOrd(cost))
+        .chain(std::iter::once(FloatOrd(1.0)))
+        .min()
+        .unwrap()
+        .0,
+      permitted_animation_links,
+    };
+    let islands = astar::find_path(&problem).path?;
+    Some(
+      std::iter::once(start_node.island_id)
+        .chain(islands)
+        .collect::<HashSet<_>>(),
+    )
+  }
+}
+
+/// The A* problem for searching the [`IslandGraph`].
+struct IslandGraphProblem<'a, CS: CoordinateSystem> {
+  /// The navigation data that the graph was built from.
+  nav_data: &'a NavigationData<CS>,
+  /// The graph to search.
+  island_graph: &'a IslandGraph,
+  /// The island that the end point is in.
+  end_island: IslandId,
+  /// The point the path is trying to reach.
+  end_point: Vec3,
+  /// The cost of reaching each exit of the start island from the start point.
+  start_costs: HashMap<OffMeshLinkId, f32>,
+  /// The cost of reaching the end point from each entry of the end island.
+  end_costs: HashMap<OffMeshLinkId, f32>,
+  /// The cost of reaching the end point directly from the start point, if they
+  /// are on the same island.
+  direct_cost: Option<f32>,
+  /// The cheapest type index cost in [`Self::nav_data`].
+  cheapest_type_index_cost: f32,
+  /// The set of permitted animation links for the agent.
+  permitted_animation_links: PermittedAnimationLinks,
+}
+
+/// A node in the [`IslandGraph`] search.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+enum IslandGraphNode {
+  /// The start point.
+  Start,
+  /// The destination of an off mesh link between islands.
+  Portal(OffMeshLinkId),
+  /// The end point.
+  End,
+}
+
+impl<CS: CoordinateSystem> IslandGraphProblem<'_, CS> {
+  /// Determines whether the agent may take `link`.
+  fn is_permitted(&self, link: &OffMeshLink) -> bool {
+    match link.kinded {
+      KindedOffMeshLink::BoundaryLink { .. } => true,
+      KindedOffMeshLink::AnimationLink { kind, .. } => {
+        self.permitted_animation_links.is_permitted(kind)
+      }
+    }
+  }
+}
+
+impl<CS: CoordinateSystem> AStarProblem for IslandGraphProblem<'_, CS> {
+  // The island that the path enters.
+  type ActionType = IslandId;
+
+  type StateType = IslandGraphNode;
+
+  fn initial_state(&self) -> Self::StateType {
+    IslandGraphNode::Start
+  }
+
+  fn successors(
+    &self,
+    state: &Self::StateType,
+  ) -> Vec<(f32, Self::ActionType, Self::StateType)> {
+    let portal = |(link_id, cost): (OffMeshLinkId, f32)| {
+      let link = self.nav_data.off_mesh_links.get(link_id).unwrap();
+      if !self.is_permitted(link) {
+        return None;
+      }
+      Some((
+        cost,
+        link.destination_node.island_id,
+        IslandGraphNode::Portal(link_id),
+      ))
+    };
+
+    match *state {
+      IslandGraphNode::Start => self
+        .start_costs
+        .iter()
+        .map(|(&link_id, &cost)| (link_id, cost))
+        .filter_map(portal)
+        .chain(
+          self
+            .direct_cost
+            .map(|cost| (cost, self.end_island, IslandGraphNode::End)),
+        )
+        .collect(),
+      IslandGraphNode::Portal(entry) => {
+        let link = self.nav_data.off_mesh_links.get(entry).unwrap();
+        let island_id = link.destination_node.island_id;
+        let portals = self.island_graph.islands.get(&island_id).unwrap();
+        let entry_index = portals.entries.binary_search(&entry).unwrap();
+        let costs = &portals.costs[entry_index * portals.exits.len()..]
+          [..portals.exits.len()];
+        portals
+          .exits
+          .iter()
+          .map(|&(link_id, _)| link_id)
+          .zip(costs.iter().copied())
+          .filter(|(_, cost)| cost.is_finite())
+          .filter_map(portal)
+          .chain(
+            self
+              .end_costs
+              .get(&entry)
+              .map(|&cost| (cost, self.end_island, IslandGraphNode::End)),
+          )
+          .collect()
+      }
+      IslandGraphNode::End => {
+        unreachable!("we never need the successors of the goal node")
+      }
+    }
+  }
+
+  fn heuristic(&self, state: &Self::StateType) -> f32 {
+    match *state {
+      // The start state is only expanded once, so its heuristic doesn't matter.
+      IslandGraphNode::Start | IslandGraphNode::End => 0.0,
+      IslandGraphNode::Portal(link_id) => {
+        let link = self.nav_data.off_mesh_links.get(link_id).unwrap();
+        destination_point(link).distance(self.end_point)
+          * self.cheapest_type_index_cost
+      }
+    }
+  }
+
+  fn is_goal_state(&self, state: &Self::StateType) -> bool {
+    matches!(state, IslandGraphNode::End)
+  }
+}
+
+/// The point that taking `link` leads to.
+fn destination_point(link: &OffMeshLink) -> Vec3 {
+  let portal = match &link.kinded {
+    KindedOffMeshLink::BoundaryLink { .. } => link.portal,
+    KindedOffMeshLink::AnimationLink { destination_portal, .. } => {
+      *destination_portal
+    }
+  };
+  portal.0.midpoint(portal.1)
+}
+
+/// Determines the default cost of the node `node_ref`.
+fn node_cost<CS: CoordinateSystem>(
+  nav_data: &NavigationData<CS>,
+  node_ref: NodeRef,
+) -> f32 {
+  if nav_data
+    .modified_nodes
+    .get(&node_ref)
+    .is_some_and(|modified_node| modified_node.is_carved_out())
+  {
+    return f32::INFINITY;
+  }
+  let island = nav_data.get_island(node_ref.island_id).unwrap();
+  let type_index = island.nav_mesh.polygons[node_ref.polygon_index].type_index;
+  nav_data.get_type_index_cost(type_index).unwrap_or(1.0)
+}
+
+/// The world-space center of `node_ref`.
+fn node_center<CS: CoordinateSystem>(
+  nav_data: &NavigationData<CS>,
+  node_ref: NodeRef,
+) -> Vec3 {
+  let island = nav_data.get_island(node_ref.island_id).unwrap();
+  island
+    .transform
+    .apply(island.nav_mesh.polygons[node_ref.polygon_index].center)
+}
+
+/// Computes the cost of travelling from `start_point` (in `start_node`) to the
+/// center of every node in the same island. Paths only travel through node
+/// centers. Unreachable nodes have infinite cost.
+fn node_costs_from_point<CS: CoordinateSystem>(
+  nav_data: &NavigationData<CS>,
+  start_node: NodeRef,
+  start_point: Vec3,
+) -> Vec<f32> {
+  let island = nav_data.get_island(start_node.island_id).unwrap();
+  let mut costs = vec![f32::INFINITY; island.nav_mesh.polygons.len()];
+  let start_cost = node_cost(nav_data, start_node);
+  if !start_cost.is_finite() {
+    return costs;
+  }
+
+  let mut queue = BinaryHeap::new();
+  let cost =
+    start_point.distance(node_center(nav_data, start_node)) * start_cost;
+  costs[start_node.polygon_index] = cost;
+  queue.push(Reverse((FloatOrd(cost), start_node.polygon_index)));
+
+  while let Some(Reverse((FloatOrd(cost), polygon_index))) = queue.pop() {
+    if cost > costs[polygon_index] {
+      continue;
+    }
+    let node_ref = NodeRef { island_id: start_node.island_id, polygon_index };
+    let center = node_center(nav_data, node_ref);
+    let current_node_cost = node_cost(nav_data, node_ref);
+
+    let polygon = &island.nav_mesh.polygons[polygon_index];
+    for (edge_index, connectivity) in polygon.connectivity.iter().enumerate() {
+      let Some(connectivity) = connectivity else {
+        continue;
+      };
+      let next_node_ref = NodeRef {
+        island_id: start_node.island_id,
+        polygon_index: connectivity.polygon_index,
+      };
+      let next_node_cost = node_cost(nav_data, next_node_ref);
+      if !next_node_cost.is_finite() {
+        continue;
+      }
+
+      let (i, j) = polygon.get_edge_indices(edge_index);
+      let portal = (
+        island.transform.apply(island.nav_mesh.vertices[i]),
+        island.transform.apply(island.nav_mesh.vertices[j]),
+      );
+      if nav_data.is_crossing_carved(
+        node_ref,
+        next_node_ref,
+        (portal.0.xy(), portal.1.xy()),
+      ) {
+        continue;
+      }
+      let midpoint = portal.0.midpoint(portal.1);
+      let next_cost = cost
+        + center.distance(midpoint) * current_node_cost
+        + midpoint.distance(node_center(nav_data, next_node_ref))
+          * next_node_cost;
+      if next_cost < costs[connectivity.polygon_index] {
+        costs[connectivity.polygon_index] = next_cost;
+        queue.push(Reverse((FloatOrd(next_cost), connectivity.polygon_index)));
+      }
+    }
+  }
+  costs
+}
+
+/// Computes the cost of reaching `point` (in `node_ref`) given the `node_costs`
+/// of the island.
+fn point_cost<CS: CoordinateSystem>(
+  nav_data: &NavigationData<CS>,
+  node_costs: &[f32],
+  node_ref: NodeRef,
+  point: Vec3,
+) -> f32 {
+  node_costs[node_ref.polygon_index]
+    + node_center(nav_data, node_ref).distance(point)
+      * node_cost(nav_data, node_ref)
+}
+
+/// Computes the cost of taking the `exit` link from `source_node` given the
+/// `node_costs` of the island.
+fn exit_cost<CS: CoordinateSystem>(
+  nav_data: &NavigationData<CS>,
+  node_costs: &[f32],
+  (exit, source_node): (OffMeshLinkId, NodeRef),
+) -> f32 {
+  let link = nav_data.off_mesh_links.get(exit).unwrap();
+  if !node_cost(nav_data, link.destination_node).is_finite() {
+    return f32::INFINITY;
+  }
+  let link_cost = match link.kinded {
+    KindedOffMeshLink::BoundaryLink { .. } => {
+      if nav_data.is_crossing_carved(
+        source_node,
+        link.destination_node,
+        (link.portal.0.xy(), link.portal.1.xy()),
+      ) {
+        return f32::INFINITY;
+      }
+      0.0
+    }
+    KindedOffMeshLink::AnimationLink { cost, .. } => cost,
+  };
+  point_cost(
+    nav_data,
+    node_costs,
+    source_node,
+    link.portal.0.midpoint(link.portal.1),
+  ) + link_cost
+}
+
+#[cfg(test)]
+#[path = "hierarchy_test.rs"]
+mod test;
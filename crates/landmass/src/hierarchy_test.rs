@@ -0,0 +1,384 @@
+use std::{
+  collections::{HashMap, HashSet},
+  sync::Arc,
+};
+
+use glam::{Quat, Vec2, Vec3};
+
+use crate::{
+  Archipelago, ArchipelagoOptions, Door, DoorEdges, FromAgentRadius, Island,
+  IslandId, NavigationMesh, PathQuery, PathfindingMode, Transform,
+  ValidNavigationMesh, agent::PermittedAnimationLinks, coords::XY,
+  nav_data::NodeRef, pathfinding::find_path,
+};
+
+fn square_nav_mesh(type_index: usize) -> Arc<ValidNavigationMesh<XY>> {
+  Arc::new(
+    NavigationMesh {
+      vertices: vec![
+        Vec2::new(0.0, 0.0),
+        Vec2::new(1.0, 0.0),
+        Vec2::new(1.0, 1.0),
+        Vec2::new(0.0, 1.0),
+      ],
+      polygons: vec![vec![0, 1, 2, 3]],
+      polygon_type_indices: vec![type_index],
+      polygon_flags: None,
+      height_mesh: None,
+    }
+    .validate()
+    .expect("nav mesh is valid"),
+  )
+}
+
+fn add_square(
+  archipelago: &mut Archipelago<XY>,
+  translation: Vec2,
+  nav_mesh: &Arc<ValidNavigationMesh<XY>>,
+) -> IslandId {
+  archipelago.add_island(Island::new(
+    Transform { translation, rotation: Quat::IDENTITY, scale: 1.0 },
+    Arc::clone(nav_mesh),
+  ))
+}
+
+fn hierarchical_archipelago() -> Archipelago<XY> {
+  Archipelago::<XY>::new(ArchipelagoOptions {
+    hierarchical_pathfinding: true,
+    ..ArchipelagoOptions::from_agent_radius(0.5)
+  })
+}
+
+fn find_island_corridor(
+  archipelago: &Archipelago<XY>,
+  start_island: IslandId,
+  end_island: IslandId,
+) -> Option<HashSet<IslandId>> {
+  archipelago.nav_data.island_graph.as_ref().unwrap().find_island_corridor(
+    &archipelago.nav_data,
+    (
+      NodeRef { island_id: start_island, polygon_index: 0 },
+      archipelago
+        .get_island(start_island)
+        .unwrap()
+        .transform
+        .apply(Vec3::new(0.5, 0.5, 0.0)),
+    ),
+    (
+      NodeRef { island_id: end_island, polygon_index: 0 },
+      archipelago
+        .get_island(end_island)
+        .unwrap()
+        .transform
+        .apply(Vec3::new(0.5, 0.5, 0.0)),
+    ),
+    PermittedAnimationLinks::All,
+  )
+}
+
+#[test]
+fn island_graph_only_built_when_enabled() {
+  let mut archipelago =
+    Archipelago::<XY>::new(ArchipelagoOptions::from_agent_radius(0.5));
+  let nav_mesh = square_nav_mesh(0);
+  add_square(&mut archipelago, Vec2::new(0.0, 0.0), &nav_mesh);
+  add_square(&mut archipelago, Vec2::new(1.0, 0.0), &nav_mesh);
+
+  archipelago.update(1.0);
+  assert!(archipelago.nav_data.island_graph.is_none());
+
+  archipelago.archipelago_options.hierarchical_pathfinding = true;
+  archipelago.update(1.0);
+  assert!(archipelago.nav_data.island_graph.is_some());
+
+  archipelago.archipelago_options.hierarchical_pathfinding = false;
+  archipelago.update(1.0);
+  assert!(archipelago.nav_data.island_graph.is_none());
+}
+
+#[test]
+fn finds_corridor_of_islands() {
+  let mut archipelago = hierarchical_archipelago();
+  let nav_mesh = square_nav_mesh(0);
+
+  // +-+
+  // |5|
+  // +-+-+-+
+  // |1|2|3|
+  // +-+-+-+
+  // |4|
+  // +-+
+  let island_1 = add_square(&mut archipelago, Vec2::new(0.0, 0.0), &nav_mesh);
+  let island_2 = add_square(&mut archipelago, Vec2::new(1.0, 0.0), &nav_mesh);
+  let island_3 = add_square(&mut archipelago, Vec2::new(2.0, 0.0), &nav_mesh);
+  add_square(&mut archipelago, Vec2::new(0.0, -1.0), &nav_mesh);
+  add_square(&mut archipelago, Vec2::new(0.0, 1.0), &nav_mesh);
+
+  archipelago.update(1.0);
+
+  assert_eq!(
+    find_island_corridor(&archipelago, island_1, island_3),
+    Some(HashSet::from([island_1, island_2, island_3]))
+  );
+  assert_eq!(
+    find_island_corridor(&archipelago, island_3, island_3),
+    Some(HashSet::from([island_3]))
+  );
+}
+
+#[test]
+fn island_graph_updates_as_islands_change() {
+  let mut archipelago = hierarchical_archipelago();
+  let nav_mesh = square_nav_mesh(0);
+
+  let island_1 = add_square(&mut archipelago, Vec2::new(0.0, 0.0), &nav_mesh);
+  let island_2 = add_square(&mut archipelago, Vec2::new(1.0, 0.0), &nav_mesh);
+  let island_3 = add_square(&mut archipelago, Vec2::new(2.0, 0.0), &nav_mesh);
+  // This island is not connected to anything yet.
+  let island_4 = add_square(&mut archipelago, Vec2::new(2.0, 5.0), &nav_mesh);
+
+  archipelago.update(1.0);
+  assert_eq!(find_island_corridor(&archipelago, island_1, island_4), None);
+
+  // Moving the island connects it to the others.
+  archipelago.get_island_mut(island_4).unwrap().set_transform(Transform {
+    translation: Vec2::new(3.0, 0.0),
+    rotation: Quat::IDENTITY,
+    scale: 1.0,
+  });
+  archipelago.update(1.0);
+  assert_eq!(
+    find_island_corridor(&archipelago, island_1, island_4),
+    Some(HashSet::from([island_1, island_2, island_3, island_4]))
+  );
+
+  // Removing an island in the middle disconnects them again.
+  archipelago.remove_island(island_2);
+  archipelago.update(1.0);
+  assert_eq!(find_island_corridor(&archipelago, island_1, island_4), None);
+
+  // Adding a replacement reconnects them.
+  let island_5 = add_square(&mut archipelago, Vec2::new(1.0, 0.0), &nav_mesh);
+  archipelago.update(1.0);
+  assert_eq!(
+    find_island_corridor(&archipelago, island_1, island_4),
+    Some(HashSet::from([island_1, island_5, island_3, island_4]))
+  );
+}
+
+#[test]
+fn island_graph_avoids_expensive_islands() {
+  let mut archipelago = hierarchical_archipelago();
+  let nav_mesh = square_nav_mesh(0);
+  let expensive_nav_mesh = square_nav_mesh(1);
+
+  // +-+-+-+
+  // |4|5|6|
+  // +-+-+-+
+  // |1|E|3|
+  // +-+-+-+
+  let island_1 = add_square(&mut archipelago, Vec2::new(0.0, 0.0), &nav_mesh);
+  let island_expensive =
+    add_square(&mut archipelago, Vec2::new(1.0, 0.0), &expensive_nav_mesh);
+  let island_3 = add_square(&mut archipelago, Vec2::new(2.0, 0.0), &nav_mesh);
+  let island_4 = add_square(&mut archipelago, Vec2::new(0.0, 1.0), &nav_mesh);
+  let island_5 = add_square(&mut archipelago, Vec2::new(1.0, 1.0), &nav_mesh);
+  let island_6 = add_square(&mut archipelago, Vec2::new(2.0, 1.0), &nav_mesh);
+
+  archipelago.update(1.0);
+  assert_eq!(
+    find_island_corridor(&archipelago, island_1, island_3),
+    Some(HashSet::from([island_1, island_expensive, island_3]))
+  );
+
+  // Changing the cost rebuilds the graph.
+  archipelago.set_type_index_cost(1, 100.0).unwrap();
+  archipelago.update(1.0);
+  assert_eq!(
+    find_island_corridor(&archipelago, island_1, island_3),
+    Some(HashSet::from([island_1, island_4, island_5, island_6, island_3]))
+  );
+}
+
+#[test]
+fn hierarchical_path_matches_full_search() {
+  let mut archipelago = hierarchical_archipelago();
+  let nav_mesh = square_nav_mesh(0);
+
+  // The path must go around the ring, but a full search will also explore the
+  // dead end (D) since it is closer to the end.
+  //
+  // E . . . .
+  //         .
+  // D D D   .
+  // D D D   .
+  // S . . . .
+  let layout = [
+    "E....", //
+    "    .", //
+    "DDD .", //
+    "DDD .", //
+    "S....", //
+  ];
+  let mut start_island = None;
+  let mut end_island = None;
+  for (y, row) in layout.iter().rev().enumerate() {
+    for (x, cell) in row.chars().enumerate() {
+      if cell == ' ' {
+        continue;
+      }
+      let island_id =
+        add_square(&mut archipelago, Vec2::new(x as f32, y as f32), &nav_mesh);
+      match cell {
+        'S' => start_island = Some(island_id),
+        'E' => end_island = Some(island_id),
+        _ => {}
+      }
+    }
+  }
+  archipelago.update(1.0);
+
+  let start_node =
+    NodeRef { island_id: start_island.unwrap(), polygon_index: 0 };
+  let start_point = Vec3::new(0.5, 0.5, 0.0);
+  let end_node = NodeRef { island_id: end_island.unwrap(), polygon_index: 0 };
+  let end_point = Vec3::new(0.5, 4.5, 0.0);
+
+  let hierarchical_result = find_path(
+    &archipelago.nav_data,
+    start_node,
+    start_point,
+    end_node,
+    end_point,
+    &PathQuery::default(),
+    PathfindingMode::EdgeMidpoints,
+  );
+
+  archipelago.archipelago_options.hierarchical_pathfinding = false;
+  archipelago.update(1.0);
+  let full_result = find_path(
+    &archipelago.nav_data,
+    start_node,
+    start_point,
+    end_node,
+    end_point,
+    &PathQuery::default(),
+    PathfindingMode::EdgeMidpoints,
+  );
+
+  assert!(hierarchical_result.path.is_some());
+  assert_eq!(hierarchical_result.path, full_result.path);
+  assert!(
+    hierarchical_result.stats.explored_nodes < full_result.stats.explored_nodes,
+    "hierarchical={:?} full={:?}",
+    hierarchical_result.stats,
+    full_result.stats
+  );
+}
+
+#[test]
+fn falls_back_to_full_search_if_corridor_is_blocked() {
+  let mut archipelago = hierarchical_archipelago();
+  let nav_mesh = square_nav_mesh(0);
+  let blocked_nav_mesh = square_nav_mesh(1);
+
+  // +-+-+-+
+  // |4|5|6|
+  // +-+-+-+
+  // |1|B|3|
+  // +-+-+-+
+  let island_1 = add_square(&mut archipelago, Vec2::new(0.0, 0.0), &nav_mesh);
+  add_square(&mut archipelago, Vec2::new(1.0, 0.0), &blocked_nav_mesh);
+  let island_3 = add_square(&mut archipelago, Vec2::new(2.0, 0.0), &nav_mesh);
+  let island_4 = add_square(&mut archipelago, Vec2::new(0.0, 1.0), &nav_mesh);
+  let island_5 = add_square(&mut archipelago, Vec2::new(1.0, 1.0), &nav_mesh);
+  let island_6 = add_square(&mut archipelago, Vec2::new(2.0, 1.0), &nav_mesh);
+  archipelago.update(1.0);
+
+  // The island graph only uses the default costs, so it plans through the
+  // blocked island.
+  let path_result = find_path(
+    &archipelago.nav_data,
+    NodeRef { island_id: island_1, polygon_index: 0 },
+    Vec3::new(0.5, 0.5, 0.0),
+    NodeRef { island_id: island_3, polygon_index: 0 },
+    Vec3::new(2.5, 0.5, 0.0),
+    &PathQuery {
+      override_type_index_costs: HashMap::from([(1, f32::INFINITY)]),
+      ..Default::default()
+    },
+    PathfindingMode::EdgeMidpoints,
+  );
+
+  assert_eq!(
+    path_result
+      .path
+      .expect("path is found")
+      .island_segments
+      .iter()
+      .map(|segment| segment.island_id)
+      .collect::<Vec<_>>(),
+    [island_1, island_4, island_5, island_6, island_3]
+  );
+}
+
+#[test]
+fn island_graph_avoids_closed_doors() {
+  let mut archipelago = hierarchical_archipelago();
+  let nav_mesh = square_nav_mesh(0);
+  // A square split into two nodes, so a door can block the portal between
+  // them.
+  let split_nav_mesh = Arc::new(
+    NavigationMesh {
+      vertices: vec![
+        Vec2::new(0.0, 0.0),
+        Vec2::new(0.5, 0.0),
+        Vec2::new(1.0, 0.0),
+        Vec2::new(1.0, 1.0),
+        Vec2::new(0.5, 1.0),
+        Vec2::new(0.0, 1.0),
+      ],
+      polygons: vec![vec![0, 1, 4, 5], vec![1, 2, 3, 4]],
+      polygon_type_indices: vec![0, 0],
+      polygon_flags: None,
+      height_mesh: None,
+    }
+    .validate()
+    .expect("nav mesh is valid"),
+  );
+
+  // +-+-+-+
+  // |4|5|6|
+  // +-+-+-+
+  // |1|D|3|
+  // +-+-+-+
+  let island_1 = add_square(&mut archipelago, Vec2::new(0.0, 0.0), &nav_mesh);
+  let island_door =
+    add_square(&mut archipelago, Vec2::new(1.0, 0.0), &split_nav_mesh);
+  let island_3 = add_square(&mut archipelago, Vec2::new(2.0, 0.0), &nav_mesh);
+  add_square(&mut archipelago, Vec2::new(0.0, 1.0), &nav_mesh);
+  let island_5 = add_square(&mut archipelago, Vec2::new(1.0, 1.0), &nav_mesh);
+  add_square(&mut archipelago, Vec2::new(2.0, 1.0), &nav_mesh);
+  let door_id = archipelago.add_door(Door {
+    edges: DoorEdges::NodeEdges(vec![(
+      NodeRef { island_id: island_door, polygon_index: 0 },
+      1,
+    )]),
+    open: true,
+  });
+
+  archipelago.update(1.0);
+  assert_eq!(
+    find_island_corridor(&archipelago, island_1, island_3),
+    Some(HashSet::from([island_1, island_door, island_3]))
+  );
+
+  // Closing the door means the island can no longer be crossed directly, so
+  // the path must detour through the island above the door.
+  archipelago.set_door_open(door_id, false);
+  archipelago.update(1.0);
+  assert_eq!(
+    find_island_corridor(&archipelago, island_1, island_3),
+    Some(HashSet::from([island_1, island_door, island_5, island_3]))
+  );
+}
@@ -23,19 +23,25 @@ pub struct Island<CS: CoordinateSystem> {
   pub(crate) transformed_bounds: BoundingBox,
   /// Whether the island has been updated recently.
   pub(crate) dirty: bool,
+  /// The transform of the island before it was moved, if the island has only
+  /// moved (and its navigation mesh is unchanged) since the last update.
+  pub(crate) moved_from: Option<Transform<CS>>,
 }
 
 impl<CS: CoordinateSystem> Island<CS> {
-  /// Creates a new island.
+  /// Creates a new island. Panics if the scale of `transform` is not positive
+  /// and finite.
   pub fn new(
     transform: Transform<CS>,
     nav_mesh: Arc<ValidNavigationMesh<CS>>,
   ) -> Self {
+    assert_valid_scale(transform.scale);
     Self {
       transformed_bounds: nav_mesh.get_bounds().transform(&transform),
       transform,
       nav_mesh,
       dirty: true,
+      moved_from: None,
     }
   }
 
@@ -44,8 +50,16 @@ impl<CS: CoordinateSystem> Island<CS> {
     &self.transform
   }
 
-  /// Sets the current transform of the island.
+  /// Sets the current transform of the island. Moving an island (without
+  /// changing its navigation mesh) does not invalidate paths on the island,
+  /// and agents standing on the island can be carried along with it (see
+  /// [`crate::Agent::carried_by_islands`]). Panics if the scale of `transform`
+  /// is not positive and finite.
   pub fn set_transform(&mut self, transform: Transform<CS>) {
+    assert_valid_scale(transform.scale);
+    if !self.dirty {
+      self.moved_from = Some(self.transform.clone());
+    }
     self.transform = transform;
     self.dirty = true;
 
@@ -62,8 +76,18 @@ impl<CS: CoordinateSystem> Island<CS> {
   pub fn set_nav_mesh(&mut self, nav_mesh: Arc<ValidNavigationMesh<CS>>) {
     self.nav_mesh = nav_mesh;
     self.dirty = true;
+    // The nodes may have changed, so the island can't just be moved.
+    self.moved_from = None;
 
     self.transformed_bounds =
       self.nav_mesh.get_bounds().transform(&self.transform);
   }
 }
+
+/// Panics if `scale` cannot be used as the scale of an island.
+fn assert_valid_scale(scale: f32) {
+  assert!(
+    scale.is_finite() && scale > 0.0,
+    "The scale of an island's transform must be positive and finite, but was {scale}."
+  );
+}
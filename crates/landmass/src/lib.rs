@@ -1,54 +1,83 @@
 #![doc = include_str!("../README.md")]
 
 mod agent;
+mod any_angle;
 mod astar;
 mod avoidance;
 mod character;
 mod coords;
+mod cost_filter;
+mod door;
+mod flow_field;
 mod geometry;
+mod hierarchy;
 mod island;
 mod link;
+mod modifier;
 mod nav_data;
 mod nav_mesh;
+mod obstacle;
 mod path;
 mod pathfinding;
 mod query;
+mod raycast;
+mod smoothing;
 mod util;
 
-use agent::{RepathResult, does_agent_need_repath};
+use agent::{
+  PartialTarget, PathProgress, RepathResult, does_agent_need_repath,
+};
+use flow_field::FlowFieldData;
 use glam::Vec3Swizzles;
 use path::PathIndex;
+use pathfinding::{PartialPath, PathResult, PathSearch};
 use slotmap::HopSlotMap;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
 
 use nav_data::NavigationData;
 
-pub use glam::Vec3;
+pub use glam::{Quat, Vec3};
 
 pub mod debug;
 
 pub use agent::{
   Agent, AgentId, AgentState, NotReachedAnimationLinkError,
-  PermittedAnimationLinks, ReachedAnimationLink, TargetReachedCondition,
+  PermittedAnimationLinks, PolygonFlagFilter, ReachedAnimationLink,
+  TargetReachedCondition,
 };
 pub use character::{Character, CharacterId};
 pub use coords::{
   CoordinateSystem, FromAgentRadius, PointSampleDistance,
-  PointSampleDistance3d, XY, XYZ,
+  PointSampleDistance3d, ThreadSafe, XY, XYZ,
 };
+pub use cost_filter::PathCostFilter;
+pub use door::{Door, DoorEdges, DoorId};
+pub use flow_field::{FlowField, FlowFieldId};
 pub use island::{Island, IslandId};
 pub use link::{AnimationLink, AnimationLinkId};
-pub use nav_data::{IslandMut, SetTypeIndexCostError};
+pub use modifier::{
+  ModifierEffect, ModifierVolume, ModifierVolumeError, ModifierVolumeId,
+};
+pub use nav_data::{IslandMut, NodeRef, SetTypeIndexCostError};
 pub use nav_mesh::{
   HeightNavigationMesh, HeightPolygon, NavigationMesh, ValidNavigationMesh,
   ValidationError,
 };
-pub use query::{FindPathError, PathStep, SamplePointError, SampledPoint};
+pub use obstacle::{Obstacle, ObstacleError, ObstacleId};
+pub use query::{
+  AgentPath, AgentPathError, FindPathError, FindReachableNodesError,
+  NearestBoundary, NodeInShape, PathQuery, PathStep, QueryNodesInShapeError,
+  QueryShape, RandomPointConstraints, RaycastHit, RaycastResult, ReachableNode,
+  SamplePointError, SampleRandomPointError, SampledPoint,
+};
+pub use smoothing::PathSmoothing;
 pub use util::Transform;
 
 use crate::{
-  avoidance::apply_avoidance_to_agents, coords::CorePointSampleDistance,
-  nav_data::NodeRef, path::StraightPathStep,
+  avoidance::apply_avoidance_to_agents,
+  coords::CorePointSampleDistance,
+  path::StraightPathStep,
+  util::{par_for_each, par_map},
 };
 
 pub struct Archipelago<CS: CoordinateSystem> {
@@ -56,7 +85,11 @@ pub struct Archipelago<CS: CoordinateSystem> {
   nav_data: NavigationData<CS>,
   agents: HopSlotMap<AgentId, Agent<CS>>,
   characters: HopSlotMap<CharacterId, Character<CS>>,
+  flow_fields: HopSlotMap<FlowFieldId, FlowField<CS>>,
   pathing_results: Vec<PathingResult>,
+  /// The agents that need a path but have not been given one yet due to the
+  /// pathfinding budget. Agents are serviced in the order they are queued.
+  pending_repaths: VecDeque<AgentId>,
 }
 
 /// Options that apply to the entire archipelago.
@@ -76,6 +109,48 @@ pub struct ArchipelagoOptions<CS: CoordinateSystem> {
   /// would mean no avoidance responsibility, but a value of 0.0 is invalid and
   /// may panic. This should be a value between 0.0 and 1.0.
   pub reached_destination_avoidance_responsibility: f32,
+  /// The amount of pathfinding that may be performed in a single update.
+  /// Agents that need a path beyond this budget are queued and serviced in
+  /// later updates.
+  pub pathfinding_budget: PathfindingBudget,
+  /// The algorithm used to find paths, both for agents and for
+  /// [`Archipelago::find_path`].
+  pub pathfinding_mode: PathfindingMode,
+  /// Whether to plan paths hierarchically. If true, paths are first planned
+  /// over an abstract graph of islands (connected by the links between them),
+  /// and then refined by searching only the islands along that plan. This can
+  /// make pathfinding much faster in worlds with many islands, at the cost of
+  /// keeping the graph up to date as islands change, and potentially slightly
+  /// longer paths.
+  pub hierarchical_pathfinding: bool,
+}
+
+/// The algorithm used to find paths.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PathfindingMode {
+  /// Paths are found assuming agents travel through the midpoints of edges.
+  /// This is fast, but can result in paths that are noticeably longer than
+  /// necessary, especially on nav meshes with large polygons.
+  EdgeMidpoints,
+  /// Paths are found by searching for the truly shortest path between the
+  /// start and end points (similar to Polyanya). This is generally slower than
+  /// [`Self::EdgeMidpoints`], and only considers distance: type index costs
+  /// are only used to exclude nodes with infinite cost.
+  AnyAngle,
+}
+
+/// Limits the amount of pathfinding performed in a single
+/// [`Archipelago::update`] call.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PathfindingBudget {
+  /// Every agent that needs a path will search for one in the same update.
+  Unlimited,
+  /// At most this many agents will search for a path in one update.
+  MaxRepaths(usize),
+  /// Agents will search for paths until the total number of explored nodes
+  /// reaches this limit. Searches that run out of budget are resumed in the
+  /// next update, so long paths can be found over several updates.
+  MaxExploredNodes(u32),
 }
 
 impl<CS: CoordinateSystem<SampleDistance: FromAgentRadius>> FromAgentRadius
@@ -88,6 +163,9 @@ impl<CS: CoordinateSystem<SampleDistance: FromAgentRadius>> FromAgentRadius
       avoidance_time_horizon: 0.5,
       obstacle_avoidance_time_horizon: 0.25,
       reached_destination_avoidance_responsibility: 0.1,
+      pathfinding_budget: PathfindingBudget::Unlimited,
+      pathfinding_mode: PathfindingMode::EdgeMidpoints,
+      hierarchical_pathfinding: false,
     }
   }
 }
@@ -99,7 +177,9 @@ impl<CS: CoordinateSystem> Archipelago<CS> {
       nav_data: NavigationData::new(),
       agents: HopSlotMap::with_key(),
       characters: HopSlotMap::with_key(),
+      flow_fields: HopSlotMap::with_key(),
       pathing_results: Vec::new(),
+      pending_repaths: VecDeque::new(),
     }
   }
 
@@ -157,6 +237,44 @@ impl<CS: CoordinateSystem> Archipelago<CS> {
     self.characters.keys()
   }
 
+  pub fn add_flow_field(&mut self, flow_field: FlowField<CS>) -> FlowFieldId {
+    self.flow_fields.insert(flow_field)
+  }
+
+  /// Removes the flow field. Any agents following the flow field stop
+  /// following it.
+  pub fn remove_flow_field(&mut self, flow_field_id: FlowFieldId) {
+    self
+      .flow_fields
+      .remove(flow_field_id)
+      .expect("Flow field should be present in the archipelago");
+    for agent in self.agents.values_mut() {
+      if agent.flow_field == Some(flow_field_id) {
+        agent.flow_field = None;
+      }
+    }
+  }
+
+  pub fn get_flow_field(
+    &self,
+    flow_field_id: FlowFieldId,
+  ) -> Option<&FlowField<CS>> {
+    self.flow_fields.get(flow_field_id)
+  }
+
+  pub fn get_flow_field_mut(
+    &mut self,
+    flow_field_id: FlowFieldId,
+  ) -> Option<&mut FlowField<CS>> {
+    self.flow_fields.get_mut(flow_field_id)
+  }
+
+  pub fn get_flow_field_ids(
+    &self,
+  ) -> impl ExactSizeIterator<Item = FlowFieldId> + '_ {
+    self.flow_fields.keys()
+  }
+
   pub fn add_island(&mut self, island: Island<CS>) -> IslandId {
     self.nav_data.add_island(island)
   }
@@ -204,6 +322,100 @@ impl<CS: CoordinateSystem> Archipelago<CS> {
     self.nav_data.get_animation_link_ids()
   }
 
+  /// Adds a modifier volume, which changes the cost of the nodes it overlaps.
+  pub fn add_modifier_volume(
+    &mut self,
+    volume: ModifierVolume<CS>,
+  ) -> Result<ModifierVolumeId, ModifierVolumeError> {
+    self.nav_data.add_modifier_volume(volume)
+  }
+
+  /// Replaces the modifier volume with `volume_id` (e.g., to move it). Only
+  /// paths through nodes that the old or new volume overlaps are invalidated.
+  /// Panics if the modifier volume ID is not in the archipelago.
+  pub fn set_modifier_volume(
+    &mut self,
+    volume_id: ModifierVolumeId,
+    volume: ModifierVolume<CS>,
+  ) -> Result<(), ModifierVolumeError> {
+    self.nav_data.set_modifier_volume(volume_id, volume)
+  }
+
+  pub fn remove_modifier_volume(&mut self, volume_id: ModifierVolumeId) {
+    self.nav_data.remove_modifier_volume(volume_id)
+  }
+
+  pub fn get_modifier_volume(
+    &self,
+    volume_id: ModifierVolumeId,
+  ) -> Option<&ModifierVolume<CS>> {
+    self.nav_data.get_modifier_volume(volume_id)
+  }
+
+  pub fn get_modifier_volume_ids(
+    &self,
+  ) -> impl ExactSizeIterator<Item = ModifierVolumeId> + '_ {
+    self.nav_data.get_modifier_volume_ids()
+  }
+
+  /// Adds an obstacle, which is carved out of the nodes it overlaps.
+  pub fn add_obstacle(
+    &mut self,
+    obstacle: Obstacle<CS>,
+  ) -> Result<ObstacleId, ObstacleError> {
+    self.nav_data.add_obstacle(obstacle)
+  }
+
+  /// Replaces the obstacle with `obstacle_id` (e.g., to move it). Only paths
+  /// through nodes that the old or new obstacle carves are invalidated. Panics
+  /// if the obstacle ID is not in the archipelago.
+  pub fn set_obstacle(
+    &mut self,
+    obstacle_id: ObstacleId,
+    obstacle: Obstacle<CS>,
+  ) -> Result<(), ObstacleError> {
+    self.nav_data.set_obstacle(obstacle_id, obstacle)
+  }
+
+  pub fn remove_obstacle(&mut self, obstacle_id: ObstacleId) {
+    self.nav_data.remove_obstacle(obstacle_id)
+  }
+
+  pub fn get_obstacle(&self, obstacle_id: ObstacleId) -> Option<&Obstacle<CS>> {
+    self.nav_data.get_obstacle(obstacle_id)
+  }
+
+  pub fn get_obstacle_ids(
+    &self,
+  ) -> impl ExactSizeIterator<Item = ObstacleId> + '_ {
+    self.nav_data.get_obstacle_ids()
+  }
+
+  /// Adds a door, which blocks its edges while closed. Edges with no
+  /// horizontal length are ignored.
+  pub fn add_door(&mut self, door: Door<CS>) -> DoorId {
+    self.nav_data.add_door(door)
+  }
+
+  /// Opens or closes the door with `door_id`. Closing a door invalidates paths
+  /// through the nodes next to it. Panics if the door ID is not in the
+  /// archipelago.
+  pub fn set_door_open(&mut self, door_id: DoorId, open: bool) {
+    self.nav_data.set_door_open(door_id, open)
+  }
+
+  pub fn remove_door(&mut self, door_id: DoorId) {
+    self.nav_data.remove_door(door_id)
+  }
+
+  pub fn get_door(&self, door_id: DoorId) -> Option<&Door<CS>> {
+    self.nav_data.get_door(door_id)
+  }
+
+  pub fn get_door_ids(&self) -> impl ExactSizeIterator<Item = DoorId> + '_ {
+    self.nav_data.get_door_ids()
+  }
+
   /// Sets the cost of `type_index` to `cost`. The cost is a multiplier on the
   /// distance travelled along this node (essentially the cost per meter).
   /// Agents will prefer to travel along low-cost terrain.
@@ -212,7 +424,12 @@ impl<CS: CoordinateSystem> Archipelago<CS> {
     type_index: usize,
     cost: f32,
   ) -> Result<(), SetTypeIndexCostError> {
-    self.nav_data.set_type_index_cost(type_index, cost)
+    self.nav_data.set_type_index_cost(type_index, cost)?;
+    // Flow fields depend on the type index costs, so they must be recomputed.
+    for flow_field in self.flow_fields.values_mut() {
+      flow_field.field = None;
+    }
+    Ok(())
   }
 
   /// Gets the cost of `type_index`.
@@ -246,32 +463,122 @@ impl<CS: CoordinateSystem> Archipelago<CS> {
     )
   }
 
+  /// Casts a ray along the navigation meshes from `start_point` towards
+  /// `end_point` (ignoring height), to check whether an agent could walk there
+  /// in a straight line (e.g., for a charge attack). The ray moves between
+  /// connected nodes and islands, but never takes animation links. Obstacles
+  /// and closed doors stop the ray like the boundary of the navigation meshes.
+  /// Node costs are ignored, so the ray can pass through nodes with infinite
+  /// cost. Only [`SampledPoint`]s from this archipelago are supported.
+  pub fn raycast(
+    &self,
+    start_point: &SampledPoint<'_, CS>,
+    end_point: &CS::Coordinate,
+  ) -> RaycastResult<CS> {
+    query::raycast(self, start_point, end_point)
+  }
+
+  /// Finds the nearest point on the boundary of the navigation meshes (i.e.,
+  /// the nearest "wall") to `point`, within `max_distance`. Seams between
+  /// connected islands are not considered boundaries. Returns [`None`] if there
+  /// is no boundary within `max_distance`. Only [`SampledPoint`]s from this
+  /// archipelago are supported.
+  pub fn find_nearest_boundary(
+    &self,
+    point: &SampledPoint<'_, CS>,
+    max_distance: f32,
+  ) -> Option<NearestBoundary<CS>> {
+    query::find_nearest_boundary(self, point, max_distance)
+  }
+
+  /// Gets the remaining path of `agent_id` as of the last [`Self::update`]:
+  /// the straight-line waypoints (and animation links) to the target, the
+  /// length of the path, and the nodes it crosses. This is useful for
+  /// previewing routes or estimating arrival times.
+  pub fn get_agent_path(
+    &self,
+    agent_id: AgentId,
+  ) -> Result<AgentPath<CS>, AgentPathError> {
+    query::agent_path(self, agent_id)
+  }
+
+  /// Picks a uniformly random point on the navigation meshes that satisfies
+  /// `constraints` (e.g., for wandering or spawning). `rng` must return
+  /// uniformly distributed values in [0, 1), so providing a seeded random
+  /// number generator makes the results reproducible.
+  pub fn sample_random_point(
+    &self,
+    constraints: &RandomPointConstraints<'_, CS>,
+    rng: &mut impl FnMut() -> f32,
+  ) -> Result<SampledPoint<'_, CS>, SampleRandomPointError> {
+    query::sample_random_point(self, constraints, rng)
+  }
+
+  /// Finds every node that overlaps `shape`, along with the area of the node
+  /// inside the shape. This is useful for finding the nodes in an area of
+  /// effect (e.g., an explosion or a trigger volume). The nodes are sorted by
+  /// their [`NodeRef`].
+  pub fn query_nodes_in_shape(
+    &self,
+    shape: &QueryShape<CS>,
+  ) -> Result<Vec<NodeInShape>, QueryNodesInShapeError> {
+    query::query_nodes_in_shape(self, shape)
+  }
+
   /// Finds a path from `start_point` and `end_point` along the navigation
   /// meshes. Only [`SampledPoint`]s from this archipelago are supported. This
   /// should only be used for querying (e.g., finding the walking distance to an
   /// object), not for controlling movement. For controlling movement, use
-  /// agents.
+  /// agents. `query` determines where the path may travel and how it is
+  /// costed (see [`PathQuery`]).
   pub fn find_path(
     &self,
     start_point: &SampledPoint<'_, CS>,
     end_point: &SampledPoint<'_, CS>,
-    override_type_index_costs: &HashMap<usize, f32>,
-    permitted_animation_links: PermittedAnimationLinks,
+    query: &PathQuery<'_>,
   ) -> Result<Vec<PathStep<CS>>, FindPathError> {
-    query::find_path(
-      self,
-      start_point,
-      end_point,
-      override_type_index_costs,
-      permitted_animation_links,
-    )
+    query::find_path(self, start_point, end_point, query)
+  }
+
+  /// Finds a path from `start_point` to whichever of `end_points` is cheapest
+  /// to reach (e.g., the closest of many health packs by walking distance).
+  /// This uses a single search, so it is much cheaper than calling
+  /// [`Self::find_path`] for each end point. Returns the index of the chosen
+  /// end point and the path to it. Only [`SampledPoint`]s from this
+  /// archipelago are supported. `query` determines where the path may travel
+  /// and how it is costed (see [`PathQuery`]). [`PathQuery::smoothing`] is
+  /// ignored.
+  pub fn find_path_to_nearest(
+    &self,
+    start_point: &SampledPoint<'_, CS>,
+    end_points: &[SampledPoint<'_, CS>],
+    query: &PathQuery<'_>,
+  ) -> Result<(usize, Vec<PathStep<CS>>), FindPathError> {
+    query::find_path_to_nearest(self, start_point, end_points, query)
+  }
+
+  /// Finds every node that can be reached from `start_point` with a cost of
+  /// at most `max_cost` (e.g., to show the movement range of a unit). Each
+  /// node includes the cheapest cost to reach it, and the point where that
+  /// path enters the node. Only [`SampledPoint`]s from this archipelago are
+  /// supported. `query` determines where paths may travel and how they are
+  /// costed (see [`PathQuery`]). [`PathQuery::smoothing`] is ignored.
+  pub fn find_reachable_nodes(
+    &self,
+    start_point: &SampledPoint<'_, CS>,
+    max_cost: f32,
+    query: &PathQuery<'_>,
+  ) -> Result<Vec<ReachableNode<CS>>, FindReachableNodesError> {
+    query::find_reachable_nodes(self, start_point, max_cost, query)
   }
 
   pub fn update(&mut self, delta_time: f32) {
     self.pathing_results.clear();
 
+    let nav_data_changed = self.nav_data.dirty;
+
     // TODO: make the edge_link_distance configurable.
-    let (invalidated_off_mesh_links, invalidated_islands) =
+    let (invalidated_off_mesh_links, invalidated_islands, invalidated_nodes) =
       self.nav_data.update(
         /* edge_link_distance= */ 0.01,
         self
@@ -279,46 +586,163 @@ impl<CS: CoordinateSystem> Archipelago<CS> {
           .point_sample_distance
           .animation_link_max_vertical_distance(),
       );
+    self.nav_data.update_island_graph(
+      self.archipelago_options.hierarchical_pathfinding,
+      nav_data_changed,
+      &invalidated_nodes,
+    );
 
-    let mut agent_id_to_agent_node = HashMap::new();
-    let mut agent_id_to_target_node = HashMap::new();
-
-    for (agent_id, agent) in self.agents.iter_mut() {
-      if agent.paused {
-        // We don't care to sample the agent location if the agent is paused.
-        agent.state = AgentState::Paused;
-        continue;
+    // Islands that only moved keep their nodes and off mesh links, so searches
+    // and partial paths are carried along with the islands instead.
+    let nav_data_restructured =
+      nav_data_changed && !self.nav_data.only_islands_moved;
+    if nav_data_restructured {
+      // Any searches in progress may refer to nav data that no longer exists,
+      // so they must start over.
+      for agent in self.agents.values_mut() {
+        agent.path_search = None;
       }
-      if agent.using_animation_link {
-        // We don't care to sample the agent location if the agent is using an
-        // animation link.
-        agent.state = AgentState::UsingAnimationLink;
-        continue;
+    }
+
+    if !self.nav_data.moved_islands.is_empty() {
+      for agent in self.agents.values_mut() {
+        if let Some(path) = agent.current_path.as_mut() {
+          path.carry_with_islands(&self.nav_data);
+        }
+        if let Some(partial_target) = agent.partial_target.as_mut()
+          && let Some(point) = self.nav_data.carry_point_with_island(
+            partial_target.node.island_id,
+            partial_target.point,
+          )
+        {
+          partial_target.point = point;
+        }
+        if let Some(path_search) = agent.path_search.as_mut()
+          && !path_search.carry_with_islands(&self.nav_data)
+        {
+          agent.path_search = None;
+        }
+        if !agent.carried_by_islands {
+          continue;
+        }
+        if let Some(position) = agent.current_island.and_then(|island_id| {
+          self.nav_data.carry_point_with_island(
+            island_id,
+            CS::to_landmass(&agent.position),
+          )
+        }) {
+          agent.position = CS::from_landmass(&position);
+        }
+        if agent.flow_field.is_some() {
+          // The flow field target is not owned by the agent.
+          continue;
+        }
+        if let Some(target) = agent.current_target.as_ref()
+          && let Some(target) = agent.target_island.and_then(|island_id| {
+            self
+              .nav_data
+              .carry_point_with_island(island_id, CS::to_landmass(target))
+          })
+        {
+          agent.current_target = Some(CS::from_landmass(&target));
+        }
       }
-      let agent_node_and_point = match self.nav_data.sample_point(
-        CS::to_landmass(&agent.position),
-        &CorePointSampleDistance::new(
-          &self.archipelago_options.point_sample_distance,
-        ),
-      ) {
-        None => continue,
-        Some(node_and_point) => node_and_point,
-      };
-      let inserted =
-        agent_id_to_agent_node.insert(agent_id, agent_node_and_point).is_none();
-      debug_assert!(inserted);
-
-      if let Some(target) = &agent.current_target {
-        let target_node_and_point = match self.nav_data.sample_point(
-          CS::to_landmass(target),
-          &CorePointSampleDistance::new(
-            &self.archipelago_options.point_sample_distance,
-          ),
-        ) {
-          None => continue,
-          Some(node_and_point) => node_and_point,
+    }
+
+    let point_sample_distance = CorePointSampleDistance::new(
+      &self.archipelago_options.point_sample_distance,
+    );
+
+    let recomputed_flow_fields = par_map(
+      self.flow_fields.iter_mut().collect(),
+      |(flow_field_id, flow_field)| {
+        let Some((target_point, target_node)) = self.nav_data.sample_point(
+          CS::to_landmass(&flow_field.target),
+          &point_sample_distance,
+        ) else {
+          flow_field.field = None;
+          return None;
         };
+        if let Some(field) = flow_field.field.as_mut()
+          && !nav_data_changed
+          && field.can_reuse_for_target(
+            &self.nav_data,
+            target_node,
+            target_point,
+          )
+        {
+          // The target is still in the same node, so the field can be reused.
+          field.target_point = target_point;
+          return None;
+        }
+        flow_field.field = Some(FlowFieldData::compute(
+          &self.nav_data,
+          target_node,
+          target_point,
+          &flow_field.permitted_animation_links,
+        ));
+        Some(flow_field_id)
+      },
+    )
+    .into_iter()
+    .flatten()
+    .collect::<HashSet<_>>();
 
+    let sampled_agents =
+      par_map(self.agents.iter_mut().collect(), |(agent_id, agent)| {
+        if agent.paused {
+          // We don't care to sample the agent location if the agent is paused.
+          agent.state = AgentState::Paused;
+          return (agent_id, None, None);
+        }
+        if agent.using_animation_link {
+          // We don't care to sample the agent location if the agent is using
+          // an animation link.
+          agent.state = AgentState::UsingAnimationLink;
+          agent.current_island = None;
+          agent.target_island = None;
+          return (agent_id, None, None);
+        }
+        let Some(agent_node_and_point) = self.nav_data.sample_point(
+          CS::to_landmass(&agent.position),
+          &point_sample_distance,
+        ) else {
+          agent.current_island = None;
+          agent.target_island = None;
+          return (agent_id, None, None);
+        };
+        agent.current_island = Some(agent_node_and_point.1.island_id);
+        let target_node_and_point = match agent.flow_field {
+          Some(flow_field_id) => self
+            .flow_fields
+            .get(flow_field_id)
+            .and_then(|flow_field| flow_field.field.as_ref())
+            .map(|field| (field.target_point, field.target_node)),
+          None => agent.current_target.as_ref().and_then(|target| {
+            self
+              .nav_data
+              .sample_point(CS::to_landmass(target), &point_sample_distance)
+          }),
+        };
+        agent.target_island = match agent.flow_field {
+          Some(_) => None,
+          None => target_node_and_point.map(|(_, node)| node.island_id),
+        };
+        (agent_id, Some(agent_node_and_point), target_node_and_point)
+      });
+
+    let mut agent_id_to_agent_node = HashMap::new();
+    let mut agent_id_to_target_node = HashMap::new();
+    for (agent_id, agent_node_and_point, target_node_and_point) in
+      sampled_agents
+    {
+      if let Some(agent_node_and_point) = agent_node_and_point {
+        let inserted = agent_id_to_agent_node
+          .insert(agent_id, agent_node_and_point)
+          .is_none();
+        debug_assert!(inserted);
+      }
+      if let Some(target_node_and_point) = target_node_and_point {
         let inserted = agent_id_to_target_node
           .insert(agent_id, target_node_and_point)
           .is_none();
@@ -326,37 +750,56 @@ impl<CS: CoordinateSystem> Archipelago<CS> {
       }
     }
 
-    let mut character_id_to_nav_mesh_point = HashMap::new();
-    for (character_id, character) in self.characters.iter() {
-      let character_point = match self.nav_data.sample_point(
-        CS::to_landmass(&character.position),
-        &CorePointSampleDistance::new(
-          &self.archipelago_options.point_sample_distance,
-        ),
-      ) {
-        None => continue,
-        Some(point_and_node) => point_and_node.0,
-      };
-      character_id_to_nav_mesh_point.insert(character_id, character_point);
-    }
+    let character_id_to_nav_mesh_point =
+      par_map(self.characters.iter().collect(), |(character_id, character)| {
+        self
+          .nav_data
+          .sample_point(
+            CS::to_landmass(&character.position),
+            &point_sample_distance,
+          )
+          .map(|(point, _)| (character_id, point))
+      })
+      .into_iter()
+      .flatten()
+      .collect::<HashMap<_, _>>();
 
     let mut agent_id_to_follow_path_indices = HashMap::new();
+    let mut agents_needing_repath = Vec::new();
 
     for (agent_id, agent) in self.agents.iter_mut() {
       // Clear the animation link whether the agent is paused or not. If we
       // still reached the same animation link, we'll re-set it.
       agent.current_animation_link = None;
 
+      if nav_data_restructured && agent.partial_target.is_some() {
+        // The target may be reachable now, so try to reach it again.
+        agent.current_path = None;
+        agent.partial_target = None;
+      }
       if agent.paused || agent.using_animation_link {
         if let Some(path) = agent.current_path.as_ref()
-          && !path.is_valid(&invalidated_off_mesh_links, &invalidated_islands)
+          && !path.is_valid(
+            &invalidated_off_mesh_links,
+            &invalidated_islands,
+            &invalidated_nodes,
+          )
         {
           // If the path has been invalidated, clear the path to keep the agent
           // consistent.
           agent.current_path = None;
+          agent.partial_target = None;
         }
         continue;
       }
+      if let Some(flow_field_id) = agent.flow_field
+        && recomputed_flow_fields.contains(&flow_field_id)
+      {
+        // The path was taken from the old field, so follow the new field
+        // instead.
+        agent.current_path = None;
+        agent.partial_target = None;
+      }
       let agent_point_and_node = agent_id_to_agent_node.get(&agent_id);
       let target_point_and_node = agent_id_to_target_node.get(&agent_id);
       match does_agent_need_repath(
@@ -365,6 +808,7 @@ impl<CS: CoordinateSystem> Archipelago<CS> {
         target_point_and_node.map(|(_, node)| *node),
         &invalidated_off_mesh_links,
         &invalidated_islands,
+        &invalidated_nodes,
       ) {
         RepathResult::DoNothing => {}
         RepathResult::FollowPath(
@@ -379,75 +823,95 @@ impl<CS: CoordinateSystem> Archipelago<CS> {
         RepathResult::ClearPathNoTarget => {
           agent.state = AgentState::Idle;
           agent.current_path = None;
+          agent.partial_target = None;
         }
         RepathResult::ClearPathBadAgent => {
           agent.state = AgentState::AgentNotOnNavMesh;
           agent.current_path = None;
+          agent.partial_target = None;
         }
         RepathResult::ClearPathBadTarget => {
           agent.state = AgentState::TargetNotOnNavMesh;
           agent.current_path = None;
+          agent.partial_target = None;
         }
         RepathResult::NeedsRepath => {
           agent.current_path = None;
-
+          agent.partial_target = None;
+          let Some(flow_field_id) = agent.flow_field else {
+            agents_needing_repath.push(agent_id);
+            continue;
+          };
+          // Following a flow field doesn't need a search, so it doesn't use
+          // the pathfinding budget.
+          agent.path_search = None;
           let (agent_point, agent_node) = agent_point_and_node.unwrap();
-          let (target_point, target_node) = target_point_and_node.unwrap();
-          let path_result = pathfinding::find_path(
-            &self.nav_data,
-            *agent_node,
-            *agent_point,
-            *target_node,
-            *target_point,
-            &agent.override_type_index_to_cost,
-            agent.permitted_animation_links.clone(),
-          );
-
-          self.pathing_results.push(PathingResult {
-            agent: agent_id,
-            success: path_result.path.is_some(),
-            explored_nodes: path_result.stats.explored_nodes,
-          });
-
-          let Some(new_path) = path_result.path else {
+          let new_path = self.flow_fields[flow_field_id]
+            .field
+            .as_ref()
+            .expect("The agent has a target node, so the field is computed.")
+            .path_from(&self.nav_data, *agent_node, *agent_point);
+          let Some(new_path) = new_path else {
             agent.state = AgentState::NoPath;
             continue;
           };
-
           agent_id_to_follow_path_indices.insert(
             agent_id,
             (PathIndex::from_corridor_index(0, 0), new_path.last_index()),
           );
           agent.current_path = Some(new_path);
+          agent.partial_target = None;
         }
       }
     }
 
-    for (agent_id, agent) in self.agents.iter_mut() {
+    self.repath_agents(
+      agents_needing_repath,
+      &agent_id_to_agent_node,
+      &agent_id_to_target_node,
+      &mut agent_id_to_follow_path_indices,
+    );
+
+    par_for_each(self.agents.iter_mut().collect(), |(agent_id, agent)| {
       let path = match &agent.current_path {
         None => {
           agent.current_desired_move = CS::from_landmass(&Vec3::ZERO);
-          continue;
+          agent.path_progress = None;
+          return;
         }
         Some(path) => path,
       };
 
-      let Some(agent_point) =
-        agent_id_to_agent_node.get(&agent_id).map(|x| x.0)
+      let Some(&(agent_point, agent_node)) =
+        agent_id_to_agent_node.get(&agent_id)
       else {
         // If the agent is paused, they may not have an agent node, even if the
         // agent has a path.
-        continue;
+        return;
+      };
+      let target_point = match &agent.partial_target {
+        Some(partial_target) => partial_target.point,
+        None => agent_id_to_target_node
+          .get(&agent_id)
+          .expect("Agent has a path and is not paused, so should have a valid target node")
+          .0,
+      };
+      let (moving_state, reached_state) = if agent.partial_target.is_some() {
+        (AgentState::MovingToPartialTarget, AgentState::ReachedPartialTarget)
+      } else {
+        (AgentState::Moving, AgentState::ReachedTarget)
       };
-      let target_point = agent_id_to_target_node
-        .get(&agent_id)
-        .expect("Agent has a path and is not paused, so should have a valid target node")
-        .0;
 
       let &(agent_node_index_in_corridor, target_node_index_in_corridor) =
         agent_id_to_follow_path_indices.get(&agent_id).expect(
           "Any agent with a path must have its follow path indices filled out.",
         );
+      agent.path_progress = Some(PathProgress {
+        agent_index: agent_node_index_in_corridor,
+        agent_point,
+        target_index: target_node_index_in_corridor,
+        target_point,
+      });
 
       let next_waypoint = path.find_next_point_in_straight_path(
         &self.nav_data,
@@ -455,6 +919,7 @@ impl<CS: CoordinateSystem> Archipelago<CS> {
         agent_point,
         target_node_index_in_corridor,
         target_point,
+        agent.corner_offset(),
       );
 
       if agent.has_reached_target(
@@ -465,11 +930,11 @@ impl<CS: CoordinateSystem> Archipelago<CS> {
         (target_node_index_in_corridor, target_point),
       ) {
         agent.current_desired_move = CS::from_landmass(&Vec3::ZERO);
-        agent.state = AgentState::ReachedTarget;
+        agent.state = reached_state;
       } else {
         let waypoint = match next_waypoint.1 {
           StraightPathStep::Waypoint(point) => {
-            agent.state = AgentState::Moving;
+            agent.state = moving_state;
             point
           }
           StraightPathStep::AnimationLink {
@@ -506,21 +971,62 @@ impl<CS: CoordinateSystem> Archipelago<CS> {
                 link_id,
               });
             } else {
-              agent.state = AgentState::Moving;
+              agent.state = moving_state;
             }
             start_point
           }
         };
 
+        let waypoint = match (&agent.path_smoothing, next_waypoint.1) {
+          (Some(smoothing), StraightPathStep::Waypoint(_)) => {
+            let next_point = ((next_waypoint.0, waypoint)
+              != (target_node_index_in_corridor, target_point))
+              .then(|| {
+                match path
+                  .find_next_point_in_straight_path(
+                    &self.nav_data,
+                    next_waypoint.0,
+                    waypoint,
+                    target_node_index_in_corridor,
+                    target_point,
+                    agent.corner_offset(),
+                  )
+                  .1
+                {
+                  StraightPathStep::Waypoint(point) => point,
+                  StraightPathStep::AnimationLink { start_point, .. } => {
+                    start_point
+                  }
+                }
+              });
+            smoothing::smoothed_steering_point(
+              &self.nav_data,
+              (agent_node, agent_point),
+              CS::to_landmass(&agent.velocity),
+              waypoint,
+              next_point,
+              smoothing,
+            )
+          }
+          _ => waypoint,
+        };
+
+        let speed = agent.arrival_speed(
+          path,
+          &self.nav_data,
+          agent_point,
+          next_waypoint,
+          (target_node_index_in_corridor, target_point),
+        );
         let desired_move = (waypoint - CS::to_landmass(&agent.position))
           .xy()
           .normalize_or_zero()
-          * agent.desired_speed;
+          * speed;
 
         agent.current_desired_move =
           CS::from_landmass(&desired_move.extend(0.0));
       }
-    }
+    });
 
     apply_avoidance_to_agents(
       &mut self.agents,
@@ -531,7 +1037,303 @@ impl<CS: CoordinateSystem> Archipelago<CS> {
       &self.archipelago_options,
       delta_time,
     );
+
+    for agent in self.agents.values_mut() {
+      // Paused agents and agents using animation links keep their desired
+      // move from before, so it shouldn't be limited again.
+      if agent.paused || agent.using_animation_link {
+        continue;
+      }
+      agent.current_desired_move =
+        CS::from_landmass(&agent.limit_desired_move(
+          CS::to_landmass(&agent.current_desired_move),
+          delta_time,
+        ));
+      agent.current_island_desired_move = agent
+        .current_island
+        .and_then(|island_id| self.nav_data.get_island(island_id))
+        .map(|island| {
+          // Only undo the rotation, so the velocity stays in world units.
+          CS::from_landmass(&island.transform.apply_inverse_rotation(
+            CS::to_landmass(&agent.current_desired_move),
+          ))
+        });
+    }
   }
+
+  /// Finds paths for `agents_needing_repath` in the order they were queued,
+  /// until the pathfinding budget is used up. The remaining agents stay in the
+  /// queue until a later update.
+  fn repath_agents(
+    &mut self,
+    agents_needing_repath: Vec<AgentId>,
+    agent_id_to_agent_node: &HashMap<AgentId, (Vec3, NodeRef)>,
+    agent_id_to_target_node: &HashMap<AgentId, (Vec3, NodeRef)>,
+    agent_id_to_follow_path_indices: &mut HashMap<
+      AgentId,
+      (PathIndex, PathIndex),
+    >,
+  ) {
+    // Drop any queued agents that no longer need a path (e.g., they were
+    // removed, paused, or lost their target), while keeping the order of the
+    // rest. New requests go to the back of the queue.
+    let agents_needing_repath_set =
+      agents_needing_repath.iter().copied().collect::<HashSet<_>>();
+    self.pending_repaths.retain(|agent_id| {
+      if agents_needing_repath_set.contains(agent_id) {
+        return true;
+      }
+      if let Some(agent) = self.agents.get_mut(*agent_id) {
+        // The agent may have been part way through a search, but it doesn't
+        // need that anymore.
+        agent.path_search = None;
+      }
+      false
+    });
+    let already_queued =
+      self.pending_repaths.iter().copied().collect::<HashSet<_>>();
+    self.pending_repaths.extend(
+      agents_needing_repath
+        .into_iter()
+        .filter(|agent_id| !already_queued.contains(agent_id)),
+    );
+
+    let completed_searches = match self.archipelago_options.pathfinding_budget {
+      PathfindingBudget::Unlimited => self.repath_batch(
+        self.pending_repaths.len(),
+        agent_id_to_agent_node,
+        agent_id_to_target_node,
+      ),
+      // Always allow at least one search per update so agents can't be starved
+      // by a tiny budget.
+      PathfindingBudget::MaxRepaths(max_repaths) => self.repath_batch(
+        max_repaths.max(1),
+        agent_id_to_agent_node,
+        agent_id_to_target_node,
+      ),
+      PathfindingBudget::MaxExploredNodes(max_explored_nodes) => self
+        .repath_until_explored(
+          max_explored_nodes,
+          agent_id_to_agent_node,
+          agent_id_to_target_node,
+        ),
+    };
+
+    for (agent_id, path_result, partial_path) in completed_searches {
+      let queued_agent_id = self.pending_repaths.pop_front();
+      debug_assert_eq!(queued_agent_id, Some(agent_id));
+
+      self.pathing_results.push(PathingResult {
+        agent: agent_id,
+        success: path_result.path.is_some(),
+        explored_nodes: path_result.stats.explored_nodes,
+        deferred: false,
+      });
+
+      let agent = self.agents.get_mut(agent_id).unwrap();
+      let (new_path, partial_target) = match (path_result.path, partial_path) {
+        (Some(new_path), _) => (new_path, None),
+        (None, Some(partial_path)) => (
+          partial_path.path,
+          Some(PartialTarget {
+            target_node: agent_id_to_target_node[&agent_id].1,
+            node: partial_path.node,
+            point: partial_path.point,
+          }),
+        ),
+        (None, None) => {
+          agent.state = AgentState::NoPath;
+          continue;
+        }
+      };
+
+      agent_id_to_follow_path_indices.insert(
+        agent_id,
+        (PathIndex::from_corridor_index(0, 0), new_path.last_index()),
+      );
+      agent.current_path = Some(new_path);
+      agent.partial_target = partial_target;
+    }
+
+    for &agent_id in self.pending_repaths.iter() {
+      let agent = self.agents.get_mut(agent_id).unwrap();
+      agent.state = AgentState::WaitingForPath;
+      self.pathing_results.push(PathingResult {
+        agent: agent_id,
+        success: false,
+        explored_nodes: agent
+          .path_search
+          .as_ref()
+          .map_or(0, |search| search.stats().explored_nodes),
+        deferred: true,
+      });
+    }
+  }
+
+  /// Finds paths for the first `batch_size` queued agents. Since these searches
+  /// are independent, they may run in parallel. Returns the results in queue
+  /// order.
+  fn repath_batch(
+    &mut self,
+    batch_size: usize,
+    agent_id_to_agent_node: &HashMap<AgentId, (Vec3, NodeRef)>,
+    agent_id_to_target_node: &HashMap<AgentId, (Vec3, NodeRef)>,
+  ) -> Vec<(AgentId, PathResult, Option<PartialPath>)> {
+    let batch = self
+      .pending_repaths
+      .iter()
+      .take(batch_size)
+      .copied()
+      .collect::<HashSet<_>>();
+    let mut agent_id_to_path_result = par_map(
+      self
+        .agents
+        .iter_mut()
+        .filter(|(agent_id, _)| batch.contains(agent_id))
+        .collect(),
+      |(agent_id, agent)| {
+        let (_, path_result) = step_agent_path_search(
+          agent,
+          agent_id_to_agent_node[&agent_id],
+          agent_id_to_target_node[&agent_id],
+          &self.nav_data,
+          self.archipelago_options.pathfinding_mode,
+          /* max_explored_nodes= */ None,
+        );
+        (
+          agent_id,
+          path_result.expect("The search has no limit, so it must finish."),
+        )
+      },
+    )
+    .into_iter()
+    .collect::<HashMap<_, _>>();
+
+    self
+      .pending_repaths
+      .iter()
+      .take(batch_size)
+      .map(|agent_id| {
+        let (path_result, partial_path) =
+          agent_id_to_path_result.remove(agent_id).unwrap();
+        (*agent_id, path_result, partial_path)
+      })
+      .collect()
+  }
+
+  /// Finds paths for queued agents in order, until `max_explored_nodes` nodes
+  /// have been explored. Returns the results of the searches that finished, in
+  /// queue order.
+  fn repath_until_explored(
+    &mut self,
+    max_explored_nodes: u32,
+    agent_id_to_agent_node: &HashMap<AgentId, (Vec3, NodeRef)>,
+    agent_id_to_target_node: &HashMap<AgentId, (Vec3, NodeRef)>,
+  ) -> Vec<(AgentId, PathResult, Option<PartialPath>)> {
+    let mut completed_searches = vec![];
+    let mut explored_nodes = 0;
+    for &agent_id in self.pending_repaths.iter() {
+      // Always allow at least one search per update so agents can't be starved
+      // by a tiny budget.
+      if !completed_searches.is_empty() && explored_nodes >= max_explored_nodes
+      {
+        break;
+      }
+
+      let (newly_explored_nodes, path_result) = step_agent_path_search(
+        self.agents.get_mut(agent_id).unwrap(),
+        agent_id_to_agent_node[&agent_id],
+        agent_id_to_target_node[&agent_id],
+        &self.nav_data,
+        self.archipelago_options.pathfinding_mode,
+        Some(max_explored_nodes.saturating_sub(explored_nodes).max(1)),
+      );
+      explored_nodes += newly_explored_nodes;
+      let Some(path_result) = path_result else {
+        // The search ran out of budget, so it will continue in the next update.
+        break;
+      };
+      let (path_result, partial_path) = path_result;
+      completed_searches.push((agent_id, path_result, partial_path));
+    }
+    completed_searches
+  }
+}
+
+/// Continues the path search for `agent` (starting a new one if needed),
+/// exploring at most `max_explored_nodes` nodes. Returns the number of nodes
+/// explored by this call, and the result if the search finished. If the search
+/// finished without a path and the agent allows partial paths, the result
+/// includes the path to the closest reachable point.
+fn step_agent_path_search<CS: CoordinateSystem>(
+  agent: &mut Agent<CS>,
+  (agent_point, agent_node): (Vec3, NodeRef),
+  (target_point, target_node): (Vec3, NodeRef),
+  nav_data: &NavigationData<CS>,
+  pathfinding_mode: PathfindingMode,
+  max_explored_nodes: Option<u32>,
+) -> (u32, Option<(PathResult, Option<PartialPath>)>) {
+  if agent.path_search.as_ref().is_some_and(|search| {
+    search.end_node() != target_node
+      || !search.is_started_with(
+        agent_node,
+        &agent.permitted_animation_links,
+        agent.polygon_flag_filter,
+        agent.radius,
+        agent.cost_filter.as_deref(),
+      )
+  }) {
+    // The target has moved to a different node, or the agent has moved to a
+    // different node or changed how it paths, so the search in progress is no
+    // longer useful.
+    agent.path_search = None;
+  }
+  let path_search = agent.path_search.get_or_insert_with(|| {
+    PathSearch::new(
+      nav_data,
+      agent_node,
+      agent_point,
+      target_node,
+      target_point,
+      &agent.override_type_index_to_cost,
+      agent.permitted_animation_links.clone(),
+      agent.polygon_flag_filter,
+      agent.radius,
+      agent.cost_filter.as_deref(),
+      pathfinding_mode,
+    )
+  });
+
+  let previously_explored_nodes = path_search.stats().explored_nodes;
+  let path_result = path_search.step(
+    nav_data,
+    &agent.override_type_index_to_cost,
+    agent.permitted_animation_links.clone(),
+    agent.polygon_flag_filter,
+    agent.radius,
+    agent.cost_filter.as_deref(),
+    max_explored_nodes,
+  );
+  let newly_explored_nodes =
+    path_search.stats().explored_nodes - previously_explored_nodes;
+  let Some(path_result) = path_result else {
+    return (newly_explored_nodes, None);
+  };
+  let partial_path = if path_result.path.is_none() && agent.allow_partial_paths
+  {
+    path_search.find_partial_path(
+      nav_data,
+      &agent.override_type_index_to_cost,
+      agent.permitted_animation_links.clone(),
+      agent.polygon_flag_filter,
+      agent.radius,
+      agent.cost_filter.as_deref(),
+    )
+  } else {
+    None
+  };
+  agent.path_search = None;
+  (newly_explored_nodes, Some((path_result, partial_path)))
 }
 
 /// The result of path finding.
@@ -544,6 +1346,12 @@ pub struct PathingResult {
   /// The number of "nodes" explored while finding the path. Note this may be
   /// zero if the start and end point are known to be disconnected.
   pub explored_nodes: u32,
+  /// Whether the search was deferred to a later update due to
+  /// [`ArchipelagoOptions::pathfinding_budget`]. Deferred searches are never
+  /// successful, and [`Self::explored_nodes`] is the number of nodes explored
+  /// by the search so far (which may span several updates). The agent will be
+  /// reported again once its search has finished.
+  pub deferred: bool,
 }
 
 #[cfg(test)]
@@ -1,6 +1,6 @@
-use std::{collections::HashMap, f32::consts::PI, fmt::Debug, sync::Arc};
+use std::{f32::consts::PI, fmt::Debug, sync::Arc};
 
-use glam::{Vec2, Vec3};
+use glam::{Quat, Vec2, Vec3};
 use googletest::{
   expect_eq, expect_false, expect_that, expect_true,
   matcher::MatcherResult,
@@ -10,10 +10,11 @@ use googletest::{
 
 use crate::{
   Agent, AgentId, AgentState, AnimationLink, Archipelago, ArchipelagoOptions,
-  Character, CharacterId, CoordinateSystem, FromAgentRadius, Island, IslandId,
-  NavigationMesh, PathStep, PointSampleDistance3d, ReachedAnimationLink,
-  Transform, ValidNavigationMesh,
-  agent::PermittedAnimationLinks,
+  Character, CharacterId, CoordinateSystem, Door, DoorEdges, FromAgentRadius,
+  Island, IslandId, ModifierEffect, ModifierVolume, NavigationMesh, Obstacle,
+  PathQuery, PathSmoothing, PathStep, PathfindingBudget, PathingResult,
+  PointSampleDistance3d, ReachedAnimationLink, Transform, ValidNavigationMesh,
+  agent::PolygonFlagFilter,
   coords::{XY, XYZ},
   nav_data::NodeRef,
   path::Path,
@@ -187,13 +188,14 @@ fn computes_and_follows_path() {
       vec![5, 6, 7, 8],
     ],
     polygon_type_indices: vec![0, 0, 0, 0, 0],
+    polygon_flags: None,
     height_mesh: None,
   }
   .validate()
   .expect("is valid");
 
   archipelago.add_island(Island::new(
-    Transform { translation: Vec3::ZERO, rotation: 0.0 },
+    Transform { translation: Vec3::ZERO, rotation: Quat::IDENTITY, scale: 1.0 },
     Arc::new(nav_mesh),
   ));
 
@@ -440,6 +442,7 @@ fn agent_speeds_up_to_avoid_character() {
       ],
       polygons: vec![vec![0, 1, 2, 3]],
       polygon_type_indices: vec![0],
+      polygon_flags: None,
       height_mesh: None,
     }
     .validate()
@@ -494,6 +497,7 @@ fn add_and_remove_islands() {
       vertices: vec![],
       polygons: vec![],
       polygon_type_indices: vec![],
+      polygon_flags: None,
       height_mesh: None,
     }
     .validate()
@@ -537,6 +541,7 @@ fn changed_island_is_not_dirty_after_update() {
         vertices: vec![],
         polygons: vec![],
         polygon_type_indices: vec![],
+        polygon_flags: None,
         height_mesh: None,
       }
       .validate()
@@ -573,6 +578,7 @@ fn simple_one_node_nav_mesh() -> Arc<ValidNavigationMesh<XY>> {
       ],
       polygons: vec![vec![0, 1, 2, 3]],
       polygon_type_indices: vec![0],
+      polygon_flags: None,
       height_mesh: None,
     }
     .validate()
@@ -589,7 +595,7 @@ fn samples_point() {
 
   let offset = Vec2::new(10.0, 10.0);
   archipelago.add_island(Island::new(
-    Transform { translation: offset, rotation: 0.0 },
+    Transform { translation: offset, rotation: Quat::IDENTITY, scale: 1.0 },
     nav_mesh,
   ));
   archipelago.update(1.0);
@@ -632,15 +638,23 @@ fn finds_path() {
 
   let offset = Vec2::new(10.0, 10.0);
   archipelago.add_island(Island::new(
-    Transform { translation: offset, rotation: 0.0 },
+    Transform { translation: offset, rotation: Quat::IDENTITY, scale: 1.0 },
     nav_mesh.clone(),
   ));
   archipelago.add_island(Island::new(
-    Transform { translation: offset + Vec2::new(1.0, 0.0), rotation: 0.0 },
+    Transform {
+      translation: offset + Vec2::new(1.0, 0.0),
+      rotation: Quat::IDENTITY,
+      scale: 1.0,
+    },
     nav_mesh.clone(),
   ));
   archipelago.add_island(Island::new(
-    Transform { translation: offset + Vec2::new(2.0, 0.5), rotation: 0.0 },
+    Transform {
+      translation: offset + Vec2::new(2.0, 0.5),
+      rotation: Quat::IDENTITY,
+      scale: 1.0,
+    },
     nav_mesh,
   ));
   archipelago.update(1.0);
@@ -652,12 +666,7 @@ fn finds_path() {
     .sample_point(offset + Vec2::new(2.5, 1.25), &1e-5)
     .expect("point is on nav mesh.");
   assert_eq!(
-    archipelago.find_path(
-      &start_point,
-      &end_point,
-      &HashMap::new(),
-      PermittedAnimationLinks::All,
-    ),
+    archipelago.find_path(&start_point, &end_point, &PathQuery::default()),
     Ok(vec![
       PathStep::Waypoint(offset + Vec2::new(0.5, 0.5)),
       PathStep::Waypoint(offset + Vec2::new(2.0, 1.0)),
@@ -666,12 +675,10 @@ fn finds_path() {
   );
 }
 
-#[test]
-fn agent_overrides_node_costs() {
-  let mut archipelago =
-    Archipelago::<XY>::new(ArchipelagoOptions::from_agent_radius(0.5));
-
-  let nav_mesh = Arc::new(
+/// Creates a nav mesh shaped like a ring around a hole, where the left side of
+/// the ring is a single node with type index 1.
+fn ring_nav_mesh() -> Arc<ValidNavigationMesh<XY>> {
+  Arc::new(
     NavigationMesh {
       vertices: vec![
         Vec2::new(0.0, 0.0),
@@ -711,11 +718,20 @@ fn agent_overrides_node_costs() {
         vec![3, 2, 13, 15],
       ],
       polygon_type_indices: vec![0, 0, 0, 0, 0, 0, 0, 1],
+      polygon_flags: None,
       height_mesh: None,
     }
     .validate()
     .expect("nav mesh is valid"),
-  );
+  )
+}
+
+#[test]
+fn agent_overrides_node_costs() {
+  let mut archipelago =
+    Archipelago::<XY>::new(ArchipelagoOptions::from_agent_radius(0.5));
+
+  let nav_mesh = ring_nav_mesh();
 
   archipelago.set_type_index_cost(1, 1.0).unwrap();
 
@@ -744,6 +760,371 @@ fn agent_overrides_node_costs() {
   );
 }
 
+#[test]
+fn agent_avoids_modifier_volume_and_repaths_when_it_moves() {
+  let mut archipelago =
+    Archipelago::<XY>::new(ArchipelagoOptions::from_agent_radius(0.5));
+  archipelago.add_island(Island::new(Transform::default(), ring_nav_mesh()));
+
+  let volume_id = archipelago
+    .add_modifier_volume(ModifierVolume {
+      vertices: vec![
+        Vec2::new(0.0, 5.0),
+        Vec2::new(1.0, 5.0),
+        Vec2::new(1.0, 6.0),
+        Vec2::new(0.0, 6.0),
+      ],
+      height_range: None,
+      effect: ModifierEffect::AddCost(10.0),
+    })
+    .unwrap();
+
+  let agent_id = archipelago.add_agent({
+    let mut agent = Agent::create(
+      /* position= */ Vec2::new(0.5, 0.5),
+      /* velocity= */ Vec2::ZERO,
+      /* radius= */ 0.5,
+      /* desired_speed= */ 1.0,
+      /* max_speed= */ 1.0,
+    );
+    agent.current_target = Some(Vec2::new(0.5, 11.5));
+    agent
+  });
+
+  archipelago.update(1.0);
+
+  // The volume makes going directly up expensive, so the agent takes the
+  // detour to the right.
+  assert_eq!(
+    *archipelago.get_agent(agent_id).unwrap().get_desired_velocity(),
+    Vec2::new(1.5, 0.5).normalize(),
+  );
+
+  // Moving the volume onto the detour invalidates the path, so the agent goes
+  // directly up instead.
+  archipelago
+    .set_modifier_volume(
+      volume_id,
+      ModifierVolume {
+        vertices: vec![
+          Vec2::new(2.0, 5.0),
+          Vec2::new(3.0, 5.0),
+          Vec2::new(3.0, 6.0),
+          Vec2::new(2.0, 6.0),
+        ],
+        height_range: None,
+        effect: ModifierEffect::AddCost(10.0),
+      },
+    )
+    .unwrap();
+  archipelago.update(1.0);
+
+  assert_eq!(
+    *archipelago.get_agent(agent_id).unwrap().get_desired_velocity(),
+    Vec2::new(0.0, 1.0),
+  );
+}
+
+#[test]
+fn agent_repaths_around_obstacle_splitting_its_path() {
+  let mut archipelago =
+    Archipelago::<XY>::new(ArchipelagoOptions::from_agent_radius(0.5));
+  archipelago.add_island(Island::new(Transform::default(), ring_nav_mesh()));
+
+  let agent_id = archipelago.add_agent({
+    let mut agent = Agent::create(
+      /* position= */ Vec2::new(0.5, 0.5),
+      /* velocity= */ Vec2::ZERO,
+      /* radius= */ 0.5,
+      /* desired_speed= */ 1.0,
+      /* max_speed= */ 1.0,
+    );
+    agent.current_target = Some(Vec2::new(0.5, 11.5));
+    agent
+  });
+
+  archipelago.update(1.0);
+
+  assert_eq!(
+    *archipelago.get_agent(agent_id).unwrap().get_desired_velocity(),
+    Vec2::new(0.0, 1.0),
+  );
+
+  // The obstacle cuts the left side of the ring in two, so the agent must take
+  // the detour to the right.
+  archipelago
+    .add_obstacle(Obstacle::Aabb {
+      min: Vec2::new(-1.0, 5.0),
+      max: Vec2::new(2.0, 6.0),
+    })
+    .unwrap();
+  archipelago.update(1.0);
+
+  assert_eq!(
+    *archipelago.get_agent(agent_id).unwrap().get_desired_velocity(),
+    Vec2::new(1.5, 0.5).normalize(),
+  );
+}
+
+#[test]
+fn agent_paths_onto_tilted_and_scaled_island() {
+  let mut archipelago =
+    Archipelago::<XYZ>::new(ArchipelagoOptions::from_agent_radius(0.5));
+
+  let square_nav_mesh = |size: f32| {
+    Arc::new(
+      NavigationMesh {
+        vertices: vec![
+          Vec3::new(0.0, 0.0, 0.0),
+          Vec3::new(size, 0.0, 0.0),
+          Vec3::new(size, size, 0.0),
+          Vec3::new(0.0, size, 0.0),
+        ],
+        polygons: vec![vec![0, 1, 2, 3]],
+        polygon_type_indices: vec![0],
+        polygon_flags: None,
+        height_mesh: None,
+      }
+      .validate()
+      .expect("nav mesh is valid"),
+    )
+  };
+
+  let floor_id = archipelago
+    .add_island(Island::new(Transform::default(), square_nav_mesh(2.0)));
+  // The ramp starts at the far edge of the floor and slopes upwards.
+  let ramp_transform = Transform {
+    translation: Vec3::new(0.0, 2.0, 0.0),
+    rotation: Quat::from_rotation_x(PI / 6.0),
+    scale: 2.0,
+  };
+  let ramp_id = archipelago
+    .add_island(Island::new(ramp_transform.clone(), square_nav_mesh(1.0)));
+
+  let target = ramp_transform.apply(Vec3::new(0.5, 0.75, 0.0));
+  let agent_id = archipelago.add_agent({
+    let mut agent = Agent::create(
+      /* position= */ Vec3::new(1.0, 1.0, 0.0),
+      /* velocity= */ Vec3::ZERO,
+      /* radius= */ 0.5,
+      /* desired_speed= */ 1.0,
+      /* max_speed= */ 1.0,
+    );
+    agent.current_target = Some(target);
+    agent
+  });
+
+  archipelago.update(1.0);
+
+  let agent = archipelago.get_agent(agent_id).unwrap();
+  assert_eq!(agent.state(), AgentState::Moving);
+  let path = agent.current_path.as_ref().expect("The agent has a path.");
+  assert_eq!(
+    path
+      .island_segments
+      .iter()
+      .map(|segment| segment.island_id)
+      .collect::<Vec<_>>(),
+    [floor_id, ramp_id]
+  );
+  assert!(
+    agent.get_desired_velocity().abs_diff_eq(Vec3::new(0.0, 1.0, 0.0), 1e-5),
+    "desired_velocity={}",
+    agent.get_desired_velocity()
+  );
+}
+
+#[test]
+fn moving_island_carries_agents_and_keeps_paths() {
+  let mut archipelago =
+    Archipelago::<XY>::new(ArchipelagoOptions::from_agent_radius(0.5));
+  let island_id = archipelago.add_island(Island::new(
+    Transform::default(),
+    Arc::new(
+      NavigationMesh {
+        vertices: vec![
+          Vec2::new(0.0, 0.0),
+          Vec2::new(2.0, 0.0),
+          Vec2::new(4.0, 0.0),
+          Vec2::new(4.0, 1.0),
+          Vec2::new(2.0, 1.0),
+          Vec2::new(0.0, 1.0),
+        ],
+        polygons: vec![vec![0, 1, 4, 5], vec![1, 2, 3, 4]],
+        polygon_type_indices: vec![0, 0],
+        polygon_flags: None,
+        height_mesh: None,
+      }
+      .validate()
+      .expect("nav mesh is valid"),
+    ),
+  ));
+
+  let create_agent = |carried_by_islands| {
+    let mut agent = Agent::create(
+      /* position= */ Vec2::new(0.5, 0.5),
+      /* velocity= */ Vec2::ZERO,
+      /* radius= */ 0.5,
+      /* desired_speed= */ 1.0,
+      /* max_speed= */ 1.0,
+    );
+    agent.current_target = Some(Vec2::new(3.5, 0.5));
+    agent.carried_by_islands = carried_by_islands;
+    agent
+  };
+  let carried_agent_id = archipelago.add_agent(create_agent(true));
+  let uncarried_agent_id = archipelago.add_agent(create_agent(false));
+
+  archipelago.update(1.0);
+
+  let agent = archipelago.get_agent(carried_agent_id).unwrap();
+  assert_eq!(agent.state(), AgentState::Moving);
+  assert_eq!(agent.get_current_island(), Some(island_id));
+  assert_eq!(agent.get_desired_velocity(), &Vec2::new(1.0, 0.0));
+  assert_eq!(agent.get_island_desired_velocity(), Some(&Vec2::new(1.0, 0.0)));
+  let path = agent.current_path.clone().expect("The agent has a path.");
+
+  archipelago.get_island_mut(island_id).unwrap().set_transform(Transform {
+    translation: Vec2::new(10.0, 0.0),
+    rotation: Quat::from_rotation_z(PI * 0.5),
+    scale: 1.0,
+  });
+  archipelago.update(1.0);
+
+  // The agent and its target moved with the island, and the agent keeps
+  // following the same path without searching for a new one.
+  let agent = archipelago.get_agent(carried_agent_id).unwrap();
+  assert_eq!(agent.state(), AgentState::Moving);
+  assert!(
+    agent.position.abs_diff_eq(Vec2::new(9.5, 0.5), 1e-5),
+    "position={}",
+    agent.position
+  );
+  let target = agent.current_target.unwrap();
+  assert!(target.abs_diff_eq(Vec2::new(9.5, 3.5), 1e-5), "target={target}");
+  let carried_path = agent.current_path.as_ref().unwrap();
+  assert_eq!(carried_path.island_segments, path.island_segments);
+  assert!(
+    carried_path.end_point.abs_diff_eq(Vec3::new(9.5, 3.5, 0.0), 1e-5),
+    "end_point={}",
+    carried_path.end_point
+  );
+  assert!(archipelago.get_pathing_results().is_empty());
+  // The desired velocity is rotated in the world, but not relative to the
+  // island.
+  assert!(
+    agent.get_desired_velocity().abs_diff_eq(Vec2::new(0.0, 1.0), 1e-5),
+    "desired_velocity={}",
+    agent.get_desired_velocity()
+  );
+  let island_desired_velocity = agent.get_island_desired_velocity().unwrap();
+  assert!(
+    island_desired_velocity.abs_diff_eq(Vec2::new(1.0, 0.0), 1e-5),
+    "island_desired_velocity={island_desired_velocity}"
+  );
+
+  // The agent that isn't carried is left behind.
+  let agent = archipelago.get_agent(uncarried_agent_id).unwrap();
+  assert_eq!(agent.position, Vec2::new(0.5, 0.5));
+  assert_eq!(agent.state(), AgentState::AgentNotOnNavMesh);
+  assert_eq!(agent.get_current_island(), None);
+  assert_eq!(agent.get_island_desired_velocity(), None);
+}
+
+#[test]
+fn island_desired_velocity_is_rotated_but_not_scaled() {
+  let mut archipelago =
+    Archipelago::<XY>::new(ArchipelagoOptions::from_agent_radius(0.5));
+  archipelago.add_island(Island::new(
+    Transform {
+      translation: Vec2::ZERO,
+      rotation: Quat::from_rotation_z(PI * 0.5),
+      scale: 2.0,
+    },
+    Arc::new(
+      NavigationMesh {
+        vertices: vec![
+          Vec2::new(0.0, 0.0),
+          Vec2::new(4.0, 0.0),
+          Vec2::new(4.0, 1.0),
+          Vec2::new(0.0, 1.0),
+        ],
+        polygons: vec![vec![0, 1, 2, 3]],
+        polygon_type_indices: vec![0],
+        polygon_flags: None,
+        height_mesh: None,
+      }
+      .validate()
+      .expect("nav mesh is valid"),
+    ),
+  ));
+
+  let mut agent = Agent::create(
+    /* position= */ Vec2::new(-1.0, 1.0),
+    /* velocity= */ Vec2::ZERO,
+    /* radius= */ 0.5,
+    /* desired_speed= */ 1.0,
+    /* max_speed= */ 1.0,
+  );
+  agent.current_target = Some(Vec2::new(-1.0, 7.0));
+  let agent_id = archipelago.add_agent(agent);
+
+  archipelago.update(1.0);
+
+  let agent = archipelago.get_agent(agent_id).unwrap();
+  assert!(
+    agent.get_desired_velocity().abs_diff_eq(Vec2::new(0.0, 1.0), 1e-5),
+    "desired_velocity={}",
+    agent.get_desired_velocity()
+  );
+  let island_desired_velocity = agent.get_island_desired_velocity().unwrap();
+  assert!(
+    island_desired_velocity.abs_diff_eq(Vec2::new(1.0, 0.0), 1e-5),
+    "island_desired_velocity={island_desired_velocity}"
+  );
+}
+
+#[test]
+fn agent_repaths_around_closed_door() {
+  let mut archipelago =
+    Archipelago::<XY>::new(ArchipelagoOptions::from_agent_radius(0.5));
+  archipelago.add_island(Island::new(Transform::default(), ring_nav_mesh()));
+
+  // The door cuts the left side of the ring in two.
+  let door_id = archipelago.add_door(Door {
+    edges: DoorEdges::Cut(Vec2::new(-1.0, 5.5), Vec2::new(2.0, 5.5)),
+    open: true,
+  });
+
+  let agent_id = archipelago.add_agent({
+    let mut agent = Agent::create(
+      /* position= */ Vec2::new(0.5, 0.5),
+      /* velocity= */ Vec2::ZERO,
+      /* radius= */ 0.5,
+      /* desired_speed= */ 1.0,
+      /* max_speed= */ 1.0,
+    );
+    agent.current_target = Some(Vec2::new(0.5, 11.5));
+    agent
+  });
+
+  archipelago.update(1.0);
+
+  assert_eq!(
+    *archipelago.get_agent(agent_id).unwrap().get_desired_velocity(),
+    Vec2::new(0.0, 1.0),
+  );
+
+  // Closing the door forces the agent to take the detour to the right.
+  archipelago.set_door_open(door_id, false);
+  archipelago.update(1.0);
+
+  assert_eq!(
+    *archipelago.get_agent(agent_id).unwrap().get_desired_velocity(),
+    Vec2::new(1.5, 0.5).normalize(),
+  );
+}
+
 fn path_start_and_end(
   expected_start: NodeRef,
   expected_end: NodeRef,
@@ -791,6 +1172,7 @@ fn paused_agent_does_not_repath() {
       ],
       polygons: vec![vec![0, 1, 2, 3], vec![3, 2, 4, 5], vec![5, 4, 6, 7]],
       polygon_type_indices: vec![0; 3],
+      polygon_flags: None,
       height_mesh: None,
     }
     .validate()
@@ -913,11 +1295,19 @@ fn paused_agent_path_is_removed_when_invalid() {
   let island_1 =
     archipelago.add_island(Island::new(Transform::default(), nav_mesh.clone()));
   let island_2 = archipelago.add_island(Island::new(
-    Transform { translation: Vec2::new(0.0, 1.0), rotation: 0.0 },
+    Transform {
+      translation: Vec2::new(0.0, 1.0),
+      rotation: Quat::IDENTITY,
+      scale: 1.0,
+    },
     nav_mesh.clone(),
   ));
   archipelago.add_island(Island::new(
-    Transform { translation: Vec2::new(1.0, 0.0), rotation: 0.0 },
+    Transform {
+      translation: Vec2::new(1.0, 0.0),
+      rotation: Quat::IDENTITY,
+      scale: 1.0,
+    },
     nav_mesh,
   ));
 
@@ -1027,6 +1417,7 @@ fn agent_can_use_animation_link() {
       ],
       polygons: vec![vec![0, 1, 2, 3], vec![2, 1, 4], vec![2, 4, 5, 6]],
       polygon_type_indices: vec![0; 3],
+      polygon_flags: None,
       height_mesh: None,
     }
     .validate()
@@ -1035,7 +1426,11 @@ fn agent_can_use_animation_link() {
 
   archipelago.add_island(Island::new(Transform::default(), nav_mesh.clone()));
   archipelago.add_island(Island::new(
-    Transform { translation: Vec2::new(3.0, 5.0), rotation: PI },
+    Transform {
+      translation: Vec2::new(3.0, 5.0),
+      rotation: Quat::from_rotation_z(PI),
+      scale: 1.0,
+    },
     nav_mesh.clone(),
   ));
   let link_id = archipelago.add_animation_link(AnimationLink {
@@ -1050,11 +1445,14 @@ fn agent_can_use_animation_link() {
     let mut agent = Agent::create(
       /* position= */ Vec2::new(0.25, 0.75),
       /* velocity= */ Vec2::ZERO,
-      /* radius= */ 0.5,
+      // The agent needs to fit around the corner of the 1 unit wide corridor.
+      /* radius= */
+      0.25,
       /* desired_speed= */ 1.0,
       /* max_speed= */ 2.0,
     );
     agent.current_target = Some(Vec2::new(2.75, 4.25));
+    agent.animation_link_reached_distance = Some(0.5);
     agent
   });
 
@@ -1135,6 +1533,7 @@ fn simple_two_node_nav_mesh() -> Arc<ValidNavigationMesh<XY>> {
       ],
       polygons: vec![vec![0, 1, 2, 3], vec![3, 2, 4, 5]],
       polygon_type_indices: vec![0; 2],
+      polygon_flags: None,
       height_mesh: None,
     }
     .validate()
@@ -1210,7 +1609,11 @@ fn agent_could_not_find_path() {
 
   archipelago.add_island(Island::new(Transform::default(), nav_mesh.clone()));
   archipelago.add_island(Island::new(
-    Transform { translation: Vec2::new(2.0, 0.0), rotation: 0.0 },
+    Transform {
+      translation: Vec2::new(2.0, 0.0),
+      rotation: Quat::IDENTITY,
+      scale: 1.0,
+    },
     nav_mesh,
   ));
   let agent_id = archipelago.add_agent({
@@ -1227,3 +1630,674 @@ fn agent_could_not_find_path() {
   expect_true!(agent.current_path.is_none());
   expect_eq!(agent.state(), AgentState::NoPath);
 }
+
+#[googletest::test]
+fn agent_moves_to_closest_point_of_unreachable_target() {
+  let mut archipelago =
+    Archipelago::<XY>::new(ArchipelagoOptions::from_agent_radius(0.5));
+
+  archipelago
+    .add_island(Island::new(Transform::default(), simple_two_node_nav_mesh()));
+  archipelago.add_island(Island::new(
+    Transform {
+      translation: Vec2::new(2.0, 1.0),
+      rotation: Quat::IDENTITY,
+      scale: 1.0,
+    },
+    simple_one_node_nav_mesh(),
+  ));
+  let agent_id = archipelago.add_agent({
+    let mut agent =
+      Agent::create(Vec2::new(0.5, 0.5), Vec2::ZERO, 0.5, 1.0, 2.0);
+    // This target isn't connected to the agent.
+    agent.current_target = Some(Vec2::new(2.5, 1.75));
+    agent.allow_partial_paths = true;
+    agent
+  });
+
+  archipelago.update(1.0);
+
+  expect_that!(
+    archipelago.get_pathing_results(),
+    elements_are!(eq(&PathingResult {
+      agent: agent_id,
+      success: false,
+      explored_nodes: 0,
+      deferred: false,
+    }))
+  );
+  let agent = archipelago.get_agent_mut(agent_id).unwrap();
+  expect_eq!(agent.state(), AgentState::MovingToPartialTarget);
+  // The top node is the closest to the target.
+  let partial_target = agent.partial_target.expect("has a partial target");
+  expect_eq!(partial_target.point, Vec3::new(1.0, 1.75, 0.0));
+  expect_eq!(
+    agent.current_path.as_ref().unwrap().island_segments[0].corridor,
+    [0, 1]
+  );
+
+  agent.position = Vec2::new(0.9, 1.7);
+  archipelago.update(1.0);
+
+  let agent = archipelago.get_agent_mut(agent_id).unwrap();
+  expect_eq!(agent.state(), AgentState::ReachedPartialTarget);
+  expect_eq!(*agent.get_desired_velocity(), Vec2::ZERO);
+
+  // Connecting the islands makes the target reachable.
+  archipelago.add_island(Island::new(
+    Transform {
+      translation: Vec2::new(1.0, 1.0),
+      rotation: Quat::IDENTITY,
+      scale: 1.0,
+    },
+    simple_one_node_nav_mesh(),
+  ));
+  archipelago.update(1.0);
+
+  let agent = archipelago.get_agent(agent_id).unwrap();
+  expect_eq!(agent.state(), AgentState::Moving);
+  expect_that!(agent.partial_target, none());
+}
+
+fn add_agent_with_target(archipelago: &mut Archipelago<XY>) -> AgentId {
+  let mut agent = Agent::create(Vec2::new(0.5, 0.5), Vec2::ZERO, 0.5, 1.0, 2.0);
+  agent.current_target = Some(Vec2::new(0.5, 1.5));
+  archipelago.add_agent(agent)
+}
+
+#[googletest::test]
+fn pathfinding_budget_defers_repaths_to_later_updates() {
+  let mut archipelago = Archipelago::<XY>::new(ArchipelagoOptions {
+    pathfinding_budget: PathfindingBudget::MaxRepaths(2),
+    ..ArchipelagoOptions::from_agent_radius(0.5)
+  });
+  let nav_mesh = simple_two_node_nav_mesh();
+
+  archipelago.add_island(Island::new(Transform::default(), nav_mesh));
+  let agent_1 = add_agent_with_target(&mut archipelago);
+  let agent_2 = add_agent_with_target(&mut archipelago);
+  let agent_3 = add_agent_with_target(&mut archipelago);
+
+  archipelago.update(1.0);
+
+  expect_that!(
+    archipelago.get_pathing_results(),
+    elements_are!(
+      &PathingResult {
+        agent: agent_1,
+        success: true,
+        explored_nodes: 3,
+        deferred: false
+      },
+      &PathingResult {
+        agent: agent_2,
+        success: true,
+        explored_nodes: 3,
+        deferred: false
+      },
+      &PathingResult {
+        agent: agent_3,
+        success: false,
+        explored_nodes: 0,
+        deferred: true
+      },
+    )
+  );
+  expect_eq!(
+    archipelago.get_agent(agent_1).unwrap().state(),
+    AgentState::Moving
+  );
+  expect_eq!(
+    archipelago.get_agent(agent_2).unwrap().state(),
+    AgentState::Moving
+  );
+  let agent = archipelago.get_agent(agent_3).unwrap();
+  expect_eq!(agent.state(), AgentState::WaitingForPath);
+  expect_true!(agent.current_path.is_none());
+
+  archipelago.update(1.0);
+
+  expect_that!(
+    archipelago.get_pathing_results(),
+    elements_are!(&PathingResult {
+      agent: agent_3,
+      success: true,
+      explored_nodes: 3,
+      deferred: false
+    })
+  );
+  expect_eq!(
+    archipelago.get_agent(agent_3).unwrap().state(),
+    AgentState::Moving
+  );
+}
+
+#[googletest::test]
+fn queued_repaths_are_serviced_in_order() {
+  let mut archipelago = Archipelago::<XY>::new(ArchipelagoOptions {
+    pathfinding_budget: PathfindingBudget::MaxRepaths(1),
+    ..ArchipelagoOptions::from_agent_radius(0.5)
+  });
+  let nav_mesh = simple_two_node_nav_mesh();
+
+  archipelago.add_island(Island::new(Transform::default(), nav_mesh));
+  let agent_1 = add_agent_with_target(&mut archipelago);
+  let agent_2 = add_agent_with_target(&mut archipelago);
+  let agent_3 = add_agent_with_target(&mut archipelago);
+
+  fn serviced_agents(archipelago: &Archipelago<XY>) -> Vec<AgentId> {
+    archipelago
+      .get_pathing_results()
+      .iter()
+      .filter(|result| !result.deferred)
+      .map(|result| result.agent)
+      .collect()
+  }
+
+  archipelago.update(1.0);
+  expect_eq!(serviced_agents(&archipelago), [agent_1]);
+
+  // A new agent is queued behind the agents that are already waiting.
+  let agent_4 = add_agent_with_target(&mut archipelago);
+  // Removing a waiting agent removes it from the queue.
+  archipelago.remove_agent(agent_2);
+
+  archipelago.update(1.0);
+  expect_eq!(serviced_agents(&archipelago), [agent_3]);
+  expect_eq!(
+    archipelago.get_agent(agent_4).unwrap().state(),
+    AgentState::WaitingForPath
+  );
+
+  archipelago.update(1.0);
+  expect_eq!(serviced_agents(&archipelago), [agent_4]);
+  expect_that!(archipelago.get_pathing_results(), len(eq(1)));
+}
+
+#[googletest::test]
+fn path_search_spans_multiple_updates() {
+  let mut archipelago = Archipelago::<XY>::new(ArchipelagoOptions {
+    pathfinding_budget: PathfindingBudget::MaxExploredNodes(1),
+    ..ArchipelagoOptions::from_agent_radius(0.5)
+  });
+  let nav_mesh = simple_two_node_nav_mesh();
+
+  archipelago.add_island(Island::new(Transform::default(), nav_mesh));
+  let agent_id = add_agent_with_target(&mut archipelago);
+
+  archipelago.update(1.0);
+  expect_that!(
+    archipelago.get_pathing_results(),
+    elements_are!(&PathingResult {
+      agent: agent_id,
+      success: false,
+      explored_nodes: 1,
+      deferred: true
+    })
+  );
+  expect_eq!(
+    archipelago.get_agent(agent_id).unwrap().state(),
+    AgentState::WaitingForPath
+  );
+
+  archipelago.update(1.0);
+  expect_that!(
+    archipelago.get_pathing_results(),
+    elements_are!(&PathingResult {
+      agent: agent_id,
+      success: false,
+      explored_nodes: 2,
+      deferred: true
+    })
+  );
+
+  archipelago.update(1.0);
+  expect_that!(
+    archipelago.get_pathing_results(),
+    elements_are!(&PathingResult {
+      agent: agent_id,
+      success: true,
+      explored_nodes: 3,
+      deferred: false
+    })
+  );
+  let agent = archipelago.get_agent(agent_id).unwrap();
+  expect_eq!(agent.state(), AgentState::Moving);
+  expect_true!(agent.current_path.is_some());
+}
+
+#[googletest::test]
+fn path_search_restarts_when_target_node_changes() {
+  let mut archipelago = Archipelago::<XY>::new(ArchipelagoOptions {
+    pathfinding_budget: PathfindingBudget::MaxExploredNodes(1),
+    ..ArchipelagoOptions::from_agent_radius(0.5)
+  });
+  let nav_mesh = simple_two_node_nav_mesh();
+
+  archipelago.add_island(Island::new(Transform::default(), nav_mesh.clone()));
+  let agent_id = add_agent_with_target(&mut archipelago);
+
+  archipelago.update(1.0);
+  archipelago.update(1.0);
+  expect_that!(
+    archipelago.get_pathing_results(),
+    elements_are!(&PathingResult {
+      agent: agent_id,
+      success: false,
+      explored_nodes: 2,
+      deferred: true
+    })
+  );
+
+  // Move the target within the same node. The search can continue.
+  archipelago.get_agent_mut(agent_id).unwrap().current_target =
+    Some(Vec2::new(0.25, 1.75));
+  archipelago.update(1.0);
+  expect_that!(
+    archipelago.get_pathing_results(),
+    elements_are!(&PathingResult {
+      agent: agent_id,
+      success: true,
+      explored_nodes: 3,
+      deferred: false
+    })
+  );
+
+  // Move the target to a different node so the agent needs a new path, but
+  // change the target again before the search can finish.
+  archipelago.get_agent_mut(agent_id).unwrap().current_target =
+    Some(Vec2::new(1.5, 0.5));
+  archipelago.add_island(Island::new(
+    Transform {
+      translation: Vec2::new(1.0, 0.0),
+      rotation: Quat::IDENTITY,
+      scale: 1.0,
+    },
+    nav_mesh,
+  ));
+  archipelago.update(1.0);
+  expect_that!(
+    archipelago.get_pathing_results(),
+    elements_are!(&PathingResult {
+      agent: agent_id,
+      success: false,
+      explored_nodes: 1,
+      deferred: true
+    })
+  );
+
+  archipelago.get_agent_mut(agent_id).unwrap().current_target =
+    Some(Vec2::new(0.5, 1.5));
+  archipelago.update(1.0);
+  // The search started over, so only one node has been explored.
+  expect_that!(
+    archipelago.get_pathing_results(),
+    elements_are!(&PathingResult {
+      agent: agent_id,
+      success: false,
+      explored_nodes: 1,
+      deferred: true
+    })
+  );
+}
+
+#[googletest::test]
+fn path_search_restarts_when_agent_node_or_settings_change() {
+  let mut archipelago = Archipelago::<XY>::new(ArchipelagoOptions {
+    pathfinding_budget: PathfindingBudget::MaxExploredNodes(1),
+    ..ArchipelagoOptions::from_agent_radius(0.5)
+  });
+  let nav_mesh = simple_two_node_nav_mesh();
+
+  let island_id =
+    archipelago.add_island(Island::new(Transform::default(), nav_mesh));
+  let agent_id = add_agent_with_target(&mut archipelago);
+
+  archipelago.update(1.0);
+  archipelago.update(1.0);
+  expect_that!(
+    archipelago.get_pathing_results(),
+    elements_are!(&PathingResult {
+      agent: agent_id,
+      success: false,
+      explored_nodes: 2,
+      deferred: true
+    })
+  );
+
+  // Changing how the agent paths makes the search start over.
+  archipelago.get_agent_mut(agent_id).unwrap().polygon_flag_filter =
+    PolygonFlagFilter { include_flags: u32::MAX, exclude_flags: 1 };
+  archipelago.update(1.0);
+  expect_that!(
+    archipelago.get_pathing_results(),
+    elements_are!(&PathingResult {
+      agent: agent_id,
+      success: false,
+      explored_nodes: 1,
+      deferred: true
+    })
+  );
+
+  // Move the agent into the target's node. The search starts over from the
+  // new node, so the path starts where the agent is.
+  archipelago.get_agent_mut(agent_id).unwrap().position = Vec2::new(0.5, 1.25);
+  archipelago.update(1.0);
+  expect_that!(
+    archipelago.get_pathing_results(),
+    elements_are!(&PathingResult {
+      agent: agent_id,
+      success: false,
+      explored_nodes: 1,
+      deferred: true
+    })
+  );
+  archipelago.update(1.0);
+  expect_that!(
+    archipelago.get_pathing_results(),
+    elements_are!(&PathingResult {
+      agent: agent_id,
+      success: true,
+      explored_nodes: 2,
+      deferred: false
+    })
+  );
+  let agent = archipelago.get_agent(agent_id).unwrap();
+  expect_eq!(
+    agent.current_path.as_ref().unwrap().island_segments[0].corridor,
+    [1]
+  );
+  expect_eq!(
+    agent.current_path.as_ref().unwrap().island_segments[0].island_id,
+    island_id
+  );
+}
+
+#[googletest::test]
+fn path_search_continues_while_island_moves() {
+  let mut archipelago = Archipelago::<XY>::new(ArchipelagoOptions {
+    pathfinding_budget: PathfindingBudget::MaxExploredNodes(1),
+    ..ArchipelagoOptions::from_agent_radius(0.5)
+  });
+  let nav_mesh = simple_two_node_nav_mesh();
+
+  let island_id =
+    archipelago.add_island(Island::new(Transform::default(), nav_mesh));
+  let agent_id = add_agent_with_target(&mut archipelago);
+  archipelago.get_agent_mut(agent_id).unwrap().carried_by_islands = true;
+
+  archipelago.update(1.0);
+  archipelago.update(1.0);
+  expect_that!(
+    archipelago.get_pathing_results(),
+    elements_are!(&PathingResult {
+      agent: agent_id,
+      success: false,
+      explored_nodes: 2,
+      deferred: true
+    })
+  );
+
+  // Moving the island doesn't change its nodes, so the search continues
+  // instead of starting over.
+  archipelago.get_island_mut(island_id).unwrap().set_transform(Transform {
+    translation: Vec2::new(5.0, 0.0),
+    rotation: Quat::IDENTITY,
+    scale: 1.0,
+  });
+  archipelago.update(1.0);
+  expect_that!(
+    archipelago.get_pathing_results(),
+    elements_are!(&PathingResult {
+      agent: agent_id,
+      success: true,
+      explored_nodes: 3,
+      deferred: false
+    })
+  );
+  let path = archipelago.get_agent(agent_id).unwrap().current_path.as_ref();
+  expect_that!(
+    path.map(|path| path.end_point),
+    some(eq(Vec3::new(5.5, 1.5, 0.0)))
+  );
+}
+
+#[googletest::test]
+fn crowd_updates_are_deterministic() {
+  fn run_crowd() -> Vec<(AgentState, Vec2, Vec<PathingResult>)> {
+    let mut archipelago = Archipelago::<XY>::new(ArchipelagoOptions {
+      pathfinding_budget: PathfindingBudget::MaxRepaths(7),
+      ..ArchipelagoOptions::from_agent_radius(0.5)
+    });
+    let nav_mesh = NavigationMesh {
+      vertices: vec![
+        Vec2::new(0.0, 0.0),
+        Vec2::new(20.0, 0.0),
+        Vec2::new(20.0, 20.0),
+        Vec2::new(0.0, 20.0),
+      ],
+      polygons: vec![vec![0, 1, 2, 3]],
+      polygon_type_indices: vec![0],
+      polygon_flags: None,
+      height_mesh: None,
+    }
+    .validate()
+    .expect("nav mesh is valid");
+    archipelago
+      .add_island(Island::new(Transform::default(), Arc::new(nav_mesh)));
+
+    // Agents start in a ring and walk to the opposite side, so they all need
+    // to avoid each other.
+    let agents = (0..50)
+      .map(|i| {
+        let angle = i as f32 / 50.0 * 2.0 * PI;
+        let offset = Vec2::new(angle.cos(), angle.sin()) * 8.0;
+        let mut agent = Agent::create(
+          /* position= */ Vec2::new(10.0, 10.0) + offset,
+          /* velocity= */ Vec2::ZERO,
+          /* radius= */ 0.5,
+          /* desired_speed= */ 1.0,
+          /* max_speed= */ 2.0,
+        );
+        agent.current_target = Some(Vec2::new(10.0, 10.0) - offset);
+        archipelago.add_agent(agent)
+      })
+      .collect::<Vec<_>>();
+
+    let mut results = vec![];
+    for _ in 0..10 {
+      archipelago.update(0.1);
+      for &agent_id in agents.iter() {
+        let agent = archipelago.get_agent_mut(agent_id).unwrap();
+        agent.velocity = *agent.get_desired_velocity();
+        agent.position += agent.velocity * 0.1;
+        results.push((
+          agent.state(),
+          agent.position,
+          archipelago.get_pathing_results().to_vec(),
+        ));
+      }
+    }
+    results
+  }
+
+  // With the `parallel` feature, agents are updated across threads, but the
+  // results must still be the same every time.
+  expect_eq!(run_crowd(), run_crowd());
+}
+
+fn open_square_nav_mesh() -> Arc<ValidNavigationMesh<XY>> {
+  Arc::new(
+    NavigationMesh {
+      vertices: vec![
+        Vec2::new(0.0, 0.0),
+        Vec2::new(4.0, 0.0),
+        Vec2::new(4.0, 4.0),
+        Vec2::new(0.0, 4.0),
+      ],
+      polygons: vec![vec![0, 1, 2, 3]],
+      polygon_type_indices: vec![0],
+      polygon_flags: None,
+      height_mesh: None,
+    }
+    .validate()
+    .expect("nav mesh is valid"),
+  )
+}
+
+#[googletest::test]
+fn agent_slows_down_near_target() {
+  let mut archipelago =
+    Archipelago::<XY>::new(ArchipelagoOptions::from_agent_radius(0.5));
+  archipelago
+    .add_island(Island::new(Transform::default(), open_square_nav_mesh()));
+
+  let mut agent = Agent::create(
+    /* position= */ Vec2::new(0.5, 2.0),
+    /* velocity= */ Vec2::ZERO,
+    /* radius= */ 0.5,
+    /* desired_speed= */ 1.0,
+    /* max_speed= */ 2.0,
+  );
+  agent.current_target = Some(Vec2::new(3.5, 2.0));
+  agent.slowing_distance = Some(2.0);
+  let agent_id = archipelago.add_agent(agent);
+
+  // The target is further than the slowing distance, so go at the desired
+  // speed.
+  archipelago.update(1.0);
+  let desired_velocity =
+    *archipelago.get_agent(agent_id).unwrap().get_desired_velocity();
+  expect_true!(
+    desired_velocity.abs_diff_eq(Vec2::new(1.0, 0.0), 1e-5),
+    "desired_velocity={desired_velocity}"
+  );
+
+  // Once within the slowing distance, the speed is proportional to the
+  // distance to the target.
+  archipelago.get_agent_mut(agent_id).unwrap().position = Vec2::new(2.5, 2.0);
+  archipelago.update(1.0);
+  let desired_velocity =
+    *archipelago.get_agent(agent_id).unwrap().get_desired_velocity();
+  expect_true!(
+    desired_velocity.abs_diff_eq(Vec2::new(0.5, 0.0), 1e-5),
+    "desired_velocity={desired_velocity}"
+  );
+}
+
+#[googletest::test]
+fn smoothed_agent_turns_gradually() {
+  let mut archipelago =
+    Archipelago::<XY>::new(ArchipelagoOptions::from_agent_radius(0.5));
+  archipelago
+    .add_island(Island::new(Transform::default(), open_square_nav_mesh()));
+
+  let mut agent = Agent::create(
+    /* position= */ Vec2::new(1.0, 1.0),
+    /* velocity= */ Vec2::new(1.0, 1.0),
+    /* radius= */ 0.5,
+    /* desired_speed= */ 1.0,
+    /* max_speed= */ 2.0,
+  );
+  agent.current_target = Some(Vec2::new(1.0, 3.0));
+  let agent_id = archipelago.add_agent(agent);
+
+  // Without smoothing, the agent turns straight towards the target.
+  archipelago.update(1.0);
+  let desired_velocity =
+    *archipelago.get_agent(agent_id).unwrap().get_desired_velocity();
+  expect_true!(
+    desired_velocity.abs_diff_eq(Vec2::new(0.0, 1.0), 1e-5),
+    "desired_velocity={desired_velocity}"
+  );
+
+  // With smoothing, the agent keeps some of its current heading.
+  let agent = archipelago.get_agent_mut(agent_id).unwrap();
+  agent.velocity = Vec2::new(1.0, 1.0);
+  agent.path_smoothing = Some(PathSmoothing::default());
+  archipelago.update(1.0);
+  let desired_velocity =
+    *archipelago.get_agent(agent_id).unwrap().get_desired_velocity();
+  expect_that!(desired_velocity.x, gt(0.0));
+  expect_that!(desired_velocity.y, gt(0.0));
+  expect_that!(desired_velocity.length(), near(1.0, 1e-5));
+}
+
+#[googletest::test]
+fn agent_desired_velocity_is_limited_by_acceleration() {
+  let mut archipelago =
+    Archipelago::<XY>::new(ArchipelagoOptions::from_agent_radius(0.5));
+  archipelago
+    .add_island(Island::new(Transform::default(), open_square_nav_mesh()));
+
+  let mut agent = Agent::create(
+    /* position= */ Vec2::new(0.5, 2.0),
+    /* velocity= */ Vec2::ZERO,
+    /* radius= */ 0.5,
+    /* desired_speed= */ 1.0,
+    /* max_speed= */ 2.0,
+  );
+  agent.current_target = Some(Vec2::new(3.5, 2.0));
+  agent.max_acceleration = Some(2.0);
+  let agent_id = archipelago.add_agent(agent);
+
+  // The agent is standing still, so it can only get part of the way to its
+  // desired speed.
+  archipelago.update(0.25);
+  let desired_velocity =
+    *archipelago.get_agent(agent_id).unwrap().get_desired_velocity();
+  expect_true!(
+    desired_velocity.abs_diff_eq(Vec2::new(0.5, 0.0), 1e-5),
+    "desired_velocity={desired_velocity}"
+  );
+
+  archipelago.get_agent_mut(agent_id).unwrap().velocity = desired_velocity;
+  archipelago.update(0.25);
+  let desired_velocity =
+    *archipelago.get_agent(agent_id).unwrap().get_desired_velocity();
+  expect_true!(
+    desired_velocity.abs_diff_eq(Vec2::new(1.0, 0.0), 1e-5),
+    "desired_velocity={desired_velocity}"
+  );
+}
+
+#[googletest::test]
+fn paused_agent_desired_velocity_is_not_limited() {
+  let mut archipelago =
+    Archipelago::<XY>::new(ArchipelagoOptions::from_agent_radius(0.5));
+  archipelago
+    .add_island(Island::new(Transform::default(), open_square_nav_mesh()));
+
+  let mut agent = Agent::create(
+    /* position= */ Vec2::new(0.5, 2.0),
+    /* velocity= */ Vec2::new(1.0, 0.0),
+    /* radius= */ 0.5,
+    /* desired_speed= */ 1.0,
+    /* max_speed= */ 2.0,
+  );
+  agent.current_target = Some(Vec2::new(3.5, 2.0));
+  agent.max_acceleration = Some(2.0);
+  let agent_id = archipelago.add_agent(agent);
+
+  archipelago.update(0.25);
+  expect_true!(
+    archipelago
+      .get_agent(agent_id)
+      .unwrap()
+      .get_desired_velocity()
+      .abs_diff_eq(Vec2::new(1.0, 0.0), 1e-5)
+  );
+
+  // The paused agent keeps its desired velocity, even though it could not
+  // reach it from a standstill.
+  let agent = archipelago.get_agent_mut(agent_id).unwrap();
+  agent.paused = true;
+  agent.velocity = Vec2::ZERO;
+  archipelago.update(0.25);
+  let desired_velocity =
+    *archipelago.get_agent(agent_id).unwrap().get_desired_velocity();
+  expect_true!(
+    desired_velocity.abs_diff_eq(Vec2::new(1.0, 0.0), 1e-5),
+    "desired_velocity={desired_velocity}"
+  );
+}
@@ -0,0 +1,127 @@
+use std::collections::HashSet;
+
+use glam::Vec3Swizzles;
+use slotmap::new_key_type;
+use thiserror::Error;
+
+use crate::{
+  CoordinateSystem,
+  geometry::to_ccw_convex_polygon,
+  nav_data::{Footprint, NodeRef},
+};
+
+new_key_type! {
+  /// The ID of a [`ModifierVolume`].
+  pub struct ModifierVolumeId;
+}
+
+/// A volume that changes the cost of the nodes it overlaps at runtime, without
+/// changing the navigation meshes. This is useful for areas that agents should
+/// temporarily avoid (e.g., fire or poison gas).
+///
+/// A node is affected if any part of it is inside the volume. Paths through
+/// affected nodes are invalidated when the volume is added, changed, or
+/// removed.
+pub struct ModifierVolume<CS: CoordinateSystem> {
+  /// The vertices of the convex shape of the volume. The shape is horizontal
+  /// (the height of the vertices is ignored). The vertices can be in either
+  /// winding order.
+  pub vertices: Vec<CS::Coordinate>,
+  /// The minimum and maximum height of the volume. Only nodes whose bounds
+  /// overlap this range are affected. If [`None`], the volume extends
+  /// infinitely up and down.
+  pub height_range: Option<(f32, f32)>,
+  /// How the volume changes the nodes it overlaps.
+  pub effect: ModifierEffect,
+}
+
+/// How a [`ModifierVolume`] changes the nodes it overlaps.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ModifierEffect {
+  /// Adds to the cost (per meter) of the nodes, on top of their type index
+  /// cost. The cost must be non-negative. An infinite cost prevents agents
+  /// from travelling through the nodes at all.
+  AddCost(f32),
+  /// Treats the nodes as if they have this type index instead, so the cost of
+  /// that type index (including agent overrides) is used. If several volumes
+  /// override the type index of a node, the most expensive one is used.
+  OverrideTypeIndex(usize),
+}
+
+/// An error from adding or setting a [`ModifierVolume`].
+#[derive(Clone, Copy, Debug, PartialEq, Error)]
+pub enum ModifierVolumeError {
+  #[error(
+    "The polygon is not convex, or has fewer than 3 (non-collinear) vertices."
+  )]
+  InvalidPolygon,
+  #[error(
+    "The height range ({0}, {1}) is invalid. The minimum must not be greater than the maximum."
+  )]
+  InvalidHeightRange(f32, f32),
+  #[error("The added cost {0} is negative. Added costs must be non-negative.")]
+  NegativeCost(f32),
+}
+
+impl<CS: CoordinateSystem> ModifierVolume<CS> {
+  /// Validates the volume and computes its footprint in world space.
+  pub(crate) fn to_footprint(&self) -> Result<Footprint, ModifierVolumeError> {
+    if let Some((min, max)) = self.height_range
+      && min > max
+    {
+      return Err(ModifierVolumeError::InvalidHeightRange(min, max));
+    }
+    if let ModifierEffect::AddCost(cost) = self.effect
+      && cost < 0.0
+    {
+      return Err(ModifierVolumeError::NegativeCost(cost));
+    }
+    let vertices = to_ccw_convex_polygon(
+      self.vertices.iter().map(|vertex| CS::to_landmass(vertex).xy()).collect(),
+    )
+    .ok_or(ModifierVolumeError::InvalidPolygon)?;
+    Ok(Footprint::ConvexPolygon(vertices))
+  }
+}
+
+/// The state of a modifier volume.
+pub(crate) struct ModifierVolumeState<CS: CoordinateSystem> {
+  /// The volume given to us by the user.
+  pub(crate) volume: ModifierVolume<CS>,
+  /// The footprint of the volume in world space.
+  pub(crate) footprint: Footprint,
+  /// The nodes that the volume overlapped as of the last update.
+  pub(crate) nodes: HashSet<NodeRef>,
+}
+
+impl<CS: CoordinateSystem<Coordinate: std::fmt::Debug>> std::fmt::Debug
+  for ModifierVolume<CS>
+{
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("ModifierVolume")
+      .field("vertices", &self.vertices)
+      .field("height_range", &self.height_range)
+      .field("effect", &self.effect)
+      .finish()
+  }
+}
+
+impl<CS: CoordinateSystem> Clone for ModifierVolume<CS> {
+  fn clone(&self) -> Self {
+    Self {
+      vertices: self.vertices.clone(),
+      height_range: self.height_range,
+      effect: self.effect,
+    }
+  }
+}
+
+impl<CS: CoordinateSystem<Coordinate: PartialEq>> PartialEq
+  for ModifierVolume<CS>
+{
+  fn eq(&self, other: &Self) -> bool {
+    self.vertices == other.vertices
+      && self.height_range == other.height_range
+      && self.effect == other.effect
+  }
+}
@@ -1,27 +1,66 @@
 use std::{
-  collections::{HashMap, HashSet, VecDeque},
+  collections::{BTreeSet, HashMap, HashSet, VecDeque},
   mem::swap,
   ops::{Deref, DerefMut},
   sync::Mutex,
 };
 
 use disjoint::DisjointSet;
-use geo::{BooleanOps, Coord, LineString, LinesIter, MultiPolygon, Polygon};
+use geo::{
+  Area, BooleanOps, Closest, ClosestPoint, Coord, Distance, Euclidean, Line,
+  LineString, LinesIter, MultiPolygon, Point, Polygon,
+};
 use glam::{Vec2, Vec3, Vec3Swizzles};
 use kdtree::{KdTree, distance::squared_euclidean};
-use slotmap::{HopSlotMap, SlotMap, new_key_type};
+use slotmap::{HopSlotMap, Key, SlotMap, new_key_type};
 use thiserror::Error;
 
 use crate::{
   CoordinateSystem, PermittedAnimationLinks,
   coords::CorePointSampleDistance,
-  geometry::edge_intersection,
+  door::{Door, DoorEdges, DoorId, DoorState, segment_to_footprint},
+  geometry::{
+    circle_polygon_intersection_area, convex_polygon_intersection_area,
+    convex_polygon_segment_interval, edge_intersection,
+    offset_convex_polygon_vertices, polygon_signed_area,
+    project_point_to_line_segment, segment_clearance, segments_cross,
+  },
+  hierarchy::IslandGraph,
   island::{Island, IslandId},
   link::{AnimationLink, AnimationLinkId, AnimationLinkState, NodePortal},
+  modifier::{
+    ModifierEffect, ModifierVolume, ModifierVolumeError, ModifierVolumeId,
+    ModifierVolumeState,
+  },
   nav_mesh::{MeshEdgeRef, nav_mesh_node_bbh},
-  util::{BoundingBox, BoundingBoxHierarchy, RaySegment},
+  obstacle::{Obstacle, ObstacleError, ObstacleId, ObstacleState},
+  util::{BoundingBox, BoundingBoxHierarchy, RaySegment, Transform},
 };
 
+/// The horizontal footprint of a shape, in world space.
+pub(crate) enum Footprint {
+  /// A circle.
+  Circle { center: Vec2, radius: f32 },
+  /// A convex polygon whose vertices are in counter-clockwise order.
+  ConvexPolygon(Vec<Vec2>),
+}
+
+impl Footprint {
+  /// Computes the minimum and maximum corners of the footprint's bounds.
+  fn bounds(&self) -> (Vec2, Vec2) {
+    match self {
+      &Self::Circle { center, radius } => {
+        (center - Vec2::splat(radius), center + Vec2::splat(radius))
+      }
+      Self::ConvexPolygon(vertices) => vertices
+        .iter()
+        .fold((Vec2::INFINITY, Vec2::NEG_INFINITY), |(min, max), &vertex| {
+          (min.min(vertex), max.max(vertex))
+        }),
+    }
+  }
+}
+
 /// The navigation data of a whole [`crate::Archipelago`]. This only includes
 /// "static" features.
 pub(crate) struct NavigationData<CS: CoordinateSystem> {
@@ -29,6 +68,12 @@ pub(crate) struct NavigationData<CS: CoordinateSystem> {
   islands: HopSlotMap<IslandId, Island<CS>>,
   /// The animation links in the [`crate::AnimationLink`].
   animation_links: HopSlotMap<AnimationLinkId, AnimationLinkState<CS>>,
+  /// The modifier volumes in the [`crate::Archipelago`].
+  modifier_volumes: HopSlotMap<ModifierVolumeId, ModifierVolumeState<CS>>,
+  /// The obstacles carved out of the [`crate::Archipelago`].
+  obstacles: HopSlotMap<ObstacleId, ObstacleState<CS>>,
+  /// The doors in the [`crate::Archipelago`].
+  doors: HopSlotMap<DoorId, DoorState<CS>>,
   /// The "default" cost of each type index. Missing type indices default to a
   /// cost of 1.0.
   type_index_to_cost: HashMap<usize, f32>,
@@ -55,19 +100,54 @@ pub(crate) struct NavigationData<CS: CoordinateSystem> {
   pub(crate) modified_nodes: HashMap<NodeRef, ModifiedNode>,
   /// The islands that have been deleted since the last update.
   deleted_islands: HashSet<IslandId>,
+  /// The islands that only moved (without changing their navigation meshes)
+  /// during the last update, along with their transforms before moving.
+  pub(crate) moved_islands: HashMap<IslandId, Transform<CS>>,
+  /// Whether the last update only moved islands, without changing any nodes,
+  /// off mesh links or anything else. Searches and partial paths can then be
+  /// carried along with the islands instead of starting over.
+  pub(crate) only_islands_moved: bool,
   /// The set of animation links created since the last update.
   new_animation_links: HashSet<AnimationLinkId>,
   /// The set of animation links deleted since the last update.
   deleted_animation_links: HashSet<AnimationLinkId>,
+  /// The modifier volumes that have been added or changed since the last
+  /// update.
+  changed_modifier_volumes: HashSet<ModifierVolumeId>,
+  /// The nodes that were overlapped by modifier volumes that have been removed
+  /// since the last update.
+  removed_modifier_volume_nodes: HashSet<NodeRef>,
+  /// The modifier volumes that overlap a particular node ref.
+  pub(crate) node_to_modifier_volume_ids:
+    HashMap<NodeRef, BTreeSet<ModifierVolumeId>>,
+  /// The obstacles that have been added or changed since the last update.
+  changed_obstacles: HashSet<ObstacleId>,
+  /// The nodes that were carved by obstacles that have been removed since the
+  /// last update.
+  removed_obstacle_nodes: HashSet<NodeRef>,
+  /// The obstacles that carve a particular node ref.
+  pub(crate) node_to_obstacle_ids: HashMap<NodeRef, BTreeSet<ObstacleId>>,
+  /// The doors that have been added or changed since the last update.
+  changed_doors: HashSet<DoorId>,
+  /// The nodes that were carved by doors that have been removed since the last
+  /// update.
+  removed_door_nodes: HashSet<NodeRef>,
+  /// The closed doors that carve a particular node ref.
+  node_to_door_ids: HashMap<NodeRef, BTreeSet<DoorId>>,
+  /// The abstract graph of islands used for hierarchical pathfinding. This is
+  /// [`None`] if hierarchical pathfinding is disabled (or the graph needs to be
+  /// rebuilt).
+  pub(crate) island_graph: Option<IslandGraph>,
 }
 
 /// A reference to a node in the navigation data.
 #[derive(PartialEq, Eq, Debug, Clone, Copy, Hash, PartialOrd, Ord)]
-pub(crate) struct NodeRef {
+pub struct NodeRef {
   /// The island of the node.
-  pub(crate) island_id: IslandId,
-  /// The index of the node in the island.
-  pub(crate) polygon_index: usize,
+  pub island_id: IslandId,
+  /// The index of the node in the island's navigation mesh (i.e., the index
+  /// of the polygon).
+  pub polygon_index: usize,
 }
 
 new_key_type! {
@@ -129,8 +209,55 @@ pub(crate) struct ModifiedNode {
   /// node. These are not vertices in the original nav mesh and should only
   /// be used by a single boundary edge.
   pub(crate) new_vertices: Vec<Vec2>,
+  /// The part of the node (in world space) that has not been carved out by
+  /// obstacles. [`None`] if no obstacles carve the node.
+  pub(crate) uncarved_region: Option<MultiPolygon<f32>>,
 }
 
+/// The area below which a carved node is considered to be fully carved out.
+const CARVED_OUT_AREA: f32 = 1e-6;
+
+impl ModifiedNode {
+  /// Determines whether obstacles have carved out the whole node.
+  pub(crate) fn is_carved_out(&self) -> bool {
+    self
+      .uncarved_region
+      .as_ref()
+      .is_some_and(|region| region.unsigned_area() < CARVED_OUT_AREA)
+  }
+
+  /// Determines whether obstacles have split the node into several
+  /// disconnected pieces.
+  pub(crate) fn is_split(&self) -> bool {
+    self.uncarved_region.as_ref().is_some_and(|region| region.0.len() > 1)
+  }
+
+  /// Determines whether `from` and `to` (portals or points, in world space)
+  /// touch the same piece of the uncarved region. Nodes that are not carved
+  /// connect everything.
+  pub(crate) fn are_connected(
+    &self,
+    from: (Vec2, Vec2),
+    to: (Vec2, Vec2),
+  ) -> bool {
+    let Some(uncarved_region) = self.uncarved_region.as_ref() else {
+      return true;
+    };
+    fn to_line((start, end): (Vec2, Vec2)) -> Line<f32> {
+      Line::new(Coord::from((start.x, start.y)), Coord::from((end.x, end.y)))
+    }
+    let (from, to) = (to_line(from), to_line(to));
+    uncarved_region.iter().any(|piece| {
+      Euclidean.distance(&from, piece) < CONNECTED_DISTANCE
+        && Euclidean.distance(&to, piece) < CONNECTED_DISTANCE
+    })
+  }
+}
+
+/// The max distance between a portal (or point) and a piece of a carved node
+/// for them to be considered touching.
+const CONNECTED_DISTANCE: f32 = 1e-4;
+
 /// A possible connection between two regions.
 #[derive(Debug)]
 struct PossibleRegionLink {
@@ -146,6 +273,9 @@ impl<CS: CoordinateSystem> NavigationData<CS> {
     Self {
       islands: HopSlotMap::with_key(),
       animation_links: HopSlotMap::with_key(),
+      modifier_volumes: HopSlotMap::with_key(),
+      obstacles: HopSlotMap::with_key(),
+      doors: HopSlotMap::with_key(),
       type_index_to_cost: HashMap::new(),
       // The navigation data is empty, so there's nothing to update (so not
       // dirty).
@@ -157,8 +287,20 @@ impl<CS: CoordinateSystem> NavigationData<CS> {
       node_to_off_mesh_link_ids: HashMap::new(),
       modified_nodes: HashMap::new(),
       deleted_islands: HashSet::new(),
+      moved_islands: HashMap::new(),
+      only_islands_moved: false,
       new_animation_links: HashSet::new(),
       deleted_animation_links: HashSet::new(),
+      changed_modifier_volumes: HashSet::new(),
+      removed_modifier_volume_nodes: HashSet::new(),
+      node_to_modifier_volume_ids: HashMap::new(),
+      changed_obstacles: HashSet::new(),
+      removed_obstacle_nodes: HashSet::new(),
+      node_to_obstacle_ids: HashMap::new(),
+      changed_doors: HashSet::new(),
+      removed_door_nodes: HashSet::new(),
+      node_to_door_ids: HashMap::new(),
+      island_graph: None,
     }
   }
 
@@ -174,6 +316,8 @@ impl<CS: CoordinateSystem> NavigationData<CS> {
       return Err(SetTypeIndexCostError::NonPositiveCost(cost));
     }
     self.type_index_to_cost.insert(type_index, cost);
+    // The island graph depends on the type index costs, so it must be rebuilt.
+    self.island_graph = None;
     Ok(())
   }
 
@@ -199,6 +343,19 @@ impl<CS: CoordinateSystem> NavigationData<CS> {
     self.islands.insert(island)
   }
 
+  /// Moves `point` (in world space) along with the island `island_id`, if the
+  /// island moved during the last update. Returns [`None`] if the island did
+  /// not move.
+  pub(crate) fn carry_point_with_island(
+    &self,
+    island_id: IslandId,
+    point: Vec3,
+  ) -> Option<Vec3> {
+    let moved_from = self.moved_islands.get(&island_id)?;
+    let island = self.islands.get(island_id)?;
+    Some(island.transform.apply(moved_from.apply_inverse(point)))
+  }
+
   /// Gets a borrow to the island with `id`.
   pub(crate) fn get_island(&self, id: IslandId) -> Option<&Island<CS>> {
     self.islands.get(id)
@@ -263,6 +420,508 @@ impl<CS: CoordinateSystem> NavigationData<CS> {
     self.animation_links.keys()
   }
 
+  /// Adds a new modifier volume to the navigation data.
+  pub(crate) fn add_modifier_volume(
+    &mut self,
+    volume: ModifierVolume<CS>,
+  ) -> Result<ModifierVolumeId, ModifierVolumeError> {
+    let footprint = volume.to_footprint()?;
+    self.dirty = true;
+    let volume_id = self.modifier_volumes.insert(ModifierVolumeState {
+      volume,
+      footprint,
+      nodes: HashSet::new(),
+    });
+    self.changed_modifier_volumes.insert(volume_id);
+    Ok(volume_id)
+  }
+
+  /// Replaces the modifier volume with `volume_id` with `volume`. Panics if
+  /// the modifier volume ID is not in the navigation data.
+  pub(crate) fn set_modifier_volume(
+    &mut self,
+    volume_id: ModifierVolumeId,
+    volume: ModifierVolume<CS>,
+  ) -> Result<(), ModifierVolumeError> {
+    let footprint = volume.to_footprint()?;
+    let state = self
+      .modifier_volumes
+      .get_mut(volume_id)
+      .expect("Modifier volume should be present in the Archipelago");
+    self.dirty = true;
+    state.volume = volume;
+    state.footprint = footprint;
+    self.changed_modifier_volumes.insert(volume_id);
+    Ok(())
+  }
+
+  pub(crate) fn remove_modifier_volume(&mut self, volume_id: ModifierVolumeId) {
+    let Some(state) = self.modifier_volumes.remove(volume_id) else {
+      return;
+    };
+    self.dirty = true;
+    self.changed_modifier_volumes.remove(&volume_id);
+    for node_ref in state.nodes {
+      remove_id_from_node(
+        &mut self.node_to_modifier_volume_ids,
+        node_ref,
+        volume_id,
+      );
+      self.removed_modifier_volume_nodes.insert(node_ref);
+    }
+  }
+
+  pub(crate) fn get_modifier_volume(
+    &self,
+    volume_id: ModifierVolumeId,
+  ) -> Option<&ModifierVolume<CS>> {
+    self.modifier_volumes.get(volume_id).map(|state| &state.volume)
+  }
+
+  pub(crate) fn get_modifier_volume_ids(
+    &self,
+  ) -> impl ExactSizeIterator<Item = ModifierVolumeId> + '_ {
+    self.modifier_volumes.keys()
+  }
+
+  /// Adds a new obstacle to the navigation data.
+  pub(crate) fn add_obstacle(
+    &mut self,
+    obstacle: Obstacle<CS>,
+  ) -> Result<ObstacleId, ObstacleError> {
+    let (footprint, height_range) = obstacle.to_footprint()?;
+    self.dirty = true;
+    let obstacle_id = self.obstacles.insert(ObstacleState {
+      obstacle,
+      footprint,
+      height_range,
+      nodes: HashSet::new(),
+    });
+    self.changed_obstacles.insert(obstacle_id);
+    Ok(obstacle_id)
+  }
+
+  /// Replaces the obstacle with `obstacle_id` with `obstacle`. Panics if the
+  /// obstacle ID is not in the navigation data.
+  pub(crate) fn set_obstacle(
+    &mut self,
+    obstacle_id: ObstacleId,
+    obstacle: Obstacle<CS>,
+  ) -> Result<(), ObstacleError> {
+    let (footprint, height_range) = obstacle.to_footprint()?;
+    let state = self
+      .obstacles
+      .get_mut(obstacle_id)
+      .expect("Obstacle should be present in the Archipelago");
+    self.dirty = true;
+    state.obstacle = obstacle;
+    state.footprint = footprint;
+    state.height_range = height_range;
+    self.changed_obstacles.insert(obstacle_id);
+    Ok(())
+  }
+
+  pub(crate) fn remove_obstacle(&mut self, obstacle_id: ObstacleId) {
+    let Some(state) = self.obstacles.remove(obstacle_id) else {
+      return;
+    };
+    self.dirty = true;
+    self.changed_obstacles.remove(&obstacle_id);
+    for node_ref in state.nodes {
+      remove_id_from_node(
+        &mut self.node_to_obstacle_ids,
+        node_ref,
+        obstacle_id,
+      );
+      self.removed_obstacle_nodes.insert(node_ref);
+    }
+  }
+
+  pub(crate) fn get_obstacle(
+    &self,
+    obstacle_id: ObstacleId,
+  ) -> Option<&Obstacle<CS>> {
+    self.obstacles.get(obstacle_id).map(|state| &state.obstacle)
+  }
+
+  pub(crate) fn get_obstacle_ids(
+    &self,
+  ) -> impl ExactSizeIterator<Item = ObstacleId> + '_ {
+    self.obstacles.keys()
+  }
+
+  /// Adds a new door to the navigation data.
+  pub(crate) fn add_door(&mut self, door: Door<CS>) -> DoorId {
+    self.dirty = true;
+    let door_id = self.doors.insert(DoorState {
+      door,
+      footprints: Vec::new(),
+      nodes: HashSet::new(),
+    });
+    self.changed_doors.insert(door_id);
+    door_id
+  }
+
+  /// Opens or closes the door with `door_id`. Panics if the door ID is not in
+  /// the navigation data.
+  pub(crate) fn set_door_open(&mut self, door_id: DoorId, open: bool) {
+    let state = self
+      .doors
+      .get_mut(door_id)
+      .expect("Door should be present in the Archipelago");
+    if state.door.open == open {
+      return;
+    }
+    self.dirty = true;
+    state.door.open = open;
+    self.changed_doors.insert(door_id);
+  }
+
+  pub(crate) fn remove_door(&mut self, door_id: DoorId) {
+    let Some(state) = self.doors.remove(door_id) else {
+      return;
+    };
+    self.dirty = true;
+    self.changed_doors.remove(&door_id);
+    for node_ref in state.nodes {
+      remove_id_from_node(&mut self.node_to_door_ids, node_ref, door_id);
+      self.removed_door_nodes.insert(node_ref);
+    }
+  }
+
+  pub(crate) fn get_door(&self, door_id: DoorId) -> Option<&Door<CS>> {
+    self.doors.get(door_id).map(|state| &state.door)
+  }
+
+  pub(crate) fn get_door_ids(
+    &self,
+  ) -> impl ExactSizeIterator<Item = DoorId> + '_ {
+    self.doors.keys()
+  }
+
+  /// Finds the footprints (in world space) of the obstacles and closed doors
+  /// that carve `node_ref`.
+  fn carving_footprints(&self, node_ref: NodeRef) -> Vec<&[Vec2]> {
+    let obstacle_footprints = self
+      .node_to_obstacle_ids
+      .get(&node_ref)
+      .into_iter()
+      .flatten()
+      .map(|id| self.obstacles.get(*id).unwrap().footprint.as_slice());
+    let door_footprints = self
+      .node_to_door_ids
+      .get(&node_ref)
+      .into_iter()
+      .flatten()
+      .flat_map(|id| self.doors.get(*id).unwrap().footprints.iter())
+      .map(|footprint| footprint.as_slice());
+    obstacle_footprints.chain(door_footprints).collect()
+  }
+
+  /// Determines whether obstacles (or closed doors) block crossing from `from`
+  /// into `to` through `portal` (in world space), because every part of the
+  /// portal has been carved out.
+  pub(crate) fn is_crossing_carved(
+    &self,
+    from: NodeRef,
+    to: NodeRef,
+    portal: (Vec2, Vec2),
+  ) -> bool {
+    let mut footprints = self.carving_footprints(from);
+    footprints.extend(self.carving_footprints(to));
+    if footprints.is_empty() {
+      return false;
+    }
+    let uncarved_intervals =
+      subtract_footprints_from_segment(portal, footprints);
+    let uncarved_length =
+      uncarved_intervals.iter().map(|(start, end)| end - start).sum::<f32>()
+        * portal.0.distance(portal.1);
+    uncarved_length < CARVED_PORTAL_LENGTH
+  }
+
+  /// Finds where `segment` (in world space) first enters the footprint of an
+  /// obstacle (or closed door) that carves `node_ref`. Returns the fraction
+  /// along the segment where it enters, and the outward normal of the
+  /// footprint edge it entered through. Footprints that the segment starts
+  /// inside of are ignored.
+  pub(crate) fn find_carved_segment_hit(
+    &self,
+    node_ref: NodeRef,
+    segment: (Vec2, Vec2),
+  ) -> Option<(f32, Vec2)> {
+    let direction = segment.1 - segment.0;
+    let length = direction.length();
+    self
+      .carving_footprints(node_ref)
+      .into_iter()
+      .filter_map(|footprint| {
+        let (start, end) = convex_polygon_segment_interval(footprint, segment)?;
+        if start <= 0.0 || (end - start) * length <= CARVED_PORTAL_LENGTH {
+          return None;
+        }
+        // The segment enters through the last edge that it crosses inwards.
+        let (_, edge) = footprint
+          .iter()
+          .zip(footprint.iter().cycle().skip(1))
+          .filter_map(|(&edge_start, &edge_end)| {
+            let edge = edge_end - edge_start;
+            let speed = edge.perp_dot(direction);
+            (speed > 0.0)
+              .then(|| (-edge.perp_dot(segment.0 - edge_start) / speed, edge))
+          })
+          .max_by(|a, b| a.0.total_cmp(&b.0))?;
+        Some((start, Vec2::new(edge.y, -edge.x).normalize()))
+      })
+      .min_by(|a, b| a.0.total_cmp(&b.0))
+  }
+
+  /// Finds the first corner of the shortest detour from `from` to `to` (in
+  /// world space) around the obstacles (and closed doors) that carve `nodes`,
+  /// along with the node the corner is in. Corners are kept `corner_offset`
+  /// away from the carved footprints, and the detour may not cross the walls
+  /// of `nodes`. Returns [`None`] if walking straight from `from` to `to` is
+  /// not blocked by any footprint, or if there is no detour.
+  pub(crate) fn find_detour_corner(
+    &self,
+    nodes: &[NodeRef],
+    from: Vec3,
+    to: Vec3,
+    corner_offset: f32,
+  ) -> Option<(NodeRef, Vec3)> {
+    let mut footprints = vec![];
+    for &node_ref in nodes {
+      for footprint in self.carving_footprints(node_ref) {
+        if !footprints.contains(&footprint) {
+          footprints.push(footprint);
+        }
+      }
+    }
+    if footprints.is_empty() {
+      return None;
+    }
+    let is_blocked_by_footprints = |start: Vec2, end: Vec2| {
+      let length = start.distance(end);
+      footprints.iter().any(|footprint| {
+        convex_polygon_segment_interval(footprint, (start, end)).is_some_and(
+          |(carve_start, carve_end)| {
+            (carve_end - carve_start) * length > CARVED_PORTAL_LENGTH
+          },
+        )
+      })
+    };
+    if !is_blocked_by_footprints(from.xy(), to.xy()) {
+      return None;
+    }
+
+    let walls =
+      nodes.iter().flat_map(|&node| self.node_walls(node)).collect::<Vec<_>>();
+    let is_blocked = |start: Vec2, end: Vec2| {
+      is_blocked_by_footprints(start, end)
+        || walls.iter().any(|&wall| segments_cross((start, end), wall))
+    };
+    let is_inside_footprint = |point: Vec2| {
+      footprints.iter().any(|footprint| {
+        footprint
+          .iter()
+          .zip(footprint.iter().cycle().skip(1))
+          .all(|(&start, &end)| (end - start).perp_dot(point - start) > 0.0)
+      })
+    };
+    let node_containing = |point: Vec2| {
+      nodes.iter().copied().find(|node_ref| {
+        let island = self.islands.get(node_ref.island_id).unwrap();
+        island.nav_mesh.node_contains_point(
+          island.transform.apply_inverse(point.extend(from.z)),
+          node_ref.polygon_index,
+        )
+      })
+    };
+
+    // The visibility graph starts with `from` and `to`, followed by the
+    // (offset) corners of the footprints that are still walkable.
+    let mut points = vec![from.xy(), to.xy()];
+    let mut corner_nodes = vec![];
+    for footprint in footprints.iter() {
+      for corner in offset_convex_polygon_vertices(
+        footprint,
+        corner_offset + DETOUR_CORNER_MARGIN,
+      ) {
+        if corner.distance(from.xy()) < CONNECTED_DISTANCE
+          || is_inside_footprint(corner)
+        {
+          continue;
+        }
+        let Some(node_ref) = node_containing(corner) else {
+          continue;
+        };
+        points.push(corner);
+        corner_nodes.push(node_ref);
+      }
+    }
+
+    // Dijkstra's algorithm over the visibility graph. The graph is small, so
+    // just scan for the closest unvisited point.
+    let mut distances = vec![f32::INFINITY; points.len()];
+    let mut previous = vec![0; points.len()];
+    let mut visited = vec![false; points.len()];
+    distances[0] = 0.0;
+    loop {
+      let current = (0..points.len())
+        .filter(|&index| !visited[index])
+        .min_by(|&a, &b| distances[a].total_cmp(&distances[b]))?;
+      if distances[current].is_infinite() {
+        return None;
+      }
+      if current == 1 {
+        break;
+      }
+      visited[current] = true;
+      for next in 0..points.len() {
+        let distance =
+          distances[current] + points[current].distance(points[next]);
+        if visited[next]
+          || distance >= distances[next]
+          || is_blocked(points[current], points[next])
+        {
+          continue;
+        }
+        distances[next] = distance;
+        previous[next] = current;
+      }
+    }
+
+    let mut corner = 1;
+    while previous[corner] != 0 {
+      corner = previous[corner];
+    }
+    // `from` can't see `to`, so the first step must be to a corner.
+    let node_ref = corner_nodes[corner - 2];
+    let island = self.islands.get(node_ref.island_id).unwrap();
+    let height = (from.z + to.z) * 0.5;
+    let point = island.transform.apply_inverse(points[corner].extend(height));
+    let point =
+      island.nav_mesh.sample_point_on_node(point, node_ref.polygon_index);
+    Some((node_ref, island.transform.apply(point)))
+  }
+
+  /// Determines the clearance (in world space) of `node_ref`, i.e., the
+  /// diameter of the widest agent that can stand somewhere in it.
+  pub(crate) fn node_clearance(&self, node_ref: NodeRef) -> f32 {
+    let island = self.islands.get(node_ref.island_id).unwrap();
+    island.nav_mesh.polygons[node_ref.polygon_index].clearance
+      * island.transform.scale
+  }
+
+  /// Determines the width (in world space) of the off mesh link `link` that
+  /// starts in `node_ref`, i.e., the diameter of the widest agent that can take
+  /// it. Boundary links are limited by the boundary edges of both nodes (not
+  /// counting the parts of those edges that are linked), while animation links
+  /// are limited by the length of their portals and the clearance of both
+  /// nodes.
+  pub(crate) fn off_mesh_link_width(
+    &self,
+    node_ref: NodeRef,
+    link: &OffMeshLink,
+  ) -> f32 {
+    match &link.kinded {
+      KindedOffMeshLink::BoundaryLink { .. } => {
+        let mut walls = self.node_walls(node_ref);
+        walls.extend(self.node_walls(link.destination_node));
+        segment_clearance((link.portal.0.xy(), link.portal.1.xy()), &walls)
+      }
+      KindedOffMeshLink::AnimationLink { destination_portal, .. } => {
+        let portal_length =
+          |(start, end): (Vec3, Vec3)| start.xy().distance(end.xy());
+        portal_length(link.portal)
+          .min(portal_length(*destination_portal))
+          .min(self.node_clearance(node_ref))
+          .min(self.node_clearance(link.destination_node))
+      }
+    }
+  }
+
+  /// Determines whether `point` (in world space) lies on the boundary edges of
+  /// `node_ref`. For modified nodes, this uses the new boundary of the node.
+  pub(crate) fn is_point_on_node_boundary(
+    &self,
+    node_ref: NodeRef,
+    point: Vec3,
+  ) -> bool {
+    let point = point.xy();
+    self.node_walls(node_ref).iter().any(|&(start, end)| {
+      let (projected_point, _) = project_point_to_line_segment(
+        point.extend(0.0),
+        (start.extend(0.0), end.extend(0.0)),
+      );
+      point.distance(projected_point.truncate()) < CONNECTED_DISTANCE
+    })
+  }
+
+  /// Finds the boundary edges (in world space) of `node_ref`. For modified
+  /// nodes, this is the new boundary of the node.
+  fn node_walls(&self, node_ref: NodeRef) -> Vec<(Vec2, Vec2)> {
+    let island = self.islands.get(node_ref.island_id).unwrap();
+    let world_vertex = |index: usize| {
+      island.transform.apply(island.nav_mesh.vertices[index]).xy()
+    };
+    let Some(modified_node) = self.modified_nodes.get(&node_ref) else {
+      let polygon = &island.nav_mesh.polygons[node_ref.polygon_index];
+      return (0..polygon.vertices.len())
+        .filter(|&edge| polygon.connectivity[edge].is_none())
+        .map(|edge| {
+          let (left, right) = polygon.get_edge_indices(edge);
+          (world_vertex(left), world_vertex(right))
+        })
+        .collect();
+    };
+    let vertex =
+      |index: usize| match index.checked_sub(island.nav_mesh.vertices.len()) {
+        None => world_vertex(index),
+        Some(new_index) => modified_node.new_vertices[new_index],
+      };
+    modified_node
+      .new_boundary
+      .iter()
+      .map(|&(start, end)| (vertex(start), vertex(end)))
+      .collect()
+  }
+
+  /// Computes the cost of `node_ref` (which has `type_index`) after applying
+  /// the modifier volumes that overlap it. `type_index_to_cost` computes the
+  /// cost of a type index. Nodes that are fully carved out by obstacles have
+  /// an infinite cost.
+  pub(crate) fn modified_node_cost(
+    &self,
+    node_ref: NodeRef,
+    type_index: usize,
+    type_index_to_cost: impl Fn(usize) -> f32,
+  ) -> f32 {
+    if self
+      .modified_nodes
+      .get(&node_ref)
+      .is_some_and(|modified_node| modified_node.is_carved_out())
+    {
+      return f32::INFINITY;
+    }
+    let Some(volume_ids) = self.node_to_modifier_volume_ids.get(&node_ref)
+    else {
+      return type_index_to_cost(type_index);
+    };
+    let mut override_cost: Option<f32> = None;
+    let mut added_cost = 0.0;
+    for &volume_id in volume_ids {
+      match self.modifier_volumes.get(volume_id).unwrap().volume.effect {
+        ModifierEffect::AddCost(cost) => added_cost += cost,
+        ModifierEffect::OverrideTypeIndex(type_index) => {
+          let cost = type_index_to_cost(type_index);
+          override_cost = Some(override_cost.map_or(cost, |c| c.max(cost)));
+        }
+      }
+    }
+    override_cost.unwrap_or_else(|| type_index_to_cost(type_index)) + added_cost
+  }
+
   /// Finds the node nearest to (and within `distance_to_node` of) `point`.
   /// Returns the point on the nav data nearest to `point` and the reference to
   /// the corresponding node.
@@ -274,6 +933,21 @@ impl<CS: CoordinateSystem> NavigationData<CS> {
     let mut best_point = None;
     for (island_id, island) in self.islands.iter() {
       let relative_point = island.transform.apply_inverse(point);
+      // Distances are measured in the island's space, so they need to be
+      // scaled.
+      let local_sample_distance = CorePointSampleDistance {
+        horizontal_distance: island
+          .transform
+          .apply_inverse_to_distance(point_sample_distance.horizontal_distance),
+        distance_above: island
+          .transform
+          .apply_inverse_to_distance(point_sample_distance.distance_above),
+        distance_below: island
+          .transform
+          .apply_inverse_to_distance(point_sample_distance.distance_below),
+        vertical_preference_ratio: point_sample_distance
+          .vertical_preference_ratio,
+      };
       if !island
         .nav_mesh
         .mesh_bounds
@@ -283,14 +957,14 @@ impl<CS: CoordinateSystem> NavigationData<CS> {
           // perspective, we need to sample up by distance_above, which is the
           // same as expanding the bounding box down by distance_above.
           Vec3::new(
-            -point_sample_distance.horizontal_distance,
-            -point_sample_distance.horizontal_distance,
-            -point_sample_distance.distance_above,
+            -local_sample_distance.horizontal_distance,
+            -local_sample_distance.horizontal_distance,
+            -local_sample_distance.distance_above,
           ),
           Vec3::new(
-            point_sample_distance.horizontal_distance,
-            point_sample_distance.horizontal_distance,
-            point_sample_distance.distance_below,
+            local_sample_distance.horizontal_distance,
+            local_sample_distance.horizontal_distance,
+            local_sample_distance.distance_below,
           ),
         )
         .contains_point(relative_point)
@@ -298,31 +972,324 @@ impl<CS: CoordinateSystem> NavigationData<CS> {
         continue;
       }
 
-      let (sampled_point, sampled_node) = match island
+      let (mut sampled_point, sampled_node) = match island
         .nav_mesh
-        .sample_point(relative_point, point_sample_distance)
+        .sample_point(relative_point, &local_sample_distance)
       {
         Some(sampled) => sampled,
         None => continue,
       };
+      let node_ref = NodeRef { island_id, polygon_index: sampled_node };
+
+      if let Some(modified_node) = self.modified_nodes.get(&node_ref)
+        && let Some(uncarved_region) = modified_node.uncarved_region.as_ref()
+      {
+        // Move the point out of any obstacles carving the node.
+        if modified_node.is_carved_out() {
+          continue;
+        }
+        let world_point = island.transform.apply(sampled_point);
+        let uncarved_point = match uncarved_region
+          .closest_point(&Point::new(world_point.x, world_point.y))
+        {
+          Closest::Intersection(point) | Closest::SinglePoint(point) => {
+            Vec2::new(point.x(), point.y())
+          }
+          Closest::Indeterminate => continue,
+        };
+        if uncarved_point.distance(point.xy())
+          > point_sample_distance.horizontal_distance
+        {
+          continue;
+        }
+        sampled_point = island.nav_mesh.closest_point_on_node(
+          island.transform.apply_inverse(uncarved_point.extend(world_point.z)),
+          sampled_node,
+        );
+      }
 
-      let distance = relative_point.distance_squared(sampled_point);
+      // Compare distances in world space, since islands may be scaled
+      // differently.
+      let sampled_point = island.transform.apply(sampled_point);
+      let distance = point.distance_squared(sampled_point);
       match best_point {
         Some((best_distance, _)) if distance >= best_distance => continue,
         _ => {}
       }
 
-      best_point = Some((
-        distance,
-        (
-          island.transform.apply(sampled_point),
-          NodeRef { island_id, polygon_index: sampled_node },
-        ),
-      ));
+      best_point = Some((distance, (sampled_point, node_ref)));
     }
     best_point.map(|(_, b)| b)
   }
 
+  /// Finds the point on a boundary edge nearest to `point`, within
+  /// `max_distance`. Boundary edges are edges that are not connected to
+  /// another node, so seams between islands (from boundary links) are not
+  /// included. Returns the point on the edge, the distance to it, and the
+  /// outward (horizontal) normal of the edge. All points are in world space.
+  pub(crate) fn find_nearest_boundary(
+    &self,
+    point: Vec3,
+    max_distance: f32,
+  ) -> Option<(Vec3, f32, Vec3)> {
+    let mut best_boundary = None;
+    // Edges are in counter-clockwise order, so the outside of the node is to
+    // the right of each edge.
+    let mut test_edge = |(start, end): (Vec3, Vec3)| {
+      let (edge_point, _) = project_point_to_line_segment(point, (start, end));
+      let distance = edge_point.distance(point);
+      if distance > max_distance
+        || best_boundary
+          .is_some_and(|(_, best_distance, _)| best_distance <= distance)
+      {
+        return;
+      }
+      let edge_delta = end.xy() - start.xy();
+      let normal = Vec3::new(edge_delta.y, -edge_delta.x, 0.0).normalize();
+      best_boundary = Some((edge_point, distance, normal));
+    };
+
+    for (island_id, island) in self.islands.iter() {
+      if !island
+        .transformed_bounds
+        .expand_by_size(Vec3::splat(max_distance))
+        .contains_point(point)
+      {
+        continue;
+      }
+
+      for edge_ref in island.nav_mesh.boundary_edges.iter() {
+        let node_ref =
+          NodeRef { island_id, polygon_index: edge_ref.polygon_index };
+        if self.modified_nodes.contains_key(&node_ref) {
+          // The boundary of modified nodes is handled below.
+          continue;
+        }
+        let (left, right) = island.nav_mesh.get_edge_points(edge_ref.clone());
+        test_edge((
+          island.transform.apply(right),
+          island.transform.apply(left),
+        ));
+      }
+
+      for (node_ref, modified_node) in self.modified_nodes.iter() {
+        if node_ref.island_id != island_id {
+          continue;
+        }
+        let index_to_vertex = |index: usize| {
+          if index < island.nav_mesh.vertices.len() {
+            return island.transform.apply(island.nav_mesh.vertices[index]);
+          }
+          // New vertices are only 2D, but they are on the boundary of the
+          // node, so we can find their height from the node.
+          let new_vertex =
+            modified_node.new_vertices[index - island.nav_mesh.vertices.len()];
+          let local_vertex =
+            island.transform.apply_inverse(new_vertex.extend(point.z));
+          island.transform.apply(
+            island
+              .nav_mesh
+              .closest_point_on_node(local_vertex, node_ref.polygon_index),
+          )
+        };
+        for &(start, end) in modified_node.new_boundary.iter() {
+          test_edge((index_to_vertex(start), index_to_vertex(end)));
+        }
+      }
+    }
+    best_boundary
+  }
+
+  /// Finds every node whose horizontal area overlaps `footprint` and whose
+  /// bounds overlap `height_range` (if provided). Returns the nodes along with
+  /// the area of the overlap. All coordinates are in world space.
+  pub(crate) fn query_nodes_in_footprint(
+    &self,
+    footprint: &Footprint,
+    height_range: Option<(f32, f32)>,
+  ) -> Vec<(NodeRef, f32)> {
+    let (footprint_min, footprint_max) = footprint.bounds();
+    let (height_min, height_max) =
+      height_range.unwrap_or((f32::NEG_INFINITY, f32::INFINITY));
+    let query_box = BoundingBox::new_box(
+      footprint_min.extend(height_min),
+      footprint_max.extend(height_max),
+    );
+
+    let mut nodes = vec![];
+    for (island_id, island) in self.islands.iter() {
+      if island.nav_mesh.polygons.is_empty()
+        || !island.transformed_bounds.intersects_bounds(&query_box)
+      {
+        continue;
+      }
+
+      // The height range may be unbounded, so limit it to the island before
+      // transforming it into the island's space.
+      let (island_min, island_max) = island.transformed_bounds.as_box();
+      let local_query_box = BoundingBox::new_box(
+        footprint_min.extend(height_min.max(island_min.z)),
+        footprint_max.extend(height_max.min(island_max.z)),
+      )
+      .transform_inverse(&island.transform);
+
+      let node_bbh = nav_mesh_node_bbh(island.nav_mesh.as_ref(), Vec3::ZERO);
+      for &polygon_index in node_bbh.query_box(local_query_box) {
+        let polygon = island.nav_mesh.polygons[polygon_index]
+          .vertices
+          .iter()
+          .map(|&vertex| {
+            island.transform.apply(island.nav_mesh.vertices[vertex]).xy()
+          })
+          .collect::<Vec<_>>();
+        let area = match footprint {
+          &Footprint::Circle { center, radius } => {
+            circle_polygon_intersection_area(center, radius, &polygon)
+          }
+          Footprint::ConvexPolygon(footprint) => {
+            convex_polygon_intersection_area(&polygon, footprint)
+          }
+        };
+        if area > 0.0 {
+          nodes.push((NodeRef { island_id, polygon_index }, area));
+        }
+      }
+    }
+    nodes
+  }
+
+  /// Picks a uniformly random point on the navigation data. If provided, the
+  /// point is restricted to be (horizontally) within `circle`, on a node with
+  /// one of `type_indices`, and on a node connected to `reachable_from`.
+  /// `rng` must return uniformly distributed values in [0, 1). Returns the
+  /// point (in world space) and its node, or [`None`] if there are no nodes to
+  /// pick from.
+  pub(crate) fn random_point(
+    &self,
+    circle: Option<(Vec2, f32)>,
+    type_indices: Option<&HashSet<usize>>,
+    reachable_from: Option<(NodeRef, &PermittedAnimationLinks)>,
+    rng: &mut impl FnMut() -> f32,
+  ) -> Option<(Vec3, NodeRef)> {
+    let node_area = |node_ref: NodeRef| {
+      let island = self.get_island(node_ref.island_id).unwrap();
+      let vertices = island.nav_mesh.polygons[node_ref.polygon_index]
+        .vertices
+        .iter()
+        .map(|&vertex| {
+          island.transform.apply(island.nav_mesh.vertices[vertex]).xy()
+        })
+        .collect::<Vec<_>>();
+      polygon_signed_area(&vertices)
+    };
+
+    // Checking connectivity can be expensive, but every node in a region has
+    // the same answer, so only check each region once.
+    let mut region_to_reachable = HashMap::new();
+    let mut is_node_permitted = |node_ref: NodeRef| {
+      let island = self.get_island(node_ref.island_id).unwrap();
+      let polygon = &island.nav_mesh.polygons[node_ref.polygon_index];
+      if type_indices
+        .is_some_and(|type_indices| !type_indices.contains(&polygon.type_index))
+      {
+        return false;
+      }
+      let Some((start_node, permitted_animation_links)) = reachable_from else {
+        return true;
+      };
+      *region_to_reachable
+        .entry(self.node_to_region_id(node_ref))
+        .or_insert_with(|| {
+          self.are_nodes_connected(
+            start_node,
+            node_ref,
+            permitted_animation_links.clone(),
+          )
+        })
+    };
+
+    // Nodes are weighted by their area (inside the circle), so that every
+    // point is equally likely.
+    let candidates = match circle {
+      Some((center, radius)) => self
+        .query_nodes_in_footprint(&Footprint::Circle { center, radius }, None),
+      None => self
+        .islands
+        .iter()
+        .flat_map(|(island_id, island)| {
+          (0..island.nav_mesh.polygons.len())
+            .map(move |polygon_index| NodeRef { island_id, polygon_index })
+        })
+        .map(|node_ref| (node_ref, node_area(node_ref)))
+        .collect(),
+    }
+    .into_iter()
+    .filter(|&(node_ref, _)| is_node_permitted(node_ref))
+    .collect::<Vec<_>>();
+
+    let total_area = candidates.iter().map(|(_, area)| area).sum::<f32>();
+    if total_area <= 0.0 {
+      return None;
+    }
+    // Default to the last node in case floating point error makes us
+    // overshoot.
+    let mut remaining_area = rng() * total_area;
+    let (node_ref, _) = candidates
+      .iter()
+      .copied()
+      .find(|&(_, area)| {
+        remaining_area -= area;
+        remaining_area < 0.0
+      })
+      .unwrap_or(*candidates.last().unwrap());
+
+    let island = self.get_island(node_ref.island_id).unwrap();
+    let Some((center, radius)) = circle else {
+      let point =
+        island.nav_mesh.random_point_on_node(node_ref.polygon_index, rng);
+      return Some((island.transform.apply(point), node_ref));
+    };
+
+    // Pick points until one is in both the node and the circle. Pick from
+    // whichever is smaller to make it more likely to hit the overlap. Points in
+    // the circle are picked at the height of the node's center, so they land
+    // on the node even if the island is tilted.
+    let node_height = island
+      .transform
+      .apply(island.nav_mesh.polygons[node_ref.polygon_index].center)
+      .z;
+    let pick_in_circle =
+      std::f32::consts::PI * radius * radius < node_area(node_ref);
+    const MAX_ATTEMPTS: usize = 32;
+    for _ in 0..MAX_ATTEMPTS {
+      let point = if pick_in_circle {
+        let distance = radius * rng().sqrt();
+        let angle = std::f32::consts::TAU * rng();
+        let point = island.transform.apply_inverse(
+          (center + Vec2::from_angle(angle) * distance).extend(node_height),
+        );
+        if !island.nav_mesh.node_contains_point(point, node_ref.polygon_index) {
+          continue;
+        }
+        island.nav_mesh.sample_point_on_node(point, node_ref.polygon_index)
+      } else {
+        island.nav_mesh.random_point_on_node(node_ref.polygon_index, rng)
+      };
+      let point = island.transform.apply(point);
+      if point.xy().distance_squared(center) > radius * radius {
+        continue;
+      }
+      return Some((point, node_ref));
+    }
+
+    // The overlap must be tiny, so just use the closest point to the center.
+    let point = island.nav_mesh.closest_point_on_node(
+      island.transform.apply_inverse(center.extend(node_height)),
+      node_ref.polygon_index,
+    );
+    Some((island.transform.apply(point), node_ref))
+  }
+
   fn update_islands(
     &mut self,
     edge_link_distance: f32,
@@ -333,6 +1300,9 @@ impl<CS: CoordinateSystem> NavigationData<CS> {
       if island.dirty {
         island.dirty = false;
         dirty_islands.insert(island_id);
+        if let Some(moved_from) = island.moved_from.take() {
+          self.moved_islands.insert(island_id, moved_from);
+        }
       }
     }
 
@@ -723,7 +1693,11 @@ impl<CS: CoordinateSystem> NavigationData<CS> {
           }
           node_bbh = Some(nav_mesh_node_bbh(
             island.nav_mesh.as_ref(),
-            Vec3::new(0.0, 0.0, max_vertical_distance),
+            Vec3::new(
+              0.0,
+              0.0,
+              island.transform.apply_inverse_to_distance(max_vertical_distance),
+            ),
           ));
         }
 
@@ -735,6 +1709,8 @@ impl<CS: CoordinateSystem> NavigationData<CS> {
           node_bbh: &BoundingBoxHierarchy<usize>,
           max_vertical_distance: f32,
         ) -> Vec<NodePortal> {
+          let max_vertical_distance =
+            island.transform.apply_inverse_to_distance(max_vertical_distance);
           if edge.0 == edge.1 {
             let point = island.transform.apply_inverse(edge.0);
             island
@@ -835,22 +1811,26 @@ impl<CS: CoordinateSystem> NavigationData<CS> {
       self.modified_nodes.remove(&node_ref);
       return;
     };
-    // Any nodes without off mesh links don't need to be modified.
-    let Some(off_mesh_links) = self.node_to_off_mesh_link_ids.get(&node_ref)
-    else {
-      self.modified_nodes.remove(&node_ref);
-      return;
-    };
+    let off_mesh_links = self.node_to_off_mesh_link_ids.get(&node_ref);
+    let has_boundary_link =
+      off_mesh_links.into_iter().flatten().any(|link_id| {
+        matches!(
+          self.off_mesh_links.get(*link_id).unwrap().kinded,
+          KindedOffMeshLink::BoundaryLink { .. }
+        )
+      });
+    let obstacle_footprints = Some(
+      self
+        .carving_footprints(node_ref)
+        .into_iter()
+        .map(|footprint| footprint.to_vec())
+        .collect::<Vec<_>>(),
+    )
+    .filter(|footprints| !footprints.is_empty());
 
-    // If none of the off mesh links for this node are boundary links, the node
-    // doesn't need to be modified.
-    'has_boundary_link: {
-      for off_mesh_link in off_mesh_links {
-        let link = self.off_mesh_links.get(*off_mesh_link).unwrap();
-        if let KindedOffMeshLink::BoundaryLink { .. } = &link.kinded {
-          break 'has_boundary_link;
-        }
-      }
+    // If the node has no boundary links and isn't carved by any obstacles (or
+    // closed doors), the node doesn't need to be modified.
+    if !has_boundary_link && obstacle_footprints.is_none() {
       self.modified_nodes.remove(&node_ref);
       return;
     }
@@ -924,18 +1904,40 @@ impl<CS: CoordinateSystem> NavigationData<CS> {
       )])
     }
 
-    let mut clip_polygons = off_mesh_links
-      .iter()
-      .map(|link_id| self.off_mesh_links.get(*link_id).unwrap())
-      .map(|link| boundary_link_to_clip_polygon(link, edge_link_distance));
+    let mut clipped_boundary_edges = boundary_edges;
+    if has_boundary_link {
+      let mut clip_polygons = off_mesh_links
+        .into_iter()
+        .flatten()
+        .map(|link_id| self.off_mesh_links.get(*link_id).unwrap())
+        .map(|link| boundary_link_to_clip_polygon(link, edge_link_distance));
 
-    let mut link_clip = clip_polygons.next().unwrap();
-    for clip_polygon in clip_polygons {
-      link_clip = link_clip.union(&clip_polygon);
+      let mut link_clip = clip_polygons.next().unwrap();
+      for clip_polygon in clip_polygons {
+        link_clip = link_clip.union(&clip_polygon);
+      }
+
+      clipped_boundary_edges =
+        link_clip.clip(&clipped_boundary_edges, /* invert= */ true);
+    }
+
+    fn footprint_to_polygon(footprint: &[Vec2]) -> Polygon<f32> {
+      Polygon::new(
+        LineString(footprint.iter().copied().map(vec2_to_coord).collect()),
+        vec![],
+      )
     }
 
-    let clipped_boundary_edges =
-      link_clip.clip(&boundary_edges, /* invert= */ true);
+    // Obstacles cover up the parts of the boundary edges inside them.
+    let obstacle_clip = obstacle_footprints.as_ref().map(|footprints| {
+      footprints.iter().fold(MultiPolygon::new(vec![]), |clip, footprint| {
+        clip.union(&footprint_to_polygon(footprint))
+      })
+    });
+    if let Some(obstacle_clip) = obstacle_clip.as_ref() {
+      clipped_boundary_edges =
+        obstacle_clip.clip(&clipped_boundary_edges, /* invert= */ true);
+    }
 
     let mut original_vertices = KdTree::new(/* dimensions= */ 2);
     for &index in polygon.vertices.iter() {
@@ -950,6 +1952,7 @@ impl<CS: CoordinateSystem> NavigationData<CS> {
       self.modified_nodes.entry(node_ref).insert_entry(ModifiedNode {
         new_boundary: Vec::new(),
         new_vertices: Vec::new(),
+        uncarved_region: None,
       });
     let modified_node = modified_node.get_mut();
 
@@ -1008,6 +2011,63 @@ impl<CS: CoordinateSystem> NavigationData<CS> {
         modified_node.new_boundary.push((start_index, end_index));
       }
     }
+
+    let (Some(obstacle_footprints), Some(obstacle_clip)) =
+      (obstacle_footprints, obstacle_clip)
+    else {
+      return;
+    };
+
+    let node_polygon = polygon
+      .vertices
+      .iter()
+      .map(|&vertex| {
+        island.transform.apply(island.nav_mesh.vertices[vertex]).xy()
+      })
+      .collect::<Vec<_>>();
+
+    // The edges of the obstacles inside the node become new boundary edges.
+    // The node is outside the obstacles, so the edges are reversed to keep the
+    // node on the left (like the nav mesh polygons).
+    for (index, footprint) in obstacle_footprints.iter().enumerate() {
+      for (&end, &start) in
+        footprint.iter().zip(footprint.iter().cycle().skip(1))
+      {
+        let Some(node_interval) =
+          convex_polygon_segment_interval(&node_polygon, (start, end))
+        else {
+          continue;
+        };
+        let intervals = subtract_footprints_from_segment(
+          (start, end),
+          obstacle_footprints
+            .iter()
+            .enumerate()
+            .filter(|&(other_index, _)| other_index != index)
+            .map(|(_, other_footprint)| other_footprint.as_slice()),
+        );
+        for (interval_start, interval_end) in intervals {
+          let (interval_start, interval_end) = (
+            interval_start.max(node_interval.0),
+            interval_end.min(node_interval.1),
+          );
+          if interval_start >= interval_end {
+            continue;
+          }
+          modified_node.new_vertices.push(start.lerp(end, interval_start));
+          modified_node.new_vertices.push(start.lerp(end, interval_end));
+          let end_index = island.nav_mesh.vertices.len()
+            + modified_node.new_vertices.len()
+            - 1;
+          modified_node.new_boundary.push((end_index - 1, end_index));
+        }
+      }
+    }
+
+    modified_node.uncarved_region = Some(
+      MultiPolygon::new(vec![footprint_to_polygon(&node_polygon)])
+        .difference(&obstacle_clip),
+    );
   }
 
   fn node_to_region_id(&self, node_ref: NodeRef) -> (IslandId, usize) {
@@ -1155,29 +2215,329 @@ impl<CS: CoordinateSystem> NavigationData<CS> {
     }
   }
 
+  /// Updates the island graph if `hierarchical_pathfinding` is enabled, or
+  /// discards it otherwise. `nav_data_changed` should be true if the
+  /// navigation data has been updated since the last call, and
+  /// `invalidated_nodes` should be the nodes invalidated by that update.
+  pub(crate) fn update_island_graph(
+    &mut self,
+    hierarchical_pathfinding: bool,
+    nav_data_changed: bool,
+    invalidated_nodes: &HashSet<NodeRef>,
+  ) {
+    if !hierarchical_pathfinding {
+      self.island_graph = None;
+      return;
+    }
+    if self.island_graph.is_some() && !nav_data_changed {
+      return;
+    }
+    let mut island_graph = self.island_graph.take().unwrap_or_default();
+    island_graph.update(self, invalidated_nodes);
+    self.island_graph = Some(island_graph);
+  }
+
   pub(crate) fn update(
     &mut self,
     edge_link_distance: f32,
     animation_link_distance: f32,
-  ) -> (HashSet<OffMeshLinkId>, HashSet<IslandId>) {
+  ) -> (HashSet<OffMeshLinkId>, HashSet<IslandId>, HashSet<NodeRef>) {
+    self.moved_islands.clear();
+    self.only_islands_moved = false;
     if !self.dirty {
-      return (HashSet::new(), HashSet::new());
+      return (HashSet::new(), HashSet::new(), HashSet::new());
     }
     self.dirty = false;
 
     let animation_links_changed = !self.new_animation_links.is_empty()
       || !self.deleted_animation_links.is_empty();
-
-    let (dropped_links, changed_islands, modified_node_refs_to_update) =
+    let only_islands_changed = !animation_links_changed
+      && self.deleted_islands.is_empty()
+      && self.changed_modifier_volumes.is_empty()
+      && self.removed_modifier_volume_nodes.is_empty()
+      && self.changed_obstacles.is_empty()
+      && self.removed_obstacle_nodes.is_empty()
+      && self.changed_doors.is_empty()
+      && self.removed_door_nodes.is_empty();
+
+    let (dropped_links, changed_islands, mut modified_node_refs_to_update) =
       self.update_islands(edge_link_distance, animation_link_distance);
+    let mut carved_nodes = self.update_obstacles(&changed_islands);
+    carved_nodes.extend(self.update_doors(&changed_islands));
+    modified_node_refs_to_update.extend(carved_nodes.iter().copied());
     for node_ref in modified_node_refs_to_update {
       self.update_modified_node(node_ref, edge_link_distance);
     }
     if animation_links_changed || !changed_islands.is_empty() {
       self.update_regions();
     }
-    (dropped_links, changed_islands)
+    let mut invalidated_nodes = self.update_modifier_volumes(&changed_islands);
+    invalidated_nodes.extend(carved_nodes);
+    // Islands that only moved keep their nodes, so paths on them are still
+    // valid.
+    let invalidated_islands = changed_islands
+      .into_iter()
+      .filter(|island_id| !self.moved_islands.contains_key(island_id))
+      .collect::<HashSet<_>>();
+    // Off mesh links on moved islands are always recreated, so any links
+    // touching a moved island mean the islands were connected differently.
+    self.only_islands_moved = only_islands_changed
+      && invalidated_islands.is_empty()
+      && invalidated_nodes.is_empty()
+      && dropped_links.is_empty()
+      && !self.off_mesh_links.values().any(|link| {
+        self.moved_islands.contains_key(&link.destination_node.island_id)
+      })
+      && !self
+        .node_to_off_mesh_link_ids
+        .keys()
+        .any(|node_ref| self.moved_islands.contains_key(&node_ref.island_id));
+    (dropped_links, invalidated_islands, invalidated_nodes)
+  }
+
+  /// Recomputes the nodes overlapped by modifier volumes that have changed (or
+  /// all of them if any islands have changed). Returns the nodes whose cost
+  /// may have changed. Nodes on `changed_islands` that are still overlapped
+  /// are not included, since their cost is unchanged (or their islands
+  /// already invalidate their paths).
+  fn update_modifier_volumes(
+    &mut self,
+    changed_islands: &HashSet<IslandId>,
+  ) -> HashSet<NodeRef> {
+    let mut invalidated_nodes =
+      std::mem::take(&mut self.removed_modifier_volume_nodes);
+    let changed_modifier_volumes =
+      std::mem::take(&mut self.changed_modifier_volumes);
+    let volume_ids = if changed_islands.is_empty() {
+      changed_modifier_volumes.iter().copied().collect::<Vec<_>>()
+    } else {
+      self.modifier_volumes.keys().collect()
+    };
+
+    for volume_id in volume_ids {
+      let state = self.modifier_volumes.get(volume_id).unwrap();
+      let new_nodes = self
+        .query_nodes_in_footprint(&state.footprint, state.volume.height_range)
+        .into_iter()
+        .map(|(node_ref, _)| node_ref)
+        .collect::<HashSet<_>>();
+
+      let state = self.modifier_volumes.get_mut(volume_id).unwrap();
+      let old_nodes = replace_overlapped_nodes(
+        &mut self.node_to_modifier_volume_ids,
+        volume_id,
+        &mut state.nodes,
+        new_nodes,
+      );
+
+      if changed_modifier_volumes.contains(&volume_id) {
+        // The effect may have changed, so every node it touches is affected.
+        invalidated_nodes.extend(old_nodes.union(&state.nodes));
+      } else {
+        invalidated_nodes.extend(old_nodes.symmetric_difference(&state.nodes));
+      }
+    }
+    invalidated_nodes
+  }
+
+  /// Recomputes the nodes carved by obstacles that have changed (or all of them
+  /// if any islands have changed). Returns the nodes whose carving may have
+  /// changed.
+  fn update_obstacles(
+    &mut self,
+    changed_islands: &HashSet<IslandId>,
+  ) -> HashSet<NodeRef> {
+    let mut carved_nodes = std::mem::take(&mut self.removed_obstacle_nodes);
+    let changed_obstacles = std::mem::take(&mut self.changed_obstacles);
+    let obstacle_ids = if changed_islands.is_empty() {
+      changed_obstacles.iter().copied().collect::<Vec<_>>()
+    } else {
+      self.obstacles.keys().collect()
+    };
+
+    for obstacle_id in obstacle_ids {
+      let state = self.obstacles.get(obstacle_id).unwrap();
+      let new_nodes = self
+        .query_nodes_in_footprint(
+          &Footprint::ConvexPolygon(state.footprint.clone()),
+          Some(state.height_range),
+        )
+        .into_iter()
+        .map(|(node_ref, _)| node_ref)
+        .collect::<HashSet<_>>();
+
+      let state = self.obstacles.get_mut(obstacle_id).unwrap();
+      let old_nodes = replace_overlapped_nodes(
+        &mut self.node_to_obstacle_ids,
+        obstacle_id,
+        &mut state.nodes,
+        new_nodes,
+      );
+
+      if changed_obstacles.contains(&obstacle_id) {
+        carved_nodes.extend(old_nodes.union(&state.nodes));
+      } else {
+        carved_nodes.extend(old_nodes.symmetric_difference(&state.nodes));
+        // Nodes on changed islands may have moved under the obstacle, so they
+        // need to be carved again.
+        carved_nodes.extend(
+          state
+            .nodes
+            .iter()
+            .filter(|node_ref| changed_islands.contains(&node_ref.island_id)),
+        );
+      }
+    }
+    carved_nodes
+  }
+
+  /// Recomputes the nodes carved by doors that have changed (or all of them if
+  /// any islands have changed). Returns the nodes whose carving may have
+  /// changed.
+  fn update_doors(
+    &mut self,
+    changed_islands: &HashSet<IslandId>,
+  ) -> HashSet<NodeRef> {
+    let mut carved_nodes = std::mem::take(&mut self.removed_door_nodes);
+    let changed_doors = std::mem::take(&mut self.changed_doors);
+    let door_ids = if changed_islands.is_empty() {
+      changed_doors.into_iter().collect::<Vec<_>>()
+    } else {
+      self.doors.keys().collect()
+    };
+
+    for door_id in door_ids {
+      let state = self.doors.get(door_id).unwrap();
+      let footprints = if state.door.open {
+        vec![]
+      } else {
+        self
+          .door_segments(&state.door.edges)
+          .into_iter()
+          .filter_map(segment_to_footprint)
+          .collect::<Vec<_>>()
+      };
+      let mut new_nodes = HashSet::new();
+      for (footprint, height_range) in footprints.iter() {
+        new_nodes.extend(
+          self
+            .query_nodes_in_footprint(
+              &Footprint::ConvexPolygon(footprint.clone()),
+              Some(*height_range),
+            )
+            .into_iter()
+            .map(|(node_ref, _)| node_ref),
+        );
+      }
+
+      let state = self.doors.get_mut(door_id).unwrap();
+      state.footprints =
+        footprints.into_iter().map(|(footprint, _)| footprint).collect();
+      let old_nodes = replace_overlapped_nodes(
+        &mut self.node_to_door_ids,
+        door_id,
+        &mut state.nodes,
+        new_nodes,
+      );
+
+      // Even if the door hasn't changed, edges of nodes move with their island,
+      // so every node needs to be carved again.
+      carved_nodes.extend(old_nodes.union(&state.nodes));
+    }
+    carved_nodes
+  }
+
+  /// Finds the segments (in world space) that `edges` of a door cover.
+  fn door_segments(&self, edges: &DoorEdges<CS>) -> Vec<(Vec3, Vec3)> {
+    match edges {
+      DoorEdges::NodeEdges(edges) => edges
+        .iter()
+        .filter_map(|&(node_ref, edge_index)| {
+          let island = self.islands.get(node_ref.island_id)?;
+          let polygon = island.nav_mesh.polygons.get(node_ref.polygon_index)?;
+          if edge_index >= polygon.vertices.len() {
+            return None;
+          }
+          let (left, right) = polygon.get_edge_indices(edge_index);
+          Some((
+            island.transform.apply(island.nav_mesh.vertices[right]),
+            island.transform.apply(island.nav_mesh.vertices[left]),
+          ))
+        })
+        .collect(),
+      DoorEdges::Cut(start, end) => {
+        vec![(CS::to_landmass(start), CS::to_landmass(end))]
+      }
+    }
+  }
+}
+
+/// Removes `id` (e.g., of a modifier volume or an obstacle) from the IDs of
+/// `node_ref`.
+fn remove_id_from_node<K: Key>(
+  node_to_ids: &mut HashMap<NodeRef, BTreeSet<K>>,
+  node_ref: NodeRef,
+  id: K,
+) {
+  let Some(ids) = node_to_ids.get_mut(&node_ref) else {
+    return;
+  };
+  ids.remove(&id);
+  if ids.is_empty() {
+    node_to_ids.remove(&node_ref);
+  }
+}
+
+/// Replaces the overlapped `nodes` of `id` (e.g., of a modifier volume or an
+/// obstacle) with `new_nodes`, keeping `node_to_ids` in sync. Returns the
+/// previously overlapped nodes.
+fn replace_overlapped_nodes<K: Key>(
+  node_to_ids: &mut HashMap<NodeRef, BTreeSet<K>>,
+  id: K,
+  nodes: &mut HashSet<NodeRef>,
+  new_nodes: HashSet<NodeRef>,
+) -> HashSet<NodeRef> {
+  let old_nodes = std::mem::replace(nodes, new_nodes);
+  for &node_ref in old_nodes.difference(nodes) {
+    remove_id_from_node(node_to_ids, node_ref, id);
+  }
+  for &node_ref in nodes.difference(&old_nodes) {
+    node_to_ids.entry(node_ref).or_default().insert(id);
+  }
+  old_nodes
+}
+
+/// The length below which a portal is considered to be fully carved out.
+const CARVED_PORTAL_LENGTH: f32 = 1e-4;
+
+/// The extra distance that corners of detours around carved footprints are
+/// kept from the footprints, so that walking between corners does not graze
+/// the footprints due to floating point error.
+const DETOUR_CORNER_MARGIN: f32 = 1e-3;
+
+/// Finds the intervals of `segment` that are outside all of `footprints`
+/// (convex, counter-clockwise polygons). The intervals are given as fractions
+/// along the segment, in order.
+fn subtract_footprints_from_segment<'a>(
+  segment: (Vec2, Vec2),
+  footprints: impl IntoIterator<Item = &'a [Vec2]>,
+) -> Vec<(f32, f32)> {
+  let mut intervals = vec![(0.0f32, 1.0f32)];
+  for footprint in footprints {
+    let Some((carve_start, carve_end)) =
+      convex_polygon_segment_interval(footprint, segment)
+    else {
+      continue;
+    };
+    intervals = intervals
+      .into_iter()
+      .flat_map(|(start, end)| {
+        [(start, end.min(carve_start)), (start.max(carve_end), end)]
+      })
+      .filter(|(start, end)| start < end)
+      .collect();
   }
+  intervals
 }
 
 /// An error for settings the cost of a type index.
@@ -1362,10 +2722,12 @@ fn world_portal_to_node_portals<CS: CoordinateSystem>(
       continue;
     }
 
+    let local_max_vertical_distance =
+      island.transform.apply_inverse_to_distance(max_vertical_distance);
     let node_bbh = island_to_node_bbh.entry(island_id).or_insert_with(|| {
       nav_mesh_node_bbh(
         island.nav_mesh.as_ref(),
-        Vec3::new(0.0, 0.0, max_vertical_distance),
+        Vec3::new(0.0, 0.0, local_max_vertical_distance),
       )
     });
     let local_portal = (
@@ -1376,7 +2738,7 @@ fn world_portal_to_node_portals<CS: CoordinateSystem>(
     node_portals.extend(
       island
         .nav_mesh
-        .sample_edge(local_portal, node_bbh, max_vertical_distance)
+        .sample_edge(local_portal, node_bbh, local_max_vertical_distance)
         .into_iter()
         .map(|edge| NodePortal {
           node: NodeRef { island_id, polygon_index: edge.node },
@@ -1402,18 +2764,21 @@ fn sample_animation_link_point<CS: CoordinateSystem>(
   for &island_id in island_bbh.query_box(query_box) {
     let island = islands.get(island_id).unwrap();
     let relative_point = island.transform.apply_inverse(point);
+    let local_max_vertical_distance =
+      island.transform.apply_inverse_to_distance(max_vertical_distance);
     let Some((sampled_point, sampled_node)) = island.nav_mesh.sample_point(
       relative_point,
       &CorePointSampleDistance {
-        distance_above: max_vertical_distance,
-        distance_below: max_vertical_distance,
+        distance_above: local_max_vertical_distance,
+        distance_below: local_max_vertical_distance,
         horizontal_distance: 0.0,
         vertical_preference_ratio: 1.0,
       },
     ) else {
       continue;
     };
-    let distance = relative_point.distance_squared(sampled_point);
+    let distance =
+      point.distance_squared(island.transform.apply(sampled_point));
     match best_point {
       Some((best_distance, _)) if distance >= best_distance => continue,
       _ => {}
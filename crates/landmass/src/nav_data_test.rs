@@ -5,7 +5,8 @@ use std::{
   sync::Arc,
 };
 
-use glam::{Vec2, Vec3};
+use geo::Area;
+use glam::{Quat, Vec2, Vec3};
 use googletest::{
   expect_eq, expect_false, expect_that, expect_true, matchers::*,
   prelude::container_eq,
@@ -17,10 +18,13 @@ use crate::{
   HeightNavigationMesh, HeightPolygon, IslandId, PermittedAnimationLinks,
   PointSampleDistance3d, SetTypeIndexCostError, Transform,
   coords::{CorePointSampleDistance, XY, XYZ},
+  door::{Door, DoorEdges},
   island::Island,
   link::{AnimationLink, NodePortal},
+  modifier::{ModifierEffect, ModifierVolume, ModifierVolumeError},
   nav_data::{KindedOffMeshLink, NodeRef, OffMeshLink},
-  nav_mesh::NavigationMesh,
+  nav_mesh::{NavigationMesh, ValidNavigationMesh},
+  obstacle::{Obstacle, ObstacleError},
 };
 
 use super::{
@@ -43,6 +47,7 @@ fn samples_points() {
     ],
     polygons: vec![vec![0, 1, 6, 7], vec![1, 2, 5, 6], vec![2, 3, 4, 5]],
     polygon_type_indices: vec![0, 0, 0],
+    polygon_flags: None,
     height_mesh: None,
   }
   .validate()
@@ -51,11 +56,15 @@ fn samples_points() {
 
   let mut nav_data = NavigationData::<XYZ>::new();
   let island_id_1 = nav_data.add_island(Island::new(
-    Transform { translation: Vec3::ZERO, rotation: 0.0 },
+    Transform { translation: Vec3::ZERO, rotation: Quat::IDENTITY, scale: 1.0 },
     Arc::clone(&nav_mesh),
   ));
   let island_id_2 = nav_data.add_island(Island::new(
-    Transform { translation: Vec3::new(5.0, 0.0, 0.1), rotation: PI * 0.5 },
+    Transform {
+      translation: Vec3::new(5.0, 0.0, 0.1),
+      rotation: Quat::from_rotation_z(PI * 0.5),
+      scale: 1.0,
+    },
     Arc::clone(&nav_mesh),
   ));
 
@@ -131,6 +140,195 @@ fn samples_points() {
   );
 }
 
+#[test]
+fn samples_points_along_up_axis_of_tilted_and_scaled_island() {
+  let nav_mesh = NavigationMesh {
+    vertices: vec![
+      Vec3::new(0.0, 0.0, 0.0),
+      Vec3::new(1.0, 0.0, 0.0),
+      Vec3::new(1.0, 1.0, 0.0),
+      Vec3::new(0.0, 1.0, 0.0),
+    ],
+    polygons: vec![vec![0, 1, 2, 3]],
+    polygon_type_indices: vec![0],
+    polygon_flags: None,
+    height_mesh: None,
+  }
+  .validate()
+  .expect("is valid");
+
+  let mut nav_data = NavigationData::<XYZ>::new();
+  let transform = Transform {
+    translation: Vec3::new(1.0, 2.0, 3.0),
+    rotation: Quat::from_rotation_x(PI * 0.25),
+    scale: 2.0,
+  };
+  let island_id =
+    nav_data.add_island(Island::new(transform.clone(), Arc::new(nav_mesh)));
+
+  // The island is 2 units wide, and its far edge is raised by sqrt(2).
+  let (bounds_min, bounds_max) =
+    nav_data.get_island(island_id).unwrap().transformed_bounds.as_box();
+  assert!(bounds_min.abs_diff_eq(Vec3::new(1.0, 2.0, 3.0), 1e-5));
+  assert!(bounds_max.abs_diff_eq(
+    Vec3::new(3.0, 2.0 + 2.0f32.sqrt(), 3.0 + 2.0f32.sqrt()),
+    1e-5
+  ));
+
+  // Half a unit above the middle of the island (along its up axis).
+  let point = transform.apply(Vec3::new(0.5, 0.5, 0.25));
+  let expected_point = transform.apply(Vec3::new(0.5, 0.5, 0.0));
+  let sample_distance = |distance_below| {
+    CorePointSampleDistance::new(&PointSampleDistance3d {
+      horizontal_distance: 0.1,
+      distance_below,
+      distance_above: 0.1,
+      vertical_preference_ratio: 1.0,
+      animation_link_max_vertical_distance: 1.0,
+    })
+  };
+
+  let (sampled_point, sampled_node) = nav_data
+    .sample_point(point, &sample_distance(0.6))
+    .expect("The point is close enough to the island.");
+  assert!(
+    sampled_point.abs_diff_eq(expected_point, 1e-5),
+    "sampled_point={sampled_point} expected_point={expected_point}"
+  );
+  assert_eq!(sampled_node, NodeRef { island_id, polygon_index: 0 });
+
+  // The distance is scaled along with the island, so the point is now too far
+  // away.
+  assert_eq!(nav_data.sample_point(point, &sample_distance(0.4)), None);
+}
+
+fn single_square_nav_mesh() -> Arc<ValidNavigationMesh<XY>> {
+  Arc::new(
+    NavigationMesh {
+      vertices: vec![
+        Vec2::new(0.0, 0.0),
+        Vec2::new(1.0, 0.0),
+        Vec2::new(1.0, 1.0),
+        Vec2::new(0.0, 1.0),
+      ],
+      polygons: vec![vec![0, 1, 2, 3]],
+      polygon_type_indices: vec![0],
+      polygon_flags: None,
+      height_mesh: None,
+    }
+    .validate()
+    .expect("is valid"),
+  )
+}
+
+#[test]
+#[should_panic(expected = "must be positive and finite, but was 0")]
+fn island_with_zero_scale_panics() {
+  Island::new(
+    Transform { scale: 0.0, ..Default::default() },
+    single_square_nav_mesh(),
+  );
+}
+
+#[test]
+#[should_panic(expected = "must be positive and finite, but was -2")]
+fn setting_island_transform_with_negative_scale_panics() {
+  let mut island = Island::new(Transform::default(), single_square_nav_mesh());
+  island.set_transform(Transform { scale: -2.0, ..Default::default() });
+}
+
+#[test]
+fn moved_island_carries_points_without_invalidating_island() {
+  let nav_mesh = Arc::new(
+    NavigationMesh {
+      vertices: vec![
+        Vec2::new(0.0, 0.0),
+        Vec2::new(1.0, 0.0),
+        Vec2::new(1.0, 1.0),
+        Vec2::new(0.0, 1.0),
+      ],
+      polygons: vec![vec![0, 1, 2, 3]],
+      polygon_type_indices: vec![0],
+      polygon_flags: None,
+      height_mesh: None,
+    }
+    .validate()
+    .expect("is valid"),
+  );
+
+  let mut nav_data = NavigationData::<XY>::new();
+  let island_id =
+    nav_data.add_island(Island::new(Transform::default(), nav_mesh.clone()));
+  nav_data.update(
+    /* edge_link_distance= */ 1e-5,
+    /* animation_link_distance= */ 1.0,
+  );
+  assert!(nav_data.moved_islands.is_empty());
+
+  // Setting the transform several times before an update moves from the
+  // transform of the last update.
+  nav_data.get_island_mut(island_id).unwrap().set_transform(Transform {
+    translation: Vec2::new(5.0, 0.0),
+    rotation: Quat::IDENTITY,
+    scale: 1.0,
+  });
+  nav_data.get_island_mut(island_id).unwrap().set_transform(Transform {
+    translation: Vec2::new(1.0, 2.0),
+    rotation: Quat::from_rotation_z(PI * 0.5),
+    scale: 1.0,
+  });
+  let (_, invalidated_islands, _) = nav_data.update(
+    /* edge_link_distance= */ 1e-5,
+    /* animation_link_distance= */ 1.0,
+  );
+  assert!(invalidated_islands.is_empty());
+
+  let carried_point = nav_data
+    .carry_point_with_island(island_id, Vec3::new(0.5, 0.25, 3.0))
+    .expect("The island moved.");
+  assert!(
+    carried_point.abs_diff_eq(Vec3::new(0.75, 2.5, 3.0), 1e-5),
+    "carried_point={carried_point}"
+  );
+
+  // Changing the nav mesh still invalidates the island, and doesn't carry
+  // points.
+  nav_data.get_island_mut(island_id).unwrap().set_transform(Transform {
+    translation: Vec2::new(3.0, 2.0),
+    rotation: Quat::IDENTITY,
+    scale: 1.0,
+  });
+  nav_data.get_island_mut(island_id).unwrap().set_nav_mesh(nav_mesh);
+  let (_, invalidated_islands, _) = nav_data.update(
+    /* edge_link_distance= */ 1e-5,
+    /* animation_link_distance= */ 1.0,
+  );
+  assert_eq!(invalidated_islands, HashSet::from([island_id]));
+  assert_eq!(
+    nav_data.carry_point_with_island(island_id, Vec3::new(0.5, 0.25, 3.0)),
+    None
+  );
+
+  // Updating again without moving the island doesn't carry points.
+  nav_data.get_island_mut(island_id).unwrap().set_transform(Transform {
+    translation: Vec2::new(4.0, 2.0),
+    rotation: Quat::IDENTITY,
+    scale: 1.0,
+  });
+  nav_data.update(
+    /* edge_link_distance= */ 1e-5,
+    /* animation_link_distance= */ 1.0,
+  );
+  nav_data.update(
+    /* edge_link_distance= */ 1e-5,
+    /* animation_link_distance= */ 1.0,
+  );
+  assert_eq!(
+    nav_data.carry_point_with_island(island_id, Vec3::new(0.5, 0.25, 3.0)),
+    None
+  );
+}
+
 fn node_ref_to_num(node_ref: &NodeRef, island_order: &[IslandId]) -> u32 {
   let island_index =
     island_order.iter().position(|id| node_ref.island_id == *id).unwrap();
@@ -203,6 +401,7 @@ fn link_edges_between_islands_links_touching_islands() {
         vec![6, 0, 5, 11],
       ],
       polygon_type_indices: vec![0, 1, 1, 1, 0, 0],
+      polygon_flags: None,
       height_mesh: None,
     }
     .validate()
@@ -233,6 +432,7 @@ fn link_edges_between_islands_links_touching_islands() {
         vec![10, 11, 2, 1],
       ],
       polygon_type_indices: vec![0, 0, 0, 0, 2],
+      polygon_flags: None,
       height_mesh: None,
     }
     .validate()
@@ -244,8 +444,11 @@ fn link_edges_between_islands_links_touching_islands() {
   let island_1_id = slotmap.insert(0);
   let island_2_id = slotmap.insert(0);
 
-  let transform =
-    Transform { translation: Vec3::new(1.0, 2.0, 3.0), rotation: PI * -0.25 };
+  let transform = Transform {
+    translation: Vec3::new(1.0, 2.0, 3.0),
+    rotation: Quat::from_rotation_z(PI * -0.25),
+    scale: 1.0,
+  };
 
   let island_1 = Island::new(transform.clone(), Arc::clone(&nav_mesh_1));
   let island_2 = Island::new(transform.clone(), Arc::clone(&nav_mesh_2));
@@ -517,6 +720,7 @@ fn update_links_islands_and_unlinks_on_delete() {
       ],
       polygons: vec![vec![0, 1, 2, 5], vec![4, 5, 2, 3]],
       polygon_type_indices: vec![0, 0],
+      polygon_flags: None,
       height_mesh: None,
     }
     .validate()
@@ -526,23 +730,39 @@ fn update_links_islands_and_unlinks_on_delete() {
   let mut nav_data = NavigationData::<XYZ>::new();
 
   let island_1_id = nav_data.add_island(Island::new(
-    Transform { translation: Vec3::ZERO, rotation: 0.0 },
+    Transform { translation: Vec3::ZERO, rotation: Quat::IDENTITY, scale: 1.0 },
     Arc::clone(&nav_mesh),
   ));
   let island_2_id = nav_data.add_island(Island::new(
-    Transform { translation: Vec3::ZERO, rotation: PI * 0.5 },
+    Transform {
+      translation: Vec3::ZERO,
+      rotation: Quat::from_rotation_z(PI * 0.5),
+      scale: 1.0,
+    },
     Arc::clone(&nav_mesh),
   ));
   let island_3_id = nav_data.add_island(Island::new(
-    Transform { translation: Vec3::ZERO, rotation: PI },
+    Transform {
+      translation: Vec3::ZERO,
+      rotation: Quat::from_rotation_z(PI),
+      scale: 1.0,
+    },
     Arc::clone(&nav_mesh),
   ));
   let island_4_id = nav_data.add_island(Island::new(
-    Transform { translation: Vec3::new(3.0, 0.0, 0.0), rotation: PI },
+    Transform {
+      translation: Vec3::new(3.0, 0.0, 0.0),
+      rotation: Quat::from_rotation_z(PI),
+      scale: 1.0,
+    },
     Arc::clone(&nav_mesh),
   ));
   let island_5_id = nav_data.add_island(Island::new(
-    Transform { translation: Vec3::new(2.0, 3.0, 0.0), rotation: PI * -0.5 },
+    Transform {
+      translation: Vec3::new(2.0, 3.0, 0.0),
+      rotation: Quat::from_rotation_z(PI * -0.5),
+      scale: 1.0,
+    },
     Arc::clone(&nav_mesh),
   ));
 
@@ -667,7 +887,11 @@ fn update_links_islands_and_unlinks_on_delete() {
     .islands
     .get_mut(island_5_id)
     .expect("island_5 still exists")
-    .set_transform(Transform { translation: Vec3::ZERO, rotation: PI * 0.5 });
+    .set_transform(Transform {
+      translation: Vec3::ZERO,
+      rotation: Quat::from_rotation_z(PI * 0.5),
+      scale: 1.0,
+    });
 
   nav_data.update(
     /* edge_link_distance= */ 0.01,
@@ -794,6 +1018,7 @@ fn clone_sort_round_modified_nodes(
         ModifiedNode {
           new_boundary: new_boundary_sorted,
           new_vertices: new_vertices_sorted,
+          uncarved_region: value.uncarved_region.clone(),
         }
       })
     })
@@ -816,6 +1041,7 @@ fn modifies_node_boundaries_for_linked_islands() {
       ],
       polygons: vec![vec![0, 1, 2, 3], vec![3, 2, 4, 5]],
       polygon_type_indices: vec![0, 0],
+      polygon_flags: None,
       height_mesh: None,
     }
     .validate()
@@ -825,15 +1051,23 @@ fn modifies_node_boundaries_for_linked_islands() {
   let mut nav_data = NavigationData::<XYZ>::new();
 
   let island_1_id = nav_data.add_island(Island::new(
-    Transform { translation: Vec3::ZERO, rotation: 0.0 },
+    Transform { translation: Vec3::ZERO, rotation: Quat::IDENTITY, scale: 1.0 },
     Arc::clone(&nav_mesh),
   ));
   let island_2_id = nav_data.add_island(Island::new(
-    Transform { translation: Vec3::new(1.0, -1.0, 0.0), rotation: 0.0 },
+    Transform {
+      translation: Vec3::new(1.0, -1.0, 0.0),
+      rotation: Quat::IDENTITY,
+      scale: 1.0,
+    },
     Arc::clone(&nav_mesh),
   ));
   let island_3_id = nav_data.add_island(Island::new(
-    Transform { translation: Vec3::new(2.0, 3.5, 0.0), rotation: PI * -0.5 },
+    Transform {
+      translation: Vec3::new(2.0, 3.5, 0.0),
+      rotation: Quat::from_rotation_z(PI * -0.5),
+      scale: 1.0,
+    },
     Arc::clone(&nav_mesh),
   ));
 
@@ -845,13 +1079,18 @@ fn modifies_node_boundaries_for_linked_islands() {
   let expected_modified_nodes = [
     (
       NodeRef { island_id: island_1_id, polygon_index: 0 },
-      ModifiedNode { new_boundary: vec![(0, 1), (3, 0)], new_vertices: vec![] },
+      ModifiedNode {
+        new_boundary: vec![(0, 1), (3, 0)],
+        new_vertices: vec![],
+        uncarved_region: None,
+      },
     ),
     (
       NodeRef { island_id: island_2_id, polygon_index: 1 },
       ModifiedNode {
         new_boundary: vec![(2, 6), (4, 5)],
         new_vertices: vec![Vec2::new(3.0, 1.5)],
+        uncarved_region: None,
       },
     ),
     (
@@ -859,6 +1098,7 @@ fn modifies_node_boundaries_for_linked_islands() {
       ModifiedNode {
         new_boundary: vec![(0, 6), (1, 2), (3, 0)],
         new_vertices: vec![Vec2::new(3.0, 2.0)],
+        uncarved_region: None,
       },
     ),
   ];
@@ -888,6 +1128,7 @@ fn stale_modified_nodes_are_removed() {
       ],
       polygons: vec![vec![0, 1, 2, 3], vec![3, 2, 4, 5]],
       polygon_type_indices: vec![0, 0],
+      polygon_flags: None,
       height_mesh: None,
     }
     .validate()
@@ -897,11 +1138,15 @@ fn stale_modified_nodes_are_removed() {
   let mut nav_data = NavigationData::<XYZ>::new();
 
   nav_data.add_island(Island::new(
-    Transform { translation: Vec3::ZERO, rotation: 0.0 },
+    Transform { translation: Vec3::ZERO, rotation: Quat::IDENTITY, scale: 1.0 },
     Arc::clone(&nav_mesh),
   ));
   let island_2_id = nav_data.add_island(Island::new(
-    Transform { translation: Vec3::new(1.0, -1.0, 0.0), rotation: 0.0 },
+    Transform {
+      translation: Vec3::new(1.0, -1.0, 0.0),
+      rotation: Quat::IDENTITY,
+      scale: 1.0,
+    },
     Arc::clone(&nav_mesh),
   ));
 
@@ -934,6 +1179,7 @@ fn modified_node_is_removed_for_no_boundary_links() {
       ],
       polygons: vec![vec![0, 1, 2, 3]],
       polygon_type_indices: vec![0],
+      polygon_flags: None,
       height_mesh: None,
     }
     .validate()
@@ -943,15 +1189,23 @@ fn modified_node_is_removed_for_no_boundary_links() {
   let mut nav_data = NavigationData::<XY>::new();
 
   let island_1_id = nav_data.add_island(Island::new(
-    Transform { translation: Vec2::ZERO, rotation: 0.0 },
+    Transform { translation: Vec2::ZERO, rotation: Quat::IDENTITY, scale: 1.0 },
     Arc::clone(&nav_mesh),
   ));
   let island_2_id = nav_data.add_island(Island::new(
-    Transform { translation: Vec2::new(1.0, 0.0), rotation: 0.0 },
+    Transform {
+      translation: Vec2::new(1.0, 0.0),
+      rotation: Quat::IDENTITY,
+      scale: 1.0,
+    },
     Arc::clone(&nav_mesh),
   ));
   nav_data.add_island(Island::new(
-    Transform { translation: Vec2::new(-2.0, 0.0), rotation: 0.0 },
+    Transform {
+      translation: Vec2::new(-2.0, 0.0),
+      rotation: Quat::IDENTITY,
+      scale: 1.0,
+    },
     Arc::clone(&nav_mesh),
   ));
 
@@ -998,6 +1252,7 @@ fn empty_navigation_mesh_is_safe() {
       ],
       polygons: vec![vec![0, 1, 2, 3]],
       polygon_type_indices: vec![0],
+      polygon_flags: None,
       height_mesh: None,
     }
     .validate()
@@ -1009,6 +1264,7 @@ fn empty_navigation_mesh_is_safe() {
       vertices: vec![],
       polygons: vec![],
       polygon_type_indices: vec![],
+      polygon_flags: None,
       height_mesh: None,
     }
     .validate()
@@ -1054,6 +1310,7 @@ fn changed_island_rebuilds_region_connectivity() {
       ],
       polygons: vec![vec![0, 1, 2, 3]],
       polygon_type_indices: vec![0],
+      polygon_flags: None,
       height_mesh: None,
     }
     .validate()
@@ -1063,7 +1320,11 @@ fn changed_island_rebuilds_region_connectivity() {
   let island_1 =
     nav_data.add_island(Island::new(Transform::default(), nav_mesh.clone()));
   let island_2 = nav_data.add_island(Island::new(
-    Transform { translation: Vec2::new(0.0, 2.0), rotation: 0.0 },
+    Transform {
+      translation: Vec2::new(0.0, 2.0),
+      rotation: Quat::IDENTITY,
+      scale: 1.0,
+    },
     nav_mesh,
   ));
   nav_data.update(
@@ -1079,7 +1340,8 @@ fn changed_island_rebuilds_region_connectivity() {
   // Making the islands touch should result in the regions being connected.
   nav_data.get_island_mut(island_2).unwrap().set_transform(Transform {
     translation: Vec2::new(0.0, 1.0),
-    rotation: 0.0,
+    rotation: Quat::IDENTITY,
+    scale: 1.0,
   });
   nav_data.update(
     /* edge_link_distance= */ 1e-5, /* animation_link_distance */ 1.0,
@@ -1094,7 +1356,8 @@ fn changed_island_rebuilds_region_connectivity() {
   // Making the islands no longer touch again should remove the connectivity.
   nav_data.get_island_mut(island_2).unwrap().set_transform(Transform {
     translation: Vec2::new(0.0, 2.0),
-    rotation: 0.0,
+    rotation: Quat::IDENTITY,
+    scale: 1.0,
   });
   nav_data.update(
     /* edge_link_distance= */ 1e-5, /* animation_link_distance */ 1.0,
@@ -1121,6 +1384,7 @@ fn changed_animation_link_rebuilds_region_connectivity() {
       ],
       polygons: vec![vec![0, 1, 2, 3]],
       polygon_type_indices: vec![0],
+      polygon_flags: None,
       height_mesh: None,
     }
     .validate()
@@ -1130,7 +1394,11 @@ fn changed_animation_link_rebuilds_region_connectivity() {
   let island_1 =
     nav_data.add_island(Island::new(Transform::default(), nav_mesh.clone()));
   let island_2 = nav_data.add_island(Island::new(
-    Transform { translation: Vec2::new(0.0, 2.0), rotation: 0.0 },
+    Transform {
+      translation: Vec2::new(0.0, 2.0),
+      rotation: Quat::IDENTITY,
+      scale: 1.0,
+    },
     nav_mesh,
   ));
   nav_data.update(
@@ -1188,6 +1456,7 @@ fn permitted_animation_link_blocks_region_connectivity() {
       ],
       polygons: vec![vec![0, 1, 2, 3]],
       polygon_type_indices: vec![0],
+      polygon_flags: None,
       height_mesh: None,
     }
     .validate()
@@ -1197,7 +1466,11 @@ fn permitted_animation_link_blocks_region_connectivity() {
   let island_1 =
     nav_data.add_island(Island::new(Transform::default(), nav_mesh.clone()));
   let island_2 = nav_data.add_island(Island::new(
-    Transform { translation: Vec2::new(0.0, 2.0), rotation: 0.0 },
+    Transform {
+      translation: Vec2::new(0.0, 2.0),
+      rotation: Quat::IDENTITY,
+      scale: 1.0,
+    },
     nav_mesh,
   ));
   // We have 2 animation links with different kinds.
@@ -1271,6 +1544,7 @@ fn generates_animation_links() {
       ],
       polygons: vec![vec![0, 1, 2, 3]],
       polygon_type_indices: vec![0],
+      polygon_flags: None,
       height_mesh: None,
     }
     .validate()
@@ -1282,15 +1556,27 @@ fn generates_animation_links() {
   let island_1 =
     nav_data.add_island(Island::new(Transform::default(), nav_mesh.clone()));
   let island_2 = nav_data.add_island(Island::new(
-    Transform { translation: Vec3::new(2.0, 0.0, 0.0), rotation: 0.0 },
+    Transform {
+      translation: Vec3::new(2.0, 0.0, 0.0),
+      rotation: Quat::IDENTITY,
+      scale: 1.0,
+    },
     nav_mesh.clone(),
   ));
   let island_3 = nav_data.add_island(Island::new(
-    Transform { translation: Vec3::new(-2.0, 0.0, 0.0), rotation: 0.0 },
+    Transform {
+      translation: Vec3::new(-2.0, 0.0, 0.0),
+      rotation: Quat::IDENTITY,
+      scale: 1.0,
+    },
     nav_mesh.clone(),
   ));
   let island_4 = nav_data.add_island(Island::new(
-    Transform { translation: Vec3::new(0.0, 4.0, 0.0), rotation: 0.0 },
+    Transform {
+      translation: Vec3::new(0.0, 4.0, 0.0),
+      rotation: Quat::IDENTITY,
+      scale: 1.0,
+    },
     nav_mesh.clone(),
   ));
 
@@ -1439,6 +1725,7 @@ fn removing_animation_link_removes_off_mesh_links() {
       ],
       polygons: vec![vec![0, 1, 2, 3]],
       polygon_type_indices: vec![0],
+      polygon_flags: None,
       height_mesh: None,
     }
     .validate()
@@ -1450,7 +1737,11 @@ fn removing_animation_link_removes_off_mesh_links() {
   let island_1 =
     nav_data.add_island(Island::new(Transform::default(), nav_mesh.clone()));
   let island_2 = nav_data.add_island(Island::new(
-    Transform { translation: Vec3::new(2.0, 0.0, 0.0), rotation: 0.0 },
+    Transform {
+      translation: Vec3::new(2.0, 0.0, 0.0),
+      rotation: Quat::IDENTITY,
+      scale: 1.0,
+    },
     nav_mesh.clone(),
   ));
 
@@ -1615,6 +1906,7 @@ fn existing_animation_link_is_linked_for_new_island() {
       ],
       polygons: vec![vec![0, 1, 2, 3]],
       polygon_type_indices: vec![0],
+      polygon_flags: None,
       height_mesh: None,
     }
     .validate()
@@ -1685,7 +1977,11 @@ fn existing_animation_link_is_linked_for_new_island() {
 
   // Adding the second island should now complete the links.
   let island_2 = nav_data.add_island(Island::new(
-    Transform { translation: Vec3::new(2.0, 0.0, 0.0), rotation: 0.0 },
+    Transform {
+      translation: Vec3::new(2.0, 0.0, 0.0),
+      rotation: Quat::IDENTITY,
+      scale: 1.0,
+    },
     nav_mesh.clone(),
   ));
   nav_data.update(
@@ -1780,6 +2076,7 @@ fn added_island_mixes_new_and_old_portals() {
       ],
       polygons: vec![vec![0, 1, 2, 3]],
       polygon_type_indices: vec![0],
+      polygon_flags: None,
       height_mesh: None,
     }
     .validate()
@@ -1791,7 +2088,11 @@ fn added_island_mixes_new_and_old_portals() {
   let island_00 =
     nav_data.add_island(Island::new(Transform::default(), nav_mesh.clone()));
   let island_11 = nav_data.add_island(Island::new(
-    Transform { translation: Vec3::new(2.0, 2.0, 0.0), rotation: 0.0 },
+    Transform {
+      translation: Vec3::new(2.0, 2.0, 0.0),
+      rotation: Quat::IDENTITY,
+      scale: 1.0,
+    },
     nav_mesh.clone(),
   ));
 
@@ -1849,11 +2150,19 @@ fn added_island_mixes_new_and_old_portals() {
 
   // Add in the remaining corners.
   let island_01 = nav_data.add_island(Island::new(
-    Transform { translation: Vec3::new(0.0, 2.0, 0.0), rotation: 0.0 },
+    Transform {
+      translation: Vec3::new(0.0, 2.0, 0.0),
+      rotation: Quat::IDENTITY,
+      scale: 1.0,
+    },
     nav_mesh.clone(),
   ));
   let island_10 = nav_data.add_island(Island::new(
-    Transform { translation: Vec3::new(2.0, 0.0, 0.0), rotation: 0.0 },
+    Transform {
+      translation: Vec3::new(2.0, 0.0, 0.0),
+      rotation: Quat::IDENTITY,
+      scale: 1.0,
+    },
     nav_mesh.clone(),
   ));
 
@@ -2013,6 +2322,7 @@ fn same_island_animation_link() {
       ],
       polygons: vec![vec![0, 1, 2, 3]],
       polygon_type_indices: vec![0],
+      polygon_flags: None,
       height_mesh: None,
     }
     .validate()
@@ -2092,6 +2402,7 @@ fn point_animation_links_are_connected() {
       ],
       polygons: vec![vec![0, 1, 2, 3], vec![4, 5, 6, 7]],
       polygon_type_indices: vec![0; 2],
+      polygon_flags: None,
       height_mesh: None,
     }
     .validate()
@@ -2219,6 +2530,7 @@ fn point_animation_links_are_connected_when_not_new() {
       ],
       polygons: vec![vec![0, 1, 2, 3], vec![4, 5, 6, 7]],
       polygon_type_indices: vec![0; 2],
+      polygon_flags: None,
       height_mesh: None,
     }
     .validate()
@@ -2347,6 +2659,7 @@ fn changing_island_does_not_cause_duplicate_off_mesh_links() {
       ],
       polygons: vec![vec![0, 1, 2, 3]],
       polygon_type_indices: vec![0],
+      polygon_flags: None,
       height_mesh: None,
     }
     .validate()
@@ -2358,15 +2671,27 @@ fn changing_island_does_not_cause_duplicate_off_mesh_links() {
   let island_00 =
     nav_data.add_island(Island::new(Transform::default(), nav_mesh.clone()));
   let island_10 = nav_data.add_island(Island::new(
-    Transform { translation: Vec3::new(3.5, 0.0, 0.0), rotation: 0.0 },
+    Transform {
+      translation: Vec3::new(3.5, 0.0, 0.0),
+      rotation: Quat::IDENTITY,
+      scale: 1.0,
+    },
     nav_mesh.clone(),
   ));
   let island_11 = nav_data.add_island(Island::new(
-    Transform { translation: Vec3::new(3.0, 3.0, 0.0), rotation: 0.0 },
+    Transform {
+      translation: Vec3::new(3.0, 3.0, 0.0),
+      rotation: Quat::IDENTITY,
+      scale: 1.0,
+    },
     nav_mesh.clone(),
   ));
   let island_01 = nav_data.add_island(Island::new(
-    Transform { translation: Vec3::new(-0.5, 3.0, 0.0), rotation: 0.0 },
+    Transform {
+      translation: Vec3::new(-0.5, 3.0, 0.0),
+      rotation: Quat::IDENTITY,
+      scale: 1.0,
+    },
     nav_mesh.clone(),
   ));
 
@@ -2458,11 +2783,13 @@ fn changing_island_does_not_cause_duplicate_off_mesh_links() {
   // Now we change two islands to see that the links are updated.
   nav_data.get_island_mut(island_01).unwrap().set_transform(Transform {
     translation: Vec3::new(0.0, 3.0, 0.0),
-    rotation: 0.0,
+    rotation: Quat::IDENTITY,
+    scale: 1.0,
   });
   nav_data.get_island_mut(island_10).unwrap().set_transform(Transform {
     translation: Vec3::new(3.0, 0.0, 0.0),
-    rotation: 0.0,
+    rotation: Quat::IDENTITY,
+    scale: 1.0,
   });
   nav_data.update(
     /* edge_link_distance= */ 1e-5, /* animation_link_distance */ 1.0,
@@ -2555,6 +2882,7 @@ fn changing_island_does_not_cause_duplicate_off_mesh_links_for_point_links() {
       ],
       polygons: vec![vec![0, 1, 2, 3]],
       polygon_type_indices: vec![0],
+      polygon_flags: None,
       height_mesh: None,
     }
     .validate()
@@ -2566,7 +2894,11 @@ fn changing_island_does_not_cause_duplicate_off_mesh_links_for_point_links() {
   let island_1 =
     nav_data.add_island(Island::new(Transform::default(), nav_mesh.clone()));
   let island_2 = nav_data.add_island(Island::new(
-    Transform { translation: Vec3::new(0.0, 2.0, 0.0), rotation: 0.0 },
+    Transform {
+      translation: Vec3::new(0.0, 2.0, 0.0),
+      rotation: Quat::IDENTITY,
+      scale: 1.0,
+    },
     nav_mesh.clone(),
   ));
 
@@ -2672,7 +3004,8 @@ fn changing_island_does_not_cause_duplicate_off_mesh_links_for_point_links() {
   // Now we change one of the islands to see that the links are updated.
   nav_data.get_island_mut(island_2).unwrap().set_transform(Transform {
     translation: Vec3::new(0.1, 2.0, 0.0),
-    rotation: 0.0,
+    rotation: Quat::IDENTITY,
+    scale: 1.0,
   });
   nav_data.update(
     /* edge_link_distance= */ 1e-5, /* animation_link_distance */ 1.0,
@@ -2780,6 +3113,7 @@ fn generates_animation_links_at_correct_height() {
       ],
       polygons: vec![vec![0, 1, 2, 3], vec![4, 5, 6, 7]],
       polygon_type_indices: vec![0; 2],
+      polygon_flags: None,
       height_mesh: None,
     }
     .validate()
@@ -2791,7 +3125,11 @@ fn generates_animation_links_at_correct_height() {
   let island_1 =
     nav_data.add_island(Island::new(Transform::default(), nav_mesh.clone()));
   let island_2 = nav_data.add_island(Island::new(
-    Transform { translation: Vec3::new(2.0, 0.0, 0.0), rotation: 0.0 },
+    Transform {
+      translation: Vec3::new(2.0, 0.0, 0.0),
+      rotation: Quat::IDENTITY,
+      scale: 1.0,
+    },
     nav_mesh.clone(),
   ));
 
@@ -2880,6 +3218,7 @@ fn generates_animation_links_using_height_mesh() {
       ],
       polygons: vec![vec![0, 1, 2, 3], vec![4, 5, 6, 7]],
       polygon_type_indices: vec![0; 2],
+      polygon_flags: None,
       height_mesh: Some(HeightNavigationMesh {
         vertices: vec![
           // The height polygons have more reasonable heights.
@@ -2918,7 +3257,11 @@ fn generates_animation_links_using_height_mesh() {
   let island_1 =
     nav_data.add_island(Island::new(Transform::default(), nav_mesh.clone()));
   let island_2 = nav_data.add_island(Island::new(
-    Transform { translation: Vec3::new(2.0, 0.0, 0.0), rotation: 0.0 },
+    Transform {
+      translation: Vec3::new(2.0, 0.0, 0.0),
+      rotation: Quat::IDENTITY,
+      scale: 1.0,
+    },
     nav_mesh.clone(),
   ));
 
@@ -3006,6 +3349,7 @@ fn animation_link_along_angled_surface() {
       ],
       polygons: vec![vec![0, 1, 2, 3], vec![3, 2, 4, 5], vec![5, 4, 6, 7]],
       polygon_type_indices: vec![0; 3],
+      polygon_flags: None,
       height_mesh: None,
     }
     .validate()
@@ -3017,7 +3361,11 @@ fn animation_link_along_angled_surface() {
   let island_1 =
     nav_data.add_island(Island::new(Transform::default(), nav_mesh.clone()));
   let island_2 = nav_data.add_island(Island::new(
-    Transform { translation: Vec3::new(2.0, 0.0, 0.0), rotation: 0.0 },
+    Transform {
+      translation: Vec3::new(2.0, 0.0, 0.0),
+      rotation: Quat::IDENTITY,
+      scale: 1.0,
+    },
     nav_mesh.clone(),
   ));
 
@@ -3197,6 +3545,7 @@ fn generates_bidirectional_animation_links() {
       ],
       polygons: vec![vec![0, 1, 2, 3]],
       polygon_type_indices: vec![0],
+      polygon_flags: None,
       height_mesh: None,
     }
     .validate()
@@ -3208,15 +3557,27 @@ fn generates_bidirectional_animation_links() {
   let island_1 =
     nav_data.add_island(Island::new(Transform::default(), nav_mesh.clone()));
   let island_2 = nav_data.add_island(Island::new(
-    Transform { translation: Vec3::new(2.0, 0.0, 0.0), rotation: 0.0 },
+    Transform {
+      translation: Vec3::new(2.0, 0.0, 0.0),
+      rotation: Quat::IDENTITY,
+      scale: 1.0,
+    },
     nav_mesh.clone(),
   ));
   let island_3 = nav_data.add_island(Island::new(
-    Transform { translation: Vec3::new(-2.0, 0.0, 0.0), rotation: 0.0 },
+    Transform {
+      translation: Vec3::new(-2.0, 0.0, 0.0),
+      rotation: Quat::IDENTITY,
+      scale: 1.0,
+    },
     nav_mesh.clone(),
   ));
   let island_4 = nav_data.add_island(Island::new(
-    Transform { translation: Vec3::new(0.0, 4.0, 0.0), rotation: 0.0 },
+    Transform {
+      translation: Vec3::new(0.0, 4.0, 0.0),
+      rotation: Quat::IDENTITY,
+      scale: 1.0,
+    },
     nav_mesh.clone(),
   ));
 
@@ -3426,6 +3787,7 @@ fn bidirectional_links_collapse_either_end() {
       ],
       polygons: vec![vec![0, 1, 2, 3], vec![4, 5, 6, 7]],
       polygon_type_indices: vec![0; 2],
+      polygon_flags: None,
       height_mesh: None,
     }
     .validate()
@@ -3575,7 +3937,8 @@ fn bidirectional_links_collapse_either_end() {
 
   nav_data.get_island_mut(island).unwrap().set_transform(Transform {
     translation: Vec3::new(100.0, 100.0, 100.0),
-    rotation: 0.0,
+    rotation: Quat::IDENTITY,
+    scale: 1.0,
   });
 
   nav_data.update(
@@ -3643,3 +4006,358 @@ fn bidirectional_links_collapse_either_end() {
     ))
   );
 }
+
+fn square_volume(
+  min: Vec2,
+  max: Vec2,
+  effect: ModifierEffect,
+) -> ModifierVolume<XY> {
+  ModifierVolume {
+    vertices: vec![min, Vec2::new(max.x, min.y), max, Vec2::new(min.x, max.y)],
+    height_range: None,
+    effect,
+  }
+}
+
+#[googletest::test]
+fn modifier_volumes_only_invalidate_overlapped_nodes() {
+  let mut nav_data = NavigationData::<XY>::new();
+
+  let nav_mesh = Arc::new(
+    NavigationMesh {
+      vertices: vec![
+        Vec2::new(0.0, 0.0),
+        Vec2::new(1.0, 0.0),
+        Vec2::new(2.0, 0.0),
+        Vec2::new(3.0, 0.0),
+        Vec2::new(3.0, 1.0),
+        Vec2::new(2.0, 1.0),
+        Vec2::new(1.0, 1.0),
+        Vec2::new(0.0, 1.0),
+      ],
+      polygons: vec![vec![0, 1, 6, 7], vec![1, 2, 5, 6], vec![2, 3, 4, 5]],
+      polygon_type_indices: vec![0, 0, 0],
+      polygon_flags: None,
+      height_mesh: None,
+    }
+    .validate()
+    .expect("A strip nav mesh is valid."),
+  );
+  let island_id =
+    nav_data.add_island(Island::new(Transform::default(), nav_mesh));
+  nav_data.update(
+    /* edge_link_distance= */ 1e-5, /* animation_link_distance */ 1.0,
+  );
+
+  let node_0 = NodeRef { island_id, polygon_index: 0 };
+  let node_1 = NodeRef { island_id, polygon_index: 1 };
+  let node_2 = NodeRef { island_id, polygon_index: 2 };
+  let type_index_to_cost = |type_index| if type_index == 3 { 4.0 } else { 1.0 };
+
+  let volume_id = nav_data
+    .add_modifier_volume(square_volume(
+      Vec2::new(0.25, 0.25),
+      Vec2::new(0.75, 0.75),
+      ModifierEffect::AddCost(5.0),
+    ))
+    .unwrap();
+  let (_, _, invalidated_nodes) = nav_data.update(
+    /* edge_link_distance= */ 1e-5, /* animation_link_distance */ 1.0,
+  );
+  expect_eq!(invalidated_nodes, HashSet::from([node_0]));
+  expect_eq!(nav_data.modified_node_cost(node_0, 0, type_index_to_cost), 6.0);
+  expect_eq!(nav_data.modified_node_cost(node_1, 0, type_index_to_cost), 1.0);
+
+  // Moving the volume invalidates the nodes it used to and now overlaps.
+  nav_data
+    .set_modifier_volume(
+      volume_id,
+      square_volume(
+        Vec2::new(1.25, 0.25),
+        Vec2::new(1.75, 0.75),
+        ModifierEffect::AddCost(5.0),
+      ),
+    )
+    .unwrap();
+  let (_, _, invalidated_nodes) = nav_data.update(
+    /* edge_link_distance= */ 1e-5, /* animation_link_distance */ 1.0,
+  );
+  expect_eq!(invalidated_nodes, HashSet::from([node_0, node_1]));
+  expect_eq!(nav_data.modified_node_cost(node_0, 0, type_index_to_cost), 1.0);
+  expect_eq!(nav_data.modified_node_cost(node_1, 0, type_index_to_cost), 6.0);
+
+  // Adding an unrelated volume leaves the first volume's nodes alone.
+  let override_volume_id = nav_data
+    .add_modifier_volume(square_volume(
+      Vec2::new(2.25, 0.25),
+      Vec2::new(2.75, 0.75),
+      ModifierEffect::OverrideTypeIndex(3),
+    ))
+    .unwrap();
+  let (_, _, invalidated_nodes) = nav_data.update(
+    /* edge_link_distance= */ 1e-5, /* animation_link_distance */ 1.0,
+  );
+  expect_eq!(invalidated_nodes, HashSet::from([node_2]));
+  expect_eq!(nav_data.modified_node_cost(node_2, 0, type_index_to_cost), 4.0);
+
+  nav_data.remove_modifier_volume(volume_id);
+  nav_data.remove_modifier_volume(override_volume_id);
+  let (_, _, invalidated_nodes) = nav_data.update(
+    /* edge_link_distance= */ 1e-5, /* animation_link_distance */ 1.0,
+  );
+  expect_eq!(invalidated_nodes, HashSet::from([node_1, node_2]));
+  expect_that!(nav_data.node_to_modifier_volume_ids, is_empty());
+  expect_eq!(nav_data.modified_node_cost(node_2, 0, type_index_to_cost), 1.0);
+}
+
+#[googletest::test]
+fn error_on_invalid_modifier_volume() {
+  let mut nav_data = NavigationData::<XY>::new();
+
+  expect_eq!(
+    nav_data.add_modifier_volume(ModifierVolume {
+      vertices: vec![Vec2::new(0.0, 0.0), Vec2::new(1.0, 0.0)],
+      height_range: None,
+      effect: ModifierEffect::AddCost(1.0),
+    }),
+    Err(ModifierVolumeError::InvalidPolygon)
+  );
+  expect_eq!(
+    nav_data.add_modifier_volume(square_volume(
+      Vec2::ZERO,
+      Vec2::ONE,
+      ModifierEffect::AddCost(-1.0),
+    )),
+    Err(ModifierVolumeError::NegativeCost(-1.0))
+  );
+  expect_eq!(
+    nav_data.add_modifier_volume(ModifierVolume {
+      height_range: Some((1.0, 0.0)),
+      ..square_volume(Vec2::ZERO, Vec2::ONE, ModifierEffect::AddCost(1.0))
+    }),
+    Err(ModifierVolumeError::InvalidHeightRange(1.0, 0.0))
+  );
+  expect_false!(nav_data.dirty);
+}
+
+fn strip_nav_mesh() -> Arc<ValidNavigationMesh<XY>> {
+  Arc::new(
+    NavigationMesh {
+      vertices: vec![
+        Vec2::new(0.0, 0.0),
+        Vec2::new(1.0, 0.0),
+        Vec2::new(2.0, 0.0),
+        Vec2::new(3.0, 0.0),
+        Vec2::new(3.0, 1.0),
+        Vec2::new(2.0, 1.0),
+        Vec2::new(1.0, 1.0),
+        Vec2::new(0.0, 1.0),
+      ],
+      polygons: vec![vec![0, 1, 6, 7], vec![1, 2, 5, 6], vec![2, 3, 4, 5]],
+      polygon_type_indices: vec![0, 0, 0],
+      polygon_flags: None,
+      height_mesh: None,
+    }
+    .validate()
+    .expect("A strip nav mesh is valid."),
+  )
+}
+
+#[googletest::test]
+fn obstacles_carve_nodes() {
+  let mut nav_data = NavigationData::<XY>::new();
+  let island_id =
+    nav_data.add_island(Island::new(Transform::default(), strip_nav_mesh()));
+  nav_data.update(
+    /* edge_link_distance= */ 1e-5, /* animation_link_distance */ 1.0,
+  );
+
+  let node_0 = NodeRef { island_id, polygon_index: 0 };
+  let node_1 = NodeRef { island_id, polygon_index: 1 };
+  let node_2 = NodeRef { island_id, polygon_index: 2 };
+
+  // Carve a hole in the first node, and cut the third node off.
+  let hole_id = nav_data
+    .add_obstacle(Obstacle::Aabb {
+      min: Vec2::new(0.25, 0.25),
+      max: Vec2::new(0.75, 0.75),
+    })
+    .unwrap();
+  nav_data
+    .add_obstacle(Obstacle::Aabb {
+      min: Vec2::new(1.5, -1.0),
+      max: Vec2::new(2.5, 2.0),
+    })
+    .unwrap();
+  let (_, _, invalidated_nodes) = nav_data.update(
+    /* edge_link_distance= */ 1e-5, /* animation_link_distance */ 1.0,
+  );
+  expect_eq!(invalidated_nodes, HashSet::from([node_0, node_1, node_2]));
+
+  let hole = &nav_data.modified_nodes[&node_0];
+  expect_false!(hole.is_carved_out());
+  expect_eq!(hole.uncarved_region.as_ref().unwrap().unsigned_area(), 0.75);
+  // The original boundary stays, and the edges of the hole are added.
+  expect_that!(hole.new_boundary, len(eq(7)));
+  expect_that!(hole.new_vertices, len(eq(8)));
+
+  // Points in the hole are moved to its edge.
+  let point_sample_distance = CorePointSampleDistance::new(&0.5);
+  expect_eq!(
+    nav_data.sample_point(Vec3::new(0.5, 0.6, 0.0), &point_sample_distance),
+    Some((Vec3::new(0.5, 0.75, 0.0), node_0))
+  );
+  expect_eq!(
+    nav_data.sample_point(Vec3::new(2.4, 0.5, 0.0), &point_sample_distance),
+    Some((Vec3::new(2.5, 0.5, 0.0), node_2))
+  );
+
+  // The portal between the second and third nodes is fully carved.
+  expect_false!(nav_data.is_crossing_carved(
+    node_0,
+    node_1,
+    (Vec2::new(1.0, 0.0), Vec2::new(1.0, 1.0)),
+  ));
+  expect_true!(nav_data.is_crossing_carved(
+    node_1,
+    node_2,
+    (Vec2::new(2.0, 0.0), Vec2::new(2.0, 1.0)),
+  ));
+
+  // Removing the hole only affects the first node.
+  nav_data.remove_obstacle(hole_id);
+  let (_, _, invalidated_nodes) = nav_data.update(
+    /* edge_link_distance= */ 1e-5, /* animation_link_distance */ 1.0,
+  );
+  expect_eq!(invalidated_nodes, HashSet::from([node_0]));
+  expect_false!(nav_data.modified_nodes.contains_key(&node_0));
+  expect_eq!(
+    nav_data.sample_point(Vec3::new(0.5, 0.6, 0.0), &point_sample_distance),
+    Some((Vec3::new(0.5, 0.6, 0.0), node_0))
+  );
+}
+
+#[googletest::test]
+fn fully_carved_node_is_impassable() {
+  let mut nav_data = NavigationData::<XY>::new();
+  let island_id =
+    nav_data.add_island(Island::new(Transform::default(), strip_nav_mesh()));
+  nav_data
+    .add_obstacle(Obstacle::Cylinder {
+      base: Vec2::new(1.5, 0.5),
+      radius: 1.0,
+      height: 1.0,
+    })
+    .unwrap();
+  nav_data.update(
+    /* edge_link_distance= */ 1e-5, /* animation_link_distance */ 1.0,
+  );
+
+  let node_1 = NodeRef { island_id, polygon_index: 1 };
+  expect_true!(nav_data.modified_nodes[&node_1].is_carved_out());
+  expect_eq!(nav_data.modified_node_cost(node_1, 0, |_| 1.0), f32::INFINITY);
+  expect_eq!(
+    nav_data.sample_point(
+      Vec3::new(1.5, 0.5, 0.0),
+      &CorePointSampleDistance::new(&0.1)
+    ),
+    None
+  );
+}
+
+#[googletest::test]
+fn closed_doors_block_edges() {
+  let mut nav_data = NavigationData::<XY>::new();
+  let island_id =
+    nav_data.add_island(Island::new(Transform::default(), strip_nav_mesh()));
+  nav_data.update(
+    /* edge_link_distance= */ 1e-5, /* animation_link_distance */ 1.0,
+  );
+
+  let node_0 = NodeRef { island_id, polygon_index: 0 };
+  let node_1 = NodeRef { island_id, polygon_index: 1 };
+  let node_2 = NodeRef { island_id, polygon_index: 2 };
+  let portal_0_1 = (Vec2::new(1.0, 0.0), Vec2::new(1.0, 1.0));
+  let portal_1_2 = (Vec2::new(2.0, 0.0), Vec2::new(2.0, 1.0));
+
+  // An open door changes nothing.
+  let door_id = nav_data.add_door(Door {
+    edges: DoorEdges::NodeEdges(vec![(node_1, 1)]),
+    open: true,
+  });
+  let (_, _, invalidated_nodes) = nav_data.update(
+    /* edge_link_distance= */ 1e-5, /* animation_link_distance */ 1.0,
+  );
+  expect_eq!(invalidated_nodes, HashSet::new());
+  expect_false!(nav_data.is_crossing_carved(node_1, node_2, portal_1_2));
+
+  // Closing the door blocks the portal between the second and third nodes.
+  nav_data.set_door_open(door_id, false);
+  let (_, _, invalidated_nodes) = nav_data.update(
+    /* edge_link_distance= */ 1e-5, /* animation_link_distance */ 1.0,
+  );
+  expect_eq!(invalidated_nodes, HashSet::from([node_1, node_2]));
+  expect_true!(nav_data.is_crossing_carved(node_1, node_2, portal_1_2));
+  expect_true!(nav_data.is_crossing_carved(node_2, node_1, portal_1_2));
+  expect_false!(nav_data.is_crossing_carved(node_0, node_1, portal_0_1));
+  expect_false!(nav_data.modified_nodes[&node_1].is_carved_out());
+
+  // Opening the door again unblocks the portal.
+  nav_data.set_door_open(door_id, true);
+  let (_, _, invalidated_nodes) = nav_data.update(
+    /* edge_link_distance= */ 1e-5, /* animation_link_distance */ 1.0,
+  );
+  expect_eq!(invalidated_nodes, HashSet::from([node_1, node_2]));
+  expect_false!(nav_data.is_crossing_carved(node_1, node_2, portal_1_2));
+  expect_false!(nav_data.modified_nodes.contains_key(&node_1));
+  expect_false!(nav_data.modified_nodes.contains_key(&node_2));
+
+  // A closed cut blocks the portals it passes along.
+  let cut_id = nav_data.add_door(Door {
+    edges: DoorEdges::Cut(Vec2::new(1.0, -1.0), Vec2::new(1.0, 2.0)),
+    open: false,
+  });
+  let (_, _, invalidated_nodes) = nav_data.update(
+    /* edge_link_distance= */ 1e-5, /* animation_link_distance */ 1.0,
+  );
+  expect_eq!(invalidated_nodes, HashSet::from([node_0, node_1]));
+  expect_true!(nav_data.is_crossing_carved(node_0, node_1, portal_0_1));
+
+  nav_data.remove_door(cut_id);
+  let (_, _, invalidated_nodes) = nav_data.update(
+    /* edge_link_distance= */ 1e-5, /* animation_link_distance */ 1.0,
+  );
+  expect_eq!(invalidated_nodes, HashSet::from([node_0, node_1]));
+  expect_false!(nav_data.is_crossing_carved(node_0, node_1, portal_0_1));
+  expect_eq!(nav_data.get_door_ids().collect::<Vec<_>>(), vec![door_id]);
+}
+
+#[googletest::test]
+fn error_on_invalid_obstacle() {
+  let mut nav_data = NavigationData::<XY>::new();
+
+  expect_eq!(
+    nav_data.add_obstacle(Obstacle::Cylinder {
+      base: Vec2::ZERO,
+      radius: -1.0,
+      height: 1.0,
+    }),
+    Err(ObstacleError::NegativeRadius(-1.0))
+  );
+  expect_eq!(
+    nav_data.add_obstacle(Obstacle::Aabb {
+      min: Vec2::ZERO,
+      max: Vec2::new(0.0, 1.0),
+    }),
+    Err(ObstacleError::NoArea)
+  );
+  expect_eq!(
+    nav_data.add_obstacle(Obstacle::ConvexHull(vec![
+      Vec2::ZERO,
+      Vec2::ONE,
+      Vec2::splat(2.0),
+    ])),
+    Err(ObstacleError::NoArea)
+  );
+  expect_false!(nav_data.dirty);
+}
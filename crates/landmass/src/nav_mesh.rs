@@ -11,7 +11,9 @@ use thiserror::Error;
 
 use crate::{
   coords::{CoordinateSystem, CorePointSampleDistance},
-  geometry::clip_edge_to_triangle,
+  geometry::{
+    clip_edge_to_triangle, convex_polygon_clearance, segment_clearance,
+  },
   util::{BoundingBox, BoundingBoxHierarchy, FloatOrd, RaySegment},
 };
 
@@ -28,6 +30,12 @@ pub struct NavigationMesh<CS: CoordinateSystem> {
   /// [`crate::NodeType`] when assigned to an [`crate::Archipelago`]. Must be
   /// the same length as [`Self::polygons`].
   pub polygon_type_indices: Vec<usize>,
+  /// The flags of each polygon. Unlike type indices (which determine the cost
+  /// of a polygon), flags determine which agents may travel through a polygon
+  /// at all (e.g., "swimmable" or "restricted to faction A"). See
+  /// [`crate::PolygonFlagFilter`]. If [`None`], every polygon has no flags.
+  /// Otherwise, must be the same length as [`Self::polygons`].
+  pub polygon_flags: Option<Vec<u32>>,
   /// A height mesh to accurately represent the height of the surface. See
   /// [`HeightNavigationMesh`] for more details. If [`None`], uses the regular
   /// polygons as the height of the surface.
@@ -98,6 +106,7 @@ where
       vertices: self.vertices.clone(),
       polygons: self.polygons.clone(),
       polygon_type_indices: self.polygon_type_indices.clone(),
+      polygon_flags: self.polygon_flags.clone(),
       height_mesh: self.height_mesh.clone(),
     }
   }
@@ -136,6 +145,11 @@ pub enum ValidationError {
     "The polygon type indices do not have the same length as the polygons. There are {0} polygons, but {1} type indices."
   )]
   TypeIndicesHaveWrongLength(usize, usize),
+  /// Stores the number of polygons and the number of polygon flags.
+  #[error(
+    "The polygon flags do not have the same length as the polygons. There are {0} polygons, but {1} polygon flags."
+  )]
+  FlagsHaveWrongLength(usize, usize),
   /// Stores the index of the polygon.
   #[error(
     "The polygon at index {0} is concave or has edges in clockwise order."
@@ -198,6 +212,14 @@ impl<CS: CoordinateSystem> NavigationMesh<CS> {
         self.polygon_type_indices.len(),
       ));
     }
+    if let Some(polygon_flags) = self.polygon_flags.as_ref()
+      && self.polygons.len() != polygon_flags.len()
+    {
+      return Err(ValidationError::FlagsHaveWrongLength(
+        self.polygons.len(),
+        polygon_flags.len(),
+      ));
+    }
 
     let height_mesh = match self.height_mesh {
       None => None,
@@ -350,6 +372,8 @@ impl<CS: CoordinateSystem> NavigationMesh<CS> {
           center: polygon_vertices.iter().map(|i| vertices[*i]).sum::<Vec3>()
             / polygon_vertices.len() as f32,
           connectivity: vec![None; polygon_vertices.len()],
+          // The clearance is computed once the connectivity is known.
+          clearance: f32::INFINITY,
           vertices: polygon_vertices,
           region: {
             let region = region_sets.root_of(polygon_index);
@@ -363,6 +387,10 @@ impl<CS: CoordinateSystem> NavigationMesh<CS> {
               .or_insert_with(|| new_normalized_region)
           },
           type_index: self.polygon_type_indices[polygon_index],
+          flags: self
+            .polygon_flags
+            .as_ref()
+            .map_or(0, |polygon_flags| polygon_flags[polygon_index]),
         }
       })
       .inspect(|polygon| {
@@ -387,20 +415,102 @@ impl<CS: CoordinateSystem> NavigationMesh<CS> {
           polygons[polygon_1].connectivity[edge_1] = Some(Connectivity {
             polygon_index: polygon_2,
             reverse_edge: edge_2,
+            // The width is computed once all the boundary edges are known.
+            width: f32::INFINITY,
           });
           polygons[polygon_2].connectivity[edge_2] = Some(Connectivity {
             polygon_index: polygon_1,
             reverse_edge: edge_1,
+            width: f32::INFINITY,
           });
         }
       }
     }
 
+    // Boundary edges are the walls that limit how wide an agent can be. Walls
+    // that only touch a polygon at a vertex still pinch it, so each polygon
+    // (and each portal) considers every wall incident to one of its vertices,
+    // not just its own boundary edges.
+    let mut vertex_walls = vec![Vec::new(); vertices.len()];
+    for polygon in polygons.iter() {
+      for edge in 0..polygon.vertices.len() {
+        if polygon.connectivity[edge].is_some() {
+          continue;
+        }
+        let wall = polygon.get_edge_indices(edge);
+        vertex_walls[wall.0].push(wall);
+        vertex_walls[wall.1].push(wall);
+      }
+    }
+    let polygon_walls = polygons
+      .iter()
+      .map(|polygon| {
+        let mut walls = polygon
+          .vertices
+          .iter()
+          .flat_map(|&vertex| vertex_walls[vertex].iter().copied())
+          .collect::<Vec<_>>();
+        walls.sort();
+        walls.dedup();
+        walls
+          .into_iter()
+          .map(|(left, right)| (vertices[left].xy(), vertices[right].xy()))
+          .collect::<Vec<_>>()
+      })
+      .collect::<Vec<_>>();
+    for (polygon_index, polygon) in polygons.iter_mut().enumerate() {
+      polygon.clearance = convex_polygon_clearance(
+        &polygon
+          .vertices
+          .iter()
+          .map(|&vertex| vertices[vertex].xy())
+          .collect::<Vec<_>>(),
+        &polygon_walls[polygon_index],
+      );
+      for edge in 0..polygon.vertices.len() {
+        let (left, right) = polygon.get_edge_indices(edge);
+        let Some(connectivity) = polygon.connectivity[edge].as_mut() else {
+          continue;
+        };
+        let walls = polygon_walls[polygon_index]
+          .iter()
+          .chain(polygon_walls[connectivity.polygon_index].iter())
+          .copied()
+          .collect::<Vec<_>>();
+        // Sample the portal in the same order from both sides, so both sides
+        // get exactly the same width.
+        let portal = if left < right { (left, right) } else { (right, left) };
+        let width = segment_clearance(
+          (vertices[portal.0].xy(), vertices[portal.1].xy()),
+          &walls,
+        );
+        // If both ends of the portal are on walls, the agent has to squeeze
+        // between them, so it can be no wider than the portal itself. This
+        // also holds when sampling misses the narrowest point.
+        connectivity.width = if !vertex_walls[portal.0].is_empty()
+          && !vertex_walls[portal.1].is_empty()
+        {
+          width.min(vertices[portal.0].xy().distance(vertices[portal.1].xy()))
+        } else {
+          width
+        };
+      }
+    }
+
+    let mut vertex_on_boundary = vec![false; vertices.len()];
+    for boundary_edge in boundary_edges.iter() {
+      let (left, right) = polygons[boundary_edge.polygon_index]
+        .get_edge_indices(boundary_edge.edge_index);
+      vertex_on_boundary[left] = true;
+      vertex_on_boundary[right] = true;
+    }
+
     Ok(ValidNavigationMesh {
       mesh_bounds,
       polygons,
       vertices,
       boundary_edges,
+      vertex_on_boundary,
       height_mesh,
       marker: Default::default(),
     })
@@ -493,6 +603,9 @@ pub struct ValidNavigationMesh<CS: CoordinateSystem> {
   /// (e.0, e.1) from e.0 to e.1 will move counter-clockwise along the
   /// boundary. The order of edges is undefined.
   pub(crate) boundary_edges: Vec<MeshEdgeRef>,
+  /// Whether each vertex in [`Self::vertices`] is the endpoint of a boundary
+  /// edge, i.e., whether it is a corner that agents can collide with.
+  pub(crate) vertex_on_boundary: Vec<bool>,
   /// The height mesh used to "refine" point positions. See
   /// [`HeightNavigationMesh`] for more details.
   pub(crate) height_mesh: Option<ValidHeightNavigationMesh>,
@@ -528,6 +641,7 @@ impl<CS: CoordinateSystem> Clone for ValidNavigationMesh<CS> {
       vertices: self.vertices.clone(),
       polygons: self.polygons.clone(),
       boundary_edges: self.boundary_edges.clone(),
+      vertex_on_boundary: self.vertex_on_boundary.clone(),
       height_mesh: self.height_mesh.clone(),
       marker: self.marker,
     }
@@ -542,6 +656,7 @@ impl<CS: CoordinateSystem> std::fmt::Debug for ValidNavigationMesh<CS> {
       .field("vertices", &self.vertices)
       .field("polygons", &self.polygons)
       .field("boundary_edges", &self.boundary_edges)
+      .field("vertex_on_boundary", &self.vertex_on_boundary)
       .field("height_mesh", &self.height_mesh)
       .field("marker", &self.marker)
       .finish()
@@ -560,6 +675,11 @@ pub(crate) struct ValidPolygon {
   /// Entries that are `None` correspond to the boundary of the navigation
   /// mesh, while `Some` entries are connected to another node.
   pub(crate) connectivity: Vec<Option<Connectivity>>,
+  /// The diameter of the widest agent that can stand somewhere in this
+  /// polygon without overlapping a boundary edge. This considers every
+  /// boundary edge touching one of the polygon's vertices, so it is
+  /// [`f32::INFINITY`] if there are none.
+  pub(crate) clearance: f32,
   /// The "region" that this polygon belongs to. Each region is disjoint from
   /// every other. A "direct" path only exists if the region matches between
   /// two nodes. An "indirect" path exists if regions are joined together
@@ -568,6 +688,8 @@ pub(crate) struct ValidPolygon {
   /// The "type" of this node. This is translated into a [`crate::NodeType`]
   /// once it is part of an island.
   pub(crate) type_index: usize,
+  /// The flags of this node. See [`NavigationMesh::polygon_flags`].
+  pub(crate) flags: u32,
   /// The bounding box of `vertices`.
   pub(crate) bounds: BoundingBox,
   /// The center of the polygon.
@@ -580,6 +702,11 @@ pub(crate) struct Connectivity {
   pub(crate) polygon_index: usize,
   /// The index of the edge that would take us back to the original node.
   pub(crate) reverse_edge: usize,
+  /// The width of this edge, i.e., the diameter of the widest agent that can
+  /// cross it. This considers every boundary edge touching a vertex of the two
+  /// polygons it connects, and is never more than the length of the edge if
+  /// both its ends are on boundary edges.
+  pub(crate) width: f32,
 }
 
 /// A reference to an edge on a navigation mesh.
@@ -813,6 +940,95 @@ impl<CS: CoordinateSystem> ValidNavigationMesh<CS> {
     )
   }
 
+  /// Returns the edges of the provided node as (start, end) pairs in
+  /// counter-clockwise order.
+  fn node_edges(
+    &self,
+    node: usize,
+  ) -> impl Iterator<Item = (Vec3, Vec3)> + Clone + '_ {
+    let polygon = &self.polygons[node];
+    (0..polygon.vertices.len()).map(|edge_index| {
+      let (left, right) = polygon.get_edge_indices(edge_index);
+      (self.vertices[right], self.vertices[left])
+    })
+  }
+
+  /// Determines whether `point` projects (vertically) onto the provided node.
+  pub(crate) fn node_contains_point(&self, point: Vec3, node: usize) -> bool {
+    // Edges are in counter-clockwise order, so the node is to the left of
+    // each edge.
+    const EPSILON: f32 = -1e-5;
+    self.node_edges(node).all(|(start, end)| {
+      (end.xy() - start.xy()).perp_dot(point.xy() - start.xy()) >= EPSILON
+    })
+  }
+
+  /// Picks a uniformly random point on the provided node. `rng` must return
+  /// uniformly distributed values in [0, 1). The height of the point is taken
+  /// from the height mesh if present.
+  pub(crate) fn random_point_on_node(
+    &self,
+    node: usize,
+    rng: &mut impl FnMut() -> f32,
+  ) -> Vec3 {
+    let polygon = &self.polygons[node];
+    // Polygons are convex, so they can be split into a fan of triangles.
+    let mut triangles = (2..polygon.vertices.len()).map(|i| {
+      (
+        self.vertices[polygon.vertices[0]],
+        self.vertices[polygon.vertices[i - 1]],
+        self.vertices[polygon.vertices[i]],
+      )
+    });
+    let triangle_area = |(a, b, c): (Vec3, Vec3, Vec3)| {
+      (b.xy() - a.xy()).perp_dot(c.xy() - a.xy())
+    };
+
+    // Pick a triangle weighted by its area. Default to the last triangle in
+    // case floating point error makes us overshoot.
+    let total_area = triangles.clone().map(triangle_area).sum::<f32>();
+    let mut remaining_area = rng() * total_area;
+    let (a, b, c) = triangles
+      .clone()
+      .find(|&triangle| {
+        remaining_area -= triangle_area(triangle);
+        remaining_area < 0.0
+      })
+      .unwrap_or_else(|| {
+        triangles.next_back().expect("polygons have 3 vertices")
+      });
+
+    // Pick a point in the parallelogram formed by the triangle, and then fold
+    // the half outside the triangle back inside.
+    let (mut u, mut v) = (rng(), rng());
+    if u + v > 1.0 {
+      (u, v) = (1.0 - u, 1.0 - v);
+    }
+    let point = a + (b - a) * u + (c - a) * v;
+    self.sample_point_on_node(point, node)
+  }
+
+  /// Finds the point on the provided node that is horizontally closest to
+  /// `point`. Unlike [`Self::sample_point_on_node`], `point` does not need to
+  /// project to the node.
+  pub(crate) fn closest_point_on_node(&self, point: Vec3, node: usize) -> Vec3 {
+    if self.node_contains_point(point, node) {
+      return self.sample_point_on_node(point, node);
+    }
+
+    self
+      .node_edges(node)
+      .map(|(start, end)| {
+        let delta = end.xy() - start.xy();
+        let fraction = (delta.dot(point.xy() - start.xy())
+          / delta.length_squared())
+        .clamp(0.0, 1.0);
+        start.lerp(end, fraction)
+      })
+      .min_by_key(|edge_point| FloatOrd(edge_point.xy().distance(point.xy())))
+      .expect("polygons have at least 3 edges")
+  }
+
   /// Samples the `edge` on this nav mesh clipping to a max vertical distance.
   ///
   /// `node_bbh` must correspond to this navigation mesh's polygons.
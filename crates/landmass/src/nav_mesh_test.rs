@@ -4,9 +4,10 @@ use googletest::{expect_that, matchers::*};
 use crate::{
   CoordinateSystem, PointSampleDistance3d, XY,
   coords::{CorePointSampleDistance, XYZ},
+  geometry::convex_polygon_clearance,
   nav_mesh::{
-    Connectivity, HeightNavigationMesh, HeightPolygon, MeshEdgeRef,
-    SampledEdge, ValidPolygon, ValidateHeightMeshError, nav_mesh_node_bbh,
+    HeightNavigationMesh, HeightPolygon, MeshEdgeRef, SampledEdge,
+    ValidPolygon, ValidateHeightMeshError, nav_mesh_node_bbh,
   },
   util::BoundingBox,
 };
@@ -26,6 +27,7 @@ fn validation_computes_bounds() {
     ],
     polygons: vec![vec![0, 1, 2], vec![3, 4, 5]],
     polygon_type_indices: vec![0, 0],
+    polygon_flags: None,
     height_mesh: None,
   };
 
@@ -47,6 +49,7 @@ fn correctly_computes_bounds_for_small_number_of_points() {
     ],
     polygons: vec![vec![0, 1, 2]],
     polygon_type_indices: vec![0],
+    polygon_flags: None,
     height_mesh: None,
   }
   .validate()
@@ -71,15 +74,33 @@ fn polygons_derived_and_vertices_copied() {
     ],
     polygons: vec![vec![0, 1, 2], vec![3, 4, 5]],
     polygon_type_indices: vec![1337, 123],
+    polygon_flags: Some(vec![1, 6]),
     height_mesh: None,
   };
 
+  // None of the polygons are connected, so all their edges are walls.
+  let clearance = |vertices: [Vec2; 3]| {
+    convex_polygon_clearance(
+      &vertices,
+      &[
+        (vertices[1], vertices[0]),
+        (vertices[2], vertices[1]),
+        (vertices[0], vertices[2]),
+      ],
+    )
+  };
   let expected_polygons = vec![
     ValidPolygon {
       vertices: source_mesh.polygons[0].clone(),
       connectivity: vec![None, None, None],
+      clearance: clearance([
+        Vec2::new(0.0, 0.0),
+        Vec2::new(1.0, 0.0),
+        Vec2::new(2.0, 1.0),
+      ]),
       region: 0,
       type_index: 1337,
+      flags: 1,
       bounds: BoundingBox::new_box(
         Vec3::new(0.0, 0.0, 0.0),
         Vec3::new(2.0, 1.0, 1.0),
@@ -89,8 +110,14 @@ fn polygons_derived_and_vertices_copied() {
     ValidPolygon {
       vertices: source_mesh.polygons[1].clone(),
       connectivity: vec![None, None, None],
+      clearance: clearance([
+        Vec2::new(0.5, 3.0),
+        Vec2::new(0.75, 4.0),
+        Vec2::new(0.25, 4.0),
+      ]),
       region: 1,
       type_index: 123,
+      flags: 6,
       bounds: BoundingBox::new_box(
         Vec3::new(0.25, 3.0, -0.25),
         Vec3::new(0.75, 4.0, 0.5),
@@ -115,6 +142,7 @@ fn error_on_wrong_type_indices_length() {
     ],
     polygons: vec![vec![0, 1, 2]],
     polygon_type_indices: vec![0, 0],
+    polygon_flags: None,
     height_mesh: None,
   };
 
@@ -134,6 +162,26 @@ fn error_on_wrong_type_indices_length() {
   };
 }
 
+#[test]
+fn error_on_wrong_flags_length() {
+  let source_mesh = NavigationMesh::<XYZ> {
+    vertices: vec![
+      Vec3::new(0.0, 0.0, 0.0),
+      Vec3::new(1.0, 1.0, 0.0),
+      Vec3::new(1.0, 0.0, 0.0),
+    ],
+    polygons: vec![vec![0, 1, 2]],
+    polygon_type_indices: vec![0],
+    polygon_flags: Some(vec![0, 0]),
+    height_mesh: None,
+  };
+
+  assert_eq!(
+    source_mesh.validate().map(|_| ()),
+    Err(ValidationError::FlagsHaveWrongLength(1, 2))
+  );
+}
+
 struct FlippedXYZ;
 
 impl CoordinateSystem for FlippedXYZ {
@@ -161,6 +209,7 @@ fn error_on_concave_polygon() {
     ],
     polygons: vec![vec![0, 1, 2]],
     polygon_type_indices: vec![0],
+    polygon_flags: None,
     height_mesh: None,
   };
 
@@ -189,6 +238,7 @@ fn no_error_on_concave_polygon_with_flipped_coordinate_system() {
     // system does this for us!
     polygons: vec![vec![0, 1, 2]],
     polygon_type_indices: vec![0],
+    polygon_flags: None,
     height_mesh: None,
   };
 
@@ -201,6 +251,7 @@ fn error_on_small_polygon() {
     vertices: vec![Vec3::new(0.0, 0.0, 0.0), Vec3::new(1.0, 1.0, 0.0)],
     polygons: vec![vec![0, 1]],
     polygon_type_indices: vec![0],
+    polygon_flags: None,
     height_mesh: None,
   };
 
@@ -229,6 +280,7 @@ fn error_on_bad_polygon_index() {
     ],
     polygons: vec![vec![0, 1, 3]],
     polygon_type_indices: vec![0],
+    polygon_flags: None,
     height_mesh: None,
   };
 
@@ -257,6 +309,7 @@ fn error_on_degenerate_edge() {
     ],
     polygons: vec![vec![0, 1, 1, 2]],
     polygon_type_indices: vec![0],
+    polygon_flags: None,
     height_mesh: None,
   };
 
@@ -289,6 +342,7 @@ fn error_on_doubly_connected_edge() {
     ],
     polygons: vec![vec![0, 1, 2], vec![1, 3, 4, 2], vec![1, 5, 6, 2]],
     polygon_type_indices: vec![0, 0, 0],
+    polygon_flags: None,
     height_mesh: None,
   };
 
@@ -328,6 +382,7 @@ fn derives_connectivity_and_boundary_edges() {
       vec![2, 4, 8, 7],
     ],
     polygon_type_indices: vec![0, 0, 0, 0],
+    polygon_flags: None,
     height_mesh: None,
   };
 
@@ -339,32 +394,28 @@ fn derives_connectivity_and_boundary_edges() {
     boundary_edge.polygon_index * 100 + boundary_edge.edge_index
   });
 
+  // The widths are checked in `computes_portal_widths_and_clearance`.
   let expected_connectivity: [&[_]; 4] = [
-    &[None, Some(Connectivity { polygon_index: 1, reverse_edge: 3 }), None],
-    &[
-      None,
-      Some(Connectivity { polygon_index: 2, reverse_edge: 3 }),
-      Some(Connectivity { polygon_index: 3, reverse_edge: 0 }),
-      Some(Connectivity { polygon_index: 0, reverse_edge: 1 }),
-    ],
-    &[
-      None,
-      None,
-      None,
-      Some(Connectivity { polygon_index: 1, reverse_edge: 1 }),
-    ],
-    &[
-      Some(Connectivity { polygon_index: 1, reverse_edge: 2 }),
-      None,
-      None,
-      None,
-    ],
+    &[None, Some((1, 3)), None],
+    &[None, Some((2, 3)), Some((3, 0)), Some((0, 1))],
+    &[None, None, None, Some((1, 1))],
+    &[Some((1, 2)), None, None, None],
   ];
   assert_eq!(
     valid_mesh
       .polygons
       .iter()
-      .map(|polygon| polygon.connectivity.clone())
+      .map(|polygon| {
+        polygon
+          .connectivity
+          .iter()
+          .map(|connectivity| {
+            connectivity.as_ref().map(|connectivity| {
+              (connectivity.polygon_index, connectivity.reverse_edge)
+            })
+          })
+          .collect::<Vec<_>>()
+      })
       .collect::<Vec<_>>(),
     expected_connectivity
   );
@@ -384,6 +435,81 @@ fn derives_connectivity_and_boundary_edges() {
   );
 }
 
+#[test]
+fn computes_portal_widths_and_clearance() {
+  let source_mesh = NavigationMesh::<XY> {
+    vertices: vec![
+      // A 2x2 room.
+      Vec2::new(0.0, 0.0),
+      Vec2::new(2.0, 0.0),
+      Vec2::new(2.0, 2.0),
+      Vec2::new(0.0, 2.0),
+      // A 1 unit wide corridor.
+      Vec2::new(3.0, 0.0),
+      Vec2::new(6.0, 0.0),
+      Vec2::new(6.0, 1.0),
+      Vec2::new(3.0, 1.0),
+    ],
+    polygons: vec![vec![0, 1, 2], vec![0, 2, 3], vec![4, 5, 6], vec![4, 6, 7]],
+    polygon_type_indices: vec![0, 0, 0, 0],
+    polygon_flags: None,
+    height_mesh: None,
+  };
+
+  let valid_mesh = source_mesh.validate().expect("Validation succeeds.");
+
+  let width = |polygon_index: usize, edge_index: usize| {
+    valid_mesh.polygons[polygon_index].connectivity[edge_index]
+      .as_ref()
+      .expect("The edge is connected.")
+      .width
+  };
+  // The diagonals are limited by the walls on either side, not by their
+  // length.
+  assert_eq!(width(0, 2), 2.0);
+  assert_eq!(width(1, 0), 2.0);
+  assert_eq!(width(2, 2), 1.0);
+  assert_eq!(width(3, 0), 1.0);
+
+  // The widest point of the room's triangles is the middle of the diagonal.
+  assert_eq!(valid_mesh.polygons[0].clearance, 2.0);
+  assert_eq!(valid_mesh.polygons[1].clearance, 2.0);
+}
+
+#[test]
+fn finds_vertices_on_boundary() {
+  // A 2x2 grid of squares, so only the center vertex is not on the boundary.
+  let mesh = NavigationMesh::<XY> {
+    vertices: vec![
+      Vec2::new(0.0, 0.0),
+      Vec2::new(1.0, 0.0),
+      Vec2::new(2.0, 0.0),
+      Vec2::new(0.0, 1.0),
+      Vec2::new(1.0, 1.0),
+      Vec2::new(2.0, 1.0),
+      Vec2::new(0.0, 2.0),
+      Vec2::new(1.0, 2.0),
+      Vec2::new(2.0, 2.0),
+    ],
+    polygons: vec![
+      vec![0, 1, 4, 3],
+      vec![1, 2, 5, 4],
+      vec![3, 4, 7, 6],
+      vec![4, 5, 8, 7],
+    ],
+    polygon_type_indices: vec![0; 4],
+    polygon_flags: None,
+    height_mesh: None,
+  }
+  .validate()
+  .expect("Mesh is valid.");
+
+  assert_eq!(
+    mesh.vertex_on_boundary,
+    [true, true, true, true, false, true, true, true, true]
+  );
+}
+
 #[test]
 fn finds_regions() {
   let mesh = NavigationMesh::<XYZ> {
@@ -412,6 +538,7 @@ fn finds_regions() {
       vec![11, 10, 12, 13],
     ],
     polygon_type_indices: vec![0, 0, 0, 0, 0],
+    polygon_flags: None,
     height_mesh: None,
   }
   .validate()
@@ -450,6 +577,7 @@ fn sample_point_returns_none_for_far_point() {
       vec![10, 4, 14, 13],
     ],
     polygon_type_indices: vec![0, 0, 0, 0],
+    polygon_flags: None,
     height_mesh: None,
   }
   .validate()
@@ -535,6 +663,7 @@ fn sample_point_in_nodes() {
       vec![10, 4, 14, 13],
     ],
     polygon_type_indices: vec![0, 0, 0, 0],
+    polygon_flags: None,
     height_mesh: None,
   }
   .validate()
@@ -611,6 +740,77 @@ fn sample_point_in_nodes() {
   );
 }
 
+#[test]
+fn closest_point_on_node_inside_and_outside() {
+  let mesh = NavigationMesh::<XYZ> {
+    vertices: vec![
+      Vec3::new(0.0, 0.0, 1.0),
+      Vec3::new(2.0, 0.0, 1.0),
+      Vec3::new(2.0, 2.0, 1.0),
+      Vec3::new(0.0, 2.0, 1.0),
+    ],
+    polygons: vec![vec![0, 1, 2, 3]],
+    polygon_type_indices: vec![0],
+    polygon_flags: None,
+    height_mesh: None,
+  }
+  .validate()
+  .expect("Mesh is valid.");
+
+  // Points inside the node are projected onto it.
+  assert_eq!(
+    mesh.closest_point_on_node(Vec3::new(1.0, 1.0, 3.0), 0),
+    Vec3::new(1.0, 1.0, 1.0)
+  );
+  // Points outside the node are moved to the closest boundary point.
+  assert_eq!(
+    mesh.closest_point_on_node(Vec3::new(5.0, 1.0, 0.0), 0),
+    Vec3::new(2.0, 1.0, 1.0)
+  );
+  assert_eq!(
+    mesh.closest_point_on_node(Vec3::new(-1.0, 5.0, 0.0), 0),
+    Vec3::new(0.0, 2.0, 1.0)
+  );
+}
+
+#[test]
+fn random_point_on_node_uses_height_mesh() {
+  let mesh = NavigationMesh::<XYZ> {
+    vertices: vec![
+      Vec3::new(0.0, 0.0, 0.0),
+      Vec3::new(2.0, 0.0, 0.0),
+      Vec3::new(2.0, 2.0, 0.0),
+      Vec3::new(0.0, 2.0, 0.0),
+    ],
+    polygons: vec![vec![0, 1, 2, 3]],
+    polygon_type_indices: vec![0],
+    // The height mesh is a ramp going up along the y axis.
+    polygon_flags: None,
+    height_mesh: Some(create_height_mesh(
+      vec![
+        Vec3::new(0.0, 0.0, 0.0),
+        Vec3::new(2.0, 0.0, 0.0),
+        Vec3::new(2.0, 2.0, 2.0),
+        Vec3::new(0.0, 2.0, 2.0),
+      ],
+      vec![vec![vec![0, 1, 2, 3]]],
+    )),
+  }
+  .validate()
+  .expect("Mesh is valid.");
+
+  let mut values = [0.1, 0.8, 0.3, 0.6, 0.9, 0.2, 0.5, 0.7, 0.4].into_iter();
+  let mut rng = || values.next().unwrap();
+  for _ in 0..3 {
+    let point = mesh.random_point_on_node(0, &mut rng);
+    assert!(
+      (0.0..=2.0).contains(&point.x) && (0.0..=2.0).contains(&point.y),
+      "{point} is not in the node"
+    );
+    assert!((point.z - point.y).abs() < 1e-5, "{point} is not on the ramp");
+  }
+}
+
 #[test]
 fn sample_point_near_node() {
   let mesh = NavigationMesh::<XYZ> {
@@ -638,6 +838,7 @@ fn sample_point_near_node() {
       vec![10, 4, 14, 13],
     ],
     polygon_type_indices: vec![0, 0, 0, 0],
+    polygon_flags: None,
     height_mesh: None,
   }
   .validate()
@@ -707,7 +908,9 @@ fn valid_polygon_gets_edge_indices() {
     vertices: vec![1, 3, 9, 2, 7],
     region: 0,
     type_index: 0,
+    flags: 0,
     connectivity: vec![],
+    clearance: f32::INFINITY,
     center: Vec3::ZERO,
   };
 
@@ -731,6 +934,7 @@ fn sample_ignores_closer_horizontal() {
     ],
     polygons: vec![vec![0, 1, 2, 3], vec![4, 5, 6, 7]],
     polygon_type_indices: vec![0; 2],
+    polygon_flags: None,
     height_mesh: None,
   }
   .validate()
@@ -768,6 +972,7 @@ fn sample_favoring_vertical() {
     ],
     polygons: vec![vec![0, 1, 2, 3], vec![4, 5, 6, 7]],
     polygon_type_indices: vec![0; 2],
+    polygon_flags: None,
     height_mesh: None,
   }
   .validate()
@@ -800,6 +1005,7 @@ fn sample_filters_vertical_points_differently() {
     ],
     polygons: vec![vec![0, 1, 2, 3]],
     polygon_type_indices: vec![0; 1],
+    polygon_flags: None,
     height_mesh: None,
   }
   .validate()
@@ -891,6 +1097,7 @@ fn error_on_wrong_number_of_height_polygons() {
     ],
     polygons: vec![vec![0, 1, 2]],
     polygon_type_indices: vec![0],
+    polygon_flags: None,
     height_mesh: Some(create_height_mesh(
       vec![
         Vec3::new(0.0, 0.0, 0.0),
@@ -931,6 +1138,7 @@ fn error_on_invalid_height_polygon_index() {
     ],
     polygons: vec![vec![0, 1, 2]],
     polygon_type_indices: vec![0],
+    polygon_flags: None,
     height_mesh: Some(HeightNavigationMesh {
       vertices: vec![
         Vec3::new(0.0, 0.0, 0.0),
@@ -970,6 +1178,7 @@ fn error_on_invalid_index_in_triangle_for_out_of_bounds() {
     ],
     polygons: vec![vec![0, 1, 2]],
     polygon_type_indices: vec![0],
+    polygon_flags: None,
     height_mesh: Some(HeightNavigationMesh {
       vertices: vec![
         Vec3::new(0.0, 0.0, 0.0),
@@ -1012,6 +1221,7 @@ fn error_on_invalid_index_in_triangle_for_wrong_vertex_count() {
     ],
     polygons: vec![vec![0, 1, 2]],
     polygon_type_indices: vec![0],
+    polygon_flags: None,
     height_mesh: Some(HeightNavigationMesh {
       vertices: vec![
         Vec3::new(0.0, 0.0, 0.0),
@@ -1055,6 +1265,7 @@ fn error_on_concave_height_polygon() {
     ],
     polygons: vec![vec![0, 1, 2]],
     polygon_type_indices: vec![0],
+    polygon_flags: None,
     height_mesh: Some(create_height_mesh(
       vec![
         Vec3::new(0.0, 0.0, 0.0),
@@ -1090,6 +1301,7 @@ fn no_error_on_concave_height_polygon_with_flipped_coordinate_system() {
     ],
     polygons: vec![vec![2, 1, 0]],
     polygon_type_indices: vec![0],
+    polygon_flags: None,
     height_mesh: Some(create_height_mesh(
       vec![
         Vec3::new(0.0, 0.0, 0.0),
@@ -1118,6 +1330,7 @@ fn sample_point_uses_height_mesh_if_available() {
     ],
     polygons: vec![vec![0, 1, 2, 3]],
     polygon_type_indices: vec![0; 1],
+    polygon_flags: None,
     height_mesh: Some(create_height_mesh(
       // The height mesh tells us that the actual surface deviates heavily from
       // the regular mesh. Namely, it looks like:
@@ -1172,6 +1385,7 @@ fn samples_edge_on_square() {
     ],
     polygons: vec![vec![0, 1, 2, 3]],
     polygon_type_indices: vec![0],
+    polygon_flags: None,
     height_mesh: None,
   }
   .validate()
@@ -1234,6 +1448,7 @@ fn samples_edge_for_multiple_nodes() {
       vec![4, 5, 8, 7],
     ],
     polygon_type_indices: vec![0; 4],
+    polygon_flags: None,
     height_mesh: None,
   }
   .validate()
@@ -1270,6 +1485,7 @@ fn samples_edge_for_multiple_floors() {
     ],
     polygons: vec![vec![0, 1, 2, 3], vec![4, 5, 6, 7]],
     polygon_type_indices: vec![0; 2],
+    polygon_flags: None,
     height_mesh: None,
   }
   .validate()
@@ -1311,6 +1527,7 @@ fn samples_edge_along_stairs() {
     ],
     polygons: vec![vec![0, 1, 2, 3], vec![3, 2, 5, 4], vec![4, 5, 7, 6]],
     polygon_type_indices: vec![0; 3],
+    polygon_flags: None,
     height_mesh: None,
   }
   .validate()
@@ -0,0 +1,187 @@
+use std::{collections::HashSet, f32::consts::PI};
+
+use geo::{ConvexHull, MultiPoint, Point};
+use glam::{Vec2, Vec3Swizzles};
+use slotmap::new_key_type;
+use thiserror::Error;
+
+use crate::{
+  CoordinateSystem, geometry::to_ccw_convex_polygon, nav_data::NodeRef,
+};
+
+new_key_type! {
+  /// The ID of an [`Obstacle`].
+  pub struct ObstacleId;
+}
+
+/// An obstacle that is carved out of the navigation meshes at runtime (e.g., a
+/// crate or a barricade placed by gameplay). Agents will not path through the
+/// carved parts of nodes, and will avoid the edges of the carved area. Paths
+/// walk around obstacles, even ones entirely inside a single node.
+///
+/// Only nodes whose bounds overlap the height of the obstacle are carved.
+pub enum Obstacle<CS: CoordinateSystem> {
+  /// An axis-aligned box between `min` and `max`.
+  Aabb { min: CS::Coordinate, max: CS::Coordinate },
+  /// A vertical cylinder whose base is centered on `base`, and which extends
+  /// `height` upwards.
+  Cylinder { base: CS::Coordinate, radius: f32, height: f32 },
+  /// The convex hull of `points`. The hull is horizontal, and extends from the
+  /// lowest point to the highest point. This can be used for rotated boxes
+  /// by passing in their corners.
+  ConvexHull(Vec<CS::Coordinate>),
+}
+
+/// The number of sides of the polygon used to carve a
+/// [`Obstacle::Cylinder`].
+const CYLINDER_SIDES: usize = 16;
+
+/// An error from adding or setting an [`Obstacle`].
+#[derive(Clone, Copy, Debug, PartialEq, Error)]
+pub enum ObstacleError {
+  #[error("The cylinder has a radius of {0}, which is negative.")]
+  NegativeRadius(f32),
+  #[error("The cylinder has a height of {0}, which is negative.")]
+  NegativeHeight(f32),
+  #[error("The obstacle has no horizontal area.")]
+  NoArea,
+}
+
+impl<CS: CoordinateSystem> Obstacle<CS> {
+  /// Validates the obstacle and computes its footprint (a convex,
+  /// counter-clockwise polygon) and height range in world space.
+  pub(crate) fn to_footprint(
+    &self,
+  ) -> Result<(Vec<Vec2>, (f32, f32)), ObstacleError> {
+    let (vertices, height_range) = match self {
+      Self::Aabb { min, max } => {
+        // Converting the coordinates may flip axes, so recompute the corners.
+        let (corner_1, corner_2) = (CS::to_landmass(min), CS::to_landmass(max));
+        let (min, max) = (corner_1.min(corner_2), corner_1.max(corner_2));
+        (
+          vec![
+            min.xy(),
+            Vec2::new(max.x, min.y),
+            max.xy(),
+            Vec2::new(min.x, max.y),
+          ],
+          (min.z, max.z),
+        )
+      }
+      Self::Cylinder { base, radius, height } => {
+        if *radius < 0.0 {
+          return Err(ObstacleError::NegativeRadius(*radius));
+        }
+        if *height < 0.0 {
+          return Err(ObstacleError::NegativeHeight(*height));
+        }
+        let base = CS::to_landmass(base);
+        // Push the vertices out so the polygon contains the whole circle.
+        let vertex_radius = radius / (PI / CYLINDER_SIDES as f32).cos();
+        (
+          (0..CYLINDER_SIDES)
+            .map(|i| {
+              let angle = i as f32 / CYLINDER_SIDES as f32 * 2.0 * PI;
+              base.xy() + Vec2::from_angle(angle) * vertex_radius
+            })
+            .collect(),
+          (base.z, base.z + height),
+        )
+      }
+      Self::ConvexHull(points) => {
+        let points =
+          points.iter().map(|point| CS::to_landmass(point)).collect::<Vec<_>>();
+        let height_range = points
+          .iter()
+          .fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), point| {
+            (min.min(point.z), max.max(point.z))
+          });
+        let hull = MultiPoint::from(
+          points
+            .iter()
+            .map(|point| Point::new(point.x, point.y))
+            .collect::<Vec<_>>(),
+        )
+        .convex_hull();
+        let mut vertices = hull
+          .exterior()
+          .coords()
+          .map(|coord| Vec2::new(coord.x, coord.y))
+          .collect::<Vec<_>>();
+        // The exterior is closed, so the last vertex repeats the first.
+        vertices.pop();
+        (vertices, height_range)
+      }
+    };
+    let vertices =
+      to_ccw_convex_polygon(vertices).ok_or(ObstacleError::NoArea)?;
+    Ok((vertices, height_range))
+  }
+}
+
+/// The state of an obstacle.
+pub(crate) struct ObstacleState<CS: CoordinateSystem> {
+  /// The obstacle given to us by the user.
+  pub(crate) obstacle: Obstacle<CS>,
+  /// The footprint of the obstacle in world space. This is a convex,
+  /// counter-clockwise polygon.
+  pub(crate) footprint: Vec<Vec2>,
+  /// The minimum and maximum height of the obstacle in world space.
+  pub(crate) height_range: (f32, f32),
+  /// The nodes that the obstacle overlapped as of the last update.
+  pub(crate) nodes: HashSet<NodeRef>,
+}
+
+impl<CS: CoordinateSystem<Coordinate: std::fmt::Debug>> std::fmt::Debug
+  for Obstacle<CS>
+{
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Self::Aabb { min, max } => {
+        f.debug_struct("Aabb").field("min", min).field("max", max).finish()
+      }
+      Self::Cylinder { base, radius, height } => f
+        .debug_struct("Cylinder")
+        .field("base", base)
+        .field("radius", radius)
+        .field("height", height)
+        .finish(),
+      Self::ConvexHull(points) => {
+        f.debug_tuple("ConvexHull").field(points).finish()
+      }
+    }
+  }
+}
+
+impl<CS: CoordinateSystem> Clone for Obstacle<CS> {
+  fn clone(&self) -> Self {
+    match self {
+      Self::Aabb { min, max } => {
+        Self::Aabb { min: min.clone(), max: max.clone() }
+      }
+      Self::Cylinder { base, radius, height } => {
+        Self::Cylinder { base: base.clone(), radius: *radius, height: *height }
+      }
+      Self::ConvexHull(points) => Self::ConvexHull(points.clone()),
+    }
+  }
+}
+
+impl<CS: CoordinateSystem<Coordinate: PartialEq>> PartialEq for Obstacle<CS> {
+  fn eq(&self, other: &Self) -> bool {
+    match (self, other) {
+      (
+        Self::Aabb { min: l_min, max: l_max },
+        Self::Aabb { min: r_min, max: r_max },
+      ) => l_min == r_min && l_max == r_max,
+      (
+        Self::Cylinder { base: l_base, radius: l_radius, height: l_height },
+        Self::Cylinder { base: r_base, radius: r_radius, height: r_height },
+      ) => l_base == r_base && l_radius == r_radius && l_height == r_height,
+      (Self::ConvexHull(l_points), Self::ConvexHull(r_points)) => {
+        l_points == r_points
+      }
+      _ => false,
+    }
+  }
+}
@@ -78,10 +78,13 @@ enum Portal {
 
 impl IslandSegment {
   /// Determines the endpoints of the portal at `portal_index` in `nav_data`.
+  /// Endpoints on the boundary of the nav mesh are moved `corner_offset`
+  /// towards the other endpoint.
   fn get_portal_endpoints<CS: CoordinateSystem>(
     &self,
     portal_index: usize,
     nav_data: &NavigationData<CS>,
+    corner_offset: f32,
   ) -> Portal {
     let polygon_index = self.corridor[portal_index];
     let edge = self.portal_edge_index[portal_index];
@@ -92,18 +95,51 @@ impl IslandSegment {
     let (left_vertex, right_vertex) =
       island_data.nav_mesh.polygons[polygon_index].get_edge_indices(edge);
 
-    Portal::Walkable(
+    let portal = (
       island_data.transform.apply(island_data.nav_mesh.vertices[left_vertex]),
       island_data.transform.apply(island_data.nav_mesh.vertices[right_vertex]),
-    )
+    );
+    if corner_offset == 0.0 {
+      return Portal::Walkable(portal.0, portal.1);
+    }
+
+    let nodes = [
+      NodeRef { island_id: self.island_id, polygon_index },
+      NodeRef {
+        island_id: self.island_id,
+        polygon_index: self.corridor[portal_index + 1],
+      },
+    ];
+    // Modified nodes may have lost (or gained) boundary edges, so check their
+    // actual boundaries instead.
+    let is_modified =
+      nodes.iter().any(|node| nav_data.modified_nodes.contains_key(node));
+    let is_corner = |vertex: usize, point: Vec3| {
+      if is_modified {
+        nodes
+          .iter()
+          .any(|&node| nav_data.is_point_on_node_boundary(node, point))
+      } else {
+        island_data.nav_mesh.vertex_on_boundary[vertex]
+      }
+    };
+    let (left, right) = shrink_portal(
+      portal,
+      (is_corner(left_vertex, portal.0), is_corner(right_vertex, portal.1)),
+      corner_offset,
+    );
+    Portal::Walkable(left, right)
   }
 }
 
 impl OffMeshLinkSegment {
   /// Gets the endpoints of the portal for this off mesh link in `nav_data`.
+  /// Endpoints of boundary links that are on the boundary of the nav meshes
+  /// are moved `corner_offset` towards the other endpoint.
   fn get_portal_endpoints<CS: CoordinateSystem>(
     &self,
     nav_data: &NavigationData<CS>,
+    corner_offset: f32,
   ) -> Portal {
     let off_mesh_link = nav_data
       .off_mesh_links
@@ -112,7 +148,20 @@ impl OffMeshLinkSegment {
     let portal = off_mesh_link.portal;
     match &off_mesh_link.kinded {
       KindedOffMeshLink::BoundaryLink { .. } => {
-        Portal::Walkable(portal.0, portal.1)
+        if corner_offset == 0.0 {
+          return Portal::Walkable(portal.0, portal.1);
+        }
+        let is_corner = |point: Vec3| {
+          [self.starting_node, self.end_node]
+            .iter()
+            .any(|&node| nav_data.is_point_on_node_boundary(node, point))
+        };
+        let (left, right) = shrink_portal(
+          portal,
+          (is_corner(portal.0), is_corner(portal.1)),
+          corner_offset,
+        );
+        Portal::Walkable(left, right)
       }
       KindedOffMeshLink::AnimationLink {
         destination_portal,
@@ -129,6 +178,33 @@ impl OffMeshLinkSegment {
   }
 }
 
+/// Moves the endpoints of `portal` that are corners (as indicated by
+/// `is_corner`) `corner_offset` towards the other endpoint. If the portal is
+/// too short, the endpoints are moved proportionally so they meet rather than
+/// pass each other.
+fn shrink_portal(
+  (left, right): (Vec3, Vec3),
+  (is_left_corner, is_right_corner): (bool, bool),
+  corner_offset: f32,
+) -> (Vec3, Vec3) {
+  let length = left.distance(right);
+  let total_offset =
+    corner_offset * (is_left_corner as u32 + is_right_corner as u32) as f32;
+  if length == 0.0 || total_offset == 0.0 {
+    return (left, right);
+  }
+  let offset = if total_offset > length {
+    corner_offset * length / total_offset
+  } else {
+    corner_offset
+  };
+  let direction = (right - left) / length;
+  (
+    if is_left_corner { left + direction * offset } else { left },
+    if is_right_corner { right - direction * offset } else { right },
+  )
+}
+
 /// An index in a path.
 #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
 pub(crate) struct PathIndex {
@@ -200,20 +276,25 @@ pub(crate) enum StraightPathStep {
 
 impl Path {
   /// Determines the endpoints of the portal at `segment_index` at
-  /// `portal_index` in `nav_data`.
+  /// `portal_index` in `nav_data`. Corners of walkable portals are moved
+  /// `corner_offset` into the portal.
   fn get_portal_endpoints<CS: CoordinateSystem>(
     &self,
     path_index: PathIndex,
     nav_data: &NavigationData<CS>,
+    corner_offset: f32,
   ) -> Portal {
     if path_index.portal_index
       == self.island_segments[path_index.segment_index].portal_edge_index.len()
     {
       self.off_mesh_link_segments[path_index.segment_index]
-        .get_portal_endpoints(nav_data)
+        .get_portal_endpoints(nav_data, corner_offset)
     } else {
-      self.island_segments[path_index.segment_index]
-        .get_portal_endpoints(path_index.portal_index, nav_data)
+      self.island_segments[path_index.segment_index].get_portal_endpoints(
+        path_index.portal_index,
+        nav_data,
+        corner_offset,
+      )
     }
   }
 
@@ -223,14 +304,73 @@ impl Path {
   /// indices into `self`. Returns the index of the node in the path where the
   /// next point is, and that next point. Note this can be called repeatedly by
   /// passing in the returned tuple as the `start_index` and `start_point` to
-  /// generate the full straight path.
+  /// generate the full straight path. Corners of the straight path (on the
+  /// boundary of the nav meshes) are offset by `corner_offset` into the
+  /// portals, so an agent with that radius does not clip the corners. If an
+  /// obstacle (or closed door) inside the nodes blocks walking straight to the
+  /// next point, the next point is instead a corner walking around the
+  /// obstacle (also offset by `corner_offset`). The end of the path is only
+  /// reached once the returned index is `end_index` and the returned point is
+  /// `end_point`.
   pub(crate) fn find_next_point_in_straight_path<CS: CoordinateSystem>(
+    &self,
+    nav_data: &NavigationData<CS>,
+    start_index: PathIndex,
+    start_point: Vec3,
+    end_index: PathIndex,
+    end_point: Vec3,
+    corner_offset: f32,
+  ) -> (PathIndex, StraightPathStep) {
+    let (next_index, step) = self.find_next_point_in_funnel(
+      nav_data,
+      start_index,
+      start_point,
+      end_index,
+      end_point,
+      corner_offset,
+    );
+
+    // Find the nodes that the agent walks through to reach the next point.
+    let (target_point, walked_nodes) = match step {
+      StraightPathStep::Waypoint(point) => {
+        (point, self.indexed_nodes_between(start_index, next_index))
+      }
+      StraightPathStep::AnimationLink { start_point, start_node, .. } => {
+        let mut nodes = self.indexed_nodes_between(start_index, next_index);
+        if let Some(link_index) =
+          nodes.iter().position(|&(_, node)| node == start_node)
+        {
+          nodes.truncate(link_index + 1);
+        }
+        (start_point, nodes)
+      }
+    };
+    let nodes = walked_nodes.iter().map(|&(_, node)| node).collect::<Vec<_>>();
+    match nav_data.find_detour_corner(
+      &nodes,
+      start_point,
+      target_point,
+      corner_offset,
+    ) {
+      None => (next_index, step),
+      Some((corner_node, corner)) => {
+        let (corner_index, _) =
+          walked_nodes.iter().find(|&&(_, node)| node == corner_node).unwrap();
+        (*corner_index, StraightPathStep::Waypoint(corner))
+      }
+    }
+  }
+
+  /// Same as [`Path::find_next_point_in_straight_path`], but ignores obstacles
+  /// and closed doors inside the nodes (only the portals are considered).
+  fn find_next_point_in_funnel<CS: CoordinateSystem>(
     &self,
     nav_data: &NavigationData<CS>,
     start_index: PathIndex,
     start_point: Vec3,
     mut end_index: PathIndex,
     end_point: Vec3,
+    corner_offset: f32,
   ) -> (PathIndex, StraightPathStep) {
     let apex = start_point;
     let (mut left_index, mut right_index) = (start_index, start_index);
@@ -250,7 +390,7 @@ impl Path {
     let (mut current_left, mut current_right) = if start_index == end_index {
       (end_point, end_point)
     } else {
-      match self.get_portal_endpoints(start_index, nav_data) {
+      match self.get_portal_endpoints(start_index, nav_data, corner_offset) {
         Portal::Walkable(left, right) => (left, right),
         Portal::AnimationLink {
           start_portal,
@@ -286,7 +426,7 @@ impl Path {
       let (portal_left, portal_right) = if portal_index == end_index {
         (end_point, end_point)
       } else {
-        match self.get_portal_endpoints(portal_index, nav_data) {
+        match self.get_portal_endpoints(portal_index, nav_data, corner_offset) {
           Portal::Walkable(left, right) => (left, right),
           Portal::AnimationLink {
             start_portal,
@@ -375,18 +515,91 @@ impl Path {
     }
   }
 
+  /// Returns the nodes of the path from `start_index` to `end_index`
+  /// (inclusive), in order.
+  pub(crate) fn nodes_between(
+    &self,
+    start_index: PathIndex,
+    end_index: PathIndex,
+  ) -> Vec<NodeRef> {
+    let mut nodes = vec![];
+    if start_index > end_index {
+      return nodes;
+    }
+    for segment_index in start_index.segment_index..=end_index.segment_index {
+      let island_segment = &self.island_segments[segment_index];
+      let first_index = if segment_index == start_index.segment_index {
+        start_index.portal_index
+      } else {
+        0
+      };
+      let last_index = if segment_index == end_index.segment_index {
+        end_index.portal_index
+      } else {
+        island_segment.corridor.len() - 1
+      };
+      nodes.extend(
+        island_segment.corridor[first_index..=last_index].iter().map(
+          |&polygon_index| NodeRef {
+            island_id: island_segment.island_id,
+            polygon_index,
+          },
+        ),
+      );
+    }
+    nodes
+  }
+
+  /// Same as [`Path::nodes_between`], but also includes the index of each
+  /// node. `end_index` may be past the last node of the path.
+  fn indexed_nodes_between(
+    &self,
+    start_index: PathIndex,
+    end_index: PathIndex,
+  ) -> Vec<(PathIndex, NodeRef)> {
+    let mut nodes = vec![];
+    let mut index = start_index;
+    while index <= end_index {
+      let island_segment = &self.island_segments[index.segment_index];
+      let Some(&polygon_index) =
+        island_segment.corridor.get(index.portal_index)
+      else {
+        break;
+      };
+      nodes.push((
+        index,
+        NodeRef { island_id: island_segment.island_id, polygon_index },
+      ));
+      index = index.next(self);
+    }
+    nodes
+  }
+
   /// Determines if a path is valid. A path may be invalid if an island it
-  /// travelled across was invalidared, or a off mesh link it used was
-  /// invalidated.
+  /// travelled across was invalidared, a off mesh link it used was
+  /// invalidated, or a node it travelled through was invalidated (e.g., by a
+  /// modifier volume).
   pub(crate) fn is_valid(
     &self,
     invalidated_off_mesh_links: &HashSet<OffMeshLinkId>,
     invalidated_islands: &HashSet<IslandId>,
+    invalidated_nodes: &HashSet<NodeRef>,
   ) -> bool {
     for island_segment in self.island_segments.iter() {
       if invalidated_islands.contains(&island_segment.island_id) {
         return false;
       }
+      if invalidated_nodes.is_empty() {
+        continue;
+      }
+      for &polygon_index in island_segment.corridor.iter() {
+        if invalidated_nodes.contains(&NodeRef {
+          island_id: island_segment.island_id,
+          polygon_index,
+        }) {
+          return false;
+        }
+      }
     }
     for off_mesh_link_segment in self.off_mesh_link_segments.iter() {
       if invalidated_off_mesh_links
@@ -399,6 +612,26 @@ impl Path {
     true
   }
 
+  /// Moves the start and end points of the path along with their islands, if
+  /// they moved during the last update of `nav_data`.
+  pub(crate) fn carry_with_islands<CS: CoordinateSystem>(
+    &mut self,
+    nav_data: &NavigationData<CS>,
+  ) {
+    if let Some(start_point) = nav_data.carry_point_with_island(
+      self.island_segments.first().unwrap().island_id,
+      self.start_point,
+    ) {
+      self.start_point = start_point;
+    }
+    if let Some(end_point) = nav_data.carry_point_with_island(
+      self.island_segments.last().unwrap().island_id,
+      self.end_point,
+    ) {
+      self.end_point = end_point;
+    }
+  }
+
   /// Finds the index of `node` in the path.
   pub(crate) fn find_index_of_node(&self, node: NodeRef) -> Option<PathIndex> {
     for (segment_index, island_segment) in
@@ -1,6 +1,6 @@
 use std::{collections::HashSet, f32::consts::PI, sync::Arc};
 
-use glam::{Vec2, Vec3};
+use glam::{Quat, Vec2, Vec3};
 use googletest::{expect_that, matchers::*};
 use slotmap::HopSlotMap;
 
@@ -22,13 +22,16 @@ fn collect_straight_path<CS: CoordinateSystem>(
   start: (PathIndex, Vec3),
   end: (PathIndex, Vec3),
   iteration_limit: u32,
+  corner_offset: f32,
 ) -> Vec<(PathIndex, StraightPathStep)> {
   let mut straight_path = Vec::with_capacity(iteration_limit as usize);
 
   let mut current = (start.0, StraightPathStep::Waypoint(start.1));
   let mut iterations = 0;
   let mut force_iteration = false;
-  while (force_iteration || current.0 != end.0) && iterations < iteration_limit
+  while (force_iteration
+    || current != (end.0, StraightPathStep::Waypoint(end.1)))
+    && iterations < iteration_limit
   {
     force_iteration = false;
 
@@ -44,6 +47,7 @@ fn collect_straight_path<CS: CoordinateSystem>(
       current_point,
       end.0,
       end.1,
+      corner_offset,
     );
     if let StraightPathStep::AnimationLink { .. } = &current.1 {
       // Make sure to do an extra iteration after an animation link so we see
@@ -83,13 +87,17 @@ fn finds_next_point_for_organic_map() {
       vec![10, 4, 14, 13],
     ],
     polygon_type_indices: vec![0, 0, 0, 0],
+    polygon_flags: None,
     height_mesh: None,
   }
   .validate()
   .expect("Mesh is valid.");
 
-  let transform =
-    Transform { translation: Vec3::new(5.0, 9.0, 7.0), rotation: PI * -0.35 };
+  let transform = Transform {
+    translation: Vec3::new(5.0, 9.0, 7.0),
+    rotation: Quat::from_rotation_z(PI * -0.35),
+    scale: 1.0,
+  };
   let mut archipelago =
     Archipelago::<XYZ>::new(ArchipelagoOptions::from_agent_radius(0.5));
   let island_id =
@@ -115,6 +123,7 @@ fn finds_next_point_for_organic_map() {
       /* end= */
       (PathIndex::from_corridor_index(0, 2), path.end_point),
       /* iteration_limit= */ 3,
+      /* corner_offset= */ 0.0,
     ),
     [
       (
@@ -184,13 +193,17 @@ fn finds_next_point_in_zig_zag() {
       vec![25, 23, 26, 27],
     ],
     polygon_type_indices: vec![0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    polygon_flags: None,
     height_mesh: None,
   }
   .validate()
   .expect("Mesh is valid.");
 
-  let transform =
-    Transform { translation: Vec2::new(-1.0, -3.0), rotation: PI * -1.8 };
+  let transform = Transform {
+    translation: Vec2::new(-1.0, -3.0),
+    rotation: Quat::from_rotation_z(PI * -1.8),
+    scale: 1.0,
+  };
   let mut archipelago =
     Archipelago::<XY>::new(ArchipelagoOptions::from_agent_radius(0.5));
   let island_id =
@@ -216,6 +229,7 @@ fn finds_next_point_in_zig_zag() {
       /* end= */
       (PathIndex::from_corridor_index(0, 14), path.end_point),
       /* iteration_limit= */ 5,
+      /* corner_offset= */ 0.0,
     ),
     [
       (
@@ -242,6 +256,108 @@ fn finds_next_point_in_zig_zag() {
   );
 }
 
+#[test]
+fn corners_are_offset_from_boundary() {
+  // An L-shaped corridor turning right.
+  let nav_mesh = NavigationMesh {
+    vertices: vec![
+      Vec2::new(0.0, 0.0),
+      Vec2::new(1.0, 0.0),
+      Vec2::new(1.0, 1.0),
+      Vec2::new(0.0, 1.0),
+      Vec2::new(1.0, 2.0),
+      Vec2::new(0.0, 2.0),
+      Vec2::new(2.0, 1.0),
+      Vec2::new(2.0, 2.0),
+    ],
+    polygons: vec![vec![0, 1, 2, 3], vec![3, 2, 4, 5], vec![2, 6, 7, 4]],
+    polygon_type_indices: vec![0; 3],
+    polygon_flags: None,
+    height_mesh: None,
+  }
+  .validate()
+  .expect("Mesh is valid.");
+
+  let mut archipelago =
+    Archipelago::<XY>::new(ArchipelagoOptions::from_agent_radius(0.5));
+  let island_id = archipelago
+    .add_island(Island::new(Transform::default(), Arc::new(nav_mesh)));
+
+  let path = Path {
+    island_segments: vec![IslandSegment {
+      island_id,
+      corridor: vec![0, 1, 2],
+      portal_edge_index: vec![2, 1],
+    }],
+    off_mesh_link_segments: vec![],
+    start_point: Vec3::new(0.5, 0.5, 0.0),
+    end_point: Vec3::new(1.75, 1.25, 0.0),
+  };
+
+  let straight_path = |corner_offset| {
+    collect_straight_path(
+      &path,
+      &archipelago.nav_data,
+      /* start= */
+      (PathIndex::from_corridor_index(0, 0), path.start_point),
+      /* end= */
+      (PathIndex::from_corridor_index(0, 2), path.end_point),
+      /* iteration_limit= */ 3,
+      corner_offset,
+    )
+  };
+
+  assert_eq!(
+    straight_path(0.0),
+    [
+      (
+        PathIndex::from_corridor_index(0, 1),
+        StraightPathStep::Waypoint(Vec3::new(1.0, 1.0, 0.0))
+      ),
+      (
+        PathIndex::from_corridor_index(0, 2),
+        StraightPathStep::Waypoint(Vec3::new(1.75, 1.25, 0.0))
+      ),
+    ]
+  );
+  // The corner is now passed at a distance.
+  assert_eq!(
+    straight_path(0.25),
+    [
+      (
+        PathIndex::from_corridor_index(0, 0),
+        StraightPathStep::Waypoint(Vec3::new(0.75, 1.0, 0.0))
+      ),
+      (
+        PathIndex::from_corridor_index(0, 1),
+        StraightPathStep::Waypoint(Vec3::new(1.0, 1.25, 0.0))
+      ),
+      (
+        PathIndex::from_corridor_index(0, 2),
+        StraightPathStep::Waypoint(Vec3::new(1.75, 1.25, 0.0))
+      ),
+    ]
+  );
+  // Narrow portals are shrunk down to their midpoint.
+  assert_eq!(
+    straight_path(2.0),
+    [
+      (
+        PathIndex::from_corridor_index(0, 0),
+        StraightPathStep::Waypoint(Vec3::new(0.5, 1.0, 0.0))
+      ),
+      (
+        PathIndex::from_corridor_index(0, 1),
+        StraightPathStep::Waypoint(Vec3::new(1.0, 1.5, 0.0))
+      ),
+      (
+        PathIndex::from_corridor_index(0, 2),
+        StraightPathStep::Waypoint(Vec3::new(1.75, 1.25, 0.0))
+      ),
+    ]
+  );
+}
+
 fn off_mesh_link_for_animation_link<CS: CoordinateSystem>(
   archipelago: &Archipelago<CS>,
   animation_link_id: AnimationLinkId,
@@ -271,6 +387,7 @@ fn starts_at_end_index_goes_to_end_point() {
     ],
     polygons: vec![vec![0, 1, 2, 3], vec![3, 2, 4, 5]],
     polygon_type_indices: vec![0, 0],
+    polygon_flags: None,
     height_mesh: None,
   }
   .validate()
@@ -279,7 +396,7 @@ fn starts_at_end_index_goes_to_end_point() {
   let mut archipelago =
     Archipelago::<XYZ>::new(ArchipelagoOptions::from_agent_radius(0.5));
   let island_id = archipelago.add_island(Island::new(
-    Transform { translation: Vec3::ZERO, rotation: 0.0 },
+    Transform { translation: Vec3::ZERO, rotation: Quat::IDENTITY, scale: 1.0 },
     Arc::new(nav_mesh),
   ));
 
@@ -301,6 +418,7 @@ fn starts_at_end_index_goes_to_end_point() {
       /* start_point= */ Vec3::new(0.25, 1.1, 0.0),
       /* end_index= */ PathIndex::from_corridor_index(0, 1),
       /* end_point= */ Vec3::new(0.75, 1.9, 0.0),
+      /* corner_offset= */ 0.0,
     ),
     (
       PathIndex::from_corridor_index(0, 1),
@@ -351,6 +469,7 @@ fn straight_path_includes_animation_link() {
       vec![11, 10, 12, 13],
     ],
     polygon_type_indices: vec![0; 5],
+    polygon_flags: None,
     height_mesh: None,
   }
   .validate()
@@ -359,7 +478,7 @@ fn straight_path_includes_animation_link() {
   let mut archipelago =
     Archipelago::<XY>::new(ArchipelagoOptions::from_agent_radius(0.5));
   let island_id = archipelago.add_island(Island::new(
-    Transform { translation: Vec2::ZERO, rotation: 0.0 },
+    Transform { translation: Vec2::ZERO, rotation: Quat::IDENTITY, scale: 1.0 },
     Arc::new(nav_mesh),
   ));
 
@@ -405,6 +524,7 @@ fn straight_path_includes_animation_link() {
       (PathIndex::from_corridor_index(0, 0), Vec3::new(0.1, 0.1, 0.0)),
       (PathIndex::from_corridor_index(1, 1), Vec3::new(0.9, 6.9, 0.0)),
       2,
+      /* corner_offset= */ 0.0,
     ),
     elements_are!(
       &(
@@ -477,6 +597,7 @@ fn multiple_animation_links_in_a_row() {
       vec![15, 14, 16, 17],
     ],
     polygon_type_indices: vec![0; 5],
+    polygon_flags: None,
     height_mesh: None,
   }
   .validate()
@@ -485,7 +606,7 @@ fn multiple_animation_links_in_a_row() {
   let mut archipelago =
     Archipelago::<XY>::new(ArchipelagoOptions::from_agent_radius(0.5));
   let island_id = archipelago.add_island(Island::new(
-    Transform { translation: Vec2::ZERO, rotation: 0.0 },
+    Transform { translation: Vec2::ZERO, rotation: Quat::IDENTITY, scale: 1.0 },
     Arc::new(nav_mesh),
   ));
 
@@ -559,6 +680,7 @@ fn multiple_animation_links_in_a_row() {
       (PathIndex::from_corridor_index(0, 0), Vec3::new(0.5, 0.5, 0.0)),
       (PathIndex::from_corridor_index(3, 1), Vec3::new(0.5, 7.5, 0.0)),
       10,
+      /* corner_offset= */ 0.0,
     ),
     elements_are!(
       &(
@@ -640,6 +762,7 @@ fn obscured_animation_link_is_made_visible_by_straight_path() {
       vec![10, 9, 11, 12],
     ],
     polygon_type_indices: vec![0; 4],
+    polygon_flags: None,
     height_mesh: None,
   }
   .validate()
@@ -648,7 +771,7 @@ fn obscured_animation_link_is_made_visible_by_straight_path() {
   let mut archipelago =
     Archipelago::<XY>::new(ArchipelagoOptions::from_agent_radius(0.5));
   let island_id = archipelago.add_island(Island::new(
-    Transform { translation: Vec2::ZERO, rotation: 0.0 },
+    Transform { translation: Vec2::ZERO, rotation: Quat::IDENTITY, scale: 1.0 },
     Arc::new(nav_mesh),
   ));
 
@@ -693,6 +816,7 @@ fn obscured_animation_link_is_made_visible_by_straight_path() {
       (PathIndex::from_corridor_index(0, 0), Vec3::new(6.5, 0.5, 0.0)),
       (PathIndex::from_corridor_index(1, 1), Vec3::new(0.1, 6.9, 0.0)),
       10,
+      /* corner_offset= */ 0.0,
     ),
     elements_are!(
       &(
@@ -754,6 +878,7 @@ fn end_point_after_animation_link_is_reported() {
     ],
     polygons: vec![vec![0, 1, 2, 3], vec![4, 5, 6, 7], vec![8, 9, 10, 11]],
     polygon_type_indices: vec![0; 3],
+    polygon_flags: None,
     height_mesh: None,
   }
   .validate()
@@ -762,7 +887,7 @@ fn end_point_after_animation_link_is_reported() {
   let mut archipelago =
     Archipelago::<XY>::new(ArchipelagoOptions::from_agent_radius(0.5));
   let island_id = archipelago.add_island(Island::new(
-    Transform { translation: Vec2::ZERO, rotation: 0.0 },
+    Transform { translation: Vec2::ZERO, rotation: Quat::IDENTITY, scale: 1.0 },
     Arc::new(nav_mesh),
   ));
 
@@ -819,6 +944,7 @@ fn end_point_after_animation_link_is_reported() {
       // want to test what happens when the end index is on the animation link.
       (PathIndex::from_corridor_index(1, 0), Vec3::new(0.5, 2.5, 0.0)),
       10,
+      /* corner_offset= */ 0.0,
     ),
     elements_are!(
       &(
@@ -886,21 +1012,25 @@ fn path_not_valid_for_invalidated_islands_or_off_mesh_links() {
 
   assert!(path.is_valid(
     /* invalidated_boundary_links= */ &HashSet::new(),
-    /* invalidated_islands= */ &HashSet::new()
+    /* invalidated_islands= */ &HashSet::new(),
+    /* invalidated_nodes= */ &HashSet::new(),
   ));
 
   // Each island is invalidated.
   assert!(!path.is_valid(
     /* invalidated_boundary_links= */ &HashSet::new(),
     /* invalidated_islands= */ &HashSet::from([island_id_1]),
+    /* invalidated_nodes= */ &HashSet::new(),
   ));
   assert!(!path.is_valid(
     /* invalidated_boundary_links= */ &HashSet::new(),
     /* invalidated_islands= */ &HashSet::from([island_id_2]),
+    /* invalidated_nodes= */ &HashSet::new(),
   ));
   assert!(!path.is_valid(
     /* invalidated_boundary_links= */ &HashSet::new(),
     /* invalidated_islands= */ &HashSet::from([island_id_3]),
+    /* invalidated_nodes= */ &HashSet::new(),
   ));
 
   // Each boundary link is invalidated.
@@ -908,11 +1038,27 @@ fn path_not_valid_for_invalidated_islands_or_off_mesh_links() {
     /* invalidated_boundary_links= */
     &HashSet::from([boundary_link_id_1]),
     /* invalidated_islands= */ &HashSet::new(),
+    /* invalidated_nodes= */ &HashSet::new(),
   ));
   assert!(!path.is_valid(
     /* invalidated_boundary_links= */
     &HashSet::from([boundary_link_id_2]),
     /* invalidated_islands= */ &HashSet::new(),
+    /* invalidated_nodes= */ &HashSet::new(),
+  ));
+
+  // Only nodes in the corridor invalidate the path.
+  assert!(!path.is_valid(
+    /* invalidated_boundary_links= */ &HashSet::new(),
+    /* invalidated_islands= */ &HashSet::new(),
+    /* invalidated_nodes= */
+    &HashSet::from([NodeRef { island_id: island_id_2, polygon_index: 1 }]),
+  ));
+  assert!(path.is_valid(
+    /* invalidated_boundary_links= */ &HashSet::new(),
+    /* invalidated_islands= */ &HashSet::new(),
+    /* invalidated_nodes= */
+    &HashSet::from([NodeRef { island_id: island_id_2, polygon_index: 2 }]),
   ));
 }
 
@@ -1087,3 +1233,62 @@ fn indices_in_path_are_found_rev() {
     None
   );
 }
+
+#[test]
+fn nodes_between_indices_span_segments() {
+  // Create unused slotmaps just to get `IslandId`s and `BoundaryLinkId`s.
+  let mut slotmap = HopSlotMap::<IslandId, _>::with_key();
+  let island_id_1 = slotmap.insert(0);
+  let island_id_2 = slotmap.insert(0);
+  let mut slotmap = HopSlotMap::<OffMeshLinkId, _>::with_key();
+  let boundary_link_id = slotmap.insert(0);
+
+  let path = Path {
+    island_segments: vec![
+      IslandSegment {
+        island_id: island_id_1,
+        corridor: vec![3, 4, 5],
+        portal_edge_index: vec![0, 0],
+      },
+      IslandSegment {
+        island_id: island_id_2,
+        corridor: vec![2, 1],
+        portal_edge_index: vec![0],
+      },
+    ],
+    off_mesh_link_segments: vec![OffMeshLinkSegment {
+      starting_node: NodeRef { island_id: island_id_1, polygon_index: 5 },
+      end_node: NodeRef { island_id: island_id_2, polygon_index: 2 },
+      off_mesh_link: boundary_link_id,
+    }],
+    start_point: Vec3::ZERO,
+    end_point: Vec3::ZERO,
+  };
+
+  assert_eq!(
+    path.nodes_between(
+      PathIndex { segment_index: 0, portal_index: 1 },
+      PathIndex { segment_index: 1, portal_index: 0 },
+    ),
+    [
+      NodeRef { island_id: island_id_1, polygon_index: 4 },
+      NodeRef { island_id: island_id_1, polygon_index: 5 },
+      NodeRef { island_id: island_id_2, polygon_index: 2 },
+    ]
+  );
+  assert_eq!(
+    path.nodes_between(
+      PathIndex { segment_index: 1, portal_index: 1 },
+      PathIndex { segment_index: 1, portal_index: 1 },
+    ),
+    [NodeRef { island_id: island_id_2, polygon_index: 1 }]
+  );
+  // The indices are in the wrong order.
+  assert_eq!(
+    path.nodes_between(
+      PathIndex { segment_index: 1, portal_index: 0 },
+      PathIndex { segment_index: 0, portal_index: 0 },
+    ),
+    []
+  );
+}
@@ -3,12 +3,14 @@ use std::{
   collections::{HashMap, HashSet},
 };
 
-use glam::Vec3;
+use glam::{Vec2, Vec3, Vec3Swizzles};
 
 use crate::{
-  CoordinateSystem, NavigationData,
-  agent::PermittedAnimationLinks,
+  CoordinateSystem, NavigationData, PathCostFilter, PathQuery, PathfindingMode,
+  agent::{PermittedAnimationLinks, PolygonFlagFilter},
+  any_angle::{AnyAnglePathNode, AnyAnglePathProblem, AnyAngleStep},
   astar::{self, AStarProblem, PathStats},
+  island::IslandId,
   nav_data::{KindedOffMeshLink, NodeRef, OffMeshLinkId},
   nav_mesh::MeshEdgeRef,
   path::{IslandSegment, OffMeshLinkSegment, Path},
@@ -24,10 +26,10 @@ struct ArchipelagoPathProblem<'a, CS: CoordinateSystem> {
   /// The center of the start_node. This is just a cached point for easy
   /// access.
   start_point: Vec3,
-  /// The node the target is in.
-  end_node: NodeRef,
-  /// The center of the end_node. This is just a cached point for easy access.
-  end_point: Vec3,
+  /// The nodes the targets are in, and the target points in those nodes.
+  /// Reaching any of the targets solves the problem. If empty, the problem can
+  /// only be explored (see [`find_reachable_nodes`]).
+  ends: Vec<(NodeRef, Vec3)>,
   /// The cheapest type index cost in [`Self::nav_data`]. This is cached once
   /// since it is constant for the whole problem.
   cheapest_type_index_cost: f32,
@@ -35,14 +37,25 @@ struct ArchipelagoPathProblem<'a, CS: CoordinateSystem> {
   override_type_index_to_cost: &'a HashMap<usize, f32>,
   /// The set of permitted animation links for the agent.
   permitted_animation_links: PermittedAnimationLinks,
+  /// The polygons that the path may travel through.
+  flag_filter: PolygonFlagFilter,
+  /// The radius of the agent. Portals and off mesh links narrower than the
+  /// agent are not taken.
+  agent_radius: f32,
+  /// The filter that customizes costs further, if any.
+  cost_filter: Option<&'a dyn PathCostFilter>,
+  /// The islands that the path may travel through. If [`None`], all islands
+  /// are permitted.
+  permitted_islands: Option<&'a HashSet<IslandId>>,
 }
 
 /// An action taken in the path.
-#[derive(Clone, Copy)]
-enum PathStep {
-  /// Just head directly to the end. This is only valid when inside the end
-  /// node.
-  GoToEnd,
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum PathStep {
+  /// Just head directly to the end with the specified index (in
+  /// [`ArchipelagoPathProblem::ends`]). This is only valid when inside the
+  /// end's node.
+  GoToEnd(usize),
   /// Take the node connection at the specified edge index in the current node.
   NodeConnection(usize),
   /// Take the off mesh link with the specified ID in the current node.
@@ -55,8 +68,8 @@ enum PathStep {
 enum PathNode {
   /// The start of the path.
   Start,
-  /// The end of the path.
-  End,
+  /// The end of the path, with the index of the end that was reached.
+  End(usize),
   /// An edge of a node in the navigation data.
   NodeEdge {
     /// The node that we are now in.
@@ -69,12 +82,102 @@ enum PathNode {
 }
 
 impl<CS: CoordinateSystem> ArchipelagoPathProblem<'_, CS> {
+  /// Determines the node that `state` is in, and the point (in world space)
+  /// where the path enters that node.
+  fn node_and_point(&self, state: &PathNode) -> (NodeRef, Vec3) {
+    match state {
+      PathNode::Start => (self.start_node, self.start_point),
+      PathNode::NodeEdge { node, start_edge } => {
+        let island = self.nav_data.get_island(node.island_id).unwrap();
+        let edge = island.get_nav_mesh().get_edge_points(MeshEdgeRef {
+          polygon_index: node.polygon_index,
+          edge_index: *start_edge,
+        });
+        (*node, island.transform.apply(edge.0.midpoint(edge.1)))
+      }
+      PathNode::OffMeshLink(link) => {
+        let link = self.nav_data.off_mesh_links.get(*link).unwrap();
+        let portal = match &link.kinded {
+          KindedOffMeshLink::BoundaryLink { .. } => link.portal,
+          KindedOffMeshLink::AnimationLink { destination_portal, .. } => {
+            *destination_portal
+          }
+        };
+        (link.destination_node, portal.0.midpoint(portal.1))
+      }
+      PathNode::End(end_index) => self.ends[*end_index],
+    }
+  }
+
+  /// Determines the portal (in world space) where the path enters the node of
+  /// `state`. For the start of the path, this is just the start point.
+  fn entry_portal(&self, state: &PathNode) -> (Vec2, Vec2) {
+    match state {
+      PathNode::Start => (self.start_point.xy(), self.start_point.xy()),
+      PathNode::NodeEdge { node, start_edge } => {
+        let island = self.nav_data.get_island(node.island_id).unwrap();
+        let edge = island.get_nav_mesh().get_edge_points(MeshEdgeRef {
+          polygon_index: node.polygon_index,
+          edge_index: *start_edge,
+        });
+        (
+          island.transform.apply(edge.0).xy(),
+          island.transform.apply(edge.1).xy(),
+        )
+      }
+      PathNode::OffMeshLink(link) => {
+        let link = self.nav_data.off_mesh_links.get(*link).unwrap();
+        let portal = match &link.kinded {
+          KindedOffMeshLink::BoundaryLink { .. } => link.portal,
+          KindedOffMeshLink::AnimationLink { destination_portal, .. } => {
+            *destination_portal
+          }
+        };
+        (portal.0.xy(), portal.1.xy())
+      }
+      PathNode::End(_) => {
+        unreachable!("we never need the successors of the goal node")
+      }
+    }
+  }
+
   /// Determines the cost of `type_index`.
   fn type_index_to_cost(&self, type_index: usize) -> f32 {
     self.override_type_index_to_cost.get(&type_index).copied().unwrap_or_else(
       || self.nav_data.get_type_index_cost(type_index).unwrap_or(1.0),
     )
   }
+
+  /// Determines the cost of travelling through `node_ref`, which has
+  /// `type_index`. Nodes excluded by the flag filter or the cost filter have an
+  /// infinite cost.
+  fn node_cost(&self, node_ref: NodeRef, type_index: usize) -> f32 {
+    let island = self.nav_data.get_island(node_ref.island_id).unwrap();
+    if !self
+      .flag_filter
+      .is_permitted(island.nav_mesh.polygons[node_ref.polygon_index].flags)
+    {
+      return f32::INFINITY;
+    }
+    let cost =
+      self.nav_data.modified_node_cost(node_ref, type_index, |type_index| {
+        self.type_index_to_cost(type_index)
+      });
+    match self.cost_filter {
+      None => cost,
+      Some(cost_filter) => cost_filter
+        .node_cost_multiplier(node_ref, type_index)
+        .map_or(f32::INFINITY, |multiplier| cost * multiplier),
+    }
+  }
+
+  /// Determines the extra cost of crossing from `from` into `to`. Crossings
+  /// excluded by the cost filter have an infinite cost.
+  fn edge_cost(&self, from: NodeRef, to: NodeRef) -> f32 {
+    self.cost_filter.map_or(0.0, |cost_filter| {
+      cost_filter.edge_cost(from, to).unwrap_or(f32::INFINITY)
+    })
+  }
 }
 
 impl<CS: CoordinateSystem> AStarProblem for ArchipelagoPathProblem<'_, CS> {
@@ -90,68 +193,64 @@ impl<CS: CoordinateSystem> AStarProblem for ArchipelagoPathProblem<'_, CS> {
     &self,
     state: &Self::StateType,
   ) -> Vec<(f32, Self::ActionType, Self::StateType)> {
-    let (node_ref, island, polygon, point, ignore_step) = match state {
-      PathNode::Start => {
-        let island =
-          self.nav_data.get_island(self.start_node.island_id).unwrap();
-        let polygon = &island.nav_mesh.polygons[self.start_node.polygon_index];
-        (self.start_node, island, polygon, self.start_point, None)
-      }
-      PathNode::NodeEdge { node, start_edge: edge } => {
-        let island = self.nav_data.get_island(node.island_id).unwrap();
-        let polygon = &island.nav_mesh.polygons[node.polygon_index];
-
-        let (i, j) = polygon.get_edge_indices(*edge);
-        let local_midpoint =
-          island.nav_mesh.vertices[i].midpoint(island.nav_mesh.vertices[j]);
-
-        (
-          *node,
-          island,
-          polygon,
-          island.transform.apply(local_midpoint),
-          Some(PathStep::NodeConnection(*edge)),
-        )
+    let (node_ref, point) = self.node_and_point(state);
+    let ignore_step = match state {
+      PathNode::Start => None,
+      PathNode::NodeEdge { start_edge, .. } => {
+        Some(PathStep::NodeConnection(*start_edge))
       }
       PathNode::OffMeshLink(link) => {
-        let link = self.nav_data.off_mesh_links.get(*link).unwrap();
-        let island =
-          self.nav_data.get_island(link.destination_node.island_id).unwrap();
-        let polygon =
-          &island.nav_mesh.polygons[link.destination_node.polygon_index];
-
-        let (portal, ignore_step) = match &link.kinded {
+        match &self.nav_data.off_mesh_links.get(*link).unwrap().kinded {
           KindedOffMeshLink::BoundaryLink { reverse_link } => {
-            (link.portal, Some(PathStep::OffMeshLink(*reverse_link)))
-          }
-          KindedOffMeshLink::AnimationLink { destination_portal, .. } => {
-            (*destination_portal, None)
+            Some(PathStep::OffMeshLink(*reverse_link))
           }
-        };
-
-        (
-          link.destination_node,
-          island,
-          polygon,
-          portal.0.midpoint(portal.1),
-          ignore_step,
-        )
+          KindedOffMeshLink::AnimationLink { .. } => None,
+        }
       }
-      PathNode::End => {
+      PathNode::End(_) => {
         unreachable!("we never need the successors of the goal node")
       }
     };
+    let island = self.nav_data.get_island(node_ref.island_id).unwrap();
+    let polygon = &island.nav_mesh.polygons[node_ref.polygon_index];
     let off_mesh_links = self
       .nav_data
       .node_to_off_mesh_link_ids
       .get(&node_ref)
       .map_or(Cow::Owned(HashSet::new()), Cow::Borrowed);
 
-    let current_node_cost = self.type_index_to_cost(polygon.type_index);
+    let current_node_cost = self.node_cost(node_ref, polygon.type_index);
+    // If obstacles have split the node, only leave through the piece of the
+    // node that we entered.
+    let split_node = self
+      .nav_data
+      .modified_nodes
+      .get(&node_ref)
+      .filter(|modified_node| modified_node.is_split())
+      .map(|modified_node| (modified_node, self.entry_portal(state)));
+    let is_reachable = |exit_portal: (Vec2, Vec2)| {
+      split_node.is_none_or(|(modified_node, entry_portal)| {
+        modified_node.are_connected(entry_portal, exit_portal)
+      })
+    };
 
-    if node_ref == self.end_node {
-      let cost = point.distance(self.end_point) * current_node_cost;
-      return vec![(cost, PathStep::GoToEnd, PathNode::End)];
+    let end_successors = self
+      .ends
+      .iter()
+      .enumerate()
+      .filter(|(_, (end_node, _))| *end_node == node_ref)
+      .filter(|(_, (_, end_point))| {
+        is_reachable((end_point.xy(), end_point.xy()))
+      })
+      .map(|(end_index, (_, end_point))| {
+        let cost = point.distance(*end_point) * current_node_cost;
+        (cost, PathStep::GoToEnd(end_index), PathNode::End(end_index))
+      })
+      .collect::<Vec<_>>();
+    if !end_successors.is_empty() && end_successors.len() == self.ends.len() {
+      // Every end is in this node, so heading directly to them is always
+      // cheapest.
+      return end_successors;
     }
 
     polygon
@@ -168,27 +267,47 @@ impl<CS: CoordinateSystem> AStarProblem for ArchipelagoPathProblem<'_, CS> {
           return None;
         }
 
-        let target_node_cost = self.type_index_to_cost(
+        let target_node = NodeRef {
+          island_id: node_ref.island_id,
+          polygon_index: conn.polygon_index,
+        };
+        let target_node_cost = self.node_cost(
+          target_node,
           island.nav_mesh.polygons[conn.polygon_index].type_index,
         );
         if !target_node_cost.is_finite() {
           return None;
         }
+        let edge_cost = self.edge_cost(node_ref, target_node);
+        if !edge_cost.is_finite() {
+          return None;
+        }
+
+        if conn.width * island.transform.scale < self.agent_radius * 2.0 {
+          return None;
+        }
 
         let (i, j) = polygon.get_edge_indices(edge_index);
+        let portal = (
+          island.transform.apply(island.nav_mesh.vertices[i]).xy(),
+          island.transform.apply(island.nav_mesh.vertices[j]).xy(),
+        );
+        if self.nav_data.is_crossing_carved(node_ref, target_node, portal)
+          || !is_reachable(portal)
+        {
+          return None;
+        }
         let local_midpoint =
           island.nav_mesh.vertices[i].midpoint(island.nav_mesh.vertices[j]);
         let cost = point.distance(island.transform.apply(local_midpoint))
-          * current_node_cost;
+          * current_node_cost
+          + edge_cost;
 
         Some((
           cost,
           PathStep::NodeConnection(edge_index),
           PathNode::NodeEdge {
-            node: NodeRef {
-              island_id: node_ref.island_id,
-              polygon_index: conn.polygon_index,
-            },
+            node: target_node,
             start_edge: conn.reverse_edge,
           },
         ))
@@ -201,22 +320,63 @@ impl<CS: CoordinateSystem> AStarProblem for ArchipelagoPathProblem<'_, CS> {
         }
 
         let link = self.nav_data.off_mesh_links.get(*link_id).unwrap();
+        if !is_reachable((link.portal.0.xy(), link.portal.1.xy())) {
+          return None;
+        }
+        if let Some(permitted_islands) = self.permitted_islands
+          && !permitted_islands.contains(&link.destination_node.island_id)
+        {
+          return None;
+        }
         let destination_node_cost =
-          self.type_index_to_cost(link.destination_type_index);
+          self.node_cost(link.destination_node, link.destination_type_index);
         if !destination_node_cost.is_finite() {
           return None;
         }
+        if self.agent_radius > 0.0
+          && self.nav_data.off_mesh_link_width(node_ref, link)
+            < self.agent_radius * 2.0
+        {
+          return None;
+        }
 
         let link_cost = match link.kinded {
-          // Boundary links have no additional cost, so 0.0 is correct.
-          KindedOffMeshLink::BoundaryLink { .. } => 0.0,
-          KindedOffMeshLink::AnimationLink { cost, kind, .. } => {
+          // Boundary links have no additional cost, so only the edge cost
+          // applies.
+          KindedOffMeshLink::BoundaryLink { .. } => {
+            if self.nav_data.is_crossing_carved(
+              node_ref,
+              link.destination_node,
+              (link.portal.0.xy(), link.portal.1.xy()),
+            ) {
+              return None;
+            }
+            self.edge_cost(node_ref, link.destination_node)
+          }
+          KindedOffMeshLink::AnimationLink {
+            cost,
+            kind,
+            animation_link,
+            ..
+          } => {
             if !self.permitted_animation_links.is_permitted(kind) {
               return None;
             }
-            cost
+            match self.cost_filter {
+              None => cost,
+              Some(cost_filter) => cost_filter.animation_link_cost(
+                animation_link,
+                kind,
+                node_ref,
+                link.destination_node,
+                cost,
+              )?,
+            }
           }
         };
+        if !link_cost.is_finite() {
+          return None;
+        }
 
         let cost = point.distance(link.portal.0.midpoint(link.portal.1))
           * current_node_cost
@@ -227,76 +387,48 @@ impl<CS: CoordinateSystem> AStarProblem for ArchipelagoPathProblem<'_, CS> {
           PathNode::OffMeshLink(*link_id),
         ))
       }))
+      .chain(end_successors)
       .collect()
   }
 
   fn heuristic(&self, state: &Self::StateType) -> f32 {
-    let world_point = match state {
-      PathNode::Start => self.start_point,
-      PathNode::End => return 0.0,
-      PathNode::NodeEdge { node, start_edge: edge } => {
-        let island = self.nav_data.get_island(node.island_id).unwrap();
-        let edge = island.get_nav_mesh().get_edge_points(MeshEdgeRef {
-          polygon_index: node.polygon_index,
-          edge_index: *edge,
-        });
-        island.transform.apply(edge.0.midpoint(edge.1))
-      }
-      PathNode::OffMeshLink(link) => {
-        let off_mesh_link = self.nav_data.off_mesh_links.get(*link).unwrap();
-        let portal = match &off_mesh_link.kinded {
-          KindedOffMeshLink::BoundaryLink { .. } => off_mesh_link.portal,
-          KindedOffMeshLink::AnimationLink { destination_portal, .. } => {
-            *destination_portal
-          }
-        };
-        portal.0.midpoint(portal.1)
-      }
-    };
-    world_point.distance(self.end_point) * self.cheapest_type_index_cost
+    if let PathNode::End(_) = state {
+      return 0.0;
+    }
+    let (_, world_point) = self.node_and_point(state);
+    // Use the closest end so the heuristic never overestimates.
+    self
+      .ends
+      .iter()
+      .map(|(_, end_point)| FloatOrd(world_point.distance(*end_point)))
+      .min()
+      .map_or(0.0, |distance| distance.0 * self.cheapest_type_index_cost)
   }
 
   fn is_goal_state(&self, state: &Self::StateType) -> bool {
-    matches!(state, PathNode::End)
+    matches!(state, PathNode::End(_))
   }
 }
 
-/// The results of pathfinding.
-#[derive(Debug)]
-pub(crate) struct PathResult {
-  /// Statistics about the pathfinding process.
-  pub(crate) stats: PathStats,
-  /// The path if one was found.
-  pub(crate) path: Option<Path>,
-}
-
-/// Finds a path in `nav_data` from `start_node` to `end_node`. Type index costs
-/// are overriden with `override_type_index_to_cost`. Returns an `Err` if no
-/// path was found. `start_point` and `end_point` are assumed to be in the
-/// corresponding nodes, and in world space.
-pub(crate) fn find_path<CS: CoordinateSystem>(
-  nav_data: &NavigationData<CS>,
-  start_node: NodeRef,
-  start_point: Vec3,
-  end_node: NodeRef,
-  end_point: Vec3,
-  override_type_index_to_cost: &HashMap<usize, f32>,
+/// Creates the A* problem for finding a path from `start` to any of `ends`,
+/// where each is a node and a point in that node.
+#[expect(clippy::too_many_arguments)]
+fn edge_midpoints_problem<'a, CS: CoordinateSystem>(
+  nav_data: &'a NavigationData<CS>,
+  (start_node, start_point): (NodeRef, Vec3),
+  ends: Vec<(NodeRef, Vec3)>,
+  override_type_index_to_cost: &'a HashMap<usize, f32>,
   permitted_animation_links: PermittedAnimationLinks,
-) -> PathResult {
-  if !nav_data.are_nodes_connected(
-    start_node,
-    end_node,
-    permitted_animation_links.clone(),
-  ) {
-    return PathResult { stats: PathStats { explored_nodes: 0 }, path: None };
-  }
-
-  let path_problem = ArchipelagoPathProblem {
+  flag_filter: PolygonFlagFilter,
+  agent_radius: f32,
+  cost_filter: Option<&'a dyn PathCostFilter>,
+  permitted_islands: Option<&'a HashSet<IslandId>>,
+) -> ArchipelagoPathProblem<'a, CS> {
+  ArchipelagoPathProblem {
     nav_data,
     start_node,
-    end_node,
     start_point,
-    end_point,
+    ends,
     cheapest_type_index_cost: *nav_data
       .get_type_index_costs()
       .map(|(type_index, cost)| {
@@ -314,13 +446,590 @@ pub(crate) fn find_path<CS: CoordinateSystem>(
       .unwrap(),
     override_type_index_to_cost,
     permitted_animation_links,
+    flag_filter,
+    agent_radius,
+    cost_filter,
+    permitted_islands,
+  }
+}
+
+/// Finds every node in `nav_data` that can be reached from `start_point` (in
+/// `start_node`) with a cost of at most `max_cost`. Returns each node with the
+/// cheapest cost to reach it and the point (in world space) where that path
+/// enters the node. Costs are computed the same way as
+/// [`PathfindingMode::EdgeMidpoints`].
+pub(crate) fn find_reachable_nodes<CS: CoordinateSystem>(
+  nav_data: &NavigationData<CS>,
+  (start_node, start_point): (NodeRef, Vec3),
+  max_cost: f32,
+  query: &PathQuery<'_>,
+) -> HashMap<NodeRef, (f32, Vec3)> {
+  let problem = ArchipelagoPathProblem {
+    nav_data,
+    start_node,
+    start_point,
+    ends: vec![],
+    // There is no end, so there is no need for a heuristic.
+    cheapest_type_index_cost: 0.0,
+    override_type_index_to_cost: &query.override_type_index_costs,
+    permitted_animation_links: query.permitted_animation_links.clone(),
+    flag_filter: query.flag_filter,
+    agent_radius: query.agent_radius,
+    cost_filter: query.cost_filter,
+    permitted_islands: None,
   };
 
-  let path_result = astar::find_path(&path_problem);
-  let Some(astar_path) = path_result.path else {
-    return PathResult { stats: path_result.stats, path: None };
+  let mut reachable_nodes = HashMap::<NodeRef, (f32, Vec3)>::new();
+  for (state, cost) in astar::explore(&problem, max_cost) {
+    let (node_ref, point) = problem.node_and_point(&state);
+    if reachable_nodes
+      .get(&node_ref)
+      .is_some_and(|&(best_cost, _)| best_cost <= cost)
+    {
+      continue;
+    }
+    reachable_nodes.insert(node_ref, (cost, point));
+  }
+  reachable_nodes
+}
+
+/// The results of pathfinding.
+#[derive(Debug)]
+pub(crate) struct PathResult {
+  /// Statistics about the pathfinding process.
+  pub(crate) stats: PathStats,
+  /// The path if one was found.
+  pub(crate) path: Option<Path>,
+}
+
+/// A path to the closest reachable point to an unreachable target.
+#[derive(Debug)]
+pub(crate) struct PartialPath {
+  /// The path to the closest reachable point.
+  pub(crate) path: Path,
+  /// The node containing the closest reachable point.
+  pub(crate) node: NodeRef,
+  /// The closest reachable point (in world space).
+  pub(crate) point: Vec3,
+}
+
+/// Finds a path in `nav_data` from `start_node` to `end_node`. `query`
+/// determines where the path may travel and how it is costed
+/// ([`PathQuery::smoothing`] is ignored). Returns an `Err` if no path was
+/// found. `start_point` and `end_point` are assumed to be in the corresponding
+/// nodes, and in world space.
+pub(crate) fn find_path<CS: CoordinateSystem>(
+  nav_data: &NavigationData<CS>,
+  start_node: NodeRef,
+  start_point: Vec3,
+  end_node: NodeRef,
+  end_point: Vec3,
+  query: &PathQuery<'_>,
+  mode: PathfindingMode,
+) -> PathResult {
+  PathSearch::new(
+    nav_data,
+    start_node,
+    start_point,
+    end_node,
+    end_point,
+    &query.override_type_index_costs,
+    query.permitted_animation_links.clone(),
+    query.flag_filter,
+    query.agent_radius,
+    query.cost_filter,
+    mode,
+  )
+  .step(
+    nav_data,
+    &query.override_type_index_costs,
+    query.permitted_animation_links.clone(),
+    query.flag_filter,
+    query.agent_radius,
+    query.cost_filter,
+    /* max_explored_nodes= */ None,
+  )
+  .expect("The search has no limit, so it must finish.")
+}
+
+/// Finds a path in `nav_data` from `start_node` to whichever of `ends` is
+/// cheapest to reach, using a single search. Returns the index of that end and
+/// the path to it, or [`None`] if none of the ends can be reached. All points
+/// are assumed to be in their corresponding nodes, and in world space.
+/// `query` is used the same as in [`find_path`].
+pub(crate) fn find_path_to_nearest<CS: CoordinateSystem>(
+  nav_data: &NavigationData<CS>,
+  (start_node, start_point): (NodeRef, Vec3),
+  ends: &[(NodeRef, Vec3)],
+  query: &PathQuery<'_>,
+  mode: PathfindingMode,
+) -> Option<(usize, Path)> {
+  // Ignore any ends that are disconnected from the start, so the search doesn't
+  // need to explore everything to find that out.
+  let connected_end_indices = ends
+    .iter()
+    .enumerate()
+    .filter(|(_, (end_node, _))| {
+      nav_data.are_nodes_connected(
+        start_node,
+        *end_node,
+        query.permitted_animation_links.clone(),
+      )
+    })
+    .map(|(end_index, _)| end_index)
+    .collect::<Vec<_>>();
+  if connected_end_indices.is_empty() {
+    return None;
+  }
+
+  let connected_ends =
+    connected_end_indices.iter().map(|&end_index| ends[end_index]).collect();
+  let astar_path = match mode {
+    PathfindingMode::EdgeMidpoints => {
+      astar::find_path(&edge_midpoints_problem(
+        nav_data,
+        (start_node, start_point),
+        connected_ends,
+        &query.override_type_index_costs,
+        query.permitted_animation_links.clone(),
+        query.flag_filter,
+        query.agent_radius,
+        query.cost_filter,
+        /* permitted_islands= */ None,
+      ))
+      .path?
+    }
+    PathfindingMode::AnyAngle => any_angle_path_to_path_steps(
+      astar::find_path(&AnyAnglePathProblem {
+        nav_data,
+        start_node,
+        start_point,
+        ends: connected_ends,
+        override_type_index_to_cost: &query.override_type_index_costs,
+        permitted_animation_links: query.permitted_animation_links.clone(),
+        flag_filter: query.flag_filter,
+        agent_radius: query.agent_radius,
+        cost_filter: query.cost_filter,
+        permitted_islands: None,
+      })
+      .path?,
+    ),
   };
+  let Some(&PathStep::GoToEnd(end_index)) = astar_path.last() else {
+    unreachable!("Every path must finish by going to an end.");
+  };
+  let end_index = connected_end_indices[end_index];
+  Some((
+    end_index,
+    astar_path_to_path(
+      nav_data,
+      (start_node, start_point),
+      ends[end_index].1,
+      astar_path,
+    ),
+  ))
+}
+
+/// A path search in progress. This allows finding a path over several steps
+/// (e.g., over several updates), rather than all at once.
+///
+/// The navigation data, the type index costs, the permitted animation links,
+/// the flag filter, the agent radius and the cost filter must not change
+/// between steps. If they do, the search must be restarted (see
+/// [`Self::is_started_with`]).
+pub(crate) struct PathSearch {
+  /// The node the search starts from.
+  start_node: NodeRef,
+  /// The point the search starts from.
+  start_point: Vec3,
+  /// The node the search is trying to reach.
+  end_node: NodeRef,
+  /// The point the search is trying to reach.
+  end_point: Vec3,
+  /// The mode the search uses.
+  mode: PathfindingMode,
+  /// The islands that the search may travel through, as planned by the island
+  /// graph. If [`None`], all islands are permitted.
+  permitted_islands: Option<HashSet<IslandId>>,
+  /// The number of nodes explored by previous searches that were abandoned
+  /// (e.g., because the planned islands did not contain a path).
+  abandoned_explored_nodes: u32,
+  /// The animation links that the search was started with.
+  permitted_animation_links: PermittedAnimationLinks,
+  /// The flag filter that the search was started with.
+  flag_filter: PolygonFlagFilter,
+  /// The agent radius that the search was started with.
+  agent_radius: f32,
+  /// The address of the cost filter that the search was started with. This is
+  /// only used to detect when the cost filter is replaced.
+  cost_filter_address: Option<usize>,
+  /// The underlying A* search.
+  search: Search,
+}
+
+/// Computes the address of `cost_filter`, to compare its identity.
+fn cost_filter_address(
+  cost_filter: Option<&dyn PathCostFilter>,
+) -> Option<usize> {
+  cost_filter
+    .map(|cost_filter| std::ptr::from_ref(cost_filter).cast::<()>().addr())
+}
+
+/// The A* search for a [`PathSearch`].
+enum Search {
+  /// The start and end are known to be disconnected, so there is nothing to
+  /// search.
+  Disconnected,
+  /// A search for [`PathfindingMode::EdgeMidpoints`].
+  EdgeMidpoints(astar::AStarSearch<PathStep, PathNode>),
+  /// A search for [`PathfindingMode::AnyAngle`].
+  AnyAngle(astar::AStarSearch<AnyAngleStep, AnyAnglePathNode>),
+}
+
+impl PathSearch {
+  /// Starts a search in `nav_data` from `start_node` to `end_node`. See
+  /// [`find_path`] for details.
+  #[expect(clippy::too_many_arguments)]
+  pub(crate) fn new<CS: CoordinateSystem>(
+    nav_data: &NavigationData<CS>,
+    start_node: NodeRef,
+    start_point: Vec3,
+    end_node: NodeRef,
+    end_point: Vec3,
+    override_type_index_to_cost: &HashMap<usize, f32>,
+    permitted_animation_links: PermittedAnimationLinks,
+    flag_filter: PolygonFlagFilter,
+    agent_radius: f32,
+    cost_filter: Option<&dyn PathCostFilter>,
+    mode: PathfindingMode,
+  ) -> Self {
+    let mut path_search = Self {
+      start_node,
+      start_point,
+      end_node,
+      end_point,
+      mode,
+      permitted_islands: None,
+      abandoned_explored_nodes: 0,
+      permitted_animation_links: permitted_animation_links.clone(),
+      flag_filter,
+      agent_radius,
+      cost_filter_address: cost_filter_address(cost_filter),
+      search: Search::Disconnected,
+    };
+    if !nav_data.are_nodes_connected(
+      start_node,
+      end_node,
+      permitted_animation_links.clone(),
+    ) {
+      return path_search;
+    }
+
+    if let Some(island_graph) = nav_data.island_graph.as_ref() {
+      path_search.permitted_islands = island_graph.find_island_corridor(
+        nav_data,
+        (start_node, start_point),
+        (end_node, end_point),
+        permitted_animation_links.clone(),
+      );
+    }
+    path_search.start_search(
+      nav_data,
+      override_type_index_to_cost,
+      permitted_animation_links,
+      flag_filter,
+      agent_radius,
+      cost_filter,
+    );
+    path_search
+  }
+
+  /// Starts the underlying A* search from scratch.
+  fn start_search<CS: CoordinateSystem>(
+    &mut self,
+    nav_data: &NavigationData<CS>,
+    override_type_index_to_cost: &HashMap<usize, f32>,
+    permitted_animation_links: PermittedAnimationLinks,
+    flag_filter: PolygonFlagFilter,
+    agent_radius: f32,
+    cost_filter: Option<&dyn PathCostFilter>,
+  ) {
+    self.search = match self.mode {
+      PathfindingMode::EdgeMidpoints => {
+        Search::EdgeMidpoints(astar::AStarSearch::new(&edge_midpoints_problem(
+          nav_data,
+          (self.start_node, self.start_point),
+          vec![(self.end_node, self.end_point)],
+          override_type_index_to_cost,
+          permitted_animation_links,
+          flag_filter,
+          agent_radius,
+          cost_filter,
+          self.permitted_islands.as_ref(),
+        )))
+      }
+      PathfindingMode::AnyAngle => {
+        Search::AnyAngle(astar::AStarSearch::new(&AnyAnglePathProblem {
+          nav_data,
+          start_node: self.start_node,
+          start_point: self.start_point,
+          ends: vec![(self.end_node, self.end_point)],
+          override_type_index_to_cost,
+          permitted_animation_links,
+          flag_filter,
+          agent_radius,
+          cost_filter,
+          permitted_islands: self.permitted_islands.as_ref(),
+        }))
+      }
+    };
+  }
+
+  /// The node that this search is trying to reach.
+  pub(crate) fn end_node(&self) -> NodeRef {
+    self.end_node
+  }
+
+  /// Returns whether this search was started from `start_node` with the same
+  /// animation links, flag filter, agent radius and cost filter. If not, the
+  /// search must be restarted, since the path it finds would not start where
+  /// the agent is or would not respect its settings.
+  pub(crate) fn is_started_with(
+    &self,
+    start_node: NodeRef,
+    permitted_animation_links: &PermittedAnimationLinks,
+    flag_filter: PolygonFlagFilter,
+    agent_radius: f32,
+    cost_filter: Option<&dyn PathCostFilter>,
+  ) -> bool {
+    self.start_node == start_node
+      && &self.permitted_animation_links == permitted_animation_links
+      && self.flag_filter == flag_filter
+      && self.agent_radius == agent_radius
+      && self.cost_filter_address == cost_filter_address(cost_filter)
+  }
+
+  /// Moves the start and end points of the search along with their islands, if
+  /// they moved during the last update of `nav_data`. Returns false if the
+  /// search cannot be carried and must be restarted instead, since
+  /// [`PathfindingMode::AnyAngle`] searches store points in world space.
+  pub(crate) fn carry_with_islands<CS: CoordinateSystem>(
+    &mut self,
+    nav_data: &NavigationData<CS>,
+  ) -> bool {
+    if nav_data.moved_islands.is_empty() {
+      return true;
+    }
+    if matches!(self.search, Search::AnyAngle(_)) {
+      return false;
+    }
+    if let Some(start_point) = nav_data
+      .carry_point_with_island(self.start_node.island_id, self.start_point)
+    {
+      self.start_point = start_point;
+    }
+    if let Some(end_point) =
+      nav_data.carry_point_with_island(self.end_node.island_id, self.end_point)
+    {
+      self.end_point = end_point;
+    }
+    true
+  }
+
+  /// Returns the stats of the search so far.
+  pub(crate) fn stats(&self) -> PathStats {
+    let stats = match &self.search {
+      Search::Disconnected => PathStats { explored_nodes: 0 },
+      Search::EdgeMidpoints(search) => search.stats(),
+      Search::AnyAngle(search) => search.stats(),
+    };
+    PathStats {
+      explored_nodes: stats.explored_nodes + self.abandoned_explored_nodes,
+    }
+  }
+
+  /// Finds a path to the reachable point closest to the end of the search, for
+  /// when the end is unreachable. The closest point is in the explored node
+  /// with the lowest heuristic (i.e., the node whose entry point is closest to
+  /// the end). This should only be called once [`Self::step`] has finished
+  /// without finding a path. Unless the search was already a full
+  /// [`PathfindingMode::EdgeMidpoints`] search, this searches for the closest
+  /// node from scratch.
+  pub(crate) fn find_partial_path<CS: CoordinateSystem>(
+    &self,
+    nav_data: &NavigationData<CS>,
+    override_type_index_to_cost: &HashMap<usize, f32>,
+    permitted_animation_links: PermittedAnimationLinks,
+    flag_filter: PolygonFlagFilter,
+    agent_radius: f32,
+    cost_filter: Option<&dyn PathCostFilter>,
+  ) -> Option<PartialPath> {
+    let problem = edge_midpoints_problem(
+      nav_data,
+      (self.start_node, self.start_point),
+      vec![(self.end_node, self.end_point)],
+      override_type_index_to_cost,
+      permitted_animation_links,
+      flag_filter,
+      agent_radius,
+      cost_filter,
+      /* permitted_islands= */ None,
+    );
+    let new_search;
+    let search = match &self.search {
+      Search::EdgeMidpoints(search) if self.permitted_islands.is_none() => {
+        search
+      }
+      _ => {
+        let mut search = astar::AStarSearch::new(&problem);
+        search.step(&problem, /* max_explored_nodes= */ None);
+        new_search = search;
+        &new_search
+      }
+    };
+
+    let (astar_path, state) = search.closest_path()?;
+    let (node, _) = problem.node_and_point(state);
+    let island = nav_data.get_island(node.island_id).unwrap();
+    let point = island.transform.apply(island.nav_mesh.closest_point_on_node(
+      island.transform.apply_inverse(self.end_point),
+      node.polygon_index,
+    ));
+    Some(PartialPath {
+      path: astar_path_to_path(
+        nav_data,
+        (self.start_node, self.start_point),
+        point,
+        astar_path,
+      ),
+      node,
+      point,
+    })
+  }
+
+  /// Continues the search, exploring at most `max_explored_nodes` nodes (or
+  /// until the search finishes if [`None`]). Returns [`None`] if the search has
+  /// not finished yet.
+  #[expect(clippy::too_many_arguments)]
+  pub(crate) fn step<CS: CoordinateSystem>(
+    &mut self,
+    nav_data: &NavigationData<CS>,
+    override_type_index_to_cost: &HashMap<usize, f32>,
+    permitted_animation_links: PermittedAnimationLinks,
+    flag_filter: PolygonFlagFilter,
+    agent_radius: f32,
+    cost_filter: Option<&dyn PathCostFilter>,
+    max_explored_nodes: Option<u32>,
+  ) -> Option<PathResult> {
+    let path_result = match &mut self.search {
+      Search::Disconnected => {
+        return Some(PathResult {
+          stats: PathStats { explored_nodes: 0 },
+          path: None,
+        });
+      }
+      Search::EdgeMidpoints(search) => {
+        let problem = edge_midpoints_problem(
+          nav_data,
+          (self.start_node, self.start_point),
+          vec![(self.end_node, self.end_point)],
+          override_type_index_to_cost,
+          permitted_animation_links.clone(),
+          flag_filter,
+          agent_radius,
+          cost_filter,
+          self.permitted_islands.as_ref(),
+        );
+        search.step(&problem, max_explored_nodes)?
+      }
+      Search::AnyAngle(search) => {
+        let problem = AnyAnglePathProblem {
+          nav_data,
+          start_node: self.start_node,
+          start_point: self.start_point,
+          ends: vec![(self.end_node, self.end_point)],
+          override_type_index_to_cost,
+          permitted_animation_links: permitted_animation_links.clone(),
+          flag_filter,
+          agent_radius,
+          cost_filter,
+          permitted_islands: self.permitted_islands.as_ref(),
+        };
+        let path_result = search.step(&problem, max_explored_nodes)?;
+        astar::PathResult {
+          stats: path_result.stats,
+          path: path_result.path.map(any_angle_path_to_path_steps),
+        }
+      }
+    };
+    let stats = PathStats {
+      explored_nodes: path_result.stats.explored_nodes
+        + self.abandoned_explored_nodes,
+    };
+    let Some(astar_path) = path_result.path else {
+      if self.permitted_islands.is_none() {
+        return Some(PathResult { stats, path: None });
+      }
+      // The island graph only estimates costs (and ignores overridden costs),
+      // so the planned islands may not contain a path. Fall back to searching
+      // all islands.
+      self.permitted_islands = None;
+      self.abandoned_explored_nodes = stats.explored_nodes;
+      self.start_search(
+        nav_data,
+        override_type_index_to_cost,
+        permitted_animation_links.clone(),
+        flag_filter,
+        agent_radius,
+        cost_filter,
+      );
+      let max_explored_nodes = max_explored_nodes.map(|max_explored_nodes| {
+        max_explored_nodes.saturating_sub(stats.explored_nodes).max(1)
+      });
+      return self.step(
+        nav_data,
+        override_type_index_to_cost,
+        permitted_animation_links,
+        flag_filter,
+        agent_radius,
+        cost_filter,
+        max_explored_nodes,
+      );
+    };
+    Some(PathResult {
+      stats,
+      path: Some(astar_path_to_path(
+        nav_data,
+        (self.start_node, self.start_point),
+        self.end_point,
+        astar_path,
+      )),
+    })
+  }
+}
 
+/// Converts the actions of an any-angle A* search into the equivalent actions of
+/// an edge midpoint search.
+fn any_angle_path_to_path_steps(path: Vec<AnyAngleStep>) -> Vec<PathStep> {
+  // Turning at corners doesn't change the corridor, so we can ignore those
+  // steps.
+  path
+    .into_iter()
+    .filter_map(|step| match step {
+      AnyAngleStep::Portal(step) => Some(step),
+      AnyAngleStep::TurnAtCorner => None,
+    })
+    .collect()
+}
+
+/// Converts the actions of an A* search from `start_node` to `end_point` into a
+/// [`Path`].
+fn astar_path_to_path<CS: CoordinateSystem>(
+  nav_data: &NavigationData<CS>,
+  (start_node, start_point): (NodeRef, Vec3),
+  end_point: Vec3,
+  astar_path: Vec<PathStep>,
+) -> Path {
   let mut output_path = Path {
     island_segments: vec![],
     off_mesh_link_segments: vec![],
@@ -340,7 +1049,7 @@ pub(crate) fn find_path<CS: CoordinateSystem>(
     let previous_node = *last_segment.corridor.last().unwrap();
 
     match path_step {
-      PathStep::GoToEnd => {
+      PathStep::GoToEnd(_) => {
         // Do nothing. The previous step already inserted the end node, so
         // there's nothing left to do. We could possibly assert here that this
         // is the last step, but it's not easy to do that, so no need.
@@ -378,7 +1087,7 @@ pub(crate) fn find_path<CS: CoordinateSystem>(
     }
   }
 
-  PathResult { stats: path_result.stats, path: Some(output_path) }
+  output_path
 }
 
 #[cfg(test)]
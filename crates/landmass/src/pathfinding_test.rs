@@ -4,12 +4,12 @@ use std::{
   sync::Arc,
 };
 
-use glam::{Vec2, Vec3};
+use glam::{Quat, Vec2, Vec3};
 
 use crate::{
   Archipelago, ArchipelagoOptions, CoordinateSystem, FromAgentRadius, Island,
-  Transform,
-  agent::PermittedAnimationLinks,
+  PathCostFilter, PathQuery, PathfindingMode, Transform,
+  agent::{PermittedAnimationLinks, PolygonFlagFilter},
   coords::{XY, XYZ},
   link::{AnimationLink, AnimationLinkId},
   nav_data::{KindedOffMeshLink, NavigationData, NodeRef, OffMeshLinkId},
@@ -43,8 +43,11 @@ fn find_path_between_nodes<CS: CoordinateSystem>(
       start_point,
       end_node,
       end_point,
-      override_type_index_to_cost,
-      PermittedAnimationLinks::All,
+      &PathQuery {
+        override_type_index_costs: override_type_index_to_cost.clone(),
+        ..Default::default()
+      },
+      PathfindingMode::EdgeMidpoints,
     ),
   )
 }
@@ -76,6 +79,7 @@ fn finds_path_in_archipelago() {
       vec![10, 4, 14, 13],
     ],
     polygon_type_indices: vec![0, 0, 0, 0],
+    polygon_flags: None,
     height_mesh: None,
   }
   .validate()
@@ -84,7 +88,7 @@ fn finds_path_in_archipelago() {
   let mut archipelago =
     Archipelago::<XYZ>::new(ArchipelagoOptions::from_agent_radius(0.5));
   let island_id = archipelago.add_island(Island::new(
-    Transform { translation: Vec3::ZERO, rotation: 0.0 },
+    Transform { translation: Vec3::ZERO, rotation: Quat::IDENTITY, scale: 1.0 },
     Arc::new(nav_mesh),
   ));
 
@@ -181,6 +185,7 @@ fn finds_paths_on_two_islands() {
       vec![10, 4, 14, 13],
     ],
     polygon_type_indices: vec![0, 0, 0, 0],
+    polygon_flags: None,
     height_mesh: None,
   }
   .validate()
@@ -190,12 +195,16 @@ fn finds_paths_on_two_islands() {
   let mut archipelago =
     Archipelago::<XYZ>::new(ArchipelagoOptions::from_agent_radius(0.5));
   let island_id_1 = archipelago.add_island(Island::new(
-    Transform { translation: Vec3::ZERO, rotation: 0.0 },
+    Transform { translation: Vec3::ZERO, rotation: Quat::IDENTITY, scale: 1.0 },
     Arc::clone(&nav_mesh),
   ));
 
   let island_id_2 = archipelago.add_island(Island::new(
-    Transform { translation: Vec3::new(6.0, 0.0, 0.0), rotation: PI * -0.5 },
+    Transform {
+      translation: Vec3::new(6.0, 0.0, 0.0),
+      rotation: Quat::from_rotation_z(PI * -0.5),
+      scale: 1.0,
+    },
     Arc::clone(&nav_mesh),
   ));
 
@@ -271,6 +280,7 @@ fn no_path_between_disconnected_islands() {
       vec![10, 4, 14, 13],
     ],
     polygon_type_indices: vec![0, 0, 0, 0],
+    polygon_flags: None,
     height_mesh: None,
   }
   .validate()
@@ -280,12 +290,16 @@ fn no_path_between_disconnected_islands() {
   let mut archipelago =
     Archipelago::<XYZ>::new(ArchipelagoOptions::from_agent_radius(0.5));
   let island_id_1 = archipelago.add_island(Island::new(
-    Transform { translation: Vec3::ZERO, rotation: 0.0 },
+    Transform { translation: Vec3::ZERO, rotation: Quat::IDENTITY, scale: 1.0 },
     Arc::clone(&nav_mesh),
   ));
 
   let island_id_2 = archipelago.add_island(Island::new(
-    Transform { translation: Vec3::new(6.0, 0.0, 0.0), rotation: PI * -0.5 },
+    Transform {
+      translation: Vec3::new(6.0, 0.0, 0.0),
+      rotation: Quat::from_rotation_z(PI * -0.5),
+      scale: 1.0,
+    },
     Arc::clone(&nav_mesh),
   ));
 
@@ -328,6 +342,7 @@ fn find_path_across_connected_islands() {
       ],
       polygons: vec![vec![0, 1, 2, 3]],
       polygon_type_indices: vec![0],
+      polygon_flags: None,
       height_mesh: None,
     }
     .validate()
@@ -338,24 +353,40 @@ fn find_path_across_connected_islands() {
     Archipelago::<XYZ>::new(ArchipelagoOptions::from_agent_radius(0.5));
 
   let island_id_1 = archipelago.add_island(Island::new(
-    Transform { rotation: 0.0, translation: Vec3::ZERO },
+    Transform { rotation: Quat::IDENTITY, scale: 1.0, translation: Vec3::ZERO },
     Arc::clone(&nav_mesh),
   ));
   let island_id_2 = archipelago.add_island(Island::new(
-    Transform { rotation: 0.0, translation: Vec3::new(1.0, 0.0, 0.0) },
+    Transform {
+      rotation: Quat::IDENTITY,
+      scale: 1.0,
+      translation: Vec3::new(1.0, 0.0, 0.0),
+    },
     Arc::clone(&nav_mesh),
   ));
   // island_id_3 is unused.
   archipelago.add_island(Island::new(
-    Transform { rotation: 0.0, translation: Vec3::new(1.0, -1.0, 0.0) },
+    Transform {
+      rotation: Quat::IDENTITY,
+      scale: 1.0,
+      translation: Vec3::new(1.0, -1.0, 0.0),
+    },
     Arc::clone(&nav_mesh),
   ));
   let island_id_4 = archipelago.add_island(Island::new(
-    Transform { rotation: 0.0, translation: Vec3::new(1.0, 1.0, 0.0) },
+    Transform {
+      rotation: Quat::IDENTITY,
+      scale: 1.0,
+      translation: Vec3::new(1.0, 1.0, 0.0),
+    },
     Arc::clone(&nav_mesh),
   ));
   let island_id_5 = archipelago.add_island(Island::new(
-    Transform { rotation: 0.0, translation: Vec3::new(1.0, 2.0, 0.0) },
+    Transform {
+      rotation: Quat::IDENTITY,
+      scale: 1.0,
+      translation: Vec3::new(1.0, 2.0, 0.0),
+    },
     Arc::clone(&nav_mesh),
   ));
 
@@ -440,6 +471,7 @@ fn finds_path_across_different_islands() {
       ],
       polygons: vec![vec![0, 1, 2, 3]],
       polygon_type_indices: vec![0],
+      polygon_flags: None,
       height_mesh: None,
     }
     .validate()
@@ -457,6 +489,7 @@ fn finds_path_across_different_islands() {
       ],
       polygons: vec![vec![0, 1, 2, 3], vec![2, 1, 4, 5]],
       polygon_type_indices: vec![0, 0],
+      polygon_flags: None,
       height_mesh: None,
     }
     .validate()
@@ -467,11 +500,15 @@ fn finds_path_across_different_islands() {
     Archipelago::<XYZ>::new(ArchipelagoOptions::from_agent_radius(0.5));
 
   let island_id_1 = archipelago.add_island(Island::new(
-    Transform { rotation: 0.0, translation: Vec3::ZERO },
+    Transform { rotation: Quat::IDENTITY, scale: 1.0, translation: Vec3::ZERO },
     nav_mesh_1,
   ));
   let island_id_2 = archipelago.add_island(Island::new(
-    Transform { rotation: 0.0, translation: Vec3::new(1.0, 0.0, 0.0) },
+    Transform {
+      rotation: Quat::IDENTITY,
+      scale: 1.0,
+      translation: Vec3::new(1.0, 0.0, 0.0),
+    },
     nav_mesh_2,
   ));
 
@@ -534,6 +571,7 @@ fn aborts_early_for_unconnected_regions() {
       ],
       polygons: vec![vec![0, 1, 2, 3]],
       polygon_type_indices: vec![0],
+      polygon_flags: None,
       height_mesh: None,
     }
     .validate()
@@ -544,15 +582,23 @@ fn aborts_early_for_unconnected_regions() {
     Archipelago::<XYZ>::new(ArchipelagoOptions::from_agent_radius(0.5));
 
   let island_id_1 = archipelago.add_island(Island::new(
-    Transform { translation: Vec3::ZERO, rotation: 0.0 },
+    Transform { translation: Vec3::ZERO, rotation: Quat::IDENTITY, scale: 1.0 },
     nav_mesh.clone(),
   ));
   let island_id_2 = archipelago.add_island(Island::new(
-    Transform { translation: Vec3::new(2.0, 0.0, 0.0), rotation: 0.0 },
+    Transform {
+      translation: Vec3::new(2.0, 0.0, 0.0),
+      rotation: Quat::IDENTITY,
+      scale: 1.0,
+    },
     nav_mesh.clone(),
   ));
   let island_id_3 = archipelago.add_island(Island::new(
-    Transform { translation: Vec3::new(1.5, 2.0, 0.0), rotation: PI * 0.5 },
+    Transform {
+      translation: Vec3::new(1.5, 2.0, 0.0),
+      rotation: Quat::from_rotation_z(PI * 0.5),
+      scale: 1.0,
+    },
     nav_mesh.clone(),
   ));
 
@@ -635,6 +681,7 @@ fn detour_for_high_cost_path() {
         vec![3, 2, 12, 14],
       ],
       polygon_type_indices: vec![0, 0, 0, 0, 0, 0, 0, 1],
+      polygon_flags: None,
       height_mesh: None,
     }
     .validate()
@@ -668,6 +715,327 @@ fn detour_for_high_cost_path() {
   );
 }
 
+#[test]
+fn detour_for_flagged_polygons() {
+  let mut archipelago =
+    Archipelago::<XY>::new(ArchipelagoOptions::from_agent_radius(0.5));
+
+  // Same as `detour_for_high_cost_path`, except the bridge is flagged instead
+  // of having a high cost.
+  let nav_mesh = Arc::new(
+    NavigationMesh {
+      vertices: vec![
+        Vec2::new(0.0, 0.0),
+        Vec2::new(1.0, 0.0),
+        Vec2::new(1.0, 1.0),
+        Vec2::new(0.0, 1.0),
+        Vec2::new(2.0, 0.0),
+        Vec2::new(2.0, 1.0),
+        Vec2::new(3.0, 0.0),
+        Vec2::new(3.0, 1.0),
+        Vec2::new(2.0, 2.0),
+        Vec2::new(3.0, 2.0),
+        Vec2::new(2.0, 3.0),
+        Vec2::new(3.0, 3.0),
+        Vec2::new(1.0, 2.0),
+        Vec2::new(1.0, 3.0),
+        Vec2::new(0.0, 2.0),
+        Vec2::new(0.0, 3.0),
+      ],
+      polygons: vec![
+        vec![0, 1, 2, 3],
+        vec![2, 1, 4, 5],
+        vec![5, 4, 6, 7],
+        vec![5, 7, 9, 8],
+        vec![8, 9, 11, 10],
+        vec![8, 10, 13, 12],
+        vec![12, 13, 15, 14],
+        // The flagged bridge.
+        vec![3, 2, 12, 14],
+      ],
+      polygon_type_indices: vec![0; 8],
+      polygon_flags: Some(vec![0, 0, 0, 0, 0, 0, 0, 0b10]),
+      height_mesh: None,
+    }
+    .validate()
+    .expect("nav mesh is valid"),
+  );
+
+  let island_id =
+    archipelago.add_island(Island::new(Transform::default(), nav_mesh));
+
+  let start_point = Vec3::new(0.5, 0.5, 0.0);
+  let end_point = Vec3::new(0.5, 2.5, 0.0);
+  let find_path_with_flag_filter = |flag_filter| {
+    find_path(
+      &archipelago.nav_data,
+      NodeRef { island_id, polygon_index: 0 },
+      start_point,
+      NodeRef { island_id, polygon_index: 6 },
+      end_point,
+      &PathQuery { flag_filter, ..Default::default() },
+      PathfindingMode::EdgeMidpoints,
+    )
+    .path
+  };
+
+  let bridge_path = Some(Path {
+    island_segments: vec![IslandSegment {
+      island_id,
+      corridor: vec![0, 7, 6],
+      portal_edge_index: vec![2, 2],
+    }],
+    off_mesh_link_segments: vec![],
+    start_point,
+    end_point,
+  });
+  let detour_path = Some(Path {
+    island_segments: vec![IslandSegment {
+      island_id,
+      corridor: vec![0, 1, 2, 3, 4, 5, 6],
+      portal_edge_index: vec![1, 2, 3, 2, 3, 2],
+    }],
+    off_mesh_link_segments: vec![],
+    start_point,
+    end_point,
+  });
+
+  assert_eq!(
+    find_path_with_flag_filter(PolygonFlagFilter::default()),
+    bridge_path
+  );
+  assert_eq!(
+    find_path_with_flag_filter(PolygonFlagFilter {
+      include_flags: !0b10,
+      exclude_flags: 0,
+    }),
+    detour_path
+  );
+  assert_eq!(
+    find_path_with_flag_filter(PolygonFlagFilter {
+      include_flags: u32::MAX,
+      exclude_flags: 0b10,
+    }),
+    detour_path
+  );
+  // Unrelated flags don't affect the path.
+  assert_eq!(
+    find_path_with_flag_filter(PolygonFlagFilter {
+      include_flags: 0b10,
+      exclude_flags: 0b01,
+    }),
+    bridge_path
+  );
+}
+
+#[test]
+fn detour_for_gap_narrower_than_agent() {
+  let mut archipelago =
+    Archipelago::<XY>::new(ArchipelagoOptions::from_agent_radius(0.5));
+
+  // The start and end are connected by a narrow gap (between polygons 1 and
+  // 2), and by a wide detour above it.
+  let nav_mesh = Arc::new(
+    NavigationMesh {
+      vertices: vec![
+        Vec2::new(0.0, 0.0),
+        Vec2::new(1.0, 0.0),
+        Vec2::new(1.0, 1.0),
+        Vec2::new(0.0, 1.0),
+        Vec2::new(2.0, 0.4),
+        Vec2::new(2.0, 0.6),
+        Vec2::new(3.0, 0.0),
+        Vec2::new(3.0, 1.0),
+        Vec2::new(4.0, 0.0),
+        Vec2::new(4.0, 1.0),
+        Vec2::new(1.0, 2.0),
+        Vec2::new(3.0, 2.0),
+        Vec2::new(4.0, 2.0),
+        Vec2::new(0.0, 2.0),
+      ],
+      polygons: vec![
+        vec![0, 1, 2, 3],
+        vec![1, 4, 5, 2],
+        vec![4, 6, 7, 5],
+        vec![6, 8, 9, 7],
+        vec![3, 2, 10, 13],
+        vec![2, 7, 11, 10],
+        vec![7, 9, 12, 11],
+      ],
+      polygon_type_indices: vec![0; 7],
+      polygon_flags: None,
+      height_mesh: None,
+    }
+    .validate()
+    .expect("nav mesh is valid"),
+  );
+
+  let island_id =
+    archipelago.add_island(Island::new(Transform::default(), nav_mesh));
+
+  let start_point = Vec3::new(0.5, 0.5, 0.0);
+  let end_point = Vec3::new(3.5, 0.5, 0.0);
+  let find_path_with_agent_radius = |agent_radius| {
+    find_path(
+      &archipelago.nav_data,
+      NodeRef { island_id, polygon_index: 0 },
+      start_point,
+      NodeRef { island_id, polygon_index: 3 },
+      end_point,
+      &PathQuery { agent_radius, ..Default::default() },
+      PathfindingMode::EdgeMidpoints,
+    )
+    .path
+  };
+
+  let gap_path = Some(Path {
+    island_segments: vec![IslandSegment {
+      island_id,
+      corridor: vec![0, 1, 2, 3],
+      portal_edge_index: vec![1, 1, 1],
+    }],
+    off_mesh_link_segments: vec![],
+    start_point,
+    end_point,
+  });
+  let detour_path = Some(Path {
+    island_segments: vec![IslandSegment {
+      island_id,
+      corridor: vec![0, 4, 5, 6, 3],
+      portal_edge_index: vec![2, 1, 1, 0],
+    }],
+    off_mesh_link_segments: vec![],
+    start_point,
+    end_point,
+  });
+
+  assert_eq!(find_path_with_agent_radius(0.0), gap_path);
+  assert_eq!(find_path_with_agent_radius(0.05), gap_path);
+  assert_eq!(find_path_with_agent_radius(0.25), detour_path);
+  // Nothing is wide enough for a huge agent.
+  assert_eq!(find_path_with_agent_radius(1.0), None);
+}
+
+#[test]
+fn rejects_pinch_between_wall_vertices_narrower_than_agent() {
+  let mut archipelago =
+    Archipelago::<XY>::new(ArchipelagoOptions::from_agent_radius(0.5));
+
+  // Two rooms joined by a 0.2 wide gap between (4.9, 5) and (5.1, 5). The
+  // triangles on either side of the gap only touch the walls at those two
+  // vertices, so none of their edges are boundary edges.
+  let nav_mesh = NavigationMesh {
+    vertices: vec![
+      Vec2::new(0.0, 0.0),
+      Vec2::new(10.0, 0.0),
+      Vec2::new(10.0, 5.0),
+      Vec2::new(5.1, 5.0),
+      Vec2::new(4.9, 5.0),
+      Vec2::new(0.0, 5.0),
+      Vec2::new(5.0, 2.0),
+      Vec2::new(10.0, 6.0),
+      Vec2::new(10.0, 10.0),
+      Vec2::new(0.0, 10.0),
+      Vec2::new(0.0, 6.0),
+      Vec2::new(5.0, 8.0),
+    ],
+    polygons: vec![
+      vec![0, 1, 6],
+      vec![1, 2, 3, 6],
+      vec![6, 3, 4],
+      vec![0, 6, 4, 5],
+      vec![4, 3, 11],
+      vec![3, 7, 8, 11],
+      vec![11, 8, 9],
+      vec![4, 11, 9, 10],
+    ],
+    polygon_type_indices: vec![0; 8],
+    polygon_flags: None,
+    height_mesh: None,
+  }
+  .validate()
+  .expect("nav mesh is valid");
+
+  let island_id = archipelago
+    .add_island(Island::new(Transform::default(), Arc::new(nav_mesh)));
+
+  let find_path_with_agent_radius = |agent_radius, mode| {
+    find_path(
+      &archipelago.nav_data,
+      NodeRef { island_id, polygon_index: 3 },
+      Vec3::new(2.0, 2.0, 0.0),
+      NodeRef { island_id, polygon_index: 7 },
+      Vec3::new(2.0, 8.0, 0.0),
+      &PathQuery { agent_radius, ..Default::default() },
+      mode,
+    )
+    .path
+    .is_some()
+  };
+
+  for mode in [PathfindingMode::EdgeMidpoints, PathfindingMode::AnyAngle] {
+    assert!(find_path_with_agent_radius(0.05, mode), "{mode:?}");
+    assert!(!find_path_with_agent_radius(1.0, mode), "{mode:?}");
+  }
+}
+
+#[test]
+fn node_clearance_does_not_limit_path_through_wide_portals() {
+  let mut archipelago =
+    Archipelago::<XY>::new(ArchipelagoOptions::from_agent_radius(0.5));
+
+  // +-+-+-+
+  // |S| |E|
+  // +-+-+-+
+  let mut nav_mesh = NavigationMesh {
+    vertices: vec![
+      Vec2::new(0.0, 0.0),
+      Vec2::new(1.0, 0.0),
+      Vec2::new(2.0, 0.0),
+      Vec2::new(3.0, 0.0),
+      Vec2::new(0.0, 1.0),
+      Vec2::new(1.0, 1.0),
+      Vec2::new(2.0, 1.0),
+      Vec2::new(3.0, 1.0),
+    ],
+    polygons: vec![vec![0, 1, 5, 4], vec![1, 2, 6, 5], vec![2, 3, 7, 6]],
+    polygon_type_indices: vec![0; 3],
+    polygon_flags: None,
+    height_mesh: None,
+  }
+  .validate()
+  .expect("nav mesh is valid");
+  // Pretend the middle node is narrower than its portals. Only the portals
+  // limit which agents can cross it.
+  nav_mesh.polygons[1].clearance = 0.5;
+
+  let island_id = archipelago.add_island(Island::new(
+    Transform { scale: 2.0, ..Default::default() },
+    Arc::new(nav_mesh),
+  ));
+
+  let find_path_with_agent_radius = |agent_radius, mode| {
+    find_path(
+      &archipelago.nav_data,
+      NodeRef { island_id, polygon_index: 0 },
+      Vec3::new(1.0, 1.0, 0.0),
+      NodeRef { island_id, polygon_index: 2 },
+      Vec3::new(5.0, 1.0, 0.0),
+      &PathQuery { agent_radius, ..Default::default() },
+      mode,
+    )
+    .path
+    .is_some()
+  };
+
+  for mode in [PathfindingMode::EdgeMidpoints, PathfindingMode::AnyAngle] {
+    assert!(find_path_with_agent_radius(0.55, mode));
+    // The portal widths are scaled with the island.
+    assert!(find_path_with_agent_radius(0.95, mode));
+    assert!(!find_path_with_agent_radius(1.05, mode));
+  }
+}
+
 #[test]
 fn detour_for_high_cost_path_across_boundary_links() {
   let mut archipelago =
@@ -689,6 +1057,7 @@ fn detour_for_high_cost_path_across_boundary_links() {
       ],
       polygons: vec![vec![0, 1, 2, 3], vec![2, 1, 4, 5], vec![5, 4, 6, 7]],
       polygon_type_indices: vec![0, 0, 0],
+      polygon_flags: None,
       height_mesh: None,
     }
     .validate()
@@ -723,6 +1092,7 @@ fn detour_for_high_cost_path_across_boundary_links() {
         vec![6, 4, 10, 11],
       ],
       polygon_type_indices: vec![0, 0, 0, 1, 0],
+      polygon_flags: None,
       height_mesh: None,
     }
     .validate()
@@ -822,6 +1192,7 @@ fn fast_path_not_ignored_by_heuristic() {
         vec![3, 2, 12, 14],
       ],
       polygon_type_indices: vec![1, 0, 0, 0, 0, 1, 1, 1],
+      polygon_flags: None,
       height_mesh: None,
     }
     .validate()
@@ -876,6 +1247,7 @@ fn infinite_or_nan_cost_cannot_find_path_between_nodes() {
       ],
       polygons: vec![vec![0, 1, 2, 3], vec![2, 1, 4, 5], vec![5, 4, 6, 7]],
       polygon_type_indices: vec![0, 1, 0],
+      polygon_flags: None,
       height_mesh: None,
     }
     .validate()
@@ -956,6 +1328,7 @@ fn detour_for_overridden_high_cost_path() {
         vec![3, 2, 12, 14],
       ],
       polygon_type_indices: vec![0, 0, 0, 0, 0, 0, 0, 1],
+      polygon_flags: None,
       height_mesh: None,
     }
     .validate()
@@ -1044,6 +1417,7 @@ fn big_node_does_not_skew_pathing() {
         vec![4, 5, 9, 8, 17, 16],
       ],
       polygon_type_indices: vec![0, 0, 0, 0, 0, 0, 0, 0],
+      polygon_flags: None,
       height_mesh: None,
     }
     .validate()
@@ -1131,6 +1505,7 @@ fn start_and_end_point_influences_path() {
         vec![7, 8, 13, 12],
       ],
       polygon_type_indices: vec![0, 0, 0, 0, 0, 0],
+      polygon_flags: None,
       height_mesh: None,
     }
     .validate()
@@ -1149,8 +1524,8 @@ fn start_and_end_point_influences_path() {
     start_point,
     NodeRef { island_id, polygon_index: 3 },
     end_point,
-    &HashMap::default(),
-    PermittedAnimationLinks::All,
+    &PathQuery::default(),
+    PathfindingMode::EdgeMidpoints,
   );
 
   assert_eq!(
@@ -1223,6 +1598,7 @@ fn animation_link_is_used() {
         vec![6, 7, 10, 9],
       ],
       polygon_type_indices: vec![0; 5],
+      polygon_flags: None,
       height_mesh: None,
     }
     .validate()
@@ -1253,8 +1629,8 @@ fn animation_link_is_used() {
     start_point,
     NodeRef { island_id, polygon_index: 4 },
     end_point,
-    &HashMap::default(),
-    PermittedAnimationLinks::All,
+    &PathQuery::default(),
+    PathfindingMode::EdgeMidpoints,
   );
 
   assert_eq!(
@@ -1283,6 +1659,89 @@ fn animation_link_is_used() {
   );
 }
 
+#[test]
+fn animation_link_narrower_than_agent_is_not_used() {
+  let mut archipelago =
+    Archipelago::<XY>::new(ArchipelagoOptions::from_agent_radius(0.5));
+
+  // Same as `animation_link_is_used`, except the animation link is only half
+  // a unit wide.
+  let nav_mesh = Arc::new(
+    NavigationMesh {
+      vertices: vec![
+        Vec2::new(0.0, 0.0),
+        Vec2::new(2.0, 0.0),
+        Vec2::new(3.0, 0.0),
+        Vec2::new(0.0, 1.0),
+        Vec2::new(2.0, 1.0),
+        Vec2::new(3.0, 1.0),
+        Vec2::new(0.0, 2.0),
+        Vec2::new(2.0, 2.0),
+        Vec2::new(3.0, 2.0),
+        Vec2::new(0.0, 3.0),
+        Vec2::new(2.0, 3.0),
+        Vec2::new(3.0, 3.0),
+      ],
+      polygons: vec![
+        vec![0, 1, 4, 3],
+        vec![1, 2, 5, 4],
+        vec![4, 5, 8, 7],
+        vec![7, 8, 11, 10],
+        vec![6, 7, 10, 9],
+      ],
+      polygon_type_indices: vec![0; 5],
+      polygon_flags: None,
+      height_mesh: None,
+    }
+    .validate()
+    .expect("nav mesh is valid"),
+  );
+
+  let island_id =
+    archipelago.add_island(Island::new(Transform::default(), nav_mesh));
+
+  archipelago.add_animation_link(AnimationLink {
+    start_edge: (Vec2::new(0.0, 1.0), Vec2::new(0.5, 1.0)),
+    end_edge: (Vec2::new(0.0, 2.0), Vec2::new(0.5, 2.0)),
+    cost: 1.0,
+    kind: 0,
+    bidirectional: false,
+  });
+  archipelago.update(1.0);
+
+  let start_point = Vec3::new(0.5, 0.5, 0.0);
+  let end_point = Vec3::new(0.5, 2.5, 0.0);
+  let find_path_with_agent_radius = |agent_radius| {
+    find_path(
+      &archipelago.nav_data,
+      NodeRef { island_id, polygon_index: 0 },
+      start_point,
+      NodeRef { island_id, polygon_index: 4 },
+      end_point,
+      &PathQuery { agent_radius, ..Default::default() },
+      PathfindingMode::EdgeMidpoints,
+    )
+    .path
+  };
+
+  let link_path = find_path_with_agent_radius(0.2).unwrap();
+  assert_eq!(link_path.off_mesh_link_segments.len(), 1);
+
+  assert_eq!(
+    find_path_with_agent_radius(0.4),
+    Some(Path {
+      island_segments: vec![IslandSegment {
+        island_id,
+        corridor: vec![0, 1, 2, 3, 4],
+        portal_edge_index: vec![1, 2, 2, 3],
+      }],
+      off_mesh_link_segments: vec![],
+      start_point,
+      end_point,
+    })
+  );
+}
+
 #[test]
 fn animation_link_is_used_if_cheaper() {
   let mut archipelago =
@@ -1323,6 +1782,7 @@ fn animation_link_is_used_if_cheaper() {
         vec![6, 7, 10, 9],
       ],
       polygon_type_indices: vec![0; 5],
+      polygon_flags: None,
       height_mesh: None,
     }
     .validate()
@@ -1355,8 +1815,8 @@ fn animation_link_is_used_if_cheaper() {
     start_point,
     NodeRef { island_id, polygon_index: 4 },
     end_point,
-    &HashMap::default(),
-    PermittedAnimationLinks::All,
+    &PathQuery::default(),
+    PathfindingMode::EdgeMidpoints,
   );
 
   assert_eq!(
@@ -1392,8 +1852,8 @@ fn animation_link_is_used_if_cheaper() {
     start_point,
     NodeRef { island_id, polygon_index: 4 },
     end_point,
-    &HashMap::default(),
-    PermittedAnimationLinks::All,
+    &PathQuery::default(),
+    PathfindingMode::EdgeMidpoints,
   );
 
   assert_eq!(
@@ -1461,6 +1921,7 @@ fn animation_link_is_not_used_if_not_permitted() {
         vec![6, 7, 10, 9],
       ],
       polygon_type_indices: vec![0; 5],
+      polygon_flags: None,
       height_mesh: None,
     }
     .validate()
@@ -1489,8 +1950,13 @@ fn animation_link_is_not_used_if_not_permitted() {
     start_point,
     NodeRef { island_id, polygon_index: 4 },
     end_point,
-    &HashMap::default(),
-    PermittedAnimationLinks::Kinds(Arc::new(HashSet::from([]))),
+    &PathQuery {
+      permitted_animation_links: PermittedAnimationLinks::Kinds(Arc::new(
+        HashSet::from([]),
+      )),
+      ..Default::default()
+    },
+    PathfindingMode::EdgeMidpoints,
   );
 
   // Despite there being an animation link that could take us all the way to the
@@ -1509,3 +1975,170 @@ fn animation_link_is_not_used_if_not_permitted() {
     })
   );
 }
+
+#[test]
+fn cost_filter_changes_path() {
+  let mut archipelago =
+    Archipelago::<XY>::new(ArchipelagoOptions::from_agent_radius(0.5));
+
+  // Same as `animation_link_is_used_if_cheaper`. Without a cost filter, the
+  // path takes the nav mesh.
+  //
+  // +----+-+
+  // |EXXX|X|
+  // +----+-+
+  //  L   |X|
+  // +----+-+
+  // |XXXX|S|
+  // +----+-+
+  let nav_mesh = Arc::new(
+    NavigationMesh {
+      vertices: vec![
+        Vec2::new(0.0, 0.0),
+        Vec2::new(2.0, 0.0),
+        Vec2::new(3.0, 0.0),
+        Vec2::new(0.0, 1.0),
+        Vec2::new(2.0, 1.0),
+        Vec2::new(3.0, 1.0),
+        Vec2::new(0.0, 2.0),
+        Vec2::new(2.0, 2.0),
+        Vec2::new(3.0, 2.0),
+        Vec2::new(0.0, 3.0),
+        Vec2::new(2.0, 3.0),
+        Vec2::new(3.0, 3.0),
+      ],
+      polygons: vec![
+        vec![0, 1, 4, 3],
+        vec![1, 2, 5, 4],
+        vec![4, 5, 8, 7],
+        vec![7, 8, 11, 10],
+        vec![6, 7, 10, 9],
+      ],
+      polygon_type_indices: vec![0; 5],
+      polygon_flags: None,
+      height_mesh: None,
+    }
+    .validate()
+    .expect("nav mesh is valid"),
+  );
+
+  let island_id = archipelago.add_island(Island::new(
+    Transform { translation: Vec2::new(10.0, 10.0), ..Default::default() },
+    nav_mesh,
+  ));
+
+  let link_id = archipelago.add_animation_link(AnimationLink {
+    start_edge: (Vec2::new(10.0, 11.0), Vec2::new(11.0, 11.0)),
+    end_edge: (Vec2::new(10.0, 12.0), Vec2::new(11.0, 12.0)),
+    kind: 0,
+    cost: 1.5,
+    bidirectional: false,
+  });
+  archipelago.update(1.0);
+
+  let off_mesh_link = off_mesh_link_for_animation_link(&archipelago, link_id);
+
+  struct TestFilter {
+    node_multipliers: HashMap<NodeRef, Option<f32>>,
+    excluded_link: Option<AnimationLinkId>,
+  }
+
+  impl PathCostFilter for TestFilter {
+    fn node_cost_multiplier(
+      &self,
+      node: NodeRef,
+      _type_index: usize,
+    ) -> Option<f32> {
+      self.node_multipliers.get(&node).copied().unwrap_or(Some(1.0))
+    }
+
+    fn animation_link_cost(
+      &self,
+      link_id: AnimationLinkId,
+      _kind: usize,
+      _from: NodeRef,
+      _to: NodeRef,
+      cost: f32,
+    ) -> Option<f32> {
+      if Some(link_id) == self.excluded_link { None } else { Some(cost) }
+    }
+  }
+
+  let start_point = Vec3::new(2.5, 0.5, 0.0) + Vec3::new(10.0, 10.0, 0.0);
+  let end_point = Vec3::new(0.5, 2.5, 0.0) + Vec3::new(10.0, 10.0, 0.0);
+  let find_path_with_filter = |filter: &TestFilter| {
+    find_path(
+      &archipelago.nav_data,
+      NodeRef { island_id, polygon_index: 1 },
+      start_point,
+      NodeRef { island_id, polygon_index: 4 },
+      end_point,
+      &PathQuery { cost_filter: Some(filter), ..Default::default() },
+      PathfindingMode::EdgeMidpoints,
+    )
+    .path
+  };
+
+  let animation_link_path = Some(Path {
+    island_segments: vec![
+      IslandSegment {
+        island_id,
+        corridor: vec![1, 0],
+        portal_edge_index: vec![3],
+      },
+      IslandSegment { island_id, corridor: vec![4], portal_edge_index: vec![] },
+    ],
+    off_mesh_link_segments: vec![OffMeshLinkSegment {
+      starting_node: NodeRef { island_id, polygon_index: 0 },
+      end_node: NodeRef { island_id, polygon_index: 4 },
+      off_mesh_link,
+    }],
+    start_point,
+    end_point,
+  });
+  let nav_mesh_path = Some(Path {
+    island_segments: vec![IslandSegment {
+      island_id,
+      corridor: vec![1, 2, 3, 4],
+      portal_edge_index: vec![2, 2, 3],
+    }],
+    off_mesh_link_segments: vec![],
+    start_point,
+    end_point,
+  });
+
+  // Making a node on the nav mesh route expensive makes the animation link
+  // cheaper.
+  assert_eq!(
+    find_path_with_filter(&TestFilter {
+      node_multipliers: HashMap::from([(
+        NodeRef { island_id, polygon_index: 3 },
+        Some(10.0),
+      )]),
+      excluded_link: None,
+    }),
+    animation_link_path
+  );
+  // Excluding a node on the nav mesh route also forces the animation link.
+  assert_eq!(
+    find_path_with_filter(&TestFilter {
+      node_multipliers: HashMap::from([(
+        NodeRef { island_id, polygon_index: 3 },
+        None,
+      )]),
+      excluded_link: None,
+    }),
+    animation_link_path
+  );
+  // Excluding the animation link forces the nav mesh route despite its cost.
+  assert_eq!(
+    find_path_with_filter(&TestFilter {
+      node_multipliers: HashMap::from([(
+        NodeRef { island_id, polygon_index: 3 },
+        Some(10.0),
+      )]),
+      excluded_link: Some(link_id),
+    }),
+    nav_mesh_path
+  );
+}
@@ -1,15 +1,24 @@
-use std::{collections::HashMap, marker::PhantomData};
+use std::{
+  collections::{HashMap, HashSet},
+  marker::PhantomData,
+};
+
+use glam::{Vec2, Vec3, Vec3Swizzles};
 
 use thiserror::Error;
 
 use crate::{
-  Archipelago, CoordinateSystem, IslandId,
-  agent::PermittedAnimationLinks,
+  AgentId, Archipelago, CoordinateSystem, IslandId, NavigationData,
+  PathCostFilter, PathSmoothing,
+  agent::{PermittedAnimationLinks, PolygonFlagFilter},
   coords::CorePointSampleDistance,
+  geometry::to_ccw_convex_polygon,
   link::AnimationLinkId,
-  nav_data::NodeRef,
-  path::{PathIndex, StraightPathStep},
-  pathfinding,
+  nav_data::{Footprint, NodeRef},
+  path::{Path, PathIndex, StraightPathStep},
+  pathfinding, raycast,
+  smoothing::smooth_polyline,
+  util::FloatOrd,
 };
 
 /// A point on the navigation meshes.
@@ -52,6 +61,11 @@ impl<CS: CoordinateSystem> SampledPoint<'_, CS> {
   pub fn type_index(&self) -> usize {
     self.type_index
   }
+
+  /// Gets the node the sampled point is on.
+  pub fn node(&self) -> NodeRef {
+    self.node_ref
+  }
 }
 
 /// An error while sampling a point.
@@ -180,14 +194,539 @@ impl<CS: CoordinateSystem<Coordinate: PartialEq>> PartialEq for PathStep<CS> {
   }
 }
 
+/// Restrictions on the points picked by
+/// [`crate::Archipelago::sample_random_point`]. By default, points are picked
+/// from anywhere on the navigation meshes.
+pub struct RandomPointConstraints<'a, CS: CoordinateSystem> {
+  /// If set, the point must be (horizontally) within the radius of the
+  /// center.
+  pub within_radius: Option<(CS::Coordinate, f32)>,
+  /// If set, the point must be on a node with one of these type indices.
+  pub type_indices: Option<HashSet<usize>>,
+  /// If set, the point must be reachable from this sampled point using only
+  /// the permitted animation links. Type index costs are ignored.
+  pub reachable_from:
+    Option<(&'a SampledPoint<'a, CS>, PermittedAnimationLinks)>,
+}
+
+// Manual Default impl to avoid `CS` having a Default bound itself.
+impl<CS: CoordinateSystem> Default for RandomPointConstraints<'_, CS> {
+  fn default() -> Self {
+    Self { within_radius: None, type_indices: None, reachable_from: None }
+  }
+}
+
+// Manual Clone impl to avoid `CS` having a Clone bound itself.
+impl<CS: CoordinateSystem> Clone for RandomPointConstraints<'_, CS> {
+  fn clone(&self) -> Self {
+    Self {
+      within_radius: self.within_radius.clone(),
+      type_indices: self.type_indices.clone(),
+      reachable_from: self.reachable_from.clone(),
+    }
+  }
+}
+
+/// Options for [`crate::Archipelago::find_path`],
+/// [`crate::Archipelago::find_path_to_nearest`] and
+/// [`crate::Archipelago::find_reachable_nodes`]. By default, paths may travel
+/// through any polygon and take any animation link, and ignore the width of
+/// gaps.
+#[derive(Clone, Default)]
+pub struct PathQuery<'a> {
+  /// Overrides the cost of each type index in this map. Costs must be
+  /// positive.
+  pub override_type_index_costs: HashMap<usize, f32>,
+  /// The animation links that the path may take.
+  pub permitted_animation_links: PermittedAnimationLinks,
+  /// Determines the polygons that the path may travel through.
+  pub flag_filter: PolygonFlagFilter,
+  /// The path does not go through gaps (or take animation links) narrower
+  /// than an agent with this radius. `0.0` ignores the width of gaps.
+  pub agent_radius: f32,
+  /// Further customizes the costs of the path.
+  pub cost_filter: Option<&'a dyn PathCostFilter>,
+  /// If set, the straight lines of the path are smoothed into curves. This is
+  /// only used by [`crate::Archipelago::find_path`].
+  pub smoothing: Option<PathSmoothing>,
+}
+
+/// An error while picking a random point.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Error)]
+pub enum SampleRandomPointError {
+  #[error("No point on the navigation meshes satisfies the constraints.")]
+  NoPointFound,
+  #[error(
+    "The navigation data of the archipelago has been mutated since the last update."
+  )]
+  NavDataDirty,
+}
+
+/// Picks a uniformly random point on the navigation meshes that satisfies
+/// `constraints`.
+pub(crate) fn sample_random_point<'archipelago, CS: CoordinateSystem>(
+  archipelago: &'archipelago Archipelago<CS>,
+  constraints: &RandomPointConstraints<'_, CS>,
+  rng: &mut impl FnMut() -> f32,
+) -> Result<SampledPoint<'archipelago, CS>, SampleRandomPointError> {
+  if archipelago.nav_data.dirty {
+    return Err(SampleRandomPointError::NavDataDirty);
+  }
+
+  let Some((point, node_ref)) = archipelago.nav_data.random_point(
+    constraints
+      .within_radius
+      .as_ref()
+      .map(|(center, radius)| (CS::to_landmass(center).xy(), *radius)),
+    constraints.type_indices.as_ref(),
+    constraints.reachable_from.as_ref().map(
+      |(start_point, permitted_animation_links)| {
+        (start_point.node_ref, permitted_animation_links)
+      },
+    ),
+    rng,
+  ) else {
+    return Err(SampleRandomPointError::NoPointFound);
+  };
+
+  let island = archipelago.nav_data.get_island(node_ref.island_id).unwrap();
+  let type_index = island.nav_mesh.polygons[node_ref.polygon_index].type_index;
+
+  Ok(SampledPoint {
+    point: CS::from_landmass(&point),
+    node_ref,
+    type_index,
+    marker: PhantomData,
+  })
+}
+
+/// An error from finding the nodes reachable from a sampled point.
+#[derive(Clone, Copy, Debug, PartialEq, Error)]
+pub enum FindReachableNodesError {
+  #[error("The type index {0:?} had a cost of {1}, which is non-positive.")]
+  NonPositiveTypeIndexCost(usize, f32),
+}
+
+/// A node that can be reached from a point, along with the cheapest way to
+/// reach it.
+pub struct ReachableNode<CS: CoordinateSystem> {
+  /// The node that can be reached.
+  pub node: NodeRef,
+  /// The type index of the node.
+  pub type_index: usize,
+  /// The cheapest cost to reach the node.
+  pub cost: f32,
+  /// The point where the cheapest path enters the node. For the node that the
+  /// search started in, this is the start point.
+  pub entry_point: CS::Coordinate,
+}
+
+impl<CS: CoordinateSystem<Coordinate: std::fmt::Debug>> std::fmt::Debug
+  for ReachableNode<CS>
+{
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("ReachableNode")
+      .field("node", &self.node)
+      .field("type_index", &self.type_index)
+      .field("cost", &self.cost)
+      .field("entry_point", &self.entry_point)
+      .finish()
+  }
+}
+
+impl<CS: CoordinateSystem> Clone for ReachableNode<CS> {
+  fn clone(&self) -> Self {
+    Self {
+      node: self.node,
+      type_index: self.type_index,
+      cost: self.cost,
+      entry_point: self.entry_point.clone(),
+    }
+  }
+}
+
+impl<CS: CoordinateSystem<Coordinate: PartialEq>> PartialEq
+  for ReachableNode<CS>
+{
+  fn eq(&self, other: &Self) -> bool {
+    self.node == other.node
+      && self.type_index == other.type_index
+      && self.cost == other.cost
+      && self.entry_point == other.entry_point
+  }
+}
+
+/// Finds every node that can be reached from `start_point` with a cost of at
+/// most `max_cost`. The nodes are sorted by their cost.
+pub(crate) fn find_reachable_nodes<'a, CS: CoordinateSystem>(
+  archipelago: &'a Archipelago<CS>,
+  start_point: &SampledPoint<'a, CS>,
+  max_cost: f32,
+  query: &PathQuery<'_>,
+) -> Result<Vec<ReachableNode<CS>>, FindReachableNodesError> {
+  // See the assert in `find_path` for why this assert is ok.
+  assert!(
+    !archipelago.nav_data.dirty,
+    "The navigation data has been mutated, but we have SampledPoints, so this should be impossible."
+  );
+
+  for (type_index, cost) in query.override_type_index_costs.iter() {
+    if *cost <= 0.0 {
+      return Err(FindReachableNodesError::NonPositiveTypeIndexCost(
+        *type_index,
+        *cost,
+      ));
+    }
+  }
+
+  let mut reachable_nodes = pathfinding::find_reachable_nodes(
+    &archipelago.nav_data,
+    (start_point.node_ref, CS::to_landmass(&start_point.point)),
+    max_cost,
+    query,
+  )
+  .into_iter()
+  .map(|(node_ref, (cost, entry_point))| {
+    let island = archipelago.nav_data.get_island(node_ref.island_id).unwrap();
+    ReachableNode {
+      node: node_ref,
+      type_index: island.nav_mesh.polygons[node_ref.polygon_index].type_index,
+      cost,
+      entry_point: CS::from_landmass(&entry_point),
+    }
+  })
+  .collect::<Vec<_>>();
+  // Sort by the node as well so that nodes with the same cost have a
+  // consistent order.
+  reachable_nodes.sort_by_key(|reachable_node| {
+    (FloatOrd(reachable_node.cost), reachable_node.node)
+  });
+  Ok(reachable_nodes)
+}
+
+/// The point where a raycast left the navigation meshes (or hit an obstacle or
+/// closed door).
+pub struct RaycastHit<CS: CoordinateSystem> {
+  /// The point on the boundary of the navigation meshes (or of the obstacle or
+  /// closed door) where the ray left.
+  pub point: CS::Coordinate,
+  /// The outward-facing normal of the boundary edge that the ray left
+  /// through. For obstacles and closed doors, this faces away from the
+  /// obstacle or door. This is always horizontal.
+  pub normal: CS::Coordinate,
+}
+
+impl<CS: CoordinateSystem<Coordinate: std::fmt::Debug>> std::fmt::Debug
+  for RaycastHit<CS>
+{
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("RaycastHit")
+      .field("point", &self.point)
+      .field("normal", &self.normal)
+      .finish()
+  }
+}
+
+impl<CS: CoordinateSystem> Clone for RaycastHit<CS> {
+  fn clone(&self) -> Self {
+    Self { point: self.point.clone(), normal: self.normal.clone() }
+  }
+}
+
+impl<CS: CoordinateSystem<Coordinate: PartialEq>> PartialEq for RaycastHit<CS> {
+  fn eq(&self, other: &Self) -> bool {
+    self.point == other.point && self.normal == other.normal
+  }
+}
+
+/// The result of a raycast across the navigation meshes.
+pub struct RaycastResult<CS: CoordinateSystem> {
+  /// Where the ray left the navigation meshes. [`None`] if the ray reached its
+  /// end point without leaving the navigation meshes (i.e., the end point can
+  /// be walked to in a straight line).
+  pub hit: Option<RaycastHit<CS>>,
+  /// The nodes that the ray passed through, in order. This always starts with
+  /// the node of the start point.
+  pub nodes: Vec<NodeRef>,
+}
+
+impl<CS: CoordinateSystem<Coordinate: std::fmt::Debug>> std::fmt::Debug
+  for RaycastResult<CS>
+{
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("RaycastResult")
+      .field("hit", &self.hit)
+      .field("nodes", &self.nodes)
+      .finish()
+  }
+}
+
+impl<CS: CoordinateSystem> Clone for RaycastResult<CS> {
+  fn clone(&self) -> Self {
+    Self { hit: self.hit.clone(), nodes: self.nodes.clone() }
+  }
+}
+
+impl<CS: CoordinateSystem<Coordinate: PartialEq>> PartialEq
+  for RaycastResult<CS>
+{
+  fn eq(&self, other: &Self) -> bool {
+    self.hit == other.hit && self.nodes == other.nodes
+  }
+}
+
+/// Casts a ray along the navigation meshes from `start_point` towards
+/// `end_point`, stopping where the ray leaves the navigation meshes.
+pub(crate) fn raycast<'a, CS: CoordinateSystem>(
+  archipelago: &'a Archipelago<CS>,
+  start_point: &SampledPoint<'a, CS>,
+  end_point: &CS::Coordinate,
+) -> RaycastResult<CS> {
+  // See the assert in `find_path` for why this assert is ok.
+  assert!(
+    !archipelago.nav_data.dirty,
+    "The navigation data has been mutated, but we have SampledPoints, so this should be impossible."
+  );
+
+  let raycast = raycast::raycast(
+    &archipelago.nav_data,
+    (start_point.node_ref, CS::to_landmass(&start_point.point)),
+    CS::to_landmass(end_point),
+  );
+  RaycastResult {
+    hit: raycast.hit.map(|(point, normal)| RaycastHit {
+      point: CS::from_landmass(&point),
+      normal: CS::from_landmass(&normal),
+    }),
+    nodes: raycast.nodes,
+  }
+}
+
+/// The nearest point on the boundary of the navigation meshes.
+pub struct NearestBoundary<CS: CoordinateSystem> {
+  /// The nearest point on a boundary edge.
+  pub point: CS::Coordinate,
+  /// The distance to [`Self::point`].
+  pub distance: f32,
+  /// The outward-facing normal of the boundary edge. This is always
+  /// horizontal.
+  pub normal: CS::Coordinate,
+}
+
+impl<CS: CoordinateSystem<Coordinate: std::fmt::Debug>> std::fmt::Debug
+  for NearestBoundary<CS>
+{
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("NearestBoundary")
+      .field("point", &self.point)
+      .field("distance", &self.distance)
+      .field("normal", &self.normal)
+      .finish()
+  }
+}
+
+impl<CS: CoordinateSystem> Clone for NearestBoundary<CS> {
+  fn clone(&self) -> Self {
+    Self {
+      point: self.point.clone(),
+      distance: self.distance,
+      normal: self.normal.clone(),
+    }
+  }
+}
+
+impl<CS: CoordinateSystem<Coordinate: PartialEq>> PartialEq
+  for NearestBoundary<CS>
+{
+  fn eq(&self, other: &Self) -> bool {
+    self.point == other.point
+      && self.distance == other.distance
+      && self.normal == other.normal
+  }
+}
+
+/// Finds the nearest point on the boundary of the navigation meshes to
+/// `point`, within `max_distance`.
+pub(crate) fn find_nearest_boundary<'a, CS: CoordinateSystem>(
+  archipelago: &'a Archipelago<CS>,
+  point: &SampledPoint<'a, CS>,
+  max_distance: f32,
+) -> Option<NearestBoundary<CS>> {
+  // See the assert in `find_path` for why this assert is ok.
+  assert!(
+    !archipelago.nav_data.dirty,
+    "The navigation data has been mutated, but we have SampledPoints, so this should be impossible."
+  );
+
+  let (point, distance, normal) = archipelago
+    .nav_data
+    .find_nearest_boundary(CS::to_landmass(&point.point), max_distance)?;
+  Some(NearestBoundary {
+    point: CS::from_landmass(&point),
+    distance,
+    normal: CS::from_landmass(&normal),
+  })
+}
+
+/// A shape to find the overlapping nodes of.
+pub enum QueryShape<CS: CoordinateSystem> {
+  /// A circle around `center`. The circle is horizontal, and extends
+  /// infinitely up and down.
+  Circle { center: CS::Coordinate, radius: f32 },
+  /// An axis-aligned box between `min` and `max`. Unlike the other shapes, the
+  /// box is also limited vertically, so only nodes whose bounds overlap the
+  /// box's height are included.
+  Aabb { min: CS::Coordinate, max: CS::Coordinate },
+  /// A convex polygon. The polygon is horizontal (the height of the vertices
+  /// is ignored), and extends infinitely up and down. The vertices can be in
+  /// either winding order.
+  ConvexPolygon(Vec<CS::Coordinate>),
+}
+
+impl<CS: CoordinateSystem<Coordinate: std::fmt::Debug>> std::fmt::Debug
+  for QueryShape<CS>
+{
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Self::Circle { center, radius } => f
+        .debug_struct("Circle")
+        .field("center", center)
+        .field("radius", radius)
+        .finish(),
+      Self::Aabb { min, max } => {
+        f.debug_struct("Aabb").field("min", min).field("max", max).finish()
+      }
+      Self::ConvexPolygon(vertices) => {
+        f.debug_tuple("ConvexPolygon").field(vertices).finish()
+      }
+    }
+  }
+}
+
+impl<CS: CoordinateSystem> Clone for QueryShape<CS> {
+  fn clone(&self) -> Self {
+    match self {
+      Self::Circle { center, radius } => {
+        Self::Circle { center: center.clone(), radius: *radius }
+      }
+      Self::Aabb { min, max } => {
+        Self::Aabb { min: min.clone(), max: max.clone() }
+      }
+      Self::ConvexPolygon(vertices) => Self::ConvexPolygon(vertices.clone()),
+    }
+  }
+}
+
+impl<CS: CoordinateSystem<Coordinate: PartialEq>> PartialEq for QueryShape<CS> {
+  fn eq(&self, other: &Self) -> bool {
+    match (self, other) {
+      (
+        Self::Circle { center: l_center, radius: l_radius },
+        Self::Circle { center: r_center, radius: r_radius },
+      ) => l_center == r_center && l_radius == r_radius,
+      (
+        Self::Aabb { min: l_min, max: l_max },
+        Self::Aabb { min: r_min, max: r_max },
+      ) => l_min == r_min && l_max == r_max,
+      (Self::ConvexPolygon(l_vertices), Self::ConvexPolygon(r_vertices)) => {
+        l_vertices == r_vertices
+      }
+      _ => false,
+    }
+  }
+}
+
+/// An error from finding the nodes in a shape.
+#[derive(Clone, Copy, Debug, PartialEq, Error)]
+pub enum QueryNodesInShapeError {
+  #[error(
+    "The navigation data of the archipelago has been mutated since the last update."
+  )]
+  NavDataDirty,
+  #[error("The circle has a radius of {0}, which is negative.")]
+  NegativeRadius(f32),
+  #[error(
+    "The polygon is not convex, or has fewer than 3 (non-collinear) vertices."
+  )]
+  InvalidPolygon,
+}
+
+/// A node that overlaps a [`QueryShape`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct NodeInShape {
+  /// The node that overlaps the shape.
+  pub node: NodeRef,
+  /// The type index of the node.
+  pub type_index: usize,
+  /// The (horizontal) area of the node that is inside the shape.
+  pub area: f32,
+}
+
+/// Finds every node that overlaps `shape`. The nodes are sorted by their
+/// [`NodeRef`].
+pub(crate) fn query_nodes_in_shape<CS: CoordinateSystem>(
+  archipelago: &Archipelago<CS>,
+  shape: &QueryShape<CS>,
+) -> Result<Vec<NodeInShape>, QueryNodesInShapeError> {
+  if archipelago.nav_data.dirty {
+    return Err(QueryNodesInShapeError::NavDataDirty);
+  }
+
+  let (footprint, height_range) = match shape {
+    QueryShape::Circle { center, radius } => {
+      if *radius < 0.0 {
+        return Err(QueryNodesInShapeError::NegativeRadius(*radius));
+      }
+      let center = CS::to_landmass(center).xy();
+      (Footprint::Circle { center, radius: *radius }, None)
+    }
+    QueryShape::Aabb { min, max } => {
+      // Converting the coordinates may flip axes, so recompute the corners.
+      let (corner_1, corner_2) = (CS::to_landmass(min), CS::to_landmass(max));
+      let (min, max) = (corner_1.min(corner_2), corner_1.max(corner_2));
+      (
+        Footprint::ConvexPolygon(vec![
+          min.xy(),
+          Vec2::new(max.x, min.y),
+          max.xy(),
+          Vec2::new(min.x, max.y),
+        ]),
+        Some((min.z, max.z)),
+      )
+    }
+    QueryShape::ConvexPolygon(vertices) => {
+      let vertices = to_ccw_convex_polygon(
+        vertices.iter().map(|vertex| CS::to_landmass(vertex).xy()).collect(),
+      )
+      .ok_or(QueryNodesInShapeError::InvalidPolygon)?;
+      (Footprint::ConvexPolygon(vertices), None)
+    }
+  };
+
+  let mut nodes = archipelago
+    .nav_data
+    .query_nodes_in_footprint(&footprint, height_range)
+    .into_iter()
+    .map(|(node_ref, area)| {
+      let island = archipelago.nav_data.get_island(node_ref.island_id).unwrap();
+      NodeInShape {
+        node: node_ref,
+        type_index: island.nav_mesh.polygons[node_ref.polygon_index].type_index,
+        area,
+      }
+    })
+    .collect::<Vec<_>>();
+  nodes.sort_by_key(|node| node.node);
+  Ok(nodes)
+}
+
 /// Finds a straight-line path across the navigation meshes from `start_point`
 /// to `end_point`.
 pub(crate) fn find_path<'a, CS: CoordinateSystem>(
   archipelago: &'a Archipelago<CS>,
   start_point: &SampledPoint<'a, CS>,
   end_point: &SampledPoint<'a, CS>,
-  override_type_index_costs: &HashMap<usize, f32>,
-  permitted_animation_links: PermittedAnimationLinks,
+  query: &PathQuery<'_>,
 ) -> Result<Vec<PathStep<CS>>, FindPathError> {
   // This assert can actually be triggered. This can happen if a user samples
   // points from one archipelago, but finds a path in a **different**
@@ -200,7 +739,7 @@ pub(crate) fn find_path<'a, CS: CoordinateSystem>(
     "The navigation data has been mutated, but we have SampledPoints, so this should be impossible."
   );
 
-  for (type_index, cost) in override_type_index_costs.iter() {
+  for (type_index, cost) in query.override_type_index_costs.iter() {
     if *cost <= 0.0 {
       return Err(FindPathError::NonPositiveTypeIndexCost(*type_index, *cost));
     }
@@ -212,39 +751,296 @@ pub(crate) fn find_path<'a, CS: CoordinateSystem>(
     CS::to_landmass(&start_point.point),
     end_point.node_ref,
     CS::to_landmass(&end_point.point),
-    override_type_index_costs,
-    permitted_animation_links,
+    query,
+    archipelago.archipelago_options.pathfinding_mode,
   )
   .path
   else {
     return Err(FindPathError::NoPathFound);
   };
 
-  let mut current_index = PathIndex::from_corridor_index(0, 0);
-  let mut current_point = CS::to_landmass(&start_point.point());
+  let steps = path_to_steps(
+    &archipelago.nav_data,
+    &path,
+    (PathIndex::from_corridor_index(0, 0), CS::to_landmass(&start_point.point)),
+    (path.last_index(), CS::to_landmass(&end_point.point)),
+    /* corner_offset= */ 0.0,
+  );
+  Ok(match &query.smoothing {
+    None => steps,
+    Some(smoothing) => smooth_steps(archipelago, steps, smoothing),
+  })
+}
 
-  let last_index = path.last_index();
-  let last_point = CS::to_landmass(&end_point.point());
+/// Finds a straight-line path across the navigation meshes from `start_point`
+/// to whichever of `end_points` is cheapest to reach. Returns the index of that
+/// end point and the path.
+pub(crate) fn find_path_to_nearest<'a, CS: CoordinateSystem>(
+  archipelago: &'a Archipelago<CS>,
+  start_point: &SampledPoint<'a, CS>,
+  end_points: &[SampledPoint<'a, CS>],
+  query: &PathQuery<'_>,
+) -> Result<(usize, Vec<PathStep<CS>>), FindPathError> {
+  // See the assert in `find_path` for why this assert is ok.
+  assert!(
+    !archipelago.nav_data.dirty,
+    "The navigation data has been mutated, but we have SampledPoints, so this should be impossible."
+  );
+
+  for (type_index, cost) in query.override_type_index_costs.iter() {
+    if *cost <= 0.0 {
+      return Err(FindPathError::NonPositiveTypeIndexCost(*type_index, *cost));
+    }
+  }
+
+  let Some((end_index, path)) = pathfinding::find_path_to_nearest(
+    &archipelago.nav_data,
+    (start_point.node_ref, CS::to_landmass(&start_point.point)),
+    &end_points
+      .iter()
+      .map(|end_point| (end_point.node_ref, CS::to_landmass(&end_point.point)))
+      .collect::<Vec<_>>(),
+    query,
+    archipelago.archipelago_options.pathfinding_mode,
+  ) else {
+    return Err(FindPathError::NoPathFound);
+  };
+
+  Ok((
+    end_index,
+    path_to_steps(
+      &archipelago.nav_data,
+      &path,
+      (
+        PathIndex::from_corridor_index(0, 0),
+        CS::to_landmass(&start_point.point),
+      ),
+      (path.last_index(), CS::to_landmass(&end_points[end_index].point)),
+      /* corner_offset= */ 0.0,
+    ),
+  ))
+}
+
+/// The remaining path of an agent.
+pub struct AgentPath<CS: CoordinateSystem> {
+  /// The straight-line path from the agent to its target, starting at the
+  /// agent's position.
+  pub steps: Vec<PathStep<CS>>,
+  /// The length of [`Self::steps`]. Animation links are counted as the
+  /// straight-line distance between their start and end points.
+  pub length: f32,
+  /// The nodes that the path crosses, starting with the agent's node and
+  /// ending with the target's node.
+  pub corridor: Vec<NodeRef>,
+}
+
+impl<CS: CoordinateSystem> AgentPath<CS> {
+  /// Returns the animation links that the path uses, in order.
+  pub fn animation_links(&self) -> impl Iterator<Item = AnimationLinkId> + '_ {
+    self.steps.iter().filter_map(|step| match step {
+      PathStep::Waypoint(_) => None,
+      PathStep::AnimationLink { link_id, .. } => Some(*link_id),
+    })
+  }
+
+  /// Returns the islands that the path crosses, in order. An island is
+  /// repeated if the path leaves it and later comes back.
+  pub fn islands(&self) -> Vec<IslandId> {
+    let mut islands =
+      self.corridor.iter().map(|node| node.island_id).collect::<Vec<_>>();
+    islands.dedup();
+    islands
+  }
+}
+
+impl<CS: CoordinateSystem<Coordinate: std::fmt::Debug>> std::fmt::Debug
+  for AgentPath<CS>
+{
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("AgentPath")
+      .field("steps", &self.steps)
+      .field("length", &self.length)
+      .field("corridor", &self.corridor)
+      .finish()
+  }
+}
+
+impl<CS: CoordinateSystem> Clone for AgentPath<CS> {
+  fn clone(&self) -> Self {
+    Self {
+      steps: self.steps.clone(),
+      length: self.length,
+      corridor: self.corridor.clone(),
+    }
+  }
+}
+
+impl<CS: CoordinateSystem<Coordinate: PartialEq>> PartialEq for AgentPath<CS> {
+  fn eq(&self, other: &Self) -> bool {
+    self.steps == other.steps
+      && self.length == other.length
+      && self.corridor == other.corridor
+  }
+}
+
+/// An error from getting the path of an agent.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Error)]
+pub enum AgentPathError {
+  #[error("The agent does not exist in the archipelago.")]
+  AgentNotFound,
+  #[error("The agent does not currently have a path.")]
+  NoPath,
+  #[error(
+    "The navigation data of the archipelago has been mutated since the last update."
+  )]
+  NavDataDirty,
+}
 
-  let mut path_points = vec![PathStep::Waypoint(start_point.point())];
-  if current_index == last_index {
-    path_points.push(PathStep::Waypoint(end_point.point.clone()));
-    return Ok(path_points);
+/// Gets the remaining path of `agent_id` as of the last update.
+pub(crate) fn agent_path<CS: CoordinateSystem>(
+  archipelago: &Archipelago<CS>,
+  agent_id: AgentId,
+) -> Result<AgentPath<CS>, AgentPathError> {
+  let agent =
+    archipelago.get_agent(agent_id).ok_or(AgentPathError::AgentNotFound)?;
+  if archipelago.nav_data.dirty {
+    return Err(AgentPathError::NavDataDirty);
+  }
+  let (Some(path), Some(progress)) =
+    (agent.current_path.as_ref(), agent.path_progress.as_ref())
+  else {
+    return Err(AgentPathError::NoPath);
+  };
+
+  let steps = path_to_steps(
+    &archipelago.nav_data,
+    path,
+    (progress.agent_index, progress.agent_point),
+    (progress.target_index, progress.target_point),
+    agent.corner_offset(),
+  );
+  let steps = match agent.path_smoothing.as_ref() {
+    None => steps,
+    Some(smoothing) => smooth_steps(archipelago, steps, smoothing),
+  };
+  let mut length = 0.0;
+  let mut current_point = progress.agent_point;
+  for step in steps.iter() {
+    match step {
+      PathStep::Waypoint(point) => {
+        let point = CS::to_landmass(point);
+        length += current_point.distance(point);
+        current_point = point;
+      }
+      PathStep::AnimationLink { start_point, end_point, .. } => {
+        let (start_point, end_point) =
+          (CS::to_landmass(start_point), CS::to_landmass(end_point));
+        length +=
+          current_point.distance(start_point) + start_point.distance(end_point);
+        current_point = end_point;
+      }
+    }
   }
 
-  // Keep looping until we reach the end index. If it's the last index, but the
-  // previous step was an animation link, run once more to get the waypoint to
-  // the end point.
-  while current_index != last_index
+  Ok(AgentPath {
+    steps,
+    length,
+    corridor: path.nodes_between(progress.agent_index, progress.target_index),
+  })
+}
+
+/// Smooths the walking parts of `steps` (the parts between animation links)
+/// into curves. See [`PathSmoothing`] for details.
+fn smooth_steps<CS: CoordinateSystem>(
+  archipelago: &Archipelago<CS>,
+  steps: Vec<PathStep<CS>>,
+  smoothing: &PathSmoothing,
+) -> Vec<PathStep<CS>> {
+  let point_sample_distance = CorePointSampleDistance::new(
+    &archipelago.archipelago_options.point_sample_distance,
+  );
+  let smooth = |polyline: &[Vec3]| {
+    smooth_polyline(
+      &archipelago.nav_data,
+      polyline,
+      smoothing,
+      &point_sample_distance,
+    )
+  };
+
+  let mut smoothed_steps = Vec::with_capacity(steps.len());
+  // The points walked in straight lines since the start of the path or the
+  // last animation link. Only the first polyline starts with a waypoint, the
+  // rest start at the end of an animation link.
+  let mut polyline = Vec::new();
+  for step in steps {
+    match step {
+      PathStep::Waypoint(point) => polyline.push(CS::to_landmass(&point)),
+      PathStep::AnimationLink { start_point, end_point, link_id } => {
+        // The animation link step includes walking to its start point, so
+        // that is not a waypoint.
+        polyline.push(CS::to_landmass(&start_point));
+        let smoothed_points = smooth(&polyline);
+        let skip_first = if smoothed_steps.is_empty() { 0 } else { 1 };
+        smoothed_steps.extend(
+          smoothed_points[skip_first..smoothed_points.len() - 1]
+            .iter()
+            .map(|point| PathStep::Waypoint(CS::from_landmass(point))),
+        );
+        polyline = vec![CS::to_landmass(&end_point)];
+        smoothed_steps.push(PathStep::AnimationLink {
+          start_point,
+          end_point,
+          link_id,
+        });
+      }
+    }
+  }
+  let smoothed_points = smooth(&polyline);
+  let skip_first = if smoothed_steps.is_empty() { 0 } else { 1 };
+  smoothed_steps.extend(
+    smoothed_points[skip_first..]
+      .iter()
+      .map(|point| PathStep::Waypoint(CS::from_landmass(point))),
+  );
+  smoothed_steps
+}
+
+/// Converts the part of `path` between `start` and `end` into a straight-line
+/// path. `start` and `end` are the index in the path and the point (in world
+/// space) to walk between. Corners of the path are offset by `corner_offset`.
+fn path_to_steps<CS: CoordinateSystem>(
+  nav_data: &NavigationData<CS>,
+  path: &Path,
+  (start_index, start_point): (PathIndex, Vec3),
+  (last_index, last_point): (PathIndex, Vec3),
+  corner_offset: f32,
+) -> Vec<PathStep<CS>> {
+  let mut current_index = start_index;
+  let mut current_point = start_point;
+
+  let mut path_points =
+    vec![PathStep::Waypoint(CS::from_landmass(&start_point))];
+  if current_index == last_index && current_point == last_point {
+    path_points.push(PathStep::Waypoint(CS::from_landmass(&last_point)));
+    return path_points;
+  }
+
+  // Keep looping until we reach the end point. Note the end index may be
+  // reached before the end point if there are obstacles to walk around in the
+  // last node. If it's the last index, but the previous step was an animation
+  // link, run once more to get the waypoint to the end point.
+  while (current_index, current_point) != (last_index, last_point)
     || matches!(path_points.last().unwrap(), PathStep::AnimationLink { .. })
   {
     let next_step;
     (current_index, next_step) = path.find_next_point_in_straight_path(
-      &archipelago.nav_data,
+      nav_data,
       current_index,
       current_point,
       last_index,
       last_point,
+      corner_offset,
     );
     let next_path_step;
     (current_point, next_path_step) = match next_step {
@@ -269,7 +1065,7 @@ pub(crate) fn find_path<'a, CS: CoordinateSystem>(
     path_points.push(next_path_step);
   }
 
-  Ok(path_points)
+  path_points
 }
 
 #[cfg(test)]
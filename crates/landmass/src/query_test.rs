@@ -1,12 +1,19 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{
+  collections::{HashMap, HashSet},
+  sync::Arc,
+};
 
-use glam::Vec2;
-use googletest::{expect_that, matchers::*};
+use glam::{Quat, Vec2};
+use googletest::{expect_eq, expect_that, matchers::*};
 
 use crate::{
-  Archipelago, ArchipelagoOptions, FindPathError, FromAgentRadius, Island,
-  NavigationMesh, PathStep, SamplePointError, Transform,
-  agent::PermittedAnimationLinks,
+  Agent, AgentPath, AgentPathError, Archipelago, ArchipelagoOptions, Door,
+  DoorEdges, FindPathError, FindReachableNodesError, FromAgentRadius, Island,
+  NavigationMesh, NearestBoundary, NodeRef, Obstacle, PathCostFilter,
+  PathQuery, PathSmoothing, PathStep, PathfindingMode, QueryNodesInShapeError,
+  QueryShape, RandomPointConstraints, RaycastHit, RaycastResult, ReachableNode,
+  SamplePointError, SampleRandomPointError, Transform, ValidNavigationMesh,
+  agent::{PermittedAnimationLinks, PolygonFlagFilter},
   coords::{CorePointSampleDistance, XY},
   link::AnimationLink,
 };
@@ -28,6 +35,7 @@ fn error_on_dirty_nav_mesh() {
       ],
       polygons: vec![vec![0, 1, 2, 3]],
       polygon_type_indices: vec![0],
+      polygon_flags: None,
       height_mesh: None,
     }
     .validate()
@@ -61,6 +69,7 @@ fn error_on_out_of_range() {
       ],
       polygons: vec![vec![0, 1, 2, 3]],
       polygon_type_indices: vec![0],
+      polygon_flags: None,
       height_mesh: None,
     }
     .validate()
@@ -96,6 +105,7 @@ fn samples_point_on_nav_mesh_or_near_nav_mesh() {
       ],
       polygons: vec![vec![0, 1, 2, 3]],
       polygon_type_indices: vec![0],
+      polygon_flags: None,
       height_mesh: None,
     }
     .validate()
@@ -104,7 +114,7 @@ fn samples_point_on_nav_mesh_or_near_nav_mesh() {
 
   let offset = Vec2::new(10.0, 10.0);
   let island_id = archipelago.add_island(Island::new(
-    Transform { translation: offset, rotation: 0.0 },
+    Transform { translation: offset, rotation: Quat::IDENTITY, scale: 1.0 },
     nav_mesh,
   ));
   archipelago.update(1.0);
@@ -168,6 +178,7 @@ fn samples_type_indices() {
         vec![7, 6, 8, 9],
       ],
       polygon_type_indices: vec![0, 1, 2, 3],
+      polygon_flags: None,
       height_mesh: None,
     }
     .validate()
@@ -176,7 +187,7 @@ fn samples_type_indices() {
 
   let offset = Vec2::new(10.0, 10.0);
   archipelago.add_island(Island::new(
-    Transform { translation: offset, rotation: 0.0 },
+    Transform { translation: offset, rotation: Quat::IDENTITY, scale: 1.0 },
     nav_mesh,
   ));
   archipelago.update(1.0);
@@ -234,6 +245,7 @@ fn no_path() {
       ],
       polygons: vec![vec![0, 1, 2, 3]],
       polygon_type_indices: vec![0],
+      polygon_flags: None,
       height_mesh: None,
     }
     .validate()
@@ -242,11 +254,15 @@ fn no_path() {
 
   let offset = Vec2::new(10.0, 10.0);
   archipelago.add_island(Island::new(
-    Transform { translation: offset, rotation: 0.0 },
+    Transform { translation: offset, rotation: Quat::IDENTITY, scale: 1.0 },
     nav_mesh.clone(),
   ));
   archipelago.add_island(Island::new(
-    Transform { translation: offset + Vec2::new(2.0, 0.0), rotation: 0.0 },
+    Transform {
+      translation: offset + Vec2::new(2.0, 0.0),
+      rotation: Quat::IDENTITY,
+      scale: 1.0,
+    },
     nav_mesh,
   ));
   archipelago.update(1.0);
@@ -258,13 +274,7 @@ fn no_path() {
     .sample_point(offset + Vec2::new(2.5, 0.5), &1e-5)
     .expect("point is on nav mesh.");
   assert_eq!(
-    find_path(
-      &archipelago,
-      &start_point,
-      &end_point,
-      &HashMap::new(),
-      PermittedAnimationLinks::All
-    ),
+    find_path(&archipelago, &start_point, &end_point, &PathQuery::default()),
     Err(FindPathError::NoPathFound)
   );
 }
@@ -284,6 +294,7 @@ fn finds_path() {
       ],
       polygons: vec![vec![0, 1, 2, 3]],
       polygon_type_indices: vec![0],
+      polygon_flags: None,
       height_mesh: None,
     }
     .validate()
@@ -292,15 +303,23 @@ fn finds_path() {
 
   let offset = Vec2::new(10.0, 10.0);
   archipelago.add_island(Island::new(
-    Transform { translation: offset, rotation: 0.0 },
+    Transform { translation: offset, rotation: Quat::IDENTITY, scale: 1.0 },
     nav_mesh.clone(),
   ));
   archipelago.add_island(Island::new(
-    Transform { translation: offset + Vec2::new(1.0, 0.0), rotation: 0.0 },
+    Transform {
+      translation: offset + Vec2::new(1.0, 0.0),
+      rotation: Quat::IDENTITY,
+      scale: 1.0,
+    },
     nav_mesh.clone(),
   ));
   archipelago.add_island(Island::new(
-    Transform { translation: offset + Vec2::new(2.0, 0.5), rotation: 0.0 },
+    Transform {
+      translation: offset + Vec2::new(2.0, 0.5),
+      rotation: Quat::IDENTITY,
+      scale: 1.0,
+    },
     nav_mesh,
   ));
   archipelago.update(1.0);
@@ -312,13 +331,7 @@ fn finds_path() {
     .sample_point(offset + Vec2::new(2.5, 1.25), &1e-5)
     .expect("point is on nav mesh.");
   assert_eq!(
-    find_path(
-      &archipelago,
-      &start_point,
-      &end_point,
-      &HashMap::new(),
-      PermittedAnimationLinks::All
-    ),
+    find_path(&archipelago, &start_point, &end_point, &PathQuery::default()),
     Ok(vec![
       PathStep::Waypoint(offset + Vec2::new(0.5, 0.5)),
       PathStep::Waypoint(offset + Vec2::new(2.0, 1.0)),
@@ -372,6 +385,7 @@ fn finds_path_with_override_type_index_costs() {
         vec![3, 2, 13, 15],
       ],
       polygon_type_indices: vec![0, 0, 0, 0, 0, 0, 0, 1],
+      polygon_flags: None,
       height_mesh: None,
     }
     .validate()
@@ -399,8 +413,10 @@ fn finds_path_with_override_type_index_costs() {
     &archipelago,
     &start_point,
     &end_point,
-    &HashMap::from([(1, 10.0)]),
-    PermittedAnimationLinks::All,
+    &PathQuery {
+      override_type_index_costs: HashMap::from([(1, 10.0)]),
+      ..Default::default()
+    },
   )
   .expect("Path found");
 
@@ -430,6 +446,7 @@ fn find_path_returns_error_on_invalid_node_cost() {
       ],
       polygons: vec![vec![0, 1, 2, 3]],
       polygon_type_indices: vec![0],
+      polygon_flags: None,
       height_mesh: None,
     }
     .validate()
@@ -452,8 +469,10 @@ fn find_path_returns_error_on_invalid_node_cost() {
       &archipelago,
       &start_point,
       &end_point,
-      &HashMap::from([(0, 0.0)]),
-      PermittedAnimationLinks::All,
+      &PathQuery {
+        override_type_index_costs: HashMap::from([(0, 0.0)]),
+        ..Default::default()
+      },
     ),
     Err(FindPathError::NonPositiveTypeIndexCost(0, 0.0))
   );
@@ -462,8 +481,10 @@ fn find_path_returns_error_on_invalid_node_cost() {
       &archipelago,
       &start_point,
       &end_point,
-      &HashMap::from([(0, -0.5)]),
-      PermittedAnimationLinks::All,
+      &PathQuery {
+        override_type_index_costs: HashMap::from([(0, -0.5)]),
+        ..Default::default()
+      },
     ),
     Err(FindPathError::NonPositiveTypeIndexCost(0, -0.5))
   );
@@ -484,6 +505,7 @@ fn start_and_end_in_same_node() {
       ],
       polygons: vec![vec![0, 1, 2, 3]],
       polygon_type_indices: vec![0],
+      polygon_flags: None,
       height_mesh: None,
     }
     .validate()
@@ -501,12 +523,7 @@ fn start_and_end_in_same_node() {
     archipelago.sample_point(start_point, &0.1).unwrap();
   let end_sampled_point = archipelago.sample_point(end_point, &0.1).unwrap();
   let path = archipelago
-    .find_path(
-      &start_sampled_point,
-      &end_sampled_point,
-      &HashMap::default(),
-      PermittedAnimationLinks::All,
-    )
+    .find_path(&start_sampled_point, &end_sampled_point, &PathQuery::default())
     .unwrap();
 
   assert_eq!(
@@ -515,6 +532,66 @@ fn start_and_end_in_same_node() {
   );
 }
 
+#[googletest::test]
+fn path_walks_around_obstacle_inside_node() {
+  let mut archipelago =
+    Archipelago::<XY>::new(ArchipelagoOptions::from_agent_radius(0.5));
+
+  let nav_mesh = Arc::new(
+    NavigationMesh {
+      vertices: vec![
+        Vec2::new(0.0, 0.0),
+        Vec2::new(10.0, 0.0),
+        Vec2::new(10.0, 10.0),
+        Vec2::new(0.0, 10.0),
+      ],
+      polygons: vec![vec![0, 1, 2, 3]],
+      polygon_type_indices: vec![0],
+      polygon_flags: None,
+      height_mesh: None,
+    }
+    .validate()
+    .expect("nav mesh is valid"),
+  );
+  archipelago.add_island(Island::new(Transform::default(), nav_mesh));
+  // The obstacle is entirely inside the only node, so it doesn't split it.
+  archipelago
+    .add_obstacle(Obstacle::Aabb {
+      min: Vec2::new(4.0, 4.0),
+      max: Vec2::new(6.0, 6.0),
+    })
+    .unwrap();
+  archipelago.update(1.0);
+
+  let start_point =
+    archipelago.sample_point(Vec2::new(1.0, 4.5), &0.1).unwrap();
+  let end_point = archipelago.sample_point(Vec2::new(9.0, 4.5), &0.1).unwrap();
+  let path = archipelago
+    .find_path(&start_point, &end_point, &PathQuery::default())
+    .unwrap();
+
+  // The path goes around the bottom of the obstacle, since that is shorter.
+  let points = path
+    .iter()
+    .map(|step| match step {
+      PathStep::Waypoint(point) => (point.x, point.y),
+      PathStep::AnimationLink { .. } => panic!("Unexpected animation link."),
+    })
+    .collect::<Vec<_>>();
+  expect_that!(
+    points,
+    elements_are!(
+      (near(1.0, 1e-2), near(4.5, 1e-2)),
+      (near(4.0, 1e-2), near(4.0, 1e-2)),
+      (near(6.0, 1e-2), near(4.0, 1e-2)),
+      (near(9.0, 1e-2), near(4.5, 1e-2)),
+    )
+  );
+  // The corners are outside the obstacle.
+  expect_that!(points[1], (lt(4.0), lt(4.0)));
+  expect_that!(points[2], (gt(6.0), lt(4.0)));
+}
+
 #[googletest::test]
 fn one_animation_link_path() {
   let mut archipelago =
@@ -543,6 +620,7 @@ fn one_animation_link_path() {
       ],
       polygons: vec![vec![0, 1, 2, 3], vec![4, 5, 6, 7]],
       polygon_type_indices: vec![0; 2],
+      polygon_flags: None,
       height_mesh: None,
     }
     .validate()
@@ -567,12 +645,7 @@ fn one_animation_link_path() {
     archipelago.sample_point(start_point, &0.1).unwrap();
   let end_sampled_point = archipelago.sample_point(end_point, &0.1).unwrap();
   let path = archipelago
-    .find_path(
-      &start_sampled_point,
-      &end_sampled_point,
-      &HashMap::default(),
-      PermittedAnimationLinks::All,
-    )
+    .find_path(&start_sampled_point, &end_sampled_point, &PathQuery::default())
     .unwrap();
 
   expect_that!(
@@ -588,3 +661,1071 @@ fn one_animation_link_path() {
     )
   );
 }
+
+#[googletest::test]
+fn finds_smoothed_path() {
+  let mut archipelago =
+    Archipelago::<XY>::new(ArchipelagoOptions::from_agent_radius(0.5));
+
+  // +-+
+  // | |
+  // | +-+
+  // |   |
+  // +---+
+  let nav_mesh = Arc::new(
+    NavigationMesh {
+      vertices: vec![
+        Vec2::new(0.0, 0.0),
+        Vec2::new(4.0, 0.0),
+        Vec2::new(4.0, 2.0),
+        Vec2::new(2.0, 2.0),
+        Vec2::new(0.0, 2.0),
+        Vec2::new(2.0, 4.0),
+        Vec2::new(0.0, 4.0),
+      ],
+      polygons: vec![vec![0, 1, 2, 3, 4], vec![4, 3, 5, 6]],
+      polygon_type_indices: vec![0; 2],
+      polygon_flags: None,
+      height_mesh: None,
+    }
+    .validate()
+    .expect("nav mesh is valid"),
+  );
+
+  archipelago.add_island(Island::new(Transform::default(), nav_mesh));
+
+  archipelago.update(1.0);
+
+  let start_point = Vec2::new(3.5, 1.0);
+  let end_point = Vec2::new(1.0, 3.5);
+
+  let start_sampled_point =
+    archipelago.sample_point(start_point, &0.1).unwrap();
+  let end_sampled_point = archipelago.sample_point(end_point, &0.1).unwrap();
+  let path = archipelago
+    .find_path(
+      &start_sampled_point,
+      &end_sampled_point,
+      &PathQuery {
+        smoothing: Some(PathSmoothing { subdivisions: 4 }),
+        ..Default::default()
+      },
+    )
+    .unwrap();
+
+  // Each of the two lines is split into 4 pieces, and the path still passes
+  // through the corner.
+  expect_that!(path.len(), eq(9));
+  expect_that!(path.first(), some(eq(&PathStep::Waypoint(start_point))));
+  expect_that!(path[4], eq(PathStep::Waypoint(Vec2::new(2.0, 2.0))));
+  expect_that!(path.last(), some(eq(&PathStep::Waypoint(end_point))));
+  for step in path {
+    let PathStep::Waypoint(point) = step else {
+      panic!("Expected only waypoints, got {step:?}");
+    };
+    expect_that!(
+      archipelago.sample_point(point, &1e-3).is_ok(),
+      eq(true),
+      "{point:?} should be on the nav mesh"
+    );
+  }
+}
+
+/// Creates a nav mesh made of a row of unit squares, one for each type index.
+fn strip_nav_mesh(
+  polygon_type_indices: Vec<usize>,
+) -> Arc<ValidNavigationMesh<XY>> {
+  let columns = polygon_type_indices.len();
+  Arc::new(
+    NavigationMesh {
+      vertices: (0..=columns)
+        .map(|x| Vec2::new(x as f32, 0.0))
+        .chain((0..=columns).map(|x| Vec2::new(x as f32, 1.0)))
+        .collect(),
+      polygons: (0..columns)
+        .map(|x| vec![x, x + 1, columns + x + 2, columns + x + 1])
+        .collect(),
+      polygon_type_indices,
+      polygon_flags: None,
+      height_mesh: None,
+    }
+    .validate()
+    .expect("nav mesh is valid"),
+  )
+}
+
+#[googletest::test]
+fn finds_reachable_nodes_within_cost() {
+  let mut archipelago =
+    Archipelago::<XY>::new(ArchipelagoOptions::from_agent_radius(0.5));
+
+  // +-+-+-+-+
+  // |S| |E| |
+  // +-+-+-+-+
+  let nav_mesh = strip_nav_mesh(vec![0, 0, 1, 0]);
+
+  let island_id =
+    archipelago.add_island(Island::new(Transform::default(), nav_mesh));
+  archipelago.update(1.0);
+
+  let start_point =
+    archipelago.sample_point(Vec2::new(0.5, 0.5), &0.1).unwrap();
+  let node = |polygon_index| NodeRef { island_id, polygon_index };
+
+  expect_that!(
+    archipelago.find_reachable_nodes(
+      &start_point,
+      /* max_cost= */ 2.0,
+      &PathQuery::default(),
+    ),
+    ok(elements_are!(
+      &ReachableNode {
+        node: node(0),
+        type_index: 0,
+        cost: 0.0,
+        entry_point: Vec2::new(0.5, 0.5),
+      },
+      &ReachableNode {
+        node: node(1),
+        type_index: 0,
+        cost: 0.5,
+        entry_point: Vec2::new(1.0, 0.5),
+      },
+      &ReachableNode {
+        node: node(2),
+        type_index: 1,
+        cost: 1.5,
+        entry_point: Vec2::new(2.0, 0.5),
+      },
+    ))
+  );
+
+  // Making the third node expensive means the last node is too far away, even
+  // with a larger budget.
+  expect_that!(
+    archipelago.find_reachable_nodes(
+      &start_point,
+      /* max_cost= */ 4.0,
+      &PathQuery {
+        override_type_index_costs: HashMap::from([(1, 3.0)]),
+        ..Default::default()
+      },
+    ),
+    ok(elements_are!(
+      &ReachableNode {
+        node: node(0),
+        type_index: 0,
+        cost: 0.0,
+        entry_point: Vec2::new(0.5, 0.5),
+      },
+      &ReachableNode {
+        node: node(1),
+        type_index: 0,
+        cost: 0.5,
+        entry_point: Vec2::new(1.0, 0.5),
+      },
+      &ReachableNode {
+        node: node(2),
+        type_index: 1,
+        cost: 1.5,
+        entry_point: Vec2::new(2.0, 0.5),
+      },
+    ))
+  );
+
+  // Excluding the second node with a cost filter means only the start node is
+  // reachable.
+  struct ExcludeNode(NodeRef);
+  impl PathCostFilter for ExcludeNode {
+    fn node_cost_multiplier(
+      &self,
+      node: NodeRef,
+      _type_index: usize,
+    ) -> Option<f32> {
+      (node != self.0).then_some(1.0)
+    }
+  }
+  expect_that!(
+    archipelago.find_reachable_nodes(
+      &start_point,
+      /* max_cost= */ 4.0,
+      &PathQuery {
+        cost_filter: Some(&ExcludeNode(node(1))),
+        ..Default::default()
+      },
+    ),
+    ok(elements_are!(&ReachableNode {
+      node: node(0),
+      type_index: 0,
+      cost: 0.0,
+      entry_point: Vec2::new(0.5, 0.5),
+    }))
+  );
+
+  expect_that!(
+    archipelago.find_reachable_nodes(
+      &start_point,
+      /* max_cost= */ 4.0,
+      &PathQuery {
+        override_type_index_costs: HashMap::from([(1, 0.0)]),
+        ..Default::default()
+      },
+    ),
+    err(eq(&FindReachableNodesError::NonPositiveTypeIndexCost(1, 0.0)))
+  );
+}
+
+#[googletest::test]
+fn reachable_nodes_include_animation_links() {
+  let mut archipelago =
+    Archipelago::<XY>::new(ArchipelagoOptions::from_agent_radius(0.5));
+
+  // +-+
+  // | |
+  // +-+
+  //  L
+  // +-+
+  // |S|
+  // +-+
+  let nav_mesh = Arc::new(
+    NavigationMesh {
+      vertices: vec![
+        Vec2::new(0.0, 0.0),
+        Vec2::new(1.0, 0.0),
+        Vec2::new(1.0, 1.0),
+        Vec2::new(0.0, 1.0),
+        Vec2::new(0.0, 2.0),
+        Vec2::new(1.0, 2.0),
+        Vec2::new(1.0, 3.0),
+        Vec2::new(0.0, 3.0),
+      ],
+      polygons: vec![vec![0, 1, 2, 3], vec![4, 5, 6, 7]],
+      polygon_type_indices: vec![0; 2],
+      polygon_flags: None,
+      height_mesh: None,
+    }
+    .validate()
+    .expect("nav mesh is valid"),
+  );
+
+  let island_id =
+    archipelago.add_island(Island::new(Transform::default(), nav_mesh));
+  archipelago.add_animation_link(AnimationLink {
+    start_edge: (Vec2::new(0.0, 1.0), Vec2::new(1.0, 1.0)),
+    end_edge: (Vec2::new(0.0, 2.0), Vec2::new(1.0, 2.0)),
+    cost: 1.0,
+    kind: 0,
+    bidirectional: false,
+  });
+  archipelago.update(1.0);
+
+  let start_point =
+    archipelago.sample_point(Vec2::new(0.5, 0.5), &0.1).unwrap();
+
+  expect_that!(
+    archipelago.find_reachable_nodes(
+      &start_point,
+      /* max_cost= */ 2.0,
+      &PathQuery::default(),
+    ),
+    ok(elements_are!(
+      &ReachableNode {
+        node: NodeRef { island_id, polygon_index: 0 },
+        type_index: 0,
+        cost: 0.0,
+        entry_point: Vec2::new(0.5, 0.5),
+      },
+      &ReachableNode {
+        node: NodeRef { island_id, polygon_index: 1 },
+        type_index: 0,
+        // Walking to the link costs 0.5, and the link costs 1.0.
+        cost: 1.5,
+        entry_point: Vec2::new(0.5, 2.0),
+      },
+    ))
+  );
+
+  // The link is too expensive for a smaller budget.
+  expect_that!(
+    archipelago.find_reachable_nodes(
+      &start_point,
+      /* max_cost= */ 1.0,
+      &PathQuery::default(),
+    ),
+    ok(len(eq(1)))
+  );
+
+  // The link cannot be used if it is not permitted.
+  expect_that!(
+    archipelago.find_reachable_nodes(
+      &start_point,
+      /* max_cost= */ 2.0,
+      &PathQuery {
+        permitted_animation_links: PermittedAnimationLinks::Kinds(Arc::new(
+          HashSet::from([1])
+        )),
+        ..Default::default()
+      },
+    ),
+    ok(len(eq(1)))
+  );
+}
+
+#[googletest::test]
+fn finds_path_to_nearest_end_point() {
+  let mut archipelago =
+    Archipelago::<XY>::new(ArchipelagoOptions::from_agent_radius(0.5));
+
+  // +-+-+-+-+-+
+  // |A|S| |B| |
+  // +-+-+-+-+-+
+  archipelago.add_island(Island::new(
+    Transform::default(),
+    strip_nav_mesh(vec![1, 0, 0, 0, 0]),
+  ));
+  // This island is disconnected, so its end point can never be reached.
+  archipelago.add_island(Island::new(
+    Transform {
+      translation: Vec2::new(0.0, 5.0),
+      rotation: Quat::IDENTITY,
+      scale: 1.0,
+    },
+    strip_nav_mesh(vec![0]),
+  ));
+  archipelago.update(1.0);
+
+  let start_point =
+    archipelago.sample_point(Vec2::new(1.5, 0.5), &0.1).unwrap();
+  let end_points = [
+    archipelago.sample_point(Vec2::new(0.5, 5.5), &0.1).unwrap(),
+    archipelago.sample_point(Vec2::new(0.25, 0.5), &0.1).unwrap(),
+    archipelago.sample_point(Vec2::new(3.5, 0.5), &0.1).unwrap(),
+  ];
+
+  expect_that!(
+    archipelago.find_path_to_nearest(
+      &start_point,
+      &end_points,
+      &PathQuery::default(),
+    ),
+    ok((
+      eq(&1),
+      elements_are!(
+        &PathStep::Waypoint(Vec2::new(1.5, 0.5)),
+        &PathStep::Waypoint(Vec2::new(0.25, 0.5)),
+      )
+    ))
+  );
+
+  // Making A expensive means B is cheaper to reach, even though it is farther
+  // away.
+  expect_that!(
+    archipelago.find_path_to_nearest(
+      &start_point,
+      &end_points,
+      &PathQuery {
+        override_type_index_costs: HashMap::from([(1, 10.0)]),
+        ..Default::default()
+      },
+    ),
+    ok((
+      eq(&2),
+      elements_are!(
+        &PathStep::Waypoint(Vec2::new(1.5, 0.5)),
+        &PathStep::Waypoint(Vec2::new(3.5, 0.5)),
+      )
+    ))
+  );
+
+  expect_that!(
+    archipelago.find_path_to_nearest(
+      &start_point,
+      &end_points[..1],
+      &PathQuery::default(),
+    ),
+    err(eq(&FindPathError::NoPathFound))
+  );
+  expect_that!(
+    archipelago.find_path_to_nearest(&start_point, &[], &PathQuery::default()),
+    err(eq(&FindPathError::NoPathFound))
+  );
+}
+
+#[googletest::test]
+fn flag_filter_blocks_reachable_nodes_and_path_to_nearest() {
+  let mut archipelago =
+    Archipelago::<XY>::new(ArchipelagoOptions::from_agent_radius(0.5));
+
+  // +-+-+-+
+  // |S|F|E|
+  // +-+-+-+
+  let nav_mesh = Arc::new(
+    NavigationMesh {
+      vertices: vec![
+        Vec2::new(0.0, 0.0),
+        Vec2::new(1.0, 0.0),
+        Vec2::new(2.0, 0.0),
+        Vec2::new(3.0, 0.0),
+        Vec2::new(0.0, 1.0),
+        Vec2::new(1.0, 1.0),
+        Vec2::new(2.0, 1.0),
+        Vec2::new(3.0, 1.0),
+      ],
+      polygons: vec![vec![0, 1, 5, 4], vec![1, 2, 6, 5], vec![2, 3, 7, 6]],
+      polygon_type_indices: vec![0, 0, 0],
+      polygon_flags: Some(vec![0, 0b1, 0]),
+      height_mesh: None,
+    }
+    .validate()
+    .expect("nav mesh is valid"),
+  );
+  archipelago.add_island(Island::new(Transform::default(), nav_mesh));
+  archipelago.update(1.0);
+
+  let start_point =
+    archipelago.sample_point(Vec2::new(0.5, 0.5), &0.1).unwrap();
+  let end_points =
+    [archipelago.sample_point(Vec2::new(2.5, 0.5), &0.1).unwrap()];
+
+  expect_that!(
+    archipelago.find_reachable_nodes(
+      &start_point,
+      /* max_cost= */ 10.0,
+      &PathQuery::default(),
+    ),
+    ok(len(eq(3)))
+  );
+  expect_that!(
+    archipelago.find_path_to_nearest(
+      &start_point,
+      &end_points,
+      &PathQuery::default(),
+    ),
+    ok((eq(&0), anything()))
+  );
+
+  // Excluding the flagged polygon blocks the only route to E.
+  let flag_filter =
+    PolygonFlagFilter { exclude_flags: 0b1, ..Default::default() };
+  expect_that!(
+    archipelago.find_reachable_nodes(
+      &start_point,
+      /* max_cost= */ 10.0,
+      &PathQuery { flag_filter, ..Default::default() },
+    ),
+    ok(len(eq(1)))
+  );
+  expect_that!(
+    archipelago.find_path_to_nearest(
+      &start_point,
+      &end_points,
+      &PathQuery { flag_filter, ..Default::default() },
+    ),
+    err(eq(&FindPathError::NoPathFound))
+  );
+}
+
+#[googletest::test]
+fn agent_radius_blocks_reachable_nodes_and_path_to_nearest() {
+  let mut archipelago =
+    Archipelago::<XY>::new(ArchipelagoOptions::from_agent_radius(0.5));
+
+  // +-+-+-+
+  // |S| |E|
+  // +-+-+-+
+  archipelago
+    .add_island(Island::new(Transform::default(), strip_nav_mesh(vec![0; 3])));
+  archipelago.update(1.0);
+
+  let start_point =
+    archipelago.sample_point(Vec2::new(0.5, 0.5), &0.1).unwrap();
+  let end_points =
+    [archipelago.sample_point(Vec2::new(2.5, 0.5), &0.1).unwrap()];
+
+  expect_that!(
+    archipelago.find_reachable_nodes(
+      &start_point,
+      /* max_cost= */ 10.0,
+      &PathQuery { agent_radius: 0.5, ..Default::default() },
+    ),
+    ok(len(eq(3)))
+  );
+  expect_that!(
+    archipelago.find_path_to_nearest(
+      &start_point,
+      &end_points,
+      &PathQuery { agent_radius: 0.5, ..Default::default() },
+    ),
+    ok((eq(&0), anything()))
+  );
+
+  // The strip is too narrow for a wider agent.
+  expect_that!(
+    archipelago.find_reachable_nodes(
+      &start_point,
+      /* max_cost= */ 10.0,
+      &PathQuery { agent_radius: 0.6, ..Default::default() },
+    ),
+    ok(len(eq(1)))
+  );
+  expect_that!(
+    archipelago.find_path_to_nearest(
+      &start_point,
+      &end_points,
+      &PathQuery { agent_radius: 0.6, ..Default::default() },
+    ),
+    err(eq(&FindPathError::NoPathFound))
+  );
+}
+
+#[googletest::test]
+fn finds_path_to_nearest_end_point_with_pathfinding_mode() {
+  // +-+-+
+  // | | |
+  // | | |
+  // |2| |
+  // | | |
+  // |S|1|
+  // +-+-+
+  let nav_mesh = Arc::new(
+    NavigationMesh {
+      vertices: vec![
+        Vec2::new(0.0, 0.0),
+        Vec2::new(1.0, 0.0),
+        Vec2::new(2.0, 0.0),
+        Vec2::new(2.0, 10.0),
+        Vec2::new(1.0, 10.0),
+        Vec2::new(0.0, 10.0),
+      ],
+      polygons: vec![vec![0, 1, 4, 5], vec![1, 2, 3, 4]],
+      polygon_type_indices: vec![0; 2],
+      polygon_flags: None,
+      height_mesh: None,
+    }
+    .validate()
+    .expect("nav mesh is valid"),
+  );
+
+  let find_nearest_end_point_index = |pathfinding_mode| {
+    let mut archipelago = Archipelago::<XY>::new(ArchipelagoOptions {
+      pathfinding_mode,
+      ..ArchipelagoOptions::from_agent_radius(0.5)
+    });
+    archipelago.add_island(Island::new(Transform::default(), nav_mesh.clone()));
+    archipelago.update(1.0);
+
+    let start_point =
+      archipelago.sample_point(Vec2::new(0.5, 0.5), &0.1).unwrap();
+    let end_points = [
+      archipelago.sample_point(Vec2::new(1.5, 0.5), &0.1).unwrap(),
+      archipelago.sample_point(Vec2::new(0.5, 3.0), &0.1).unwrap(),
+    ];
+    archipelago
+      .find_path_to_nearest(&start_point, &end_points, &PathQuery::default())
+      .map(|(end_index, _)| end_index)
+  };
+
+  // Going through the midpoint of the long edge makes the first end point
+  // seem far away.
+  expect_that!(
+    find_nearest_end_point_index(PathfindingMode::EdgeMidpoints),
+    ok(eq(1))
+  );
+  // The first end point is actually the closest.
+  expect_that!(
+    find_nearest_end_point_index(PathfindingMode::AnyAngle),
+    ok(eq(0))
+  );
+}
+
+#[googletest::test]
+fn finds_nearest_end_point_in_same_node() {
+  let mut archipelago =
+    Archipelago::<XY>::new(ArchipelagoOptions::from_agent_radius(0.5));
+  archipelago
+    .add_island(Island::new(Transform::default(), strip_nav_mesh(vec![0; 3])));
+  archipelago.update(1.0);
+
+  let start_point =
+    archipelago.sample_point(Vec2::new(1.5, 0.5), &0.1).unwrap();
+  let end_points = [
+    archipelago.sample_point(Vec2::new(2.5, 0.5), &0.1).unwrap(),
+    archipelago.sample_point(Vec2::new(1.75, 0.75), &0.1).unwrap(),
+    archipelago.sample_point(Vec2::new(1.1, 0.1), &0.1).unwrap(),
+  ];
+
+  expect_that!(
+    archipelago.find_path_to_nearest(
+      &start_point,
+      &end_points,
+      &PathQuery::default(),
+    ),
+    ok((
+      eq(&1),
+      elements_are!(
+        &PathStep::Waypoint(Vec2::new(1.5, 0.5)),
+        &PathStep::Waypoint(Vec2::new(1.75, 0.75)),
+      )
+    ))
+  );
+}
+
+#[googletest::test]
+fn raycast_walks_across_islands_until_boundary() {
+  let mut archipelago =
+    Archipelago::<XY>::new(ArchipelagoOptions::from_agent_radius(0.5));
+
+  // +-+-+-+-+-+
+  // |S| | |   |
+  // +-+-+-+-+-+
+  // The last two nodes are a separate island.
+  let island_1 = archipelago
+    .add_island(Island::new(Transform::default(), strip_nav_mesh(vec![0; 3])));
+  let island_2 = archipelago.add_island(Island::new(
+    Transform {
+      translation: Vec2::new(3.0, 0.0),
+      rotation: Quat::IDENTITY,
+      scale: 1.0,
+    },
+    strip_nav_mesh(vec![0; 2]),
+  ));
+  archipelago.update(1.0);
+
+  let start_point =
+    archipelago.sample_point(Vec2::new(0.5, 0.5), &0.1).unwrap();
+  let node_1 = |polygon_index| NodeRef { island_id: island_1, polygon_index };
+  let node_2 = |polygon_index| NodeRef { island_id: island_2, polygon_index };
+
+  expect_that!(
+    archipelago.raycast(&start_point, &Vec2::new(0.7, 0.6)),
+    eq(&RaycastResult { hit: None, nodes: vec![node_1(0)] })
+  );
+  expect_that!(
+    archipelago.raycast(&start_point, &Vec2::new(4.5, 0.5)),
+    eq(&RaycastResult {
+      hit: None,
+      nodes: vec![node_1(0), node_1(1), node_1(2), node_2(0), node_2(1)],
+    })
+  );
+  expect_that!(
+    archipelago.raycast(&start_point, &Vec2::new(10.0, 0.5)),
+    eq(&RaycastResult {
+      hit: Some(RaycastHit {
+        point: Vec2::new(5.0, 0.5),
+        normal: Vec2::new(1.0, 0.0),
+      }),
+      nodes: vec![node_1(0), node_1(1), node_1(2), node_2(0), node_2(1)],
+    })
+  );
+  expect_that!(
+    archipelago.raycast(&start_point, &Vec2::new(0.5, 3.0)),
+    eq(&RaycastResult {
+      hit: Some(RaycastHit {
+        point: Vec2::new(0.5, 1.0),
+        normal: Vec2::new(0.0, 1.0),
+      }),
+      nodes: vec![node_1(0)],
+    })
+  );
+}
+
+#[googletest::test]
+fn raycast_stops_at_closed_door() {
+  let mut archipelago =
+    Archipelago::<XY>::new(ArchipelagoOptions::from_agent_radius(0.5));
+
+  // +-+-+-+
+  // |S| | |
+  // +-+-+-+
+  // The door cuts through the middle node.
+  let island_id = archipelago
+    .add_island(Island::new(Transform::default(), strip_nav_mesh(vec![0; 3])));
+  let door_id = archipelago.add_door(Door {
+    edges: DoorEdges::Cut(Vec2::new(1.5, -1.0), Vec2::new(1.5, 2.0)),
+    open: false,
+  });
+  archipelago.update(1.0);
+
+  let start_point =
+    archipelago.sample_point(Vec2::new(0.5, 0.5), &0.1).unwrap();
+  let node = |polygon_index| NodeRef { island_id, polygon_index };
+
+  let result = archipelago.raycast(&start_point, &Vec2::new(2.5, 0.5));
+  expect_that!(result.nodes, elements_are!(eq(&node(0)), eq(&node(1))));
+  let hit = result.hit.expect("The ray hits the door.");
+  expect_that!((hit.point.x, hit.point.y), (near(1.5, 1e-3), near(0.5, 1e-5)));
+  expect_that!(hit.point.x, lt(1.5));
+  expect_eq!(hit.normal, Vec2::new(-1.0, 0.0));
+
+  // Opening the door lets the ray through.
+  archipelago.set_door_open(door_id, true);
+  archipelago.update(1.0);
+  let start_point =
+    archipelago.sample_point(Vec2::new(0.5, 0.5), &0.1).unwrap();
+  expect_that!(
+    archipelago.raycast(&start_point, &Vec2::new(2.5, 0.5)),
+    eq(&RaycastResult { hit: None, nodes: vec![node(0), node(1), node(2)] })
+  );
+}
+
+#[googletest::test]
+fn finds_nearest_boundary_ignoring_island_seams() {
+  let mut archipelago =
+    Archipelago::<XY>::new(ArchipelagoOptions::from_agent_radius(0.5));
+
+  // +-+-+-+-+-+
+  // | | | |   |
+  // +-+-+-+-+-+
+  // The last two nodes are a separate island.
+  archipelago
+    .add_island(Island::new(Transform::default(), strip_nav_mesh(vec![0; 3])));
+  archipelago.add_island(Island::new(
+    Transform {
+      translation: Vec2::new(3.0, 0.0),
+      rotation: Quat::IDENTITY,
+      scale: 1.0,
+    },
+    strip_nav_mesh(vec![0; 2]),
+  ));
+  archipelago.update(1.0);
+
+  // The seam between the islands is closer, but it isn't a boundary.
+  let point = archipelago.sample_point(Vec2::new(2.875, 0.375), &0.1).unwrap();
+  expect_that!(
+    archipelago.find_nearest_boundary(&point, /* max_distance= */ 1.0),
+    some(eq(&NearestBoundary {
+      point: Vec2::new(2.875, 0.0),
+      distance: 0.375,
+      normal: Vec2::new(0.0, -1.0),
+    }))
+  );
+
+  let point = archipelago.sample_point(Vec2::new(4.75, 0.5), &0.1).unwrap();
+  expect_that!(
+    archipelago.find_nearest_boundary(&point, /* max_distance= */ 1.0),
+    some(eq(&NearestBoundary {
+      point: Vec2::new(5.0, 0.5),
+      distance: 0.25,
+      normal: Vec2::new(1.0, 0.0),
+    }))
+  );
+  expect_that!(
+    archipelago.find_nearest_boundary(&point, /* max_distance= */ 0.125),
+    none()
+  );
+}
+
+#[googletest::test]
+fn queries_nodes_in_shapes() {
+  let mut archipelago =
+    Archipelago::<XY>::new(ArchipelagoOptions::from_agent_radius(0.5));
+
+  // +-+-+-+-+
+  // | | | | |
+  // +-+-+-+-+
+  let island_id_1 = archipelago.add_island(Island::new(
+    Transform {
+      translation: Vec2::new(1.0, 0.0),
+      rotation: Quat::IDENTITY,
+      scale: 1.0,
+    },
+    strip_nav_mesh(vec![0, 1, 0, 1]),
+  ));
+  // The same strip, but rotated to be vertical, from (-1, 5) to (0, 7).
+  let island_id_2 = archipelago.add_island(Island::new(
+    Transform {
+      translation: Vec2::new(0.0, 5.0),
+      rotation: Quat::from_rotation_z(std::f32::consts::FRAC_PI_2),
+      scale: 1.0,
+    },
+    strip_nav_mesh(vec![2, 3]),
+  ));
+
+  expect_that!(
+    archipelago.query_nodes_in_shape(&QueryShape::Circle {
+      center: Vec2::new(2.0, 0.5),
+      radius: 0.5,
+    }),
+    err(eq(&QueryNodesInShapeError::NavDataDirty))
+  );
+  archipelago.update(1.0);
+
+  let query = |shape: QueryShape<XY>| {
+    archipelago
+      .query_nodes_in_shape(&shape)
+      .unwrap()
+      .into_iter()
+      .map(|node| (node.node, node.type_index, node.area))
+      .collect::<Vec<_>>()
+  };
+  let node_1 =
+    |polygon_index| NodeRef { island_id: island_id_1, polygon_index };
+  let node_2 =
+    |polygon_index| NodeRef { island_id: island_id_2, polygon_index };
+  let quarter_circle = std::f32::consts::PI * 0.25 * 0.25;
+
+  expect_that!(
+    query(QueryShape::Circle { center: Vec2::new(2.0, 0.5), radius: 0.5 }),
+    elements_are!(
+      (eq(&node_1(0)), eq(&0), near(quarter_circle * 2.0, 1e-5)),
+      (eq(&node_1(1)), eq(&1), near(quarter_circle * 2.0, 1e-5)),
+    )
+  );
+  // The min and max of the box are in the "wrong" order, but that's fine.
+  expect_that!(
+    query(QueryShape::Aabb {
+      min: Vec2::new(0.0, 6.5),
+      max: Vec2::new(-1.0, 5.5),
+    }),
+    elements_are!(
+      (eq(&node_2(0)), eq(&2), near(0.5, 1e-5)),
+      (eq(&node_2(1)), eq(&3), near(0.5, 1e-5)),
+    )
+  );
+  // The triangle is clockwise, and tapers across the last two nodes.
+  expect_that!(
+    query(QueryShape::ConvexPolygon(vec![
+      Vec2::new(3.0, 0.0),
+      Vec2::new(3.0, 1.0),
+      Vec2::new(5.0, 0.0),
+    ])),
+    elements_are!(
+      (eq(&node_1(2)), eq(&0), near(0.75, 1e-5)),
+      (eq(&node_1(3)), eq(&1), near(0.25, 1e-5)),
+    )
+  );
+  // Shapes off the nav meshes don't find anything.
+  expect_that!(
+    query(QueryShape::Circle { center: Vec2::new(10.0, 10.0), radius: 1.0 }),
+    elements_are!()
+  );
+
+  expect_that!(
+    archipelago.query_nodes_in_shape(&QueryShape::Circle {
+      center: Vec2::new(2.0, 0.5),
+      radius: -1.0,
+    }),
+    err(eq(&QueryNodesInShapeError::NegativeRadius(-1.0)))
+  );
+  expect_that!(
+    archipelago.query_nodes_in_shape(&QueryShape::ConvexPolygon(vec![
+      Vec2::new(0.0, 0.0),
+      Vec2::new(2.0, 0.0),
+      Vec2::new(1.0, 0.5),
+      Vec2::new(2.0, 1.0),
+      Vec2::new(0.0, 1.0),
+    ])),
+    err(eq(&QueryNodesInShapeError::InvalidPolygon))
+  );
+}
+
+/// Creates a simple deterministic random number generator.
+fn test_rng() -> impl FnMut() -> f32 {
+  let mut state = 12345u32;
+  move || {
+    state = state.wrapping_mul(1664525).wrapping_add(1013904223);
+    (state >> 8) as f32 / (1 << 24) as f32
+  }
+}
+
+#[googletest::test]
+fn samples_random_points_with_constraints() {
+  let mut archipelago =
+    Archipelago::<XY>::new(ArchipelagoOptions::from_agent_radius(0.5));
+
+  // +-+-+-+-+     +-+
+  // | | | | |     | |
+  // +-+-+-+-+     +-+
+  let island_id_1 = archipelago.add_island(Island::new(
+    Transform::default(),
+    strip_nav_mesh(vec![0, 1, 0, 1]),
+  ));
+  let island_id_2 = archipelago.add_island(Island::new(
+    Transform {
+      translation: Vec2::new(10.0, 0.0),
+      rotation: Quat::IDENTITY,
+      scale: 1.0,
+    },
+    strip_nav_mesh(vec![0]),
+  ));
+
+  let mut rng = test_rng();
+  expect_that!(
+    archipelago
+      .sample_random_point(&RandomPointConstraints::default(), &mut rng)
+      .map(|point| point.point()),
+    err(eq(SampleRandomPointError::NavDataDirty))
+  );
+  archipelago.update(1.0);
+
+  let mut sample = |constraints: &RandomPointConstraints<XY>| {
+    (0..100)
+      .map(|_| {
+        let point =
+          archipelago.sample_random_point(constraints, &mut rng).unwrap();
+        (point.point(), point.node(), point.type_index())
+      })
+      .collect::<Vec<_>>()
+  };
+
+  // Points are picked by area, so the larger island gets most of the points.
+  let points = sample(&RandomPointConstraints::default());
+  let island_1_points =
+    points.iter().filter(|(_, node, _)| node.island_id == island_id_1).count();
+  expect_that!(island_1_points, gt(60));
+  expect_that!(island_1_points, lt(100));
+  for (point, node, _) in points {
+    let offset = if node.island_id == island_id_1 { 0.0 } else { 10.0 };
+    let local_point = point - Vec2::new(offset, 0.0);
+    expect_that!(local_point.x - node.polygon_index as f32, ge(-1e-5));
+    expect_that!(local_point.x - node.polygon_index as f32, le(1.0 + 1e-5));
+    expect_that!(local_point.y, ge(-1e-5));
+    expect_that!(local_point.y, le(1.0 + 1e-5));
+  }
+
+  let points = sample(&RandomPointConstraints {
+    type_indices: Some(HashSet::from([1])),
+    ..Default::default()
+  });
+  expect_that!(points, each((anything(), anything(), eq(&1))));
+
+  let center = Vec2::new(1.0, 0.5);
+  let points = sample(&RandomPointConstraints {
+    within_radius: Some((center, 0.25)),
+    ..Default::default()
+  });
+  for (point, _, _) in points {
+    expect_that!(point.distance(center), le(0.25 + 1e-5));
+  }
+
+  let start_point =
+    archipelago.sample_point(Vec2::new(10.5, 0.5), &0.1).unwrap();
+  let points = sample(&RandomPointConstraints {
+    reachable_from: Some((&start_point, PermittedAnimationLinks::All)),
+    ..Default::default()
+  });
+  expect_that!(
+    points,
+    each((
+      anything(),
+      eq(&NodeRef { island_id: island_id_2, polygon_index: 0 }),
+      anything()
+    ))
+  );
+
+  expect_that!(
+    archipelago
+      .sample_random_point(
+        &RandomPointConstraints {
+          type_indices: Some(HashSet::from([5])),
+          ..Default::default()
+        },
+        &mut rng
+      )
+      .map(|point| point.point()),
+    err(eq(SampleRandomPointError::NoPointFound))
+  );
+}
+
+#[googletest::test]
+fn random_points_are_reproducible() {
+  let mut archipelago =
+    Archipelago::<XY>::new(ArchipelagoOptions::from_agent_radius(0.5));
+  archipelago
+    .add_island(Island::new(Transform::default(), strip_nav_mesh(vec![0; 4])));
+  archipelago.update(1.0);
+
+  // Using the same sequence of random numbers gives the same points.
+  let sample = || {
+    let mut rng = test_rng();
+    (0..10)
+      .map(|_| {
+        archipelago
+          .sample_random_point(&RandomPointConstraints::default(), &mut rng)
+          .unwrap()
+          .point()
+      })
+      .collect::<Vec<_>>()
+  };
+  expect_that!(sample(), eq(&sample()));
+}
+
+#[googletest::test]
+fn gets_remaining_path_of_agent() {
+  let mut archipelago =
+    Archipelago::<XY>::new(ArchipelagoOptions::from_agent_radius(0.5));
+
+  // +-+-+-+-+-+
+  // |A| | | |T|
+  // +-+-+-+-+-+
+  // The last two nodes are a separate island.
+  let island_id_1 = archipelago
+    .add_island(Island::new(Transform::default(), strip_nav_mesh(vec![0; 3])));
+  let island_id_2 = archipelago.add_island(Island::new(
+    Transform {
+      translation: Vec2::new(3.0, 0.0),
+      rotation: Quat::IDENTITY,
+      scale: 1.0,
+    },
+    strip_nav_mesh(vec![0; 2]),
+  ));
+
+  let agent_id = archipelago.add_agent(Agent::create(
+    /* position= */ Vec2::new(0.5, 0.5),
+    /* velocity= */ Vec2::ZERO,
+    /* radius= */ 0.5,
+    /* desired_speed= */ 1.0,
+    /* max_speed= */ 1.0,
+  ));
+  archipelago.update(1.0);
+  expect_that!(
+    archipelago.get_agent_path(agent_id),
+    err(eq(&AgentPathError::NoPath))
+  );
+
+  archipelago.get_agent_mut(agent_id).unwrap().current_target =
+    Some(Vec2::new(4.5, 0.25));
+  archipelago.update(1.0);
+
+  let node_1 =
+    |polygon_index| NodeRef { island_id: island_id_1, polygon_index };
+  let node_2 =
+    |polygon_index| NodeRef { island_id: island_id_2, polygon_index };
+  let agent_path = archipelago.get_agent_path(agent_id).unwrap();
+  expect_that!(
+    agent_path,
+    eq(&AgentPath {
+      steps: vec![
+        PathStep::Waypoint(Vec2::new(0.5, 0.5)),
+        PathStep::Waypoint(Vec2::new(4.5, 0.25)),
+      ],
+      length: Vec2::new(4.0, -0.25).length(),
+      corridor: vec![node_1(0), node_1(1), node_1(2), node_2(0), node_2(1)],
+    })
+  );
+  expect_that!(
+    agent_path.islands(),
+    elements_are!(eq(&island_id_1), eq(&island_id_2))
+  );
+  expect_that!(agent_path.animation_links().count(), eq(0));
+
+  // Once the agent moves, the path only includes the rest of the corridor.
+  archipelago.get_agent_mut(agent_id).unwrap().position = Vec2::new(3.5, 0.5);
+  archipelago.update(1.0);
+  expect_that!(
+    archipelago.get_agent_path(agent_id).map(|path| path.corridor),
+    ok(elements_are!(eq(&node_2(0)), eq(&node_2(1))))
+  );
+
+  archipelago.remove_island(island_id_2);
+  expect_that!(
+    archipelago.get_agent_path(agent_id),
+    err(eq(&AgentPathError::NavDataDirty))
+  );
+
+  archipelago.remove_agent(agent_id);
+  expect_that!(
+    archipelago.get_agent_path(agent_id),
+    err(eq(&AgentPathError::AgentNotFound))
+  );
+}
@@ -0,0 +1,134 @@
+use std::collections::HashSet;
+
+use glam::{Vec3, Vec3Swizzles};
+
+use crate::{
+  CoordinateSystem, NavigationData,
+  geometry::project_point_to_line_segment,
+  nav_data::{KindedOffMeshLink, NodeRef},
+};
+
+/// The result of walking a straight line across the navigation data.
+#[derive(PartialEq, Debug)]
+pub(crate) struct Raycast {
+  /// The point (in world space) where the line left the navigation data, and
+  /// the outward normal of the boundary edge it left through. Entering the
+  /// footprint of an obstacle or closed door also counts as leaving the
+  /// navigation data. [`None`] if the line reached its end without leaving the
+  /// navigation data.
+  pub(crate) hit: Option<(Vec3, Vec3)>,
+  /// The nodes that the line passed through, in order.
+  pub(crate) nodes: Vec<NodeRef>,
+}
+
+/// The max distance between a point and a boundary link's portal for the point
+/// to be considered on the portal.
+const PORTAL_EPSILON: f32 = 1e-4;
+
+/// Walks a straight line (ignoring height) from `start_point` in `start_node`
+/// to `end_point`, moving between nodes through their connectivity and
+/// boundary links, until the line ends, leaves the navigation data, or enters
+/// the footprint of an obstacle or closed door. Animation links are never
+/// taken, and node costs are ignored.
+pub(crate) fn raycast<CS: CoordinateSystem>(
+  nav_data: &NavigationData<CS>,
+  (start_node, start_point): (NodeRef, Vec3),
+  end_point: Vec3,
+) -> Raycast {
+  let ray_start = start_point.xy();
+  let ray_delta = end_point.xy() - ray_start;
+
+  let mut nodes = vec![start_node];
+  let mut visited_nodes = HashSet::from([start_node]);
+  let mut current_node = start_node;
+  loop {
+    let island = nav_data.get_island(current_node.island_id).unwrap();
+    let polygon = &island.nav_mesh.polygons[current_node.polygon_index];
+
+    // The line leaves a convex polygon through the first edge that it crosses
+    // going outwards.
+    let mut exit = None;
+    for edge_index in 0..polygon.vertices.len() {
+      let (left, right) = polygon.get_edge_indices(edge_index);
+      // Edges are in counter-clockwise order, so the node is to the left of
+      // each edge.
+      let edge_start = island.transform.apply(island.nav_mesh.vertices[right]);
+      let edge_end = island.transform.apply(island.nav_mesh.vertices[left]);
+      let edge_delta = edge_end.xy() - edge_start.xy();
+      let outward_speed = -edge_delta.perp_dot(ray_delta);
+      if outward_speed <= 0.0 {
+        continue;
+      }
+      let t = edge_delta.perp_dot(ray_start - edge_start.xy()) / outward_speed;
+      if exit.is_none_or(|(exit_t, _, _)| t < exit_t) {
+        exit = Some((t, edge_index, (edge_start, edge_end)));
+      }
+    }
+
+    let Some((t, edge_index, edge)) = exit else {
+      // The line has no length, so it never leaves the node.
+      return Raycast { hit: None, nodes };
+    };
+
+    // Obstacles and closed doors stop the line while it is still in the node.
+    if let Some((hit_t, normal)) = nav_data
+      .find_carved_segment_hit(current_node, (ray_start, ray_start + ray_delta))
+      && hit_t < t.min(1.0)
+    {
+      let hit_point = start_point.lerp(end_point, hit_t);
+      let hit_point =
+        island.transform.apply(island.nav_mesh.sample_point_on_node(
+          island.transform.apply_inverse(hit_point),
+          current_node.polygon_index,
+        ));
+      return Raycast { hit: Some((hit_point, normal.extend(0.0))), nodes };
+    }
+
+    if t >= 1.0 {
+      return Raycast { hit: None, nodes };
+    }
+
+    // Take the point on the edge so the exit point has the edge's height.
+    let edge_delta = edge.1.xy() - edge.0.xy();
+    let fraction = (edge_delta.dot(ray_start + ray_delta * t - edge.0.xy())
+      / edge_delta.length_squared())
+    .clamp(0.0, 1.0);
+    let exit_point = edge.0.lerp(edge.1, fraction);
+
+    let next_node =
+      if let Some(connectivity) = &polygon.connectivity[edge_index] {
+        Some(NodeRef {
+          island_id: current_node.island_id,
+          polygon_index: connectivity.polygon_index,
+        })
+      } else {
+        nav_data
+          .node_to_off_mesh_link_ids
+          .get(&current_node)
+          .into_iter()
+          .flatten()
+          .map(|link_id| nav_data.off_mesh_links.get(*link_id).unwrap())
+          .filter(|link| {
+            matches!(link.kinded, KindedOffMeshLink::BoundaryLink { .. })
+          })
+          .find(|link| {
+            let portal = (link.portal.0.with_z(0.0), link.portal.1.with_z(0.0));
+            let (portal_point, _) =
+              project_point_to_line_segment(exit_point.with_z(0.0), portal);
+            portal_point.distance(exit_point.with_z(0.0)) <= PORTAL_EPSILON
+          })
+          .map(|link| link.destination_node)
+      };
+
+    // A straight line can't enter a convex node twice, so revisiting a node can
+    // only be due to floating point error (e.g., passing exactly through a
+    // vertex). Treat it like a boundary to guarantee we terminate.
+    let Some(next_node) = next_node.filter(|node| visited_nodes.insert(*node))
+    else {
+      let normal = Vec3::new(edge_delta.y, -edge_delta.x, 0.0).normalize();
+      return Raycast { hit: Some((exit_point, normal)), nodes };
+    };
+    nodes.push(next_node);
+    current_node = next_node;
+  }
+}
@@ -0,0 +1,161 @@
+use glam::{Vec3, Vec3Swizzles};
+
+use crate::{
+  CoordinateSystem, NavigationData, coords::CorePointSampleDistance,
+  nav_data::NodeRef, raycast::raycast,
+};
+
+/// Options for smoothing straight-line paths into curves. The curves pass
+/// through the corners of the straight-line path (so they swing wide around
+/// the corners, rather than cutting them), and are only used where they stay
+/// on the navigation meshes.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PathSmoothing {
+  /// The number of pieces that each straight line of the path is split into
+  /// when it is curved. Agents steer towards the end of the first piece, so
+  /// fewer pieces make agents turn more gradually.
+  pub subdivisions: u32,
+}
+
+impl Default for PathSmoothing {
+  fn default() -> Self {
+    Self { subdivisions: 8 }
+  }
+}
+
+/// The max horizontal distance between a point and the point sampled on the
+/// navigation meshes for the point to be considered on the navigation meshes.
+const ON_MESH_DISTANCE: f32 = 1e-3;
+
+/// Evaluates the centripetal Catmull-Rom spline through `points` at `t`. The
+/// spline goes from `points[1]` (at `t` = 0) to `points[2]` (at `t` = 1), and
+/// the outer points determine the tangents at the ends. Centripetal splines
+/// don't overshoot or form loops between the points.
+pub(crate) fn catmull_rom(points: [Vec3; 4], t: f32) -> Vec3 {
+  // Clamp the knot intervals to avoid dividing by zero for repeated points.
+  let knot_interval =
+    |start: Vec3, end: Vec3| start.distance(end).sqrt().max(1e-4);
+  let t0 = 0.0;
+  let t1 = t0 + knot_interval(points[0], points[1]);
+  let t2 = t1 + knot_interval(points[1], points[2]);
+  let t3 = t2 + knot_interval(points[2], points[3]);
+  let u = t1 + (t2 - t1) * t;
+
+  let blend = |(start, start_knot): (Vec3, f32),
+               (end, end_knot): (Vec3, f32)| {
+    start.lerp(end, (u - start_knot) / (end_knot - start_knot))
+  };
+  let a1 = blend((points[0], t0), (points[1], t1));
+  let a2 = blend((points[1], t1), (points[2], t2));
+  let a3 = blend((points[2], t2), (points[3], t3));
+  let b1 = a1.lerp(a2, (u - t0) / (t2 - t0));
+  let b2 = a2.lerp(a3, (u - t1) / (t3 - t1));
+  b1.lerp(b2, (u - t1) / (t2 - t1))
+}
+
+/// Determines whether an agent can walk in a straight line from `start_point`
+/// (in `start_node`) to `end_point`. Leaving the navigation meshes right at
+/// `end_point` is allowed, since curves often end on corners of the
+/// navigation meshes.
+fn is_line_walkable<CS: CoordinateSystem>(
+  nav_data: &NavigationData<CS>,
+  (start_node, start_point): (NodeRef, Vec3),
+  end_point: Vec3,
+) -> bool {
+  raycast(nav_data, (start_node, start_point), end_point).hit.is_none_or(
+    |(hit_point, _)| hit_point.xy().distance(end_point.xy()) < ON_MESH_DISTANCE,
+  )
+}
+
+/// Smooths the polyline `points` (in world space) into a curve. Each line is
+/// split into `smoothing.subdivisions` pieces, unless the curve along that
+/// line would leave the navigation meshes, in which case the line is kept
+/// straight. The first and last points are kept as is.
+pub(crate) fn smooth_polyline<CS: CoordinateSystem>(
+  nav_data: &NavigationData<CS>,
+  points: &[Vec3],
+  smoothing: &PathSmoothing,
+  point_sample_distance: &CorePointSampleDistance,
+) -> Vec<Vec3> {
+  let Some(&first_point) = points.first() else {
+    return vec![];
+  };
+  let mut smoothed_points = vec![first_point];
+  for (index, line) in points.windows(2).enumerate() {
+    let (start, end) = (line[0], line[1]);
+    // Mirror the ends of the polyline so the curve starts and ends heading
+    // along the polyline.
+    let before = if index == 0 { start * 2.0 - end } else { points[index - 1] };
+    let after = points.get(index + 2).copied().unwrap_or(end * 2.0 - start);
+
+    let curve = (1..smoothing.subdivisions)
+      .map(|i| {
+        let point = catmull_rom(
+          [before, start, end, after],
+          i as f32 / smoothing.subdivisions as f32,
+        );
+        let (sampled_point, node) =
+          nav_data.sample_point(point, point_sample_distance)?;
+        (sampled_point.xy().distance(point.xy()) < ON_MESH_DISTANCE)
+          .then_some((sampled_point, node))
+      })
+      .collect::<Option<Vec<_>>>();
+    // Walk from each point on the curve to its neighbours, so we never need
+    // to start walking from the (often ambiguous) corners of the path.
+    let curve = curve.filter(|curve| {
+      curve.iter().enumerate().all(|(i, &(point, node))| {
+        let previous_point = if i == 0 { start } else { curve[i - 1].0 };
+        let next_point = curve.get(i + 1).map_or(end, |&(point, _)| point);
+        is_line_walkable(nav_data, (node, point), previous_point)
+          && is_line_walkable(nav_data, (node, point), next_point)
+      })
+    });
+    if let Some(curve) = curve {
+      smoothed_points.extend(curve.into_iter().map(|(point, _)| point));
+    }
+    smoothed_points.push(end);
+  }
+  smoothed_points
+}
+
+/// Finds the point that an agent at `agent_point` (in `agent_node`) moving
+/// with `velocity` should steer towards in order to follow the curve through
+/// `waypoint` and then `next_point` (if there is one). The curve starts
+/// heading along the agent's velocity, so the agent turns gradually. Returns
+/// `waypoint` if the agent cannot walk straight to the curve.
+pub(crate) fn smoothed_steering_point<CS: CoordinateSystem>(
+  nav_data: &NavigationData<CS>,
+  (agent_node, agent_point): (NodeRef, Vec3),
+  velocity: Vec3,
+  waypoint: Vec3,
+  next_point: Option<Vec3>,
+  smoothing: &PathSmoothing,
+) -> Vec3 {
+  if smoothing.subdivisions <= 1 {
+    return waypoint;
+  }
+  let to_waypoint = waypoint - agent_point;
+  let heading = velocity.xy().normalize_or_zero();
+  // If the agent is not moving towards the waypoint, there is no curve to
+  // follow, so head straight to the waypoint.
+  let before = if heading.dot(to_waypoint.xy()) > 0.0 {
+    agent_point - heading.extend(0.0) * to_waypoint.length()
+  } else {
+    agent_point - to_waypoint
+  };
+  let after = next_point.unwrap_or(waypoint + to_waypoint);
+
+  let steering_point = catmull_rom(
+    [before, agent_point, waypoint, after],
+    1.0 / smoothing.subdivisions as f32,
+  );
+  if is_line_walkable(nav_data, (agent_node, agent_point), steering_point) {
+    steering_point
+  } else {
+    waypoint
+  }
+}
+
+#[cfg(test)]
+#[path = "smoothing_test.rs"]
+mod test;
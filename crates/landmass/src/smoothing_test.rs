@@ -0,0 +1,96 @@
+use std::sync::Arc;
+
+use glam::{Vec2, Vec3};
+
+use crate::{
+  Archipelago, ArchipelagoOptions, FromAgentRadius, Island, Transform,
+  coords::{CorePointSampleDistance, XY},
+  nav_mesh::NavigationMesh,
+};
+
+use super::{PathSmoothing, catmull_rom, smooth_polyline};
+
+#[test]
+fn catmull_rom_goes_between_middle_points() {
+  let points = [
+    Vec3::new(0.0, 0.0, 0.0),
+    Vec3::new(1.0, 0.0, 0.0),
+    Vec3::new(2.0, 1.0, 0.0),
+    Vec3::new(3.0, 1.0, 0.0),
+  ];
+  assert_eq!(catmull_rom(points, 0.0), points[1]);
+  assert!(catmull_rom(points, 1.0).distance(points[2]) < 1e-5);
+
+  // Evenly spaced points on a line stay on the line.
+  let points = [
+    Vec3::new(0.0, 0.0, 0.0),
+    Vec3::new(1.0, 0.0, 0.0),
+    Vec3::new(2.0, 0.0, 0.0),
+    Vec3::new(3.0, 0.0, 0.0),
+  ];
+  assert!(catmull_rom(points, 0.5).distance(Vec3::new(1.5, 0.0, 0.0)) < 1e-5);
+}
+
+#[test]
+fn smooths_lines_that_stay_on_nav_mesh() {
+  let nav_mesh = NavigationMesh {
+    vertices: vec![
+      Vec2::new(0.0, 0.0),
+      Vec2::new(4.0, 0.0),
+      Vec2::new(4.0, 4.0),
+      Vec2::new(0.0, 4.0),
+    ],
+    polygons: vec![vec![0, 1, 2, 3]],
+    polygon_type_indices: vec![0],
+    polygon_flags: None,
+    height_mesh: None,
+  }
+  .validate()
+  .expect("Mesh is valid.");
+
+  let mut archipelago =
+    Archipelago::<XY>::new(ArchipelagoOptions::from_agent_radius(0.5));
+  archipelago.add_island(Island::new(Transform::default(), Arc::new(nav_mesh)));
+  archipelago.update(1.0);
+
+  let point_sample_distance = CorePointSampleDistance::new(
+    &archipelago.archipelago_options.point_sample_distance,
+  );
+  let smoothing = PathSmoothing { subdivisions: 4 };
+
+  // Away from the walls, both lines are curved.
+  let smoothed_points = smooth_polyline(
+    &archipelago.nav_data,
+    &[
+      Vec3::new(1.0, 1.0, 0.0),
+      Vec3::new(3.0, 1.0, 0.0),
+      Vec3::new(3.0, 3.0, 0.0),
+    ],
+    &smoothing,
+    &point_sample_distance,
+  );
+  assert_eq!(smoothed_points.len(), 9);
+  assert_eq!(smoothed_points[0], Vec3::new(1.0, 1.0, 0.0));
+  assert_eq!(smoothed_points[4], Vec3::new(3.0, 1.0, 0.0));
+  assert_eq!(smoothed_points[8], Vec3::new(3.0, 3.0, 0.0));
+  // The curve swings wide of the corner.
+  assert!(smoothed_points[3].y < 1.0);
+  assert!(smoothed_points[5].x > 3.0);
+
+  // The first line is along a wall, so curving it would leave the nav mesh.
+  let smoothed_points = smooth_polyline(
+    &archipelago.nav_data,
+    &[
+      Vec3::new(1.0, 0.0, 0.0),
+      Vec3::new(3.0, 0.0, 0.0),
+      Vec3::new(3.0, 3.0, 0.0),
+    ],
+    &smoothing,
+    &point_sample_distance,
+  );
+  assert_eq!(smoothed_points.len(), 6);
+  assert_eq!(smoothed_points[0], Vec3::new(1.0, 0.0, 0.0));
+  assert_eq!(smoothed_points[1], Vec3::new(3.0, 0.0, 0.0));
+  assert!(smoothed_points[2].x > 3.0);
+  assert_eq!(smoothed_points[5], Vec3::new(3.0, 3.0, 0.0));
+}
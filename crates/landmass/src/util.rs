@@ -4,6 +4,44 @@ use glam::{Quat, Vec3};
 
 use crate::CoordinateSystem;
 
+/// Maps each of `items` with `map`, returning the results in the same order as
+/// `items`. With the `parallel` feature, the items are mapped across a thread
+/// pool.
+#[cfg(feature = "parallel")]
+pub(crate) fn par_map<T: Send, R: Send>(
+  items: Vec<T>,
+  map: impl Fn(T) -> R + Send + Sync,
+) -> Vec<R> {
+  use rayon::iter::{IntoParallelIterator, ParallelIterator};
+  items.into_par_iter().map(map).collect()
+}
+
+/// Maps each of `items` with `map`, returning the results in the same order as
+/// `items`. With the `parallel` feature, the items are mapped across a thread
+/// pool.
+#[cfg(not(feature = "parallel"))]
+pub(crate) fn par_map<T, R>(items: Vec<T>, map: impl Fn(T) -> R) -> Vec<R> {
+  items.into_iter().map(map).collect()
+}
+
+/// Calls `f` on each of `items`. With the `parallel` feature, the items are
+/// processed across a thread pool.
+#[cfg(feature = "parallel")]
+pub(crate) fn par_for_each<T: Send>(
+  items: Vec<T>,
+  f: impl Fn(T) + Send + Sync,
+) {
+  use rayon::iter::{IntoParallelIterator, ParallelIterator};
+  items.into_par_iter().for_each(f);
+}
+
+/// Calls `f` on each of `items`. With the `parallel` feature, the items are
+/// processed across a thread pool.
+#[cfg(not(feature = "parallel"))]
+pub(crate) fn par_for_each<T>(items: Vec<T>, f: impl Fn(T)) {
+  items.into_iter().for_each(f);
+}
+
 /// An f32 that supports `Ord` operations.
 pub(crate) struct FloatOrd(pub(crate) f32);
 
@@ -82,7 +120,6 @@ impl BoundingBox {
   }
 
   /// Returns the bounds of the box, assuming it is non-empty.
-  #[allow(unused)] // Used by tests.
   pub(crate) fn as_box(&self) -> (Vec3, Vec3) {
     match self {
       Self::Empty => panic!("BoundingBox is not a box."),
@@ -267,63 +304,61 @@ impl BoundingBox {
     &self,
     transform: &Transform<CS>,
   ) -> Self {
+    self.map_corners(|corner| transform.apply(corner))
+  }
+
+  /// Creates a conservative bounding box around `self` after transforming it by
+  /// the inverse of `transform`.
+  pub(crate) fn transform_inverse<CS: CoordinateSystem>(
+    &self,
+    transform: &Transform<CS>,
+  ) -> Self {
+    self.map_corners(|corner| transform.apply_inverse(corner))
+  }
+
+  /// Creates a bounding box around the eight corners of `self` after they are
+  /// mapped by `map`.
+  fn map_corners(&self, map: impl Fn(Vec3) -> Vec3) -> Self {
     let (min, max) = match self {
       BoundingBox::Empty => return BoundingBox::Empty,
       BoundingBox::Box { min, max } => (min, max),
     };
-    let flat_max = Vec3::new(max.x, max.y, min.z);
-
-    let points = [
-      transform.apply(*min),
-      transform.apply(flat_max),
-      transform.apply(Vec3::new(min.x, max.y, min.z)),
-      transform.apply(Vec3::new(max.x, min.y, min.z)),
-    ];
-
-    BoundingBox::Box {
-      min: Vec3::new(
-        points
-          .iter()
-          .map(|p| p.x)
-          .min_by(|a, b| a.partial_cmp(b).unwrap())
-          .unwrap(),
-        points
-          .iter()
-          .map(|p| p.y)
-          .min_by(|a, b| a.partial_cmp(b).unwrap())
-          .unwrap(),
-        points[0].z,
-      ),
-      max: Vec3::new(
-        points
-          .iter()
-          .map(|p| p.x)
-          .max_by(|a, b| a.partial_cmp(b).unwrap())
-          .unwrap(),
-        points
-          .iter()
-          .map(|p| p.y)
-          .max_by(|a, b| a.partial_cmp(b).unwrap())
-          .unwrap(),
-        points[0].z + (max.z - min.z),
-      ),
-    }
+    (0..8).fold(BoundingBox::Empty, |bounds, corner: usize| {
+      bounds.expand_to_point(map(Vec3::new(
+        if corner & 1 == 0 { min.x } else { max.x },
+        if corner & 2 == 0 { min.y } else { max.y },
+        if corner & 4 == 0 { min.z } else { max.z },
+      )))
+    })
   }
 }
 
 /// A transform that can be applied to Vec3's.
+///
+/// Navigation meshes are authored with their own "up" direction (perpendicular
+/// to the plane of movement). Rotating the island tilts this up direction, so
+/// heights on the island (e.g., when sampling points) are measured along the
+/// tilted up direction, while agents still move in the world's plane of
+/// movement.
 pub struct Transform<CS: CoordinateSystem> {
   /// The translation to apply.
   pub translation: CS::Coordinate,
-  /// The rotation to apply around the "up" direction. Specifically, the up
-  /// direction is perpendicular to the plane of movement.
-  pub rotation: f32,
+  /// The rotation to apply. This is in the standard coordinate system (see
+  /// [`crate::coords::XYZ`]), so a rotation around the Z axis spins the island
+  /// around the up direction, while other rotations tilt the island.
+  pub rotation: Quat,
+  /// The uniform scale to apply. Must be positive.
+  pub scale: f32,
 }
 
 // Manual Clone impl to avoid `CS` having a Clone bound itself.
 impl<CS: CoordinateSystem> Clone for Transform<CS> {
   fn clone(&self) -> Self {
-    Self { translation: self.translation.clone(), rotation: self.rotation }
+    Self {
+      translation: self.translation.clone(),
+      rotation: self.rotation,
+      scale: self.scale,
+    }
   }
 }
 
@@ -335,6 +370,7 @@ impl<CS: CoordinateSystem<Coordinate: std::fmt::Debug>> std::fmt::Debug
     f.debug_struct("Transform")
       .field("translation", &self.translation)
       .field("rotation", &self.rotation)
+      .field("scale", &self.scale)
       .finish()
   }
 }
@@ -342,28 +378,44 @@ impl<CS: CoordinateSystem<Coordinate: std::fmt::Debug>> std::fmt::Debug
 // Manual Default impl to avoid `CS` having a Default bound itself.
 impl<CS: CoordinateSystem<Coordinate: Default>> Default for Transform<CS> {
   fn default() -> Self {
-    Self { translation: Default::default(), rotation: Default::default() }
+    Self {
+      translation: Default::default(),
+      rotation: Quat::IDENTITY,
+      scale: 1.0,
+    }
   }
 }
 
 // Manual PartialEq impl to avoid `CS` having a PartialEq bound itself.
 impl<CS: CoordinateSystem<Coordinate: PartialEq>> PartialEq for Transform<CS> {
   fn eq(&self, other: &Self) -> bool {
-    self.translation == other.translation && self.rotation == other.rotation
+    self.translation == other.translation
+      && self.rotation == other.rotation
+      && self.scale == other.scale
   }
 }
 
 impl<CS: CoordinateSystem> Transform<CS> {
   /// Applies the transformation.
   pub(crate) fn apply(&self, point: Vec3) -> Vec3 {
-    Quat::from_rotation_z(self.rotation) * point
-      + CS::to_landmass(&self.translation)
+    self.rotation * (point * self.scale) + CS::to_landmass(&self.translation)
   }
 
   /// Inverses the transformation.
   pub(crate) fn apply_inverse(&self, point: Vec3) -> Vec3 {
-    Quat::from_rotation_z(-self.rotation)
-      * (point - CS::to_landmass(&self.translation))
+    self.rotation.inverse() * (point - CS::to_landmass(&self.translation))
+      / self.scale
+  }
+
+  /// Inverses the rotation of a vector (e.g., a velocity). The scale is not
+  /// inverted, so the vector keeps its length.
+  pub(crate) fn apply_inverse_rotation(&self, vector: Vec3) -> Vec3 {
+    self.rotation.inverse() * vector
+  }
+
+  /// Converts a distance in world space into a distance in the island's space.
+  pub(crate) fn apply_inverse_to_distance(&self, distance: f32) -> f32 {
+    distance / self.scale
   }
 }
 
@@ -1,13 +1,13 @@
 use std::f32::consts::PI;
 
-use glam::Vec3;
+use glam::{Quat, Vec3};
 
 use crate::{
   Transform, XYZ,
   util::{BoundingBox, RaySegment},
 };
 
-use super::BoundingBoxHierarchy;
+use super::{BoundingBoxHierarchy, par_map};
 
 #[test]
 fn bounding_box_expands_to_points() {
@@ -241,7 +241,8 @@ fn transform_empty_does_nothing() {
   assert_eq!(
     BoundingBox::Empty.transform(&Transform::<XYZ> {
       translation: Vec3::new(1.0, 2.0, 3.0),
-      rotation: 0.75
+      rotation: Quat::from_rotation_z(0.75),
+      scale: 1.0
     }),
     BoundingBox::Empty
   );
@@ -254,7 +255,8 @@ fn transforms_bounds() {
     BoundingBox::new_box(Vec3::new(1.0, 3.0, 2.0), Vec3::new(6.0, 4.0, 5.0))
       .transform(&Transform::<XYZ> {
         translation: Vec3::new(-4.0, 1.0, -3.0),
-        rotation: PI * -0.75,
+        rotation: Quat::from_rotation_z(PI * -0.75),
+        scale: 1.0,
       })
       .as_box();
   let expected_min = Vec3::new(-3.0 / root_2 - 4.0, -10.0 / root_2 + 1.0, -1.0);
@@ -269,6 +271,49 @@ fn transforms_bounds() {
   );
 }
 
+#[test]
+fn transforms_bounds_with_tilt_and_scale() {
+  let (actual_min, actual_max) =
+    BoundingBox::new_box(Vec3::ZERO, Vec3::new(1.0, 2.0, 0.0))
+      .transform(&Transform::<XYZ> {
+        translation: Vec3::new(1.0, 1.0, 1.0),
+        rotation: Quat::from_rotation_x(PI * 0.5),
+        scale: 2.0,
+      })
+      .as_box();
+  let expected_min = Vec3::new(1.0, 1.0, 1.0);
+  assert!(
+    actual_min.abs_diff_eq(expected_min, 1e-6),
+    "actual_min={actual_min} expected_min={expected_min}"
+  );
+  let expected_max = Vec3::new(3.0, 1.0, 5.0);
+  assert!(
+    actual_max.abs_diff_eq(expected_max, 1e-6),
+    "actual_max={actual_max} expected_max={expected_max}"
+  );
+}
+
+#[test]
+fn transform_inverse_undoes_transform() {
+  let transform = Transform::<XYZ> {
+    translation: Vec3::new(-4.0, 1.0, -3.0),
+    rotation: Quat::from_euler(glam::EulerRot::XYZ, 0.3, -0.2, 1.5),
+    scale: 0.5,
+  };
+  let point = Vec3::new(1.0, 2.0, 3.0);
+  let transformed = transform.apply(point);
+  assert!(
+    transformed.abs_diff_eq(
+      transform.rotation * Vec3::new(0.5, 1.0, 1.5) + transform.translation,
+      1e-6
+    ),
+    "transformed={transformed}"
+  );
+  let round_trip = transform.apply_inverse(transformed);
+  assert!(round_trip.abs_diff_eq(point, 1e-5), "round_trip={round_trip}");
+  assert_eq!(transform.apply_inverse_to_distance(3.0), 6.0);
+}
+
 #[test]
 fn octant_bounding_box_hierarchy() {
   let mut values = vec![
@@ -377,3 +422,12 @@ fn bounding_box_hierarchy_with_same_big_dimension() {
     [&0],
   );
 }
+
+#[test]
+fn par_map_preserves_order() {
+  let items = (0..1000).collect::<Vec<_>>();
+  assert_eq!(
+    par_map(items.clone(), |item| item * 2),
+    items.iter().map(|item| item * 2).collect::<Vec<_>>()
+  );
+}
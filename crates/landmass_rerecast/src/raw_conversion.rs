@@ -35,6 +35,10 @@ pub fn convert_rerecast_navmesh_to_landmass_navmesh(
       .iter()
       .map(|a| a.0 as usize)
       .collect(),
+    // The flags may be empty (e.g., for nav meshes built by hand).
+    polygon_flags: (!rerecast_navmesh.polygon.flags.is_empty()).then(|| {
+      rerecast_navmesh.polygon.flags.iter().map(|&flags| flags as u32).collect()
+    }),
     height_mesh: HeightNavigationMesh3d {
       polygons: rerecast_navmesh
         .detail
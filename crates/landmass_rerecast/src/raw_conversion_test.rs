@@ -63,8 +63,7 @@ fn converts_mesh() {
       // We compute our own neighbours and regions so we don't use these.
       polygon_neighbors: vec![],
       regions: vec![],
-      // We don't care about these.
-      flags: vec![],
+      flags: vec![0, 1, 0, 4, 0],
       border_size: 0,
       max_edge_error: 0.0,
     },
@@ -126,6 +125,10 @@ fn converts_mesh() {
     landmass_mesh.polygon_type_indices,
     elements_are!(&1, &2, &2, &255, &123)
   );
+  expect_that!(
+    landmass_mesh.polygon_flags,
+    some(elements_are!(&0, &1, &0, &4, &0))
+  );
 
   let Some(height_mesh) = landmass_mesh.height_mesh.as_ref() else {
     panic!("The height mesh should have been converted");